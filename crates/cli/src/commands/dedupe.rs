@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+use photopack_core::dedupe::DedupeProgress;
+use photopack_core::domain::Confidence;
+use photopack_core::error::Error;
+use photopack_core::Vault;
+
+use super::status::format_size;
+use crate::output::Format;
+
+pub fn quarantine(
+    vault: &mut Vault,
+    dest: &Path,
+    min_confidence: Confidence,
+    format: Format,
+) -> Result<()> {
+    if !format.is_table() {
+        let report = vault.quarantine_duplicates(
+            dest,
+            min_confidence,
+            Some(&mut |progress| format.event(&progress)),
+        )?;
+        return format.document(&report);
+    }
+
+    let pb = progress_bar();
+    pb.set_message("Quarantining duplicates...");
+
+    let report = vault.quarantine_duplicates(
+        dest,
+        min_confidence,
+        Some(&mut |progress| match progress {
+            DedupeProgress::Start { total } => {
+                pb.set_length(total as u64);
+                pb.set_position(0);
+            }
+            DedupeProgress::Quarantined { target, .. } => {
+                pb.inc(1);
+                pb.set_message(format!("-> {}", target.display()));
+            }
+            DedupeProgress::Skipped { path } => {
+                pb.inc(1);
+                pb.println(format!("  SKIPPED {}", path.display()));
+            }
+            DedupeProgress::Restored { .. } | DedupeProgress::Linked { .. } => {}
+            DedupeProgress::Complete { files, bytes } => {
+                pb.finish_with_message(format!("{files} file(s) moved, {} reclaimed", format_size(bytes)));
+            }
+        }),
+    )?;
+
+    println!("Journal: {}", report.journal.display());
+    println!(
+        "Undo with `photopack dedupe --undo {}`.",
+        report.journal.display()
+    );
+    Ok(())
+}
+
+pub fn undo(vault: &Vault, journal: &Path, format: Format) -> Result<()> {
+    if !format.is_table() {
+        let report = vault.undo_quarantine(journal, Some(&mut |progress| format.event(&progress)))?;
+        return format.document(&report);
+    }
+
+    let pb = progress_bar();
+    pb.set_message("Restoring quarantined files...");
+
+    let report = vault.undo_quarantine(
+        journal,
+        Some(&mut |progress| match progress {
+            DedupeProgress::Start { total } => {
+                pb.set_length(total as u64);
+                pb.set_position(0);
+            }
+            DedupeProgress::Restored { path, .. } => {
+                pb.inc(1);
+                pb.set_message(format!("<- {}", path.display()));
+            }
+            DedupeProgress::Skipped { .. } => {
+                pb.inc(1);
+            }
+            DedupeProgress::Quarantined { .. } | DedupeProgress::Linked { .. } => {}
+            DedupeProgress::Complete { files, .. } => {
+                pb.finish_with_message(format!("{files} file(s) restored"));
+            }
+        }),
+    )?;
+
+    for path in &report.conflicts {
+        println!("  CONFLICT {} (occupied, quarantined copy kept)", path.display());
+    }
+    for path in &report.missing {
+        println!("  MISSING  {}", path.display());
+    }
+    if !report.restored.is_empty() {
+        println!("Run `photopack scan` to catalog the restored files.");
+    }
+    Ok(())
+}
+
+pub fn link(vault: &Vault, format: Format) -> Result<()> {
+    if !format.is_table() {
+        let report = vault.link_duplicates(Some(&mut |progress| format.event(&progress)))?;
+        return format.document(&report);
+    }
+
+    let pb = progress_bar();
+    pb.set_message("Re-hashing and linking identical copies...");
+
+    let report = vault.link_duplicates(Some(&mut |progress| match progress {
+        DedupeProgress::Start { total } => {
+            pb.set_length(total as u64);
+            pb.set_position(0);
+        }
+        DedupeProgress::Linked { path, kind, .. } => {
+            pb.inc(1);
+            pb.set_message(format!("{kind}: {}", path.display()));
+        }
+        DedupeProgress::Skipped { .. } => {
+            pb.inc(1);
+        }
+        DedupeProgress::Quarantined { .. } | DedupeProgress::Restored { .. } => {}
+        DedupeProgress::Complete { files, bytes } => {
+            pb.finish_with_message(format!("{files} copy(ies) linked, {} saved", format_size(bytes)));
+        }
+    }))?;
+
+    if !report.skipped.is_empty() {
+        println!(
+            "{} copy(ies) skipped (already linked, on another filesystem, or changed since the last scan).",
+            report.skipped.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn parse_confidence(value: &str) -> std::result::Result<Confidence, String> {
+    Confidence::parse(value).ok_or_else(|| Error::UnknownConfidence(value.to_string()).to_string())
+}
+
+fn progress_bar() -> ProgressBar {
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    pb
+}
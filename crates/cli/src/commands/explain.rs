@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use photopack_core::domain::{MatchEvidence, MatchPhase};
+use photopack_core::Vault;
+use serde::Serialize;
+
+use crate::output::Format;
+
+/// One evidence edge of `explain`, with both members' paths.
+#[derive(Serialize)]
+struct EvidenceRecord<'a> {
+    group: i64,
+    #[serde(flatten)]
+    edge: &'a MatchEvidence,
+    path_a: Option<&'a Path>,
+    path_b: Option<&'a Path>,
+    reason: String,
+}
+
+pub fn run(vault: &Vault, id: i64, format: Format) -> Result<()> {
+    let group = vault.group(id)?;
+    let evidence = vault.group_evidence(id)?;
+
+    if !format.is_table() {
+        let path = |id: i64| {
+            group
+                .members
+                .iter()
+                .find(|m| m.id == id)
+                .map(|m| m.path.as_path())
+        };
+        let records: Vec<EvidenceRecord> = evidence
+            .iter()
+            .map(|edge| EvidenceRecord {
+                group: group.id,
+                edge,
+                path_a: path(edge.photo_a),
+                path_b: path(edge.photo_b),
+                reason: describe(edge),
+            })
+            .collect();
+        return format.records(&records);
+    }
+
+    println!("Group #{} ({})", group.id, group.confidence);
+    println!("{}", "-".repeat(60));
+
+    let mut names: HashMap<i64, String> = HashMap::new();
+    for member in &group.members {
+        let marker = if member.id == group.source_of_truth_id {
+            " [SOURCE]"
+        } else {
+            ""
+        };
+        println!("  #{:<6} {}{}", member.id, member.path.display(), marker);
+        let name = member
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        names.insert(member.id, format!("#{} {}", member.id, name));
+    }
+
+    println!();
+    if evidence.is_empty() {
+        println!("No match evidence recorded. Run `photopack scan` to record it.");
+        return Ok(());
+    }
+    println!("Evidence");
+    for edge in &evidence {
+        let name = |id: i64| names.get(&id).cloned().unwrap_or_else(|| format!("#{id}"));
+        println!(
+            "  {} ── {} ── {}",
+            name(edge.photo_a),
+            describe(edge),
+            name(edge.photo_b)
+        );
+    }
+
+    Ok(())
+}
+
+/// One-line reason for an evidence edge, e.g. `same EXIF 2024:01:15 10:00:00|EOS R5, aHash 1, dHash 2`.
+fn describe(edge: &MatchEvidence) -> String {
+    let label = match edge.phase {
+        MatchPhase::Sha256 => "identical bytes",
+        MatchPhase::Exif => "same EXIF",
+        MatchPhase::Perceptual => "perceptual",
+        MatchPhase::Merge => "merge",
+        MatchPhase::Orphan => "orphan attach",
+        MatchPhase::Manual => "manual merge",
+    };
+    let mut parts = vec![match &edge.exif_key {
+        Some(key) => format!("{label} {key}"),
+        None => label.to_string(),
+    }];
+    if edge.phase != MatchPhase::Sha256 {
+        if let Some(d) = edge.phash_distance {
+            parts.push(format!("aHash {d}"));
+        }
+        if let Some(d) = edge.dhash_distance {
+            parts.push(format!("dHash {d}"));
+        }
+    }
+    parts.join(", ")
+}
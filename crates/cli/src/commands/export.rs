@@ -1,10 +1,52 @@
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use indicatif::{ProgressBar, ProgressStyle};
-use photopack_core::{export::ExportProgress, Vault};
+use photopack_core::domain::FailedFile;
+use photopack_core::query::Filter;
+use photopack_core::{export, export::ExportProgress, Vault};
+use serde_json::json;
+
+use crate::output::Format;
+
+pub fn run(
+    vault: &mut Vault,
+    path: &Path,
+    encoder: &str,
+    quality: u8,
+    filter: Option<&Filter>,
+    format: Format,
+) -> Result<()> {
+    let encoder = export::encoder_by_name(encoder)?;
+
+    if !format.is_table() {
+        let mut counts = (0, 0);
+        let failed = convert(vault, path, encoder.as_ref(), quality, filter, &mut |progress| {
+            format.event(&progress);
+            if let ExportProgress::Complete {
+                converted, skipped, ..
+            } = progress
+            {
+                counts = (converted, skipped);
+            }
+        })?;
+        let (converted, skipped) = counts;
+        format.document(&json!({
+            "export": path,
+            "encoder": encoder.name(),
+            "extension": encoder.extension(),
+            "converted": converted,
+            "skipped": skipped,
+            "failed": failed,
+        }))?;
+        return ensure_complete(&failed);
+    }
+
+    println!("Encoder: {} (.{})", encoder.name(), encoder.extension());
+    if let Some(filter) = filter {
+        println!("Exporting files matching: {filter}");
+    }
 
-pub fn run(vault: &mut Vault, path: &Path, quality: u8) -> Result<()> {
     let pb = ProgressBar::new(0);
     pb.set_style(
         ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
@@ -12,14 +54,17 @@ pub fn run(vault: &mut Vault, path: &Path, quality: u8) -> Result<()> {
             .progress_chars("=>-"),
     );
 
-    vault.export(
+    let failed = convert(
+        vault,
         path,
+        encoder.as_ref(),
         quality,
-        Some(&mut |progress| match progress {
+        filter,
+        &mut |progress| match progress {
             ExportProgress::Start { total } => {
                 pb.set_length(total as u64);
                 pb.set_position(0);
-                pb.set_message("Converting photos to HEIC...");
+                pb.set_message(format!("Converting photos to {}...", encoder.extension()));
             }
             ExportProgress::Converted { target, .. } => {
                 pb.inc(1);
@@ -28,15 +73,65 @@ pub fn run(vault: &mut Vault, path: &Path, quality: u8) -> Result<()> {
             ExportProgress::Skipped { .. } => {
                 pb.inc(1);
             }
+            ExportProgress::Failed { path, error } => {
+                pb.inc(1);
+                pb.println(format!("  FAILED {}: {error}", path.display()));
+            }
             ExportProgress::Complete {
                 converted,
                 skipped,
+                failed,
             } => {
-                pb.finish_with_message(format!("{converted} converted, {skipped} skipped"));
+                let mut msg = format!("{converted} converted, {skipped} skipped");
+                if failed > 0 {
+                    msg.push_str(&format!(", {failed} failed"));
+                }
+                pb.finish_with_message(msg);
             }
-        }),
+        },
     )?;
 
+    ensure_complete(&failed)?;
     println!("Export complete.");
     Ok(())
 }
+
+fn ensure_complete(failed: &[FailedFile]) -> Result<()> {
+    if !failed.is_empty() {
+        bail!("export incomplete: {} file(s) could not be converted", failed.len());
+    }
+    Ok(())
+}
+
+/// Export every photo, or only those matching `filter`.
+fn convert(
+    vault: &Vault,
+    path: &Path,
+    encoder: &dyn export::ExportEncoder,
+    quality: u8,
+    filter: Option<&Filter>,
+    progress_cb: &mut dyn FnMut(ExportProgress),
+) -> photopack_core::error::Result<Vec<FailedFile>> {
+    match filter {
+        Some(filter) => vault.export_matching(path, encoder, quality, filter, Some(progress_cb)),
+        None => vault.export(path, encoder, quality, Some(progress_cb)),
+    }
+}
+
+pub fn dry_run(
+    vault: &Vault,
+    path: &Path,
+    encoder: &str,
+    filter: Option<&Filter>,
+    format: Format,
+) -> Result<()> {
+    let encoder = export::encoder_by_name(encoder)?;
+    if format.is_table() {
+        println!("Encoder: {} (.{})", encoder.name(), encoder.extension());
+    }
+    let plan = match filter {
+        Some(filter) => vault.plan_export_matching(path, encoder.as_ref(), filter)?,
+        None => vault.plan_export(path, encoder.as_ref())?,
+    };
+    super::plan::print(&plan, format)
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use photopack_core::Vault;
+use serde_json::json;
+
+use crate::output::Format;
+
+pub fn add(vault: &Vault, pattern: &str, format: Format) -> Result<()> {
+    let added = vault.add_ignore_pattern(pattern)?;
+    if !format.is_table() {
+        return format.document(&json!({ "pattern": pattern, "added": added }));
+    }
+    if added {
+        println!("Added ignore pattern: {pattern}");
+    } else {
+        println!("Ignore pattern already present: {pattern}");
+    }
+    Ok(())
+}
+
+pub fn rm(vault: &Vault, pattern: &str, format: Format) -> Result<()> {
+    let removed = vault.remove_ignore_pattern(pattern)?;
+    if !format.is_table() {
+        return format.document(&json!({ "pattern": pattern, "removed": removed }));
+    }
+    if removed {
+        println!("Removed ignore pattern: {pattern}");
+    } else {
+        println!("Ignore pattern not found: {pattern}");
+    }
+    Ok(())
+}
+
+pub fn ls(vault: &Vault, format: Format) -> Result<()> {
+    let patterns = vault.ignore_patterns()?;
+    if !format.is_table() {
+        let records: Vec<_> = patterns.iter().map(|p| json!({ "pattern": p })).collect();
+        return format.records(&records);
+    }
+    if patterns.is_empty() {
+        println!("No global ignore patterns. Sources can also use a .photopackignore file.");
+        return Ok(());
+    }
+    for pattern in &patterns {
+        println!("{pattern}");
+    }
+    Ok(())
+}
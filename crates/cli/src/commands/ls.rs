@@ -1,25 +1,94 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use anyhow::Result;
 use comfy_table::{presets::UTF8_FULL, Cell, ContentArrangement, Table};
+use photopack_core::domain::{Confidence, PhotoFile};
+use photopack_core::query::{Filter, Query, SortKey};
 use photopack_core::Vault;
+use serde::Serialize;
 
 use super::status::{
     add_photo_row, compute_aggregates, sort_photos_for_display, source_display_name, StatusData,
 };
+use crate::output::Format;
 
-pub fn run(vault: &Vault, dupes: bool, id: Option<i64>) -> Result<()> {
+/// One row of `ls`: the cataloged photo plus its place in the duplicate groups.
+#[derive(Serialize)]
+struct FileRecord<'a> {
+    #[serde(flatten)]
+    photo: &'a PhotoFile,
+    source: &'a str,
+    group: Option<i64>,
+    role: &'static str,
+    vault: bool,
+}
+
+/// One row of `ls --dupes`.
+#[derive(Serialize)]
+struct GroupRecord<'a> {
+    id: i64,
+    confidence: Confidence,
+    members: usize,
+    source_of_truth: Option<&'a Path>,
+    pinned: bool,
+}
+
+/// One member of `ls --dupes <id>`.
+#[derive(Serialize)]
+struct MemberRecord<'a> {
+    group: i64,
+    #[serde(flatten)]
+    photo: &'a PhotoFile,
+    source_of_truth: bool,
+    pinned: bool,
+}
+
+impl<'a> FileRecord<'a> {
+    fn new(
+        photo: &'a PhotoFile,
+        source_names: &'a HashMap<i64, String>,
+        data: &StatusData,
+    ) -> Self {
+        FileRecord {
+            photo,
+            source: source_names.get(&photo.source_id).map_or("?", String::as_str),
+            group: data.photo_group.get(&photo.id).copied(),
+            role: data.role(photo.id),
+            vault: data.vault_eligible(photo.id),
+        }
+    }
+}
+
+pub fn parse_filter(value: &str) -> std::result::Result<Filter, String> {
+    Filter::parse(value).map_err(|e| e.to_string())
+}
+
+pub fn parse_sort(value: &str) -> std::result::Result<SortKey, String> {
+    SortKey::parse(value).map_err(|e| e.to_string())
+}
+
+/// `query` is set by `--where`, `--sort` or `--limit`.
+pub fn run(
+    vault: &Vault,
+    dupes: bool,
+    id: Option<i64>,
+    query: Option<Query>,
+    format: Format,
+) -> Result<()> {
     if dupes {
         match id {
-            Some(id) => show_group(vault, id),
-            None => list_groups(vault),
+            Some(id) => show_group(vault, id, format),
+            None => list_groups(vault, format),
         }
+    } else if let Some(query) = query {
+        list_matching(vault, &query, format)
     } else {
-        list_files(vault)
+        list_files(vault, format)
     }
 }
 
-fn list_files(vault: &Vault) -> Result<()> {
+fn list_files(vault: &Vault, format: Format) -> Result<()> {
     let sources = vault.sources()?;
     let photos = vault.photos()?;
     let groups = vault.groups()?;
@@ -32,6 +101,18 @@ fn list_files(vault: &Vault) -> Result<()> {
         .map(|s| (s.id, source_display_name(s)))
         .collect();
 
+    // Partition and sort
+    let (grouped_photos, ungrouped_photos) = sort_photos_for_display(&photos, &data);
+
+    if !format.is_table() {
+        let records: Vec<FileRecord> = grouped_photos
+            .iter()
+            .chain(&ungrouped_photos)
+            .map(|photo| FileRecord::new(photo, &source_name_map, &data))
+            .collect();
+        return format.records(&records);
+    }
+
     let mut files_table = Table::new();
     files_table.load_preset(UTF8_FULL);
     files_table.set_content_arrangement(ContentArrangement::Dynamic);
@@ -47,9 +128,6 @@ fn list_files(vault: &Vault) -> Result<()> {
 
     let header_len = 7; // File, Source, Fmt, Size, Group, Role, Vault
 
-    // Partition and sort
-    let (grouped_photos, ungrouped_photos) = sort_photos_for_display(&photos, &data);
-
     // Add grouped photo rows
     let mut last_group_id: Option<i64> = None;
 
@@ -89,8 +167,78 @@ fn list_files(vault: &Vault) -> Result<()> {
     Ok(())
 }
 
-fn list_groups(vault: &Vault) -> Result<()> {
+/// Files matching a query, in the query's order, without group separators.
+fn list_matching(vault: &Vault, query: &Query, format: Format) -> Result<()> {
+    let photos = vault.query_photos(query)?;
     let groups = vault.groups()?;
+    let data = StatusData::build(&groups);
+
+    let source_name_map: HashMap<i64, String> = vault
+        .sources()?
+        .iter()
+        .map(|s| (s.id, source_display_name(s)))
+        .collect();
+
+    if !format.is_table() {
+        let records: Vec<FileRecord> = photos
+            .iter()
+            .map(|photo| FileRecord::new(photo, &source_name_map, &data))
+            .collect();
+        return format.records(&records);
+    }
+
+    if photos.is_empty() {
+        println!("No files match.");
+        return Ok(());
+    }
+
+    let mut files_table = Table::new();
+    files_table.load_preset(UTF8_FULL);
+    files_table.set_content_arrangement(ContentArrangement::Dynamic);
+    files_table.set_header(vec![
+        Cell::new("File"),
+        Cell::new("Source"),
+        Cell::new("Fmt"),
+        Cell::new("Size"),
+        Cell::new("Group"),
+        Cell::new("Role"),
+        Cell::new("Vault"),
+    ]);
+    for photo in &photos {
+        add_photo_row(&mut files_table, photo, &source_name_map, &data);
+    }
+
+    println!("{files_table}");
+    println!();
+    println!("  {} files", photos.len());
+    println!();
+
+    Ok(())
+}
+
+fn list_groups(vault: &Vault, format: Format) -> Result<()> {
+    let groups = vault.groups()?;
+    let pins = vault.pinned_hashes()?;
+
+    if !format.is_table() {
+        let records: Vec<GroupRecord> = groups
+            .iter()
+            .map(|group| {
+                let sot = group
+                    .members
+                    .iter()
+                    .find(|m| m.id == group.source_of_truth_id);
+                GroupRecord {
+                    id: group.id,
+                    confidence: group.confidence,
+                    members: group.members.len(),
+                    source_of_truth: sot.map(|m| m.path.as_path()),
+                    pinned: sot.is_some_and(|m| pins.contains(&m.sha256)),
+                }
+            })
+            .collect();
+        return format.records(&records);
+    }
 
     if groups.is_empty() {
         println!("No duplicates found. Run `photopack scan` first.");
@@ -108,7 +256,10 @@ fn list_groups(vault: &Vault) -> Result<()> {
             .members
             .iter()
             .find(|m| m.id == group.source_of_truth_id)
-            .map(|m| m.path.display().to_string())
+            .map(|m| {
+                let marker = if pins.contains(&m.sha256) { " [PINNED]" } else { "" };
+                format!("{}{}", m.path.display(), marker)
+            })
             .unwrap_or_else(|| "?".to_string());
 
         println!(
@@ -123,17 +274,32 @@ fn list_groups(vault: &Vault) -> Result<()> {
     Ok(())
 }
 
-fn show_group(vault: &Vault, id: i64) -> Result<()> {
+fn show_group(vault: &Vault, id: i64, format: Format) -> Result<()> {
     let group = vault.group(id)?;
+    let pins = vault.pinned_hashes()?;
+
+    if !format.is_table() {
+        let records: Vec<MemberRecord> = group
+            .members
+            .iter()
+            .map(|photo| MemberRecord {
+                group: group.id,
+                photo,
+                source_of_truth: photo.id == group.source_of_truth_id,
+                pinned: pins.contains(&photo.sha256),
+            })
+            .collect();
+        return format.records(&records);
+    }
 
     println!("Group #{} ({})", group.id, group.confidence);
     println!("{}", "-".repeat(60));
 
     for member in &group.members {
-        let marker = if member.id == group.source_of_truth_id {
-            " [SOURCE]"
-        } else {
-            ""
+        let marker = match (member.id == group.source_of_truth_id, pins.contains(&member.sha256)) {
+            (true, true) => " [SOURCE, PINNED]",
+            (true, false) => " [SOURCE]",
+            _ => "",
         };
         println!(
             "  {} ({}, {:.1} KB){}",
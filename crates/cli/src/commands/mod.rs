@@ -1,5 +1,16 @@
+pub mod dedupe;
+pub mod explain;
 pub mod export;
+pub mod ignore;
 pub mod ls;
+pub mod overrides;
 pub mod pack;
+pub mod pin;
+pub mod plan;
+pub mod ranking;
+pub mod repair;
 pub mod sources;
 pub mod status;
+pub mod trash;
+pub mod unpack;
+pub mod verify;
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use anyhow::Result;
+use photopack_core::Vault;
+use serde_json::json;
+
+use crate::output::Format;
+
+pub fn split(vault: &mut Vault, a: &Path, b: &Path, undo: bool, format: Format) -> Result<()> {
+    if undo {
+        return clear(vault, a, b, format);
+    }
+    vault.split_photos(a, b)?;
+    if !format.is_table() {
+        return document(vault, a, b, "split", format);
+    }
+    println!(
+        "{} and {} will never be grouped together.",
+        a.display(),
+        b.display()
+    );
+    print_group_count(vault)
+}
+
+pub fn merge(vault: &mut Vault, a: &Path, b: &Path, undo: bool, format: Format) -> Result<()> {
+    if undo {
+        return clear(vault, a, b, format);
+    }
+    vault.merge_photos(a, b)?;
+    if !format.is_table() {
+        return document(vault, a, b, "merge", format);
+    }
+    println!(
+        "{} and {} will always be grouped together.",
+        a.display(),
+        b.display()
+    );
+    print_group_count(vault)
+}
+
+fn clear(vault: &mut Vault, a: &Path, b: &Path, format: Format) -> Result<()> {
+    let cleared = vault.clear_match_override(a, b)?;
+    if !format.is_table() {
+        return document(vault, a, b, if cleared { "cleared" } else { "none" }, format);
+    }
+    if cleared {
+        println!("Removed the manual decision for {} and {}.", a.display(), b.display());
+        print_group_count(vault)
+    } else {
+        println!("No manual decision for {} and {}.", a.display(), b.display());
+        Ok(())
+    }
+}
+
+/// The decision now recorded for `a` and `b`: `split`, `merge`, `cleared` or `none`.
+fn document(vault: &Vault, a: &Path, b: &Path, decision: &str, format: Format) -> Result<()> {
+    format.document(&json!({
+        "a": a,
+        "b": b,
+        "decision": decision,
+        "groups": vault.status()?.total_groups,
+    }))
+}
+
+fn print_group_count(vault: &Vault) -> Result<()> {
+    println!("{} duplicate groups.", vault.status()?.total_groups);
+    Ok(())
+}
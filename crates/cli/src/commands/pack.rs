@@ -1,15 +1,77 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use indicatif::{ProgressBar, ProgressStyle};
+use photopack_core::domain::{FailedFile, PackPolicy};
+use photopack_core::error::Error;
+use photopack_core::query::Filter;
 use photopack_core::{vault_save::VaultSaveProgress, Vault};
+use serde_json::json;
 
-pub fn run(vault: &mut Vault, path: Option<PathBuf>) -> Result<()> {
+use crate::output::Format;
+
+pub fn run(
+    vault: &mut Vault,
+    path: Option<PathBuf>,
+    views: Option<bool>,
+    policy: Option<&str>,
+    filter: Option<&Filter>,
+    format: Format,
+) -> Result<()> {
     if let Some(path) = path {
         vault.set_vault_path(&path)?;
         let resolved = vault.get_vault_path()?.unwrap();
-        println!("Vault path set to: {}", resolved.display());
-        println!("Vault registered as scan source.");
+        if format.is_table() {
+            println!("Vault path set to: {}", resolved.display());
+            println!("Vault registered as scan source.");
+        }
+    }
+    if let Some(enabled) = views {
+        vault.set_pack_views(enabled)?;
+        if format.is_table() {
+            if enabled {
+                println!("Pack views enabled (by-date/, by-camera/).");
+            } else {
+                println!("Pack views disabled.");
+            }
+        }
+    }
+    if let Some(name) = policy {
+        let policy =
+            PackPolicy::parse(name).ok_or_else(|| Error::UnknownPackPolicy(name.to_string()))?;
+        vault.set_pack_policy(policy)?;
+        if format.is_table() {
+            println!("Pack policy set to {policy}.");
+        }
+    }
+
+    if !format.is_table() {
+        let mut counts = (0, 0, 0);
+        let failed = save(vault, filter, &mut |progress| {
+            format.event(&progress);
+            if let VaultSaveProgress::Complete {
+                copied,
+                skipped,
+                removed,
+                ..
+            } = progress
+            {
+                counts = (copied, skipped, removed);
+            }
+        })?;
+        let (copied, skipped, removed) = counts;
+        format.document(&json!({
+            "pack": vault.get_vault_path()?,
+            "copied": copied,
+            "skipped": skipped,
+            "removed": removed,
+            "failed": failed,
+        }))?;
+        return ensure_complete(&failed);
+    }
+
+    if let Some(filter) = filter {
+        println!("Packing files matching: {filter}");
     }
 
     let pb = ProgressBar::new(0);
@@ -19,7 +81,7 @@ pub fn run(vault: &mut Vault, path: Option<PathBuf>) -> Result<()> {
             .progress_chars("=>-"),
     );
 
-    vault.vault_save(Some(&mut |progress| match progress {
+    let failed = save(vault, filter, &mut |progress| match progress {
         VaultSaveProgress::Start { total } => {
             pb.set_length(total as u64);
             pb.set_position(0);
@@ -32,22 +94,69 @@ pub fn run(vault: &mut Vault, path: Option<PathBuf>) -> Result<()> {
         VaultSaveProgress::Skipped { .. } => {
             pb.inc(1);
         }
+        VaultSaveProgress::Failed { path, error } => {
+            pb.inc(1);
+            pb.println(format!("  FAILED {}: {error}", path.display()));
+        }
+        VaultSaveProgress::SidecarCopied { target, .. } => {
+            pb.set_message(format!("-> {}", target.display()));
+        }
         VaultSaveProgress::Removed { path } => {
-            pb.set_message(format!("removed superseded: {}", path.display()));
+            pb.set_message(format!("trashed superseded: {}", path.display()));
+        }
+        VaultSaveProgress::TrashPurged { count } => {
+            pb.println(format!("  Trash: {count} expired file(s) deleted"));
+        }
+        VaultSaveProgress::ViewsSynced { linked, removed } => {
+            if linked > 0 || removed > 0 {
+                pb.println(format!("  Views: {linked} linked, {removed} stale removed"));
+            }
         }
         VaultSaveProgress::Complete {
             copied,
             skipped,
             removed,
+            failed,
         } => {
             let mut msg = format!("{copied} copied, {skipped} skipped");
             if removed > 0 {
-                msg.push_str(&format!(", {removed} superseded removed"));
+                msg.push_str(&format!(", {removed} superseded moved to trash"));
+            }
+            if failed > 0 {
+                msg.push_str(&format!(", {failed} failed"));
             }
             pb.finish_with_message(msg);
         }
-    }))?;
+    })?;
 
+    ensure_complete(&failed)?;
     println!("Vault sync complete.");
     Ok(())
 }
+
+fn ensure_complete(failed: &[FailedFile]) -> Result<()> {
+    if !failed.is_empty() {
+        bail!("vault sync incomplete: {} file(s) could not be copied", failed.len());
+    }
+    Ok(())
+}
+
+/// Sync the whole pack, or only the files matching `filter`.
+fn save(
+    vault: &mut Vault,
+    filter: Option<&Filter>,
+    progress_cb: &mut dyn FnMut(VaultSaveProgress),
+) -> photopack_core::error::Result<Vec<FailedFile>> {
+    match filter {
+        Some(filter) => vault.vault_save_matching(filter, Some(progress_cb)),
+        None => vault.vault_save(Some(progress_cb)),
+    }
+}
+
+pub fn dry_run(vault: &Vault, filter: Option<&Filter>, format: Format) -> Result<()> {
+    let plan = match filter {
+        Some(filter) => vault.plan_vault_save_matching(filter)?,
+        None => vault.plan_vault_save()?,
+    };
+    super::plan::print(&plan, format)
+}
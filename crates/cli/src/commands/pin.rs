@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use anyhow::Result;
+use photopack_core::Vault;
+use serde_json::json;
+
+use crate::output::Format;
+
+pub fn pin(vault: &mut Vault, path: &Path, format: Format) -> Result<()> {
+    vault.pin_photo(path)?;
+    if !format.is_table() {
+        return format.document(&json!({ "path": path, "pinned": true }));
+    }
+    println!("{} is pinned as the source of truth.", path.display());
+    Ok(())
+}
+
+pub fn unpin(vault: &mut Vault, path: &Path, format: Format) -> Result<()> {
+    let unpinned = vault.unpin_photo(path)?;
+    if !format.is_table() {
+        return format.document(&json!({ "path": path, "pinned": false, "changed": unpinned }));
+    }
+    if unpinned {
+        println!("Unpinned {}.", path.display());
+    } else {
+        println!("{} is not pinned.", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use photopack_core::plan::{Plan, PlannedAction};
+
+use super::status::format_size;
+use crate::output::Format;
+
+/// Print a dry-run plan: one line per effect (skips are only counted), then totals.
+/// Machine formats get one record per action, skips included.
+pub fn print(plan: &Plan, format: Format) -> Result<()> {
+    if !format.is_table() {
+        return format.records(&plan.actions);
+    }
+    for action in &plan.actions {
+        match action {
+            PlannedAction::Copy {
+                source,
+                target,
+                bytes,
+            } => println!(
+                "  COPY    {} -> {} ({})",
+                source.display(),
+                target.display(),
+                format_size(*bytes)
+            ),
+            PlannedAction::Convert { source, target, .. } => {
+                println!("  CONVERT {} -> {}", source.display(), target.display())
+            }
+            PlannedAction::Skip { .. } => {}
+            PlannedAction::Remove { path, bytes } => {
+                println!("  TRASH   {} ({})", path.display(), format_size(*bytes))
+            }
+            PlannedAction::Untrash { path } => println!("  UNTRASH {}", path.display()),
+            PlannedAction::Purge { path, bytes } => {
+                println!("  PURGE   {} ({})", path.display(), format_size(*bytes))
+            }
+            PlannedAction::Move {
+                source,
+                target,
+                bytes,
+            } => println!(
+                "  MOVE    {} -> {} ({})",
+                source.display(),
+                target.display(),
+                format_size(*bytes)
+            ),
+            PlannedAction::Link { path, keeper, bytes } => println!(
+                "  LINK    {} -> {} ({})",
+                path.display(),
+                keeper.display(),
+                format_size(*bytes)
+            ),
+        }
+    }
+
+    let summary = plan.summary();
+    let mut parts = Vec::new();
+    let counts = [
+        (summary.copies, "to copy"),
+        (summary.conversions, "to convert"),
+        (summary.untrashed, "to restore from trash"),
+        (summary.removals, "to trash"),
+        (summary.purges, "to purge from trash"),
+        (summary.moves, "to move"),
+        (summary.links, "to link"),
+        (summary.skips, "up to date"),
+    ];
+    for (count, label) in counts {
+        if count > 0 {
+            parts.push(format!("{count} {label}"));
+        }
+    }
+    if parts.is_empty() {
+        parts.push("nothing to do".to_string());
+    }
+    println!("{}", parts.join(", "));
+    if summary.bytes_to_write > 0 {
+        println!("Bytes to write: {}", format_size(summary.bytes_to_write));
+    }
+    if summary.bytes_freed > 0 {
+        println!("Bytes freed:    {}", format_size(summary.bytes_freed));
+    }
+    println!("Dry run — nothing was changed.");
+    Ok(())
+}
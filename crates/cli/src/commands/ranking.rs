@@ -0,0 +1,17 @@
+use anyhow::Result;
+use photopack_core::ranking::RankingPolicy;
+use photopack_core::Vault;
+use serde_json::json;
+
+use crate::output::Format;
+
+pub fn run(vault: &mut Vault, policy: Option<&str>, format: Format) -> Result<()> {
+    if let Some(policy) = policy {
+        vault.set_ranking_policy(&RankingPolicy::parse(policy)?)?;
+    }
+    if !format.is_table() {
+        return format.document(&json!({ "ranking": vault.ranking_policy()?.to_string() }));
+    }
+    println!("Ranking: {}", vault.ranking_policy()?);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::{bail, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use photopack_core::repair::{RepairProgress, RepairReport};
+use photopack_core::Vault;
+
+use crate::output::Format;
+
+pub fn run(vault: &Vault, format: Format) -> Result<()> {
+    if !format.is_table() {
+        let report = vault.repair_pack(Some(&mut |progress| format.event(&progress)))?;
+        format.document(&report)?;
+        return ensure_repaired(&report);
+    }
+
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    pb.set_message("Verifying pack...");
+
+    let report = vault.repair_pack(Some(&mut |progress| match progress {
+        RepairProgress::Start { total } => {
+            pb.set_length(total as u64);
+            pb.set_position(0);
+            pb.set_message("Restoring damaged pack files...");
+        }
+        RepairProgress::Repaired { path, source } => {
+            pb.inc(1);
+            pb.println(format!("  REPAIRED      {} <- {}", path.display(), source.display()));
+        }
+        RepairProgress::Unrecoverable { path } => {
+            pb.inc(1);
+            pb.println(format!("  UNRECOVERABLE {}", path.display()));
+        }
+        RepairProgress::Complete {
+            repaired,
+            unrecoverable,
+        } => {
+            pb.finish_with_message(format!("{repaired} repaired, {unrecoverable} unrecoverable"));
+        }
+    }))?;
+
+    for path in &report.orphaned {
+        println!("  ORPHANED      {} (left in place)", path.display());
+    }
+    for path in &report.misnamed {
+        println!("  MISNAMED      {} (left in place)", path.display());
+    }
+
+    ensure_repaired(&report)?;
+    println!("Pack repair complete.");
+    Ok(())
+}
+
+fn ensure_repaired(report: &RepairReport) -> Result<()> {
+    if !report.unrecoverable.is_empty() {
+        bail!(
+            "{} pack file(s) could not be repaired — no intact source copy found",
+            report.unrecoverable.len()
+        );
+    }
+    Ok(())
+}
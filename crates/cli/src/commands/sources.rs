@@ -2,16 +2,41 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use photopack_core::error::Error;
 use photopack_core::{ScanProgress, Vault};
+use serde_json::json;
 
-pub fn add(vault: &Vault, path: PathBuf) -> Result<()> {
-    let source = vault.add_source(&path)?;
-    println!("Added source: {}", source.path.display());
+use crate::output::Format;
+
+pub fn add(vault: &mut Vault, path: PathBuf, priority: Option<i64>, format: Format) -> Result<()> {
+    let added = vault.add_source(&path);
+    // Re-adding with --priority only updates the priority
+    let Some(priority) = priority else {
+        let source = added?;
+        if !format.is_table() {
+            return format.document(&source);
+        }
+        println!("Added source: {}", source.path.display());
+        return Ok(());
+    };
+    match added {
+        Ok(source) if format.is_table() => println!("Added source: {}", source.path.display()),
+        Ok(_) | Err(Error::SourceAlreadyExists(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    let source = vault.set_source_priority(&path, priority)?;
+    if !format.is_table() {
+        return format.document(&source);
+    }
+    println!("Priority of {}: {}", source.path.display(), source.priority);
     Ok(())
 }
 
-pub fn rm(vault: &Vault, path: PathBuf) -> Result<()> {
+pub fn rm(vault: &Vault, path: PathBuf, format: Format) -> Result<()> {
     let (source, photo_count) = vault.remove_source(&path)?;
+    if !format.is_table() {
+        return format.document(&json!({ "source": source, "photos_removed": photo_count }));
+    }
     println!(
         "Removed source: {} ({} photos removed from catalog)",
         source.path.display(),
@@ -39,7 +64,19 @@ fn source_display_name(source: &str) -> &str {
         .unwrap_or(source)
 }
 
-pub fn scan(vault: &mut Vault) -> Result<()> {
+pub fn scan(vault: &mut Vault, profile: Option<&str>, format: Format) -> Result<()> {
+    if let Some(name) = profile {
+        vault.set_match_profile(name)?;
+        if format.is_table() {
+            println!("Matching profile set to {name}.");
+        }
+    }
+
+    if !format.is_table() {
+        vault.scan(Some(&mut |progress| format.event(&progress)))?;
+        return format.document(&vault.status()?);
+    }
+
     let mp = MultiProgress::new();
     let mut active_pb: Option<ProgressBar> = None;
     let mut current_len: u64 = 0;
@@ -71,6 +108,14 @@ pub fn scan(vault: &mut Vault) -> Result<()> {
             pb.enable_steady_tick(std::time::Duration::from_millis(80));
             active_pb = Some(pb);
         }
+        ScanProgress::SourceOffline { source } => {
+            mp.println(String::new()).ok();
+            mp.println(format!(
+                "  Skipping {} (offline, photos kept)",
+                source_display_name(&source)
+            ))
+            .ok();
+        }
         ScanProgress::FileHashed { path } => {
             if let Some(ref pb) = active_pb {
                 let name = path
@@ -81,9 +126,16 @@ pub fn scan(vault: &mut Vault) -> Result<()> {
                 pb.inc(1);
             }
         }
+        ScanProgress::Excluded { path } => {
+            mp.println(format!("  Excluded {}", path.display())).ok();
+        }
         ScanProgress::FilesRemoved { count } => {
             mp.println(format!("  Cleaned {count} stale entries")).ok();
         }
+        ScanProgress::FileMoved { from, to } => {
+            mp.println(format!("  Moved {} -> {}", from.display(), to.display()))
+                .ok();
+        }
         ScanProgress::AnalysisStart { count } => {
             // Finish hashing bar — stays visible with done style
             if let Some(pb) = active_pb.take() {
@@ -4,6 +4,9 @@ use anyhow::Result;
 use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Table};
 use photopack_core::domain::{DuplicateGroup, PhotoFile, Source};
 use photopack_core::Vault;
+use serde::Serialize;
+
+use crate::output::Format;
 
 /// Precomputed lookup data for rendering the status dashboard.
 pub(crate) struct StatusData {
@@ -16,7 +19,7 @@ pub(crate) struct StatusData {
 }
 
 /// Aggregated statistics derived from photos and groups.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub(crate) struct Aggregates {
     pub(crate) total_photos: usize,
     pub(crate) total_groups: usize,
@@ -59,6 +62,15 @@ impl StatusData {
             && !self.photo_is_sot.get(&photo_id).copied().unwrap_or(false)
     }
 
+    /// Machine-readable role: `best_copy`, `duplicate` or `unique`.
+    pub(crate) fn role(&self, photo_id: i64) -> &'static str {
+        match (self.grouped_ids.contains(&photo_id), self.is_duplicate(photo_id)) {
+            (true, false) => "best_copy",
+            (true, true) => "duplicate",
+            _ => "unique",
+        }
+    }
+
     pub(crate) fn vault_eligible(&self, photo_id: i64) -> bool {
         if self.grouped_ids.contains(&photo_id) {
             self.photo_is_sot.get(&photo_id).copied().unwrap_or(false)
@@ -103,16 +115,55 @@ pub(crate) fn compute_source_stats(photos: &[PhotoFile]) -> HashMap<i64, SourceS
     stats
 }
 
-pub fn run(vault: &Vault) -> Result<()> {
+/// The status dashboard as a single document.
+#[derive(Serialize)]
+struct StatusDocument<'a> {
+    #[serde(flatten)]
+    overview: &'a Aggregates,
+    vault: Option<&'a std::path::Path>,
+    ranking: String,
+    sources: Vec<SourceRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct SourceRecord<'a> {
+    #[serde(flatten)]
+    source: &'a Source,
+    photos: usize,
+    size: u64,
+}
+
+pub fn run(vault: &Vault, format: Format) -> Result<()> {
     let sources = vault.sources()?;
     let photos = vault.photos()?;
     let groups = vault.groups()?;
     let vault_path = vault.get_vault_path()?;
+    let policy = vault.ranking_policy()?;
 
     let data = StatusData::build(&groups);
     let agg = compute_aggregates(&photos, &groups, &data);
     let source_stats = compute_source_stats(&photos);
 
+    if !format.is_table() {
+        let sources = sources
+            .iter()
+            .map(|source| {
+                let ss = source_stats.get(&source.id);
+                SourceRecord {
+                    source,
+                    photos: ss.map(|s| s.photo_count).unwrap_or(0),
+                    size: ss.map(|s| s.total_size).unwrap_or(0),
+                }
+            })
+            .collect();
+        return format.document(&StatusDocument {
+            overview: &agg,
+            vault: vault_path.as_deref(),
+            ranking: policy.to_string(),
+            sources,
+        });
+    }
+
     let vault_display = match &vault_path {
         Some(p) => p.display().to_string(),
         None => "not configured".to_string(),
@@ -144,6 +195,7 @@ pub fn run(vault: &Vault) -> Result<()> {
         "   Duplicates: {:>8}        Vault:       {}",
         agg.total_duplicates, vault_display
     );
+    println!("   Ranking:    {policy}");
 
     // Sources table
     let mut sources_table = Table::new();
@@ -155,6 +207,8 @@ pub fn run(vault: &Vault) -> Result<()> {
         Cell::new("Photos"),
         Cell::new("Size"),
         Cell::new("Last Scanned"),
+        Cell::new("Priority"),
+        Cell::new("Status"),
     ]);
 
     for source in &sources {
@@ -174,6 +228,8 @@ pub fn run(vault: &Vault) -> Result<()> {
             Cell::new(count),
             Cell::new(format_size(size)),
             Cell::new(scanned),
+            Cell::new(source.priority),
+            source_status_cell(source),
         ]);
     }
 
@@ -189,6 +245,24 @@ pub fn run(vault: &Vault) -> Result<()> {
     Ok(())
 }
 
+/// Volume status of a source as shown in the sources table.
+pub(crate) fn source_status_label(source: &Source) -> &'static str {
+    if source.online {
+        "online"
+    } else {
+        "offline"
+    }
+}
+
+fn source_status_cell(source: &Source) -> Cell {
+    let cell = Cell::new(source_status_label(source));
+    if source.online {
+        cell
+    } else {
+        cell.fg(Color::Yellow)
+    }
+}
+
 /// Sort photos for display: grouped first (by group ID, SOT first), then ungrouped (by path).
 pub(crate) fn sort_photos_for_display<'a>(
     photos: &'a [PhotoFile],
@@ -343,6 +417,9 @@ mod tests {
             id: 1,
             path: PathBuf::from("/home/user/photos"),
             last_scanned: None,
+            volume_id: None,
+            online: true,
+            priority: 0,
         };
         assert_eq!(source_display_name(&source), "photos");
     }
@@ -353,6 +430,9 @@ mod tests {
             id: 1,
             path: PathBuf::from("/mnt/external/camera/2024"),
             last_scanned: None,
+            volume_id: None,
+            online: true,
+            priority: 0,
         };
         assert_eq!(source_display_name(&source), "2024");
     }
@@ -363,11 +443,29 @@ mod tests {
             id: 1,
             path: PathBuf::from("/"),
             last_scanned: None,
+            volume_id: None,
+            online: true,
+            priority: 0,
         };
         // Root has no file_name(), falls back to display()
         assert_eq!(source_display_name(&source), "/");
     }
 
+    #[test]
+    fn test_source_status_label() {
+        let mut source = Source {
+            id: 1,
+            path: PathBuf::from("/Volumes/Backup"),
+            last_scanned: None,
+            volume_id: Some("vol".to_string()),
+            online: true,
+            priority: 0,
+        };
+        assert_eq!(source_status_label(&source), "online");
+        source.online = false;
+        assert_eq!(source_status_label(&source), "offline");
+    }
+
     // ── Helper to build PhotoFile for tests ─────────────────────────
 
     fn make_photo(id: i64, source_id: i64, path: &str, size: u64) -> PhotoFile {
@@ -451,6 +549,18 @@ mod tests {
         assert!(!data.is_duplicate(99));
     }
 
+    // ── role ────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_role_labels() {
+        let groups = vec![make_group(1, 10, &[10, 11])];
+        let data = StatusData::build(&groups);
+
+        assert_eq!(data.role(10), "best_copy");
+        assert_eq!(data.role(11), "duplicate");
+        assert_eq!(data.role(99), "unique");
+    }
+
     // ── vault_eligible ──────────────────────────────────────────────
 
     #[test]
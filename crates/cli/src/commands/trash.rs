@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use anyhow::Result;
+use comfy_table::{presets::UTF8_FULL, Cell, ContentArrangement, Table};
+use photopack_core::Vault;
+use serde_json::json;
+
+use super::status::format_size;
+use crate::output::Format;
+
+pub fn ls(vault: &Vault, format: Format) -> Result<()> {
+    let entries = vault.trash_entries()?;
+    if !format.is_table() {
+        return format.records(&entries);
+    }
+    if entries.is_empty() {
+        println!("Trash is empty.");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("Hash"),
+        Cell::new("File"),
+        Cell::new("Fmt"),
+        Cell::new("Size"),
+        Cell::new("Trashed (UTC)"),
+    ]);
+    let mut total = 0;
+    for entry in &entries {
+        total += entry.size;
+        table.add_row(vec![
+            Cell::new(&entry.sha256[..12]),
+            Cell::new(&entry.original_filename),
+            Cell::new(&entry.format),
+            Cell::new(format_size(entry.size)),
+            Cell::new(&entry.trashed_at),
+        ]);
+    }
+    println!("{table}");
+    println!(
+        "{} file(s), {} — purged {} day(s) after trashing.",
+        entries.len(),
+        format_size(total),
+        vault.trash_retention_days()?
+    );
+    Ok(())
+}
+
+pub fn restore(vault: &Vault, hash: &str, dest: &Path, format: Format) -> Result<()> {
+    let restored = vault.restore_from_trash(hash, dest)?;
+    if !format.is_table() {
+        return format.document(&json!({ "restored": restored }));
+    }
+    println!("Restored to {}", restored.display());
+    Ok(())
+}
+
+pub fn purge(vault: &Vault, all: bool, format: Format) -> Result<()> {
+    let purged = vault.purge_trash(all)?;
+    if !format.is_table() {
+        return format.records(&purged);
+    }
+    let bytes: u64 = purged.iter().map(|e| e.size).sum();
+    println!("Purged {} file(s), {} freed.", purged.len(), format_size(bytes));
+    Ok(())
+}
+
+pub fn retention(vault: &Vault, days: Option<u32>, format: Format) -> Result<()> {
+    if let Some(days) = days {
+        vault.set_trash_retention_days(days)?;
+    }
+    if !format.is_table() {
+        return format.document(&json!({ "retention_days": vault.trash_retention_days()? }));
+    }
+    println!("Trash retention: {} day(s)", vault.trash_retention_days()?);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use photopack_core::restore::{self, RestoreMode, RestoreProgress, RestoreReport};
+use photopack_core::Vault;
+
+use crate::output::Format;
+
+/// Which pack to unpack.
+pub enum PackSource<'a> {
+    /// The pack configured in the catalog.
+    Vault(&'a Vault),
+    /// A pack directory given with `--from`; no catalog is opened.
+    Path(&'a Path),
+}
+
+pub fn run(source: PackSource, dest: &Path, mode: RestoreMode, format: Format) -> Result<()> {
+    if !format.is_table() {
+        let mut on_progress = |progress: RestoreProgress| format.event(&progress);
+        let report = match source {
+            PackSource::Vault(vault) => vault.restore_pack(dest, mode, Some(&mut on_progress))?,
+            PackSource::Path(pack) => {
+                restore::restore_pack(pack, dest, mode, Some(&mut on_progress))?
+            }
+        };
+        format.document(&report)?;
+        return ensure_complete(&report);
+    }
+
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+
+    let mut on_progress = |progress| match progress {
+        RestoreProgress::Start { total } => {
+            pb.set_length(total as u64);
+            pb.set_position(0);
+            pb.set_message("Unpacking...");
+        }
+        RestoreProgress::Restored { .. } | RestoreProgress::Skipped { .. } => {
+            pb.inc(1);
+        }
+        RestoreProgress::Missing { path } => {
+            pb.inc(1);
+            pb.println(format!("  MISSING   {}", path.display()));
+        }
+        RestoreProgress::Complete {
+            restored,
+            skipped,
+            missing,
+        } => {
+            pb.finish_with_message(format!(
+                "{restored} restored, {skipped} skipped, {missing} missing"
+            ));
+        }
+    };
+
+    let report = match source {
+        PackSource::Vault(vault) => vault.restore_pack(dest, mode, Some(&mut on_progress))?,
+        PackSource::Path(pack) => restore::restore_pack(pack, dest, mode, Some(&mut on_progress))?,
+    };
+
+    ensure_complete(&report)?;
+    println!("Unpacked to {}", dest.display());
+    Ok(())
+}
+
+fn ensure_complete(report: &RestoreReport) -> Result<()> {
+    if !report.missing.is_empty() {
+        bail!(
+            "{} pack file(s) listed in the manifest are missing — run `photopack repair`",
+            report.missing.len()
+        );
+    }
+    Ok(())
+}
+
+/// Parse a `--mode` value: `copy`, `hardlink` or `symlink`.
+pub fn parse_mode(value: &str) -> std::result::Result<RestoreMode, String> {
+    match value {
+        "copy" => Ok(RestoreMode::Copy),
+        "hardlink" => Ok(RestoreMode::Hardlink),
+        "symlink" => Ok(RestoreMode::Symlink),
+        _ => Err(format!("invalid mode: {value} (expected copy, hardlink or symlink)")),
+    }
+}
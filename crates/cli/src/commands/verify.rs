@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use photopack_core::verify::{VerifyProgress, VerifyReport};
+use photopack_core::Vault;
+
+use crate::output::Format;
+
+pub fn run(vault: &Vault, sample: Option<f64>, format: Format) -> Result<()> {
+    if !format.is_table() {
+        let report = vault.verify_pack(sample, Some(&mut |progress| format.event(&progress)))?;
+        format.document(&report)?;
+        return ensure_clean(&report);
+    }
+
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+
+    let report = vault.verify_pack(
+        sample,
+        Some(&mut |progress| match progress {
+            VerifyProgress::Start { total } => {
+                pb.set_length(total as u64);
+                pb.set_position(0);
+                pb.set_message("Re-hashing pack files...");
+            }
+            VerifyProgress::Verified { .. } => {
+                pb.inc(1);
+            }
+            VerifyProgress::Corrupted { path } => {
+                pb.inc(1);
+                pb.println(format!("  CORRUPTED {}", path.display()));
+            }
+            VerifyProgress::Missing { path } => {
+                pb.inc(1);
+                pb.println(format!("  MISSING   {}", path.display()));
+            }
+            VerifyProgress::Orphaned { path } => {
+                pb.println(format!("  ORPHANED  {}", path.display()));
+            }
+            VerifyProgress::Misnamed { path } => {
+                pb.println(format!("  MISNAMED  {}", path.display()));
+            }
+            VerifyProgress::Complete { verified, problems } => {
+                pb.finish_with_message(format!("{verified} verified, {problems} problems"));
+            }
+        }),
+    )?;
+
+    if sample.is_some() {
+        println!(
+            "Sampled {} of {} pack files.",
+            report.verified + report.corrupted.len() + report.missing.len(),
+            report.total_entries
+        );
+    }
+
+    ensure_clean(&report)?;
+    println!("Pack verified.");
+    Ok(())
+}
+
+fn ensure_clean(report: &VerifyReport) -> Result<()> {
+    if !report.is_clean() {
+        bail!(
+            "pack verification failed: {} corrupted, {} missing, {} orphaned, {} misnamed",
+            report.corrupted.len(),
+            report.missing.len(),
+            report.orphaned.len(),
+            report.misnamed.len()
+        );
+    }
+    Ok(())
+}
+
+/// Parse a `--sample` value such as `10%` or `2.5` into a percentage.
+pub fn parse_sample(value: &str) -> std::result::Result<f64, String> {
+    let percent: f64 = value
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid percentage: {value}"))?;
+    if percent > 0.0 && percent <= 100.0 {
+        Ok(percent)
+    } else {
+        Err(format!("percentage must be in (0, 100]: {value}"))
+    }
+}
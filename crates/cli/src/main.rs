@@ -1,11 +1,18 @@
 mod commands;
+mod output;
 
 use std::path::PathBuf;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
+use commands::unpack::PackSource;
+use photopack_core::domain::Confidence;
+use photopack_core::query::{Filter, Query, SortKey};
+use photopack_core::restore::RestoreMode;
 use photopack_core::Vault;
 
+use output::Format;
+
 /// Photopack — pack your photo library tight
 #[derive(Parser)]
 #[command(name = "photopack", version, about)]
@@ -14,6 +21,10 @@ struct Cli {
     #[arg(long, default_value_t = default_catalog_path())]
     catalog: String,
 
+    /// Output format: table, or json, jsonl or csv for scripts (progress as JSON lines)
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -24,6 +35,9 @@ enum Commands {
     Add {
         /// Path to the photo directory
         path: PathBuf,
+        /// Preference for this source's copies when ranking by source (higher wins)
+        #[arg(long, allow_hyphen_values = true)]
+        priority: Option<i64>,
     },
     /// Unregister a source and remove its photos from the catalog
     Rm {
@@ -31,32 +45,198 @@ enum Commands {
         path: PathBuf,
     },
     /// Scan all sources for photos and find duplicates
-    Scan,
+    Scan {
+        /// Matching profile: strict, default or scans (saved)
+        #[arg(long)]
+        profile: Option<String>,
+    },
     /// Show catalog dashboard (overview, sources, vault info)
     Status,
     /// List files, or duplicate groups with --dupes
     Ls {
         /// Show duplicate groups instead of files
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["filter", "sort", "limit"])]
         dupes: bool,
         /// Group ID (with --dupes)
         id: Option<i64>,
+        /// Only files matching an expression, e.g. "format in (raw) and date >= 2023-01"
+        #[arg(long = "where", value_name = "EXPR", value_parser = commands::ls::parse_filter)]
+        filter: Option<Filter>,
+        /// Comma-separated sort keys, each a field with an optional :desc (e.g. date:desc,size)
+        #[arg(long, value_delimiter = ',', value_parser = commands::ls::parse_sort)]
+        sort: Vec<SortKey>,
+        /// Show at most this many files
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Show why each photo of a duplicate group was matched
+    Explain {
+        /// Group ID (from `ls --dupes`)
+        id: i64,
+    },
+    /// Never group two files (and their copies) again, even after rescans
+    Split {
+        /// First file
+        a: PathBuf,
+        /// Second file
+        b: PathBuf,
+        /// Remove the decision instead
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Always group two files (and their copies), even after rescans
+    Merge {
+        /// First file
+        a: PathBuf,
+        /// Second file
+        b: PathBuf,
+        /// Remove the decision instead
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Pin a file (and its copies) as the source of truth of its group
+    Pin {
+        /// File to keep as the original
+        path: PathBuf,
+    },
+    /// Remove a pin and let the group elect its source of truth again
+    Unpin {
+        /// Pinned file
+        path: PathBuf,
+    },
+    /// Show or set the criteria used to elect each group's source of truth
+    Ranking {
+        /// Comma-separated criteria: format, megapixels, size, source, exif, mtime, path:<pattern>
+        policy: Option<String>,
     },
     /// Pack best-quality originals into a permanent lossless archive
     Pack {
         /// Destination directory (saved for future runs)
         path: Option<PathBuf>,
+        /// Maintain by-date/ and by-camera/ link views inside the pack (saved)
+        #[arg(long, conflicts_with = "no_views")]
+        views: bool,
+        /// Remove the link views and stop maintaining them (saved)
+        #[arg(long)]
+        no_views: bool,
+        /// Group members to keep: `source` (best copy only) or `families`
+        /// (best copy of each format family), saved in the pack
+        #[arg(long)]
+        policy: Option<String>,
+        /// Only pack files matching an expression (see `ls --where`); nothing is trashed
+        #[arg(long = "where", value_name = "EXPR", value_parser = commands::ls::parse_filter)]
+        filter: Option<Filter>,
+        /// Print what would be copied, skipped and trashed without touching the pack
+        #[arg(long, conflicts_with_all = ["path", "views", "no_views", "policy"])]
+        dry_run: bool,
     },
-    /// Export compressed HEIC photos for space savings (macOS)
+    /// Export compressed photos (HEIC, JPEG, WebP or AVIF) for space savings
     Export {
         /// Destination directory
         path: PathBuf,
-        /// HEIC quality 0-100
+        /// Encoder: auto, sips, heif-enc, magick, vips, jpeg, webp, avif
+        #[arg(long, default_value = "auto")]
+        encoder: String,
+        /// Quality 0-100
         #[arg(long, default_value_t = 85)]
         quality: u8,
+        /// Only export photos matching an expression (see `ls --where`)
+        #[arg(long = "where", value_name = "EXPR", value_parser = commands::ls::parse_filter)]
+        filter: Option<Filter>,
+        /// Print what would be converted or skipped without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Reclaim space in the sources: quarantine redundant copies or link identical ones
+    #[command(group(ArgGroup::new("action").required(true)))]
+    Dedupe {
+        /// Quarantine folder; original paths are mirrored inside it
+        #[arg(long, group = "action")]
+        quarantine: Option<PathBuf>,
+        /// Put back everything recorded in a quarantine journal
+        #[arg(long, group = "action")]
+        undo: Option<PathBuf>,
+        /// Replace byte-identical copies in place with reflinks or hard links
+        #[arg(long, group = "action")]
+        link: bool,
+        /// Only groups at or above this confidence: certain, near-certain, high, probable, low
+        #[arg(long, default_value = "certain", value_parser = commands::dedupe::parse_confidence)]
+        min_confidence: Confidence,
+        /// Print what would be moved or linked without touching any file
+        #[arg(long, conflicts_with = "undo")]
+        dry_run: bool,
+    },
+    /// Re-hash pack files and report corrupted, missing, orphaned or misnamed files
+    Verify {
+        /// Only re-hash this share of files (e.g. 10%), least recently verified first
+        #[arg(long, value_parser = commands::verify::parse_sample)]
+        sample: Option<f64>,
+    },
+    /// Restore corrupted or missing pack files from source copies with the same hash
+    Repair,
+    /// Rebuild a YYYY/MM/DD/original_filename tree from the pack's manifest
+    Unpack {
+        /// Destination directory
+        dest: PathBuf,
+        /// Pack directory to read instead of the configured one (no catalog needed)
+        #[arg(long)]
+        from: Option<PathBuf>,
+        /// How files are materialized: copy, hardlink or symlink
+        #[arg(long, default_value = "copy", value_parser = commands::unpack::parse_mode)]
+        mode: RestoreMode,
+    },
+    /// Manage global ignore patterns (gitignore syntax, applied to every source)
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreAction,
+    },
+    /// Manage superseded pack files waiting in .photopack/trash/
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
     },
 }
 
+#[derive(Subcommand)]
+enum TrashAction {
+    /// List trashed files
+    Ls,
+    /// Move a trashed file out to <dest>/<original filename>
+    Restore {
+        /// Hash (or unique hash prefix) shown by `trash ls`
+        hash: String,
+        /// Destination directory
+        dest: PathBuf,
+    },
+    /// Delete trashed files past the retention window
+    Purge {
+        /// Delete everything in the trash, regardless of age
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show or set how many days trashed files are kept
+    Retention {
+        /// New retention in days (0 deletes on the next pack)
+        days: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum IgnoreAction {
+    /// Add a pattern, e.g. `@eaDir/` or `*.lrdata/`
+    Add {
+        /// Gitignore-style pattern
+        pattern: String,
+    },
+    /// Remove a pattern
+    Rm {
+        /// Pattern to remove
+        pattern: String,
+    },
+    /// List global patterns
+    Ls,
+}
+
 fn default_catalog_path() -> String {
     dirs_path().to_string_lossy().to_string()
 }
@@ -70,17 +250,129 @@ fn dirs_path() -> PathBuf {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let format = cli.format;
+    // Unpacking an explicit pack reads its manifest only — no catalog required
+    if let Commands::Unpack {
+        dest,
+        from: Some(pack),
+        mode,
+    } = &cli.command
+    {
+        return commands::unpack::run(PackSource::Path(pack), dest, *mode, format);
+    }
+
     let catalog_path = PathBuf::from(&cli.catalog);
     let mut vault = Vault::open(&catalog_path)?;
 
     match cli.command {
-        Commands::Add { path } => commands::sources::add(&vault, path)?,
-        Commands::Rm { path } => commands::sources::rm(&vault, path)?,
-        Commands::Scan => commands::sources::scan(&mut vault)?,
-        Commands::Status => commands::status::run(&vault)?,
-        Commands::Ls { dupes, id } => commands::ls::run(&vault, dupes, id)?,
-        Commands::Pack { path } => commands::pack::run(&mut vault, path)?,
-        Commands::Export { path, quality } => commands::export::run(&mut vault, &path, quality)?,
+        Commands::Add { path, priority } => {
+            commands::sources::add(&mut vault, path, priority, format)?
+        }
+        Commands::Rm { path } => commands::sources::rm(&vault, path, format)?,
+        Commands::Scan { profile } => {
+            commands::sources::scan(&mut vault, profile.as_deref(), format)?
+        }
+        Commands::Status => commands::status::run(&vault, format)?,
+        Commands::Ls {
+            dupes,
+            id,
+            filter,
+            sort,
+            limit,
+        } => {
+            let query = (filter.is_some() || !sort.is_empty() || limit.is_some()).then_some(Query {
+                filter,
+                sort,
+                limit,
+            });
+            commands::ls::run(&vault, dupes, id, query, format)?
+        }
+        Commands::Explain { id } => commands::explain::run(&vault, id, format)?,
+        Commands::Split { a, b, undo } => {
+            commands::overrides::split(&mut vault, &a, &b, undo, format)?
+        }
+        Commands::Merge { a, b, undo } => {
+            commands::overrides::merge(&mut vault, &a, &b, undo, format)?
+        }
+        Commands::Pin { path } => commands::pin::pin(&mut vault, &path, format)?,
+        Commands::Unpin { path } => commands::pin::unpin(&mut vault, &path, format)?,
+        Commands::Ranking { policy } => {
+            commands::ranking::run(&mut vault, policy.as_deref(), format)?
+        }
+        Commands::Pack {
+            path,
+            views,
+            no_views,
+            policy,
+            filter,
+            dry_run,
+        } => {
+            if dry_run {
+                commands::pack::dry_run(&vault, filter.as_ref(), format)?
+            } else {
+                let views = (views || no_views).then_some(views);
+                commands::pack::run(
+                    &mut vault,
+                    path,
+                    views,
+                    policy.as_deref(),
+                    filter.as_ref(),
+                    format,
+                )?
+            }
+        }
+        Commands::Export {
+            path,
+            encoder,
+            quality,
+            filter,
+            dry_run,
+        } => {
+            if dry_run {
+                commands::export::dry_run(&vault, &path, &encoder, filter.as_ref(), format)?
+            } else {
+                let filter = filter.as_ref();
+                commands::export::run(&mut vault, &path, &encoder, quality, filter, format)?
+            }
+        }
+        Commands::Dedupe {
+            quarantine,
+            undo,
+            link,
+            min_confidence,
+            dry_run,
+        } => match (quarantine, undo) {
+            (Some(dest), _) if dry_run => {
+                commands::plan::print(&vault.plan_quarantine(&dest, min_confidence)?, format)?
+            }
+            (Some(dest), _) => {
+                commands::dedupe::quarantine(&mut vault, &dest, min_confidence, format)?
+            }
+            (_, Some(journal)) => commands::dedupe::undo(&vault, &journal, format)?,
+            _ if link && dry_run => commands::plan::print(&vault.plan_link()?, format)?,
+            _ if link => commands::dedupe::link(&vault, format)?,
+            _ => unreachable!("clap requires --quarantine, --undo or --link"),
+        },
+        Commands::Verify { sample } => commands::verify::run(&vault, sample, format)?,
+        Commands::Repair => commands::repair::run(&vault, format)?,
+        Commands::Unpack { dest, mode, .. } => {
+            commands::unpack::run(PackSource::Vault(&vault), &dest, mode, format)?
+        }
+        Commands::Ignore { action } => match action {
+            IgnoreAction::Add { pattern } => commands::ignore::add(&vault, &pattern, format)?,
+            IgnoreAction::Rm { pattern } => commands::ignore::rm(&vault, &pattern, format)?,
+            IgnoreAction::Ls => commands::ignore::ls(&vault, format)?,
+        },
+        Commands::Trash { action } => match action {
+            TrashAction::Ls => commands::trash::ls(&vault, format)?,
+            TrashAction::Restore { hash, dest } => {
+                commands::trash::restore(&vault, &hash, &dest, format)?
+            }
+            TrashAction::Purge { all } => commands::trash::purge(&vault, all, format)?,
+            TrashAction::Retention { days } => {
+                commands::trash::retention(&vault, days, format)?
+            }
+        },
     }
 
     Ok(())
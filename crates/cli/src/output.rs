@@ -0,0 +1,212 @@
+//! Machine-readable output selected with the global `--format` flag.
+//!
+//! `table` is the human rendering each command draws itself. The other formats
+//! print serde records on stdout: `json` as one pretty document, `jsonl` as one
+//! object per line, `csv` as a header row plus one row per record, with nested
+//! fields flattened to dotted columns (`exif.camera_model`).
+//!
+//! Progress events are single JSON lines tagged with `event`. With `jsonl` they
+//! stream on stdout ahead of the result; with `json` and `csv` they go to stderr
+//! so stdout stays one parseable document.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Tables and progress bars for humans
+    #[default]
+    Table,
+    /// One JSON document
+    Json,
+    /// One JSON object per line, progress events included
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl Format {
+    pub fn is_table(self) -> bool {
+        self == Format::Table
+    }
+
+    /// Print a list of records: a JSON array, one line per record, or CSV rows.
+    pub fn records<T: Serialize>(self, records: &[T]) -> Result<()> {
+        let values = records
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        let mut out = std::io::stdout().lock();
+        match self {
+            Format::Table | Format::Json => {
+                writeln!(out, "{}", serde_json::to_string_pretty(&values)?)?
+            }
+            Format::Jsonl => {
+                for value in &values {
+                    writeln!(out, "{value}")?;
+                }
+            }
+            Format::Csv => out.write_all(to_csv(&values).as_bytes())?,
+        }
+        Ok(())
+    }
+
+    /// Print a command's single result: a JSON object, one line, or a one-row CSV.
+    pub fn document<T: Serialize>(self, document: &T) -> Result<()> {
+        if self == Format::Json {
+            println!("{}", serde_json::to_string_pretty(document)?);
+            return Ok(());
+        }
+        self.records(std::slice::from_ref(document))
+    }
+
+    /// Report a progress event. Table output draws progress bars instead.
+    pub fn event<T: Serialize>(self, event: &T) {
+        let Ok(line) = serde_json::to_string(event) else {
+            return;
+        };
+        match self {
+            Format::Table => {}
+            Format::Jsonl => println!("{line}"),
+            Format::Json | Format::Csv => eprintln!("{line}"),
+        }
+    }
+}
+
+/// Render records as CSV. Columns are the union of every record's flattened
+/// keys, in first-seen order; missing cells are empty. No records, no output.
+fn to_csv(values: &[Value]) -> String {
+    if values.is_empty() {
+        return String::new();
+    }
+    let rows: Vec<Vec<(String, String)>> = values
+        .iter()
+        .map(|value| {
+            let mut cells = Vec::new();
+            flatten("", value, &mut cells);
+            cells
+        })
+        .collect();
+
+    let mut header: Vec<&str> = Vec::new();
+    for row in &rows {
+        for (key, _) in row {
+            if !header.contains(&key.as_str()) {
+                header.push(key);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    push_row(&mut out, header.iter().copied());
+    for row in &rows {
+        let cells: HashMap<&str, &str> =
+            row.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        push_row(
+            &mut out,
+            header.iter().map(|key| cells.get(key).copied().unwrap_or("")),
+        );
+    }
+    out
+}
+
+/// Flatten a JSON value into `(column, cell)` pairs. Objects become dotted
+/// columns, arrays of scalars are joined with `;`, other arrays stay JSON.
+fn flatten(prefix: &str, value: &Value, cells: &mut Vec<(String, String)>) {
+    let column = if prefix.is_empty() { "value" } else { prefix };
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, cells);
+            }
+        }
+        Value::Array(items) if items.iter().all(|i| !i.is_object() && !i.is_array()) => {
+            let joined: Vec<String> = items.iter().map(scalar).collect();
+            cells.push((column.to_string(), joined.join(";")));
+        }
+        Value::Array(_) => cells.push((column.to_string(), value.to_string())),
+        _ => cells.push((column.to_string(), scalar(value))),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn push_row<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    let fields: Vec<String> = fields.map(escape).collect();
+    out.push_str(&fields.join(","));
+    out.push('\n');
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break (RFC 4180).
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_csv_flattens_nested_objects_to_dotted_columns() {
+        let csv = to_csv(&[json!({"id": 1, "exif": {"camera_model": "ILCE-7M3", "width": 6000}})]);
+        assert_eq!(csv, "id,exif.camera_model,exif.width\n1,ILCE-7M3,6000\n");
+    }
+
+    #[test]
+    fn test_csv_header_is_union_of_record_keys() {
+        let csv = to_csv(&[
+            json!({"action": "copy", "source": "/a.jpg", "bytes": 10}),
+            json!({"action": "skip", "path": "/b.jpg"}),
+        ]);
+        assert_eq!(
+            csv,
+            "action,source,bytes,path\ncopy,/a.jpg,10,\nskip,,,/b.jpg\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_joins_scalar_arrays_and_blanks_nulls() {
+        let csv = to_csv(&[json!({"missing": ["/a", "/b"], "date": null})]);
+        assert_eq!(csv, "missing,date\n/a;/b,\n");
+    }
+
+    #[test]
+    fn test_csv_keeps_arrays_of_objects_as_json() {
+        let csv = to_csv(&[json!({"moved": [{"sha256": "ab"}]})]);
+        assert_eq!(csv, "moved\n\"[{\"\"sha256\"\":\"\"ab\"\"}]\"\n");
+    }
+
+    #[test]
+    fn test_csv_of_no_records_is_empty() {
+        assert_eq!(to_csv(&[]), "");
+    }
+
+    #[test]
+    fn test_csv_escapes_commas_quotes_and_newlines() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -1,12 +1,15 @@
 pub mod schema;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use rusqlite::{params, Connection};
 
 use crate::domain::*;
 use crate::error::{Error, Result};
+use crate::matching::MatchGroup;
+use crate::query::{Filter, Query};
+use crate::scanner::moves::VanishedPhoto;
 
 /// SQLite-backed catalog for photo metadata and duplicate groups.
 pub struct Catalog {
@@ -65,19 +68,25 @@ impl Catalog {
             id,
             path: canonical,
             last_scanned: None,
+            volume_id: None,
+            online: true,
+            priority: 0,
         })
     }
 
     pub fn list_sources(&self) -> Result<Vec<Source>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, path, last_scanned FROM sources")?;
+            .prepare("SELECT id, path, last_scanned, volume_id, online, priority FROM sources")?;
         let sources = stmt
             .query_map([], |row| {
                 Ok(Source {
                     id: row.get(0)?,
                     path: PathBuf::from(row.get::<_, String>(1)?),
                     last_scanned: row.get(2)?,
+                    volume_id: row.get(3)?,
+                    online: row.get(4)?,
+                    priority: row.get(5)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -92,6 +101,33 @@ impl Catalog {
         Ok(())
     }
 
+    /// Record the volume identity of a source (see [`crate::volume`]).
+    pub fn set_source_volume_id(&self, source_id: i64, volume_id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sources SET volume_id = ?1 WHERE id = ?2",
+            params![volume_id, source_id],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a source online (volume present) or offline (volume absent).
+    pub fn set_source_online(&self, source_id: i64, online: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sources SET online = ?1 WHERE id = ?2",
+            params![online, source_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set the ranking priority of a source (higher wins).
+    pub fn set_source_priority(&self, source_id: i64, priority: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sources SET priority = ?1 WHERE id = ?2",
+            params![priority, source_id],
+        )?;
+        Ok(())
+    }
+
     /// Remove a source and all its photos from the catalog.
     /// Also cleans up group_members and empty duplicate_groups.
     pub fn remove_source(&self, path: &Path) -> Result<(Source, usize)> {
@@ -103,13 +139,16 @@ impl Catalog {
         let source: Source = self
             .conn
             .query_row(
-                "SELECT id, path, last_scanned FROM sources WHERE path = ?1",
+                "SELECT id, path, last_scanned, volume_id, online, priority FROM sources WHERE path = ?1",
                 params![path_str.as_ref()],
                 |row| {
                     Ok(Source {
                         id: row.get(0)?,
                         path: PathBuf::from(row.get::<_, String>(1)?),
                         last_scanned: row.get(2)?,
+                        volume_id: row.get(3)?,
+                        online: row.get(4)?,
+                        priority: row.get(5)?,
                     })
                 },
             )
@@ -221,6 +260,56 @@ impl Catalog {
         Ok(total_removed)
     }
 
+    /// Look up `(id, path, size, mtime, sha256)` of the photos at the given paths.
+    pub fn get_identities_by_paths(
+        &self,
+        paths: &[&Path],
+    ) -> Result<Vec<VanishedPhoto>> {
+        let path_strs: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+        let mut result = Vec::new();
+        // Query in batches to avoid SQLite variable limits
+        for chunk in path_strs.chunks(500) {
+            let placeholders: Vec<String> = (0..chunk.len()).map(|i| format!("?{}", i + 1)).collect();
+            let sql = format!(
+                "SELECT id, path, size, mtime, sha256 FROM photos WHERE path IN ({})",
+                placeholders.join(", ")
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let params: Vec<&dyn rusqlite::types::ToSql> = chunk
+                .iter()
+                .map(|s| s as &dyn rusqlite::types::ToSql)
+                .collect();
+            let rows = stmt
+                .query_map(params.as_slice(), |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        PathBuf::from(row.get::<_, String>(1)?),
+                        row.get::<_, i64>(2)? as u64,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            result.extend(rows);
+        }
+        Ok(result)
+    }
+
+    /// Point existing photo rows at new paths (files moved on disk), keeping
+    /// their ids and group membership. Returns the number of rows updated.
+    pub fn move_photos(&mut self, moves: &[(i64, &Path)]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut moved = 0usize;
+        for (id, path) in moves {
+            moved += tx.execute(
+                "UPDATE photos SET path = ?1 WHERE id = ?2",
+                params![path.to_string_lossy().as_ref(), id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(moved)
+    }
+
     // ── Photos ───────────────────────────────────────────────────────
 
     pub fn upsert_photo(&self, photo: &PhotoFile) -> Result<i64> {
@@ -241,8 +330,8 @@ impl Catalog {
             self.conn.execute(
                 "UPDATE photos SET source_id=?1, size=?2, format=?3, sha256=?4, phash=?5, dhash=?6, mtime=?7,
                  exif_date=?8, exif_camera_make=?9, exif_camera_model=?10, exif_gps_lat=?11, exif_gps_lon=?12,
-                 exif_width=?13, exif_height=?14
-                 WHERE id=?15",
+                 exif_width=?13, exif_height=?14, exif_content_id=?15
+                 WHERE id=?16",
                 params![
                     photo.source_id,
                     photo.size as i64,
@@ -258,6 +347,7 @@ impl Catalog {
                     photo.exif.as_ref().and_then(|e| e.gps_lon),
                     photo.exif.as_ref().and_then(|e| e.width),
                     photo.exif.as_ref().and_then(|e| e.height),
+                    photo.exif.as_ref().and_then(|e| e.content_id.clone()),
                     id,
                 ],
             )?;
@@ -265,8 +355,9 @@ impl Catalog {
         } else {
             self.conn.execute(
                 "INSERT INTO photos (source_id, path, size, format, sha256, phash, dhash, mtime,
-                 exif_date, exif_camera_make, exif_camera_model, exif_gps_lat, exif_gps_lon, exif_width, exif_height)
-                 VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15)",
+                 exif_date, exif_camera_make, exif_camera_model, exif_gps_lat, exif_gps_lon, exif_width, exif_height,
+                 exif_content_id)
+                 VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16)",
                 params![
                     photo.source_id,
                     path_str.as_ref(),
@@ -283,6 +374,7 @@ impl Catalog {
                     photo.exif.as_ref().and_then(|e| e.gps_lon),
                     photo.exif.as_ref().and_then(|e| e.width),
                     photo.exif.as_ref().and_then(|e| e.height),
+                    photo.exif.as_ref().and_then(|e| e.content_id.clone()),
                 ],
             )?;
             Ok(self.conn.last_insert_rowid())
@@ -310,8 +402,8 @@ impl Catalog {
                 tx.execute(
                     "UPDATE photos SET source_id=?1, size=?2, format=?3, sha256=?4, phash=?5, dhash=?6, mtime=?7,
                      exif_date=?8, exif_camera_make=?9, exif_camera_model=?10, exif_gps_lat=?11, exif_gps_lon=?12,
-                     exif_width=?13, exif_height=?14
-                     WHERE id=?15",
+                     exif_width=?13, exif_height=?14, exif_content_id=?15
+                     WHERE id=?16",
                     params![
                         photo.source_id,
                         photo.size as i64,
@@ -327,6 +419,7 @@ impl Catalog {
                         photo.exif.as_ref().and_then(|e| e.gps_lon),
                         photo.exif.as_ref().and_then(|e| e.width),
                         photo.exif.as_ref().and_then(|e| e.height),
+                        photo.exif.as_ref().and_then(|e| e.content_id.clone()),
                         id,
                     ],
                 )?;
@@ -334,8 +427,9 @@ impl Catalog {
             } else {
                 tx.execute(
                     "INSERT INTO photos (source_id, path, size, format, sha256, phash, dhash, mtime,
-                     exif_date, exif_camera_make, exif_camera_model, exif_gps_lat, exif_gps_lon, exif_width, exif_height)
-                     VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15)",
+                     exif_date, exif_camera_make, exif_camera_model, exif_gps_lat, exif_gps_lon, exif_width, exif_height,
+                     exif_content_id)
+                     VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16)",
                     params![
                         photo.source_id,
                         path_str.as_ref(),
@@ -352,6 +446,7 @@ impl Catalog {
                         photo.exif.as_ref().and_then(|e| e.gps_lon),
                         photo.exif.as_ref().and_then(|e| e.width),
                         photo.exif.as_ref().and_then(|e| e.height),
+                        photo.exif.as_ref().and_then(|e| e.content_id.clone()),
                     ],
                 )?;
                 ids.push(tx.last_insert_rowid());
@@ -423,58 +518,67 @@ impl Catalog {
         Ok(result)
     }
 
+    /// Look up the paths of all photos with the given SHA-256 values.
+    /// Returns a map of sha256 → paths (ordered by path) for hashes present in the catalog.
+    pub fn get_paths_by_sha256s(&self, sha256s: &[&str]) -> Result<HashMap<String, Vec<PathBuf>>> {
+        let mut result: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        // Query in batches to avoid SQLite variable limits
+        for chunk in sha256s.chunks(500) {
+            let placeholders: Vec<String> = (0..chunk.len()).map(|i| format!("?{}", i + 1)).collect();
+            let sql = format!(
+                "SELECT sha256, path FROM photos WHERE sha256 IN ({}) ORDER BY path",
+                placeholders.join(", ")
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let params: Vec<&dyn rusqlite::types::ToSql> = chunk
+                .iter()
+                .map(|s| s as &dyn rusqlite::types::ToSql)
+                .collect();
+            let rows = stmt
+                .query_map(params.as_slice(), |row| {
+                    Ok((row.get::<_, String>(0)?, PathBuf::from(row.get::<_, String>(1)?)))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            for (sha, path) in rows {
+                result.entry(sha).or_default().push(path);
+            }
+        }
+        Ok(result)
+    }
+
     pub fn list_all_photos(&self) -> Result<Vec<PhotoFile>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, source_id, path, size, format, sha256, phash, dhash, mtime,
-             exif_date, exif_camera_make, exif_camera_model, exif_gps_lat, exif_gps_lon,
-             exif_width, exif_height
-             FROM photos",
-        )?;
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {PHOTO_COLUMNS} FROM photos p"))?;
         let photos = stmt
-            .query_map([], |row| {
-                let exif_date: Option<String> = row.get(9)?;
-                let exif_make: Option<String> = row.get(10)?;
-                let exif_model: Option<String> = row.get(11)?;
-                let exif_lat: Option<f64> = row.get(12)?;
-                let exif_lon: Option<f64> = row.get(13)?;
-                let exif_w: Option<u32> = row.get(14)?;
-                let exif_h: Option<u32> = row.get(15)?;
-
-                let exif = if exif_date.is_some()
-                    || exif_make.is_some()
-                    || exif_model.is_some()
-                    || exif_lat.is_some()
-                {
-                    Some(ExifData {
-                        date: exif_date,
-                        camera_make: exif_make,
-                        camera_model: exif_model,
-                        gps_lat: exif_lat,
-                        gps_lon: exif_lon,
-                        width: exif_w,
-                        height: exif_h,
-                    })
-                } else {
-                    None
-                };
+            .query_map([], row_to_photo)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(photos)
+    }
 
-                Ok(PhotoFile {
-                    id: row.get(0)?,
-                    source_id: row.get(1)?,
-                    path: PathBuf::from(row.get::<_, String>(2)?),
-                    size: row.get::<_, i64>(3)? as u64,
-                    format: parse_format(&row.get::<_, String>(4)?),
-                    sha256: row.get(5)?,
-                    phash: row.get::<_, Option<i64>>(6)?.map(|v| v as u64),
-                    dhash: row.get::<_, Option<i64>>(7)?.map(|v| v as u64),
-                    exif,
-                    mtime: row.get(8)?,
-                })
-            })?
+    /// Photos matching a query's filter, in its sort order and up to its limit.
+    pub fn query_photos(&self, query: &Query) -> Result<Vec<PhotoFile>> {
+        let (clauses, values) = query.sql();
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {PHOTO_COLUMNS} FROM photos p{clauses}"))?;
+        let photos = stmt
+            .query_map(rusqlite::params_from_iter(values), row_to_photo)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(photos)
     }
 
+    /// IDs of the photos matching a filter.
+    pub fn matching_photo_ids(&self, filter: &Filter) -> Result<HashSet<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT p.id FROM photos p WHERE {}", filter.sql()))?;
+        let ids = stmt
+            .query_map(rusqlite::params_from_iter(filter.params()), |row| row.get(0))?
+            .collect::<std::result::Result<HashSet<_>, _>>()?;
+        Ok(ids)
+    }
+
     pub fn count_photos(&self) -> Result<usize> {
         let count: i64 = self
             .conn
@@ -503,6 +607,67 @@ impl Catalog {
         Ok((photos, groups, duplicates))
     }
 
+    // ── Sidecars ─────────────────────────────────────────────────────
+
+    /// Sidecars bound to photos of a source, used to reuse hashes of unchanged files.
+    pub fn get_sidecars_for_source(&self, source_id: i64) -> Result<Vec<Sidecar>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.photo_id, s.path, s.kind, s.sha256, s.size, s.mtime
+             FROM sidecars s JOIN photos p ON p.id = s.photo_id
+             WHERE p.source_id = ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![source_id], row_to_sidecar)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    /// Replace every sidecar of a source's photos with `sidecars` (scanned sidecar,
+    /// sha256). Sidecars whose photo is not in the catalog are skipped.
+    /// Returns the number of sidecars stored.
+    pub fn replace_sidecars_for_source(
+        &mut self,
+        source_id: i64,
+        sidecars: &[(ScannedSidecar, String)],
+    ) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM sidecars WHERE photo_id IN (SELECT id FROM photos WHERE source_id = ?1)",
+            params![source_id],
+        )?;
+        let mut stored = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO sidecars (photo_id, kind, path, sha256, size, mtime)
+                 SELECT id, ?2, ?3, ?4, ?5, ?6 FROM photos WHERE path = ?1 AND source_id = ?7",
+            )?;
+            for (sidecar, sha256) in sidecars {
+                stored += stmt.execute(params![
+                    sidecar.photo_path.to_string_lossy().as_ref(),
+                    sidecar.kind.as_str(),
+                    sidecar.path.to_string_lossy().as_ref(),
+                    sha256,
+                    sidecar.size as i64,
+                    sidecar.mtime,
+                    source_id,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    /// All sidecars in the catalog, ordered by photo then kind.
+    pub fn list_sidecars(&self) -> Result<Vec<Sidecar>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_id, path, kind, sha256, size, mtime FROM sidecars ORDER BY photo_id, kind",
+        )?;
+        let rows = stmt
+            .query_map([], row_to_sidecar)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+
     // ── Duplicate Groups ─────────────────────────────────────────────
 
     pub fn clear_groups(&self) -> Result<()> {
@@ -527,28 +692,46 @@ impl Catalog {
         Ok(group_id)
     }
 
-    /// Clear existing groups and insert new ones in a single transaction.
-    pub fn replace_groups_batch(&mut self, groups: &[(i64, Confidence, Vec<i64>)]) -> Result<Vec<i64>> {
+    /// Clear existing groups and insert new ones, with their match evidence,
+    /// in a single transaction. Each entry is `(source_of_truth_id, group)`.
+    pub fn replace_groups_batch(&mut self, groups: &[(i64, MatchGroup)]) -> Result<Vec<i64>> {
         let tx = self.conn.transaction()?;
 
+        tx.execute("DELETE FROM match_evidence", [])?;
         tx.execute("DELETE FROM group_members", [])?;
         tx.execute("DELETE FROM duplicate_groups", [])?;
 
         let mut group_ids = Vec::with_capacity(groups.len());
 
-        for (source_of_truth_id, confidence, member_ids) in groups {
+        for (source_of_truth_id, group) in groups {
             tx.execute(
                 "INSERT INTO duplicate_groups (source_of_truth_id, confidence) VALUES (?1, ?2)",
-                params![source_of_truth_id, confidence.as_str()],
+                params![source_of_truth_id, group.confidence.as_str()],
             )?;
             let group_id = tx.last_insert_rowid();
 
-            for &photo_id in member_ids {
+            for &photo_id in &group.member_ids {
                 tx.execute(
                     "INSERT INTO group_members (group_id, photo_id) VALUES (?1, ?2)",
                     params![group_id, photo_id],
                 )?;
             }
+            for e in &group.evidence {
+                tx.execute(
+                    "INSERT INTO match_evidence
+                     (group_id, photo_a, photo_b, phase, phash_distance, dhash_distance, exif_key)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        group_id,
+                        e.photo_a,
+                        e.photo_b,
+                        e.phase.as_str(),
+                        e.phash_distance,
+                        e.dhash_distance,
+                        e.exif_key
+                    ],
+                )?;
+            }
             group_ids.push(group_id);
         }
 
@@ -562,7 +745,7 @@ impl Catalog {
             "SELECT dg.id, dg.source_of_truth_id, dg.confidence,
                     p.id, p.source_id, p.path, p.size, p.format, p.sha256, p.phash, p.dhash, p.mtime,
                     p.exif_date, p.exif_camera_make, p.exif_camera_model, p.exif_gps_lat, p.exif_gps_lon,
-                    p.exif_width, p.exif_height
+                    p.exif_width, p.exif_height, p.exif_content_id
              FROM duplicate_groups dg
              JOIN group_members gm ON gm.group_id = dg.id
              JOIN photos p ON p.id = gm.photo_id
@@ -578,11 +761,13 @@ impl Catalog {
                 let exif_lon: Option<f64> = row.get(16)?;
                 let exif_w: Option<u32> = row.get(17)?;
                 let exif_h: Option<u32> = row.get(18)?;
+                let exif_content_id: Option<String> = row.get(19)?;
 
                 let exif = if exif_date.is_some()
                     || exif_make.is_some()
                     || exif_model.is_some()
                     || exif_lat.is_some()
+                    || exif_content_id.is_some()
                 {
                     Some(ExifData {
                         date: exif_date,
@@ -592,6 +777,7 @@ impl Catalog {
                         gps_lon: exif_lon,
                         width: exif_w,
                         height: exif_h,
+                        content_id: exif_content_id,
                     })
                 } else {
                     None
@@ -666,6 +852,102 @@ impl Catalog {
         })
     }
 
+    /// Evidence edges recorded for a group at its last scan, in phase order.
+    pub fn get_group_evidence(&self, group_id: i64) -> Result<Vec<MatchEvidence>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_a, photo_b, phase, phash_distance, dhash_distance, exif_key
+             FROM match_evidence WHERE group_id = ?1 ORDER BY rowid",
+        )?;
+        let rows = stmt
+            .query_map(params![group_id], |row| {
+                let phase: String = row.get(2)?;
+                let Some(phase) = MatchPhase::parse(&phase) else {
+                    return Ok(None);
+                };
+                Ok(Some(MatchEvidence {
+                    photo_a: row.get(0)?,
+                    photo_b: row.get(1)?,
+                    phase,
+                    phash_distance: row.get(3)?,
+                    dhash_distance: row.get(4)?,
+                    exif_key: row.get(5)?,
+                }))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut evidence: Vec<MatchEvidence> = rows.into_iter().flatten().collect();
+        evidence.sort_by_key(|e| (e.phase as u8, e.photo_a, e.photo_b));
+        Ok(evidence)
+    }
+
+    // ── Match overrides ──────────────────────────────────────────────
+
+    /// Record a manual decision for a pair, replacing any earlier one.
+    pub fn set_match_override(&self, o: &MatchOverride) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO match_overrides (sha_a, sha_b, kind) VALUES (?1, ?2, ?3)
+             ON CONFLICT(sha_a, sha_b) DO UPDATE SET kind = excluded.kind",
+            params![o.sha_a, o.sha_b, o.kind.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Remove the decision for a pair. Returns `false` if there was none.
+    pub fn remove_match_override(&self, sha_1: &str, sha_2: &str) -> Result<bool> {
+        let key = MatchOverride::new(sha_1, sha_2, OverrideKind::NeverMatch);
+        let removed = self.conn.execute(
+            "DELETE FROM match_overrides WHERE sha_a = ?1 AND sha_b = ?2",
+            params![key.sha_a, key.sha_b],
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub fn list_match_overrides(&self) -> Result<Vec<MatchOverride>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT sha_a, sha_b, kind FROM match_overrides ORDER BY sha_a, sha_b")?;
+        let rows = stmt
+            .query_map([], |row| {
+                let kind: String = row.get(2)?;
+                let Some(kind) = OverrideKind::parse(&kind) else {
+                    return Ok(None);
+                };
+                Ok(Some(MatchOverride {
+                    sha_a: row.get(0)?,
+                    sha_b: row.get(1)?,
+                    kind,
+                }))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    // ── Pins ─────────────────────────────────────────────────────────
+
+    /// Pin a hash as the source of truth of whatever group holds it.
+    pub fn add_pin(&self, sha256: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO pins (sha256) VALUES (?1)",
+            params![sha256],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a pin. Returns `false` if the hash was not pinned.
+    pub fn remove_pin(&self, sha256: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM pins WHERE sha256 = ?1", params![sha256])?;
+        Ok(removed > 0)
+    }
+
+    pub fn list_pins(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT sha256 FROM pins")?;
+        let pins = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<HashSet<_>, _>>()?;
+        Ok(pins)
+    }
+
     pub fn count_groups(&self) -> Result<usize> {
         let count: i64 = self
             .conn
@@ -690,7 +972,7 @@ impl Catalog {
         let mut stmt = self.conn.prepare(
             "SELECT p.id, p.source_id, p.path, p.size, p.format, p.sha256, p.phash, p.dhash, p.mtime,
              p.exif_date, p.exif_camera_make, p.exif_camera_model, p.exif_gps_lat, p.exif_gps_lon,
-             p.exif_width, p.exif_height
+             p.exif_width, p.exif_height, p.exif_content_id
              FROM photos p
              JOIN group_members gm ON gm.photo_id = p.id
              WHERE gm.group_id = ?1",
@@ -704,11 +986,13 @@ impl Catalog {
                 let exif_lon: Option<f64> = row.get(13)?;
                 let exif_w: Option<u32> = row.get(14)?;
                 let exif_h: Option<u32> = row.get(15)?;
+                let exif_content_id: Option<String> = row.get(16)?;
 
                 let exif = if exif_date.is_some()
                     || exif_make.is_some()
                     || exif_model.is_some()
                     || exif_lat.is_some()
+                    || exif_content_id.is_some()
                 {
                     Some(ExifData {
                         date: exif_date,
@@ -718,6 +1002,7 @@ impl Catalog {
                         gps_lon: exif_lon,
                         width: exif_w,
                         height: exif_h,
+                        content_id: exif_content_id,
                     })
                 } else {
                     None
@@ -779,6 +1064,71 @@ impl Catalog {
     }
 }
 
+/// Map a `sidecars` row; `None` for kinds this version does not know.
+/// Columns read by [`row_to_photo`], over `photos p`.
+const PHOTO_COLUMNS: &str = "p.id, p.source_id, p.path, p.size, p.format, p.sha256, p.phash, p.dhash,
+     p.mtime, p.exif_date, p.exif_camera_make, p.exif_camera_model, p.exif_gps_lat,
+     p.exif_gps_lon, p.exif_width, p.exif_height, p.exif_content_id";
+
+fn row_to_photo(row: &rusqlite::Row) -> rusqlite::Result<PhotoFile> {
+    let exif_date: Option<String> = row.get(9)?;
+    let exif_make: Option<String> = row.get(10)?;
+    let exif_model: Option<String> = row.get(11)?;
+    let exif_lat: Option<f64> = row.get(12)?;
+    let exif_lon: Option<f64> = row.get(13)?;
+    let exif_w: Option<u32> = row.get(14)?;
+    let exif_h: Option<u32> = row.get(15)?;
+    let exif_content_id: Option<String> = row.get(16)?;
+
+    let exif = if exif_date.is_some()
+        || exif_make.is_some()
+        || exif_model.is_some()
+        || exif_lat.is_some()
+        || exif_content_id.is_some()
+    {
+        Some(ExifData {
+            date: exif_date,
+            camera_make: exif_make,
+            camera_model: exif_model,
+            gps_lat: exif_lat,
+            gps_lon: exif_lon,
+            width: exif_w,
+            height: exif_h,
+            content_id: exif_content_id,
+        })
+    } else {
+        None
+    };
+
+    Ok(PhotoFile {
+        id: row.get(0)?,
+        source_id: row.get(1)?,
+        path: PathBuf::from(row.get::<_, String>(2)?),
+        size: row.get::<_, i64>(3)? as u64,
+        format: parse_format(&row.get::<_, String>(4)?),
+        sha256: row.get(5)?,
+        phash: row.get::<_, Option<i64>>(6)?.map(|v| v as u64),
+        dhash: row.get::<_, Option<i64>>(7)?.map(|v| v as u64),
+        exif,
+        mtime: row.get(8)?,
+    })
+}
+
+fn row_to_sidecar(row: &rusqlite::Row) -> rusqlite::Result<Option<Sidecar>> {
+    let kind: String = row.get(2)?;
+    let Some(kind) = SidecarKind::parse(&kind) else {
+        return Ok(None);
+    };
+    Ok(Some(Sidecar {
+        photo_id: row.get(0)?,
+        path: PathBuf::from(row.get::<_, String>(1)?),
+        kind,
+        sha256: row.get(3)?,
+        size: row.get::<_, i64>(4)? as u64,
+        mtime: row.get(5)?,
+    }))
+}
+
 fn parse_format(s: &str) -> PhotoFormat {
     match s {
         "CR2" => PhotoFormat::Cr2,
@@ -793,6 +1143,8 @@ fn parse_format(s: &str) -> PhotoFormat {
         "PNG" => PhotoFormat::Png,
         "HEIC" => PhotoFormat::Heic,
         "WebP" => PhotoFormat::Webp,
+        "MOV" => PhotoFormat::Mov,
+        "MP4" => PhotoFormat::Mp4,
         _ => PhotoFormat::Jpeg,
     }
 }
@@ -874,6 +1226,28 @@ mod tests {
         assert_eq!(sources[0].last_scanned, Some(1700000000));
     }
 
+    #[test]
+    fn test_source_volume_id_and_online() {
+        let (catalog, source, _tmp) = make_catalog_with_source();
+        assert!(source.online);
+        assert!(source.volume_id.is_none());
+
+        catalog.set_source_volume_id(source.id, "vol-1").unwrap();
+        catalog.set_source_online(source.id, false).unwrap();
+        let sources = catalog.list_sources().unwrap();
+        assert_eq!(sources[0].volume_id.as_deref(), Some("vol-1"));
+        assert!(!sources[0].online);
+    }
+
+    #[test]
+    fn test_source_priority() {
+        let (catalog, source, _tmp) = make_catalog_with_source();
+        assert_eq!(source.priority, 0);
+
+        catalog.set_source_priority(source.id, 5).unwrap();
+        assert_eq!(catalog.list_sources().unwrap()[0].priority, 5);
+    }
+
     #[test]
     fn test_multiple_sources() {
         let tmp = tempfile::tempdir().unwrap();
@@ -962,6 +1336,114 @@ mod tests {
         assert_eq!(photos[0].size, 2048);
     }
 
+    #[test]
+    fn test_move_photos_keeps_id_and_group() {
+        let (mut catalog, source, _tmp) = make_catalog_with_source();
+        let a = catalog.upsert_photo(&make_photo(source.id, "/tmp/a.jpg", "aaa")).unwrap();
+        let b = catalog.upsert_photo(&make_photo(source.id, "/tmp/b.jpg", "aaa")).unwrap();
+        let group = catalog.insert_group(a, Confidence::Certain, &[a, b]).unwrap();
+
+        let identities = catalog
+            .get_identities_by_paths(&[Path::new("/tmp/a.jpg")])
+            .unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].0, a);
+        assert_eq!(identities[0].4, "aaa");
+
+        let moved = catalog
+            .move_photos(&[(a, Path::new("/tmp/sorted/a.jpg"))])
+            .unwrap();
+        assert_eq!(moved, 1);
+
+        let group = catalog.get_group(group).unwrap();
+        let member = group.members.iter().find(|m| m.id == a).unwrap();
+        assert_eq!(member.path, PathBuf::from("/tmp/sorted/a.jpg"));
+        assert_eq!(group.source_of_truth_id, a);
+    }
+
+    #[test]
+    fn test_replace_groups_batch_stores_evidence() {
+        let (mut catalog, source, _tmp) = make_catalog_with_source();
+        let a = catalog.upsert_photo(&make_photo(source.id, "/tmp/a.jpg", "aaa")).unwrap();
+        let b = catalog.upsert_photo(&make_photo(source.id, "/tmp/b.jpg", "aaa")).unwrap();
+        let c = catalog.upsert_photo(&make_photo(source.id, "/tmp/c.heic", "ccc")).unwrap();
+        let evidence = vec![
+            MatchEvidence {
+                photo_a: a,
+                photo_b: c,
+                phase: MatchPhase::Orphan,
+                phash_distance: None,
+                dhash_distance: None,
+                exif_key: Some("2024:01:15 10:00:00|EOS R5".to_string()),
+            },
+            MatchEvidence {
+                photo_a: a,
+                photo_b: b,
+                phase: MatchPhase::Sha256,
+                phash_distance: Some(0),
+                dhash_distance: Some(0),
+                exif_key: None,
+            },
+        ];
+        let group = MatchGroup {
+            member_ids: vec![a, b, c],
+            confidence: Confidence::Certain,
+            evidence: evidence.clone(),
+        };
+        let ids = catalog.replace_groups_batch(&[(a, group)]).unwrap();
+
+        let stored = catalog.get_group_evidence(ids[0]).unwrap();
+        assert_eq!(stored, vec![evidence[1].clone(), evidence[0].clone()]);
+
+        // Removing a photo drops its edges with it
+        catalog.remove_photos_by_paths(&[Path::new("/tmp/c.heic")]).unwrap();
+        assert_eq!(catalog.get_group_evidence(ids[0]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_match_overrides_keyed_by_pair() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        catalog
+            .set_match_override(&MatchOverride::new("bbb", "aaa", OverrideKind::NeverMatch))
+            .unwrap();
+        // Same pair in the other order replaces the decision
+        catalog
+            .set_match_override(&MatchOverride::new("aaa", "bbb", OverrideKind::AlwaysMatch))
+            .unwrap();
+        catalog
+            .set_match_override(&MatchOverride::new("ccc", "aaa", OverrideKind::NeverMatch))
+            .unwrap();
+
+        let overrides = catalog.list_match_overrides().unwrap();
+        assert_eq!(
+            overrides,
+            vec![
+                MatchOverride::new("aaa", "bbb", OverrideKind::AlwaysMatch),
+                MatchOverride::new("aaa", "ccc", OverrideKind::NeverMatch),
+            ]
+        );
+
+        assert!(catalog.remove_match_override("bbb", "aaa").unwrap());
+        assert!(!catalog.remove_match_override("bbb", "aaa").unwrap());
+        assert_eq!(catalog.list_match_overrides().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_pins_add_and_remove() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        catalog.add_pin("aaa").unwrap();
+        catalog.add_pin("aaa").unwrap();
+        catalog.add_pin("bbb").unwrap();
+        assert_eq!(
+            catalog.list_pins().unwrap(),
+            HashSet::from(["aaa".to_string(), "bbb".to_string()])
+        );
+
+        assert!(catalog.remove_pin("aaa").unwrap());
+        assert!(!catalog.remove_pin("aaa").unwrap());
+        assert_eq!(catalog.list_pins().unwrap(), HashSet::from(["bbb".to_string()]));
+    }
+
     #[test]
     fn test_upsert_photo_with_exif() {
         let (catalog, source, _tmp) = make_catalog_with_source();
@@ -974,6 +1456,7 @@ mod tests {
             gps_lon: Some(2.3522),
             width: Some(8192),
             height: Some(5464),
+            content_id: None,
         });
 
         catalog.upsert_photo(&photo).unwrap();
@@ -1007,6 +1490,61 @@ mod tests {
         assert_eq!(catalog.count_photos().unwrap(), 2);
     }
 
+    // ── Sidecar tests ────────────────────────────────────────────
+
+    fn make_sidecar(photo: &str, path: &str, kind: SidecarKind) -> ScannedSidecar {
+        ScannedSidecar {
+            path: PathBuf::from(path),
+            photo_path: PathBuf::from(photo),
+            kind,
+            size: 42,
+            mtime: 7,
+        }
+    }
+
+    #[test]
+    fn test_replace_sidecars_for_source() {
+        let (mut catalog, source, _tmp) = make_catalog_with_source();
+        let id = catalog.upsert_photo(&make_photo(source.id, "/photos/a.cr2", "aaa")).unwrap();
+
+        let stored = catalog
+            .replace_sidecars_for_source(
+                source.id,
+                &[
+                    (make_sidecar("/photos/a.cr2", "/photos/a.xmp", SidecarKind::Xmp), "x1".into()),
+                    (make_sidecar("/photos/gone.cr2", "/photos/gone.xmp", SidecarKind::Xmp), "x2".into()),
+                ],
+            )
+            .unwrap();
+        assert_eq!(stored, 1);
+
+        let sidecars = catalog.list_sidecars().unwrap();
+        assert_eq!(sidecars.len(), 1);
+        assert_eq!(sidecars[0].photo_id, id);
+        assert_eq!(sidecars[0].kind, SidecarKind::Xmp);
+        assert_eq!(sidecars[0].sha256, "x1");
+        assert_eq!(catalog.get_sidecars_for_source(source.id).unwrap(), sidecars);
+
+        // Replacing drops sidecars that are no longer present
+        catalog.replace_sidecars_for_source(source.id, &[]).unwrap();
+        assert!(catalog.list_sidecars().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sidecars_removed_with_photo() {
+        let (mut catalog, source, _tmp) = make_catalog_with_source();
+        catalog.upsert_photo(&make_photo(source.id, "/photos/a.cr2", "aaa")).unwrap();
+        catalog
+            .replace_sidecars_for_source(
+                source.id,
+                &[(make_sidecar("/photos/a.cr2", "/photos/a.xmp", SidecarKind::Xmp), "x1".into())],
+            )
+            .unwrap();
+
+        catalog.remove_photos_by_paths(&[Path::new("/photos/a.cr2")]).unwrap();
+        assert!(catalog.list_sidecars().unwrap().is_empty());
+    }
+
     // ── Group tests ──────────────────────────────────────────────
 
     #[test]
@@ -1232,7 +1770,7 @@ mod tests {
     fn test_schema_version_set_on_fresh_db() {
         let catalog = Catalog::open_in_memory().unwrap();
         let version = catalog.get_config("schema_version").unwrap();
-        assert_eq!(version, Some("1".to_string()));
+        assert_eq!(version, Some("8".to_string()));
     }
 
     #[test]
@@ -1242,16 +1780,16 @@ mod tests {
 
         {
             let catalog = Catalog::open(&db_path).unwrap();
-            assert_eq!(catalog.get_config("schema_version").unwrap(), Some("1".to_string()));
+            assert_eq!(catalog.get_config("schema_version").unwrap(), Some("8".to_string()));
         }
         {
             let catalog = Catalog::open(&db_path).unwrap();
-            assert_eq!(catalog.get_config("schema_version").unwrap(), Some("1".to_string()));
+            assert_eq!(catalog.get_config("schema_version").unwrap(), Some("8".to_string()));
         }
     }
 
     #[test]
-    fn test_pre_versioning_db_upgraded_to_v1() {
+    fn test_pre_versioning_db_upgraded_to_latest() {
         // Create a DB with schema but no schema_version key.
         let conn = Connection::open_in_memory().unwrap();
         conn.pragma_update(None, "foreign_keys", "ON").unwrap();
@@ -1263,12 +1801,16 @@ mod tests {
             .ok();
         assert!(v.is_none());
 
-        // Running migrate should set it to 1.
+        // Running migrate treats it as v1 and runs every migration.
         schema::migrate(&conn).unwrap();
         let v: String = conn
             .query_row("SELECT value FROM config WHERE key = 'schema_version'", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(v, "1");
+        assert_eq!(v, "8");
+        let sidecars: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sidecars", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(sidecars, 0);
     }
 
     #[test]
@@ -1285,7 +1827,7 @@ mod tests {
         .unwrap();
 
         let err = schema::migrate(&conn).unwrap_err();
-        assert!(matches!(err, Error::SchemaTooNew { db: 999, code: 1 }));
+        assert!(matches!(err, Error::SchemaTooNew { db: 999, code: 8 }));
     }
 
     #[test]
@@ -1298,7 +1840,7 @@ mod tests {
         let v: String = conn
             .query_row("SELECT value FROM config WHERE key = 'schema_version'", [], |r| r.get(0))
             .unwrap();
-        assert_eq!(v, "1");
+        assert_eq!(v, "8");
     }
 
     // ── Schema structure pinning ────────────────────────────────
@@ -1315,7 +1857,7 @@ mod tests {
             .unwrap()
             .map(|r| r.unwrap())
             .collect();
-        assert_eq!(tables, vec!["config", "duplicate_groups", "group_members", "photos", "sources"]);
+        assert_eq!(tables, vec!["config", "duplicate_groups", "group_members", "match_evidence", "match_overrides", "photos", "pins", "sidecars", "sources"]);
     }
 
     #[test]
@@ -1334,10 +1876,13 @@ mod tests {
             indexes,
             vec![
                 "idx_group_members_photo",
+                "idx_match_evidence_group",
+                "idx_photos_content_id",
                 "idx_photos_path",
                 "idx_photos_sha256",
                 "idx_photos_source",
                 "idx_photos_source_mtime",
+                "idx_sidecars_path",
             ]
         );
     }
@@ -1360,7 +1905,7 @@ mod tests {
                 "id", "source_id", "path", "size", "format", "sha256",
                 "phash", "dhash", "mtime", "exif_date", "exif_camera_make",
                 "exif_camera_model", "exif_gps_lat", "exif_gps_lon",
-                "exif_width", "exif_height",
+                "exif_width", "exif_height", "exif_content_id",
             ]
         );
     }
@@ -1390,7 +1935,11 @@ mod tests {
         assert!(normalized.iter().any(|s| s.contains("CREATE TABLE config")));
         assert!(normalized.iter().any(|s| s.contains("CREATE TABLE duplicate_groups")));
         assert!(normalized.iter().any(|s| s.contains("CREATE TABLE group_members")));
+        assert!(normalized.iter().any(|s| s.contains("CREATE TABLE match_evidence")));
+        assert!(normalized.iter().any(|s| s.contains("CREATE TABLE match_overrides")));
         assert!(normalized.iter().any(|s| s.contains("CREATE TABLE photos")));
+        assert!(normalized.iter().any(|s| s.contains("CREATE TABLE pins")));
+        assert!(normalized.iter().any(|s| s.contains("CREATE TABLE sidecars")));
         assert!(normalized.iter().any(|s| s.contains("CREATE TABLE sources")));
 
         // Indexes
@@ -1398,7 +1947,10 @@ mod tests {
         assert!(normalized.iter().any(|s| s.contains("idx_photos_source")));
         assert!(normalized.iter().any(|s| s.contains("idx_photos_path")));
         assert!(normalized.iter().any(|s| s.contains("idx_photos_source_mtime")));
+        assert!(normalized.iter().any(|s| s.contains("idx_photos_content_id")));
         assert!(normalized.iter().any(|s| s.contains("idx_group_members_photo")));
+        assert!(normalized.iter().any(|s| s.contains("idx_sidecars_path")));
+        assert!(normalized.iter().any(|s| s.contains("idx_match_evidence_group")));
     }
 
     // ── Data integrity ──────────────────────────────────────────
@@ -1462,4 +2014,22 @@ mod tests {
         let result = catalog.insert_group(9999, Confidence::Certain, &[]);
         assert!(result.is_err());
     }
+
+    // ── Hash lookups ────────────────────────────────────────────
+
+    #[test]
+    fn test_get_paths_by_sha256s() {
+        let (catalog, source, _tmp) = make_catalog_with_source();
+        catalog.upsert_photo(&make_photo(source.id, "/photos/b.jpg", "aaa")).unwrap();
+        catalog.upsert_photo(&make_photo(source.id, "/photos/a.jpg", "aaa")).unwrap();
+        catalog.upsert_photo(&make_photo(source.id, "/photos/c.jpg", "bbb")).unwrap();
+
+        let paths = catalog.get_paths_by_sha256s(&["aaa", "zzz"]).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(
+            paths["aaa"],
+            vec![PathBuf::from("/photos/a.jpg"), PathBuf::from("/photos/b.jpg")]
+        );
+        assert!(catalog.get_paths_by_sha256s(&[]).unwrap().is_empty());
+    }
 }
@@ -24,10 +24,18 @@ use rusqlite::{params, Connection};
 use crate::error::{Error, Result};
 
 /// Current schema version. Bump when adding a migration.
-pub const SCHEMA_VERSION: i64 = 1;
+pub const SCHEMA_VERSION: i64 = 8;
 
 /// Ordered list of migrations. `MIGRATIONS[i]` migrates from version `i+1` to `i+2`.
-pub const MIGRATIONS: &[fn(&Connection) -> Result<()>] = &[];
+pub const MIGRATIONS: &[fn(&Connection) -> Result<()>] = &[
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+];
 
 pub fn initialize(conn: &Connection) -> Result<()> {
     conn.execute_batch(
@@ -85,6 +93,95 @@ pub fn initialize(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// v2: `sidecars` binds XMP/AAE/THM files to their photo (one per kind).
+/// Rows are deleted along with the photo.
+fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE sidecars (
+            photo_id INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+            kind     TEXT NOT NULL,
+            path     TEXT NOT NULL,
+            sha256   TEXT NOT NULL,
+            size     INTEGER NOT NULL,
+            mtime    INTEGER NOT NULL,
+            PRIMARY KEY (photo_id, kind)
+        );
+
+        CREATE INDEX idx_sidecars_path ON sidecars(path);",
+    )?;
+    Ok(())
+}
+
+/// v3: `exif_content_id` holds the Apple content identifier that pairs a Live
+/// Photo still with its motion video.
+fn migrate_v2_to_v3(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE photos ADD COLUMN exif_content_id TEXT;
+        CREATE INDEX idx_photos_content_id ON photos(exif_content_id);",
+    )?;
+    Ok(())
+}
+
+/// v4: `volume_id` is the identity written to a source's marker file at `add`
+/// time; `online` is cleared when a scan finds the volume absent, so its photos
+/// are kept instead of purged.
+fn migrate_v3_to_v4(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN volume_id TEXT;
+        ALTER TABLE sources ADD COLUMN online INTEGER NOT NULL DEFAULT 1;",
+    )?;
+    Ok(())
+}
+
+/// v5: `match_evidence` holds the edges that put each photo in its duplicate
+/// group (phase, hash distances, EXIF key). Rebuilt with the groups on every scan.
+fn migrate_v4_to_v5(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE match_evidence (
+            group_id       INTEGER NOT NULL REFERENCES duplicate_groups(id) ON DELETE CASCADE,
+            photo_a        INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+            photo_b        INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+            phase          TEXT NOT NULL,
+            phash_distance INTEGER,
+            dhash_distance INTEGER,
+            exif_key       TEXT
+        );
+
+        CREATE INDEX idx_match_evidence_group ON match_evidence(group_id);",
+    )?;
+    Ok(())
+}
+
+/// v6: `match_overrides` holds manual split/merge decisions keyed by the
+/// SHA-256 pair (`sha_a < sha_b`), so they survive moves and rescans.
+fn migrate_v5_to_v6(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE match_overrides (
+            sha_a TEXT NOT NULL,
+            sha_b TEXT NOT NULL,
+            kind  TEXT NOT NULL,
+            PRIMARY KEY (sha_a, sha_b)
+        );",
+    )?;
+    Ok(())
+}
+
+/// v7: `pins` holds the hashes manually pinned as their group's source of truth.
+fn migrate_v6_to_v7(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE pins (
+            sha256 TEXT PRIMARY KEY
+        );",
+    )?;
+    Ok(())
+}
+
+/// v8: `priority` ranks sources for the `source` ranking criterion (higher wins).
+fn migrate_v7_to_v8(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE sources ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;")?;
+    Ok(())
+}
+
 /// Read the schema version from the config table. Returns 0 if the key is absent
 /// (pre-versioning database).
 fn get_schema_version(conn: &Connection) -> Result<i64> {
@@ -134,7 +231,7 @@ pub fn migrate(conn: &Connection) -> Result<()> {
     // Run pending migrations inside a transaction.
     if db_version < SCHEMA_VERSION {
         let tx = conn.unchecked_transaction()?;
-        for migration in MIGRATIONS.iter().skip(db_version as usize) {
+        for migration in MIGRATIONS.iter().skip((db_version - 1) as usize) {
             migration(&tx)?;
         }
         set_schema_version(&tx, SCHEMA_VERSION)?;
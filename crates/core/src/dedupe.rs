@@ -0,0 +1,899 @@
+//! Reclaim space in the sources themselves by moving redundant copies out.
+//!
+//! Quarantine moves every non-source-of-truth member of a duplicate group into
+//! a quarantine folder that mirrors the file's original absolute path, together
+//! with its sidecars. Each move is written and synced to a journal before it
+//! happens, so an interrupted run can still be undone: [`undo_quarantine`]
+//! moves every journal entry back to where it came from.
+//!
+//! Linking keeps every path and replaces byte-identical copies in place with
+//! reflinks (copy-on-write clones, where the filesystem supports them) or hard
+//! links to the group's source of truth, after re-hashing both files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{ErrorKind, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::domain::{Confidence, DuplicateGroup, PhotoFile, Sidecar};
+use crate::error::{Error, Result};
+use crate::hasher;
+use crate::plan::{Plan, PlannedAction};
+use crate::vault_save;
+
+/// First line of every quarantine journal.
+const JOURNAL_HEADER: &str = "# photopack quarantine journal v1";
+
+/// Progress callback events for dedupe operations.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DedupeProgress {
+    /// Starting with the number of files to process.
+    Start { total: usize },
+    /// A file was moved into the quarantine folder.
+    Quarantined { path: PathBuf, target: PathBuf },
+    /// A quarantined file was moved back to its original path.
+    Restored { path: PathBuf, from: PathBuf },
+    /// A copy was replaced by a link to `keeper`.
+    Linked {
+        path: PathBuf,
+        keeper: PathBuf,
+        kind: LinkKind,
+    },
+    /// A file was left where it is (target occupied, file gone, or move failed).
+    Skipped { path: PathBuf },
+    /// Operation completed.
+    Complete { files: usize, bytes: u64 },
+}
+
+/// A file moved by quarantine, as recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JournalEntry {
+    pub sha256: String,
+    pub original: PathBuf,
+    pub quarantined: PathBuf,
+}
+
+/// Outcome of [`quarantine_files`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QuarantineReport {
+    /// Journal to pass to [`undo_quarantine`].
+    pub journal: PathBuf,
+    /// Photos and sidecars moved, in journal order.
+    pub moved: Vec<JournalEntry>,
+    /// Photos left in place (target occupied, file gone, or move failed).
+    pub skipped: Vec<PathBuf>,
+    /// Bytes moved out of the sources.
+    pub bytes: u64,
+}
+
+/// Outcome of [`undo_quarantine`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UndoReport {
+    /// Original paths that were put back.
+    pub restored: Vec<PathBuf>,
+    /// Original paths occupied by another file; the quarantined copy is kept.
+    pub conflicts: Vec<PathBuf>,
+    /// Quarantined copies no longer on disk.
+    pub missing: Vec<PathBuf>,
+}
+
+/// How a redundant copy shares its bytes with the kept file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// Copy-on-write clone (`FICLONE` on btrfs, XFS): later edits stay separate.
+    Reflink,
+    /// Hard link: both paths are the same file, edits show through both.
+    Hardlink,
+}
+
+impl LinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reflink => "reflink",
+            Self::Hardlink => "hardlink",
+        }
+    }
+}
+
+impl std::fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A copy replaced by a link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkedFile {
+    pub path: PathBuf,
+    pub keeper: PathBuf,
+    pub kind: LinkKind,
+}
+
+/// Outcome of [`link_copies`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LinkReport {
+    pub linked: Vec<LinkedFile>,
+    /// Copies left alone: already linked, on another filesystem, changed since
+    /// the last scan, or the link failed.
+    pub skipped: Vec<PathBuf>,
+    /// Catalog size of the linked copies, counted like the status savings.
+    pub bytes_saved: u64,
+}
+
+/// A redundant photo and the sidecars that move with it.
+pub struct Redundant<'a> {
+    pub photo: &'a PhotoFile,
+    pub sidecars: Vec<&'a Sidecar>,
+}
+
+/// Non-source-of-truth members of groups at or above `min_confidence`, each
+/// with the sidecars from `sidecars` that belong to it.
+///
+/// Members under an `excluded` root (the pack, offline sources) are never
+/// selected. Groups whose source of truth is excluded or missing from disk are
+/// skipped entirely, so every selected file keeps an intact copy in place.
+pub fn select_redundant<'a>(
+    groups: &'a [DuplicateGroup],
+    sidecars: &'a [Sidecar],
+    min_confidence: Confidence,
+    excluded: &[PathBuf],
+) -> Vec<Redundant<'a>> {
+    let is_excluded = |photo: &PhotoFile| excluded.iter().any(|root| photo.path.starts_with(root));
+    let mut sidecars_by_photo: HashMap<i64, Vec<&Sidecar>> = HashMap::new();
+    for sidecar in sidecars {
+        sidecars_by_photo.entry(sidecar.photo_id).or_default().push(sidecar);
+    }
+
+    groups
+        .iter()
+        .filter(|group| group.confidence >= min_confidence)
+        .filter(|group| {
+            group
+                .members
+                .iter()
+                .find(|m| m.id == group.source_of_truth_id)
+                .is_some_and(|sot| !is_excluded(sot) && sot.path.is_file())
+        })
+        .flat_map(|group| {
+            group
+                .members
+                .iter()
+                .filter(move |m| m.id != group.source_of_truth_id)
+        })
+        .filter(|photo| !is_excluded(photo))
+        .map(|photo| Redundant {
+            photo,
+            sidecars: sidecars_by_photo.remove(&photo.id).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// What [`quarantine_files`] would move into `dest`, without moving anything.
+pub fn plan_quarantine(dest: &Path, files: &[Redundant<'_>]) -> Plan {
+    let mut plan = Plan::default();
+    for file in files {
+        let target = quarantine_path(dest, &file.photo.path);
+        if target.symlink_metadata().is_ok() || !file.photo.path.is_file() {
+            plan.actions.push(PlannedAction::Skip {
+                path: file.photo.path.clone(),
+            });
+            continue;
+        }
+        plan.actions.push(PlannedAction::Move {
+            source: file.photo.path.clone(),
+            target,
+            bytes: file.photo.size,
+        });
+        for sidecar in &file.sidecars {
+            plan.actions.push(PlannedAction::Move {
+                source: sidecar.path.clone(),
+                target: quarantine_path(dest, &sidecar.path),
+                bytes: sidecar.size,
+            });
+        }
+    }
+    plan
+}
+
+/// What [`link_copies`] would link. Copies already linked or on another
+/// filesystem are skipped; hashes are only checked when linking for real.
+pub fn plan_links(pairs: &[(&PhotoFile, &PhotoFile)]) -> Plan {
+    let actions = pairs
+        .iter()
+        .map(|(keeper, copy)| {
+            if linkable_on_disk(&keeper.path, &copy.path) {
+                PlannedAction::Link {
+                    path: copy.path.clone(),
+                    keeper: keeper.path.clone(),
+                    bytes: copy.size,
+                }
+            } else {
+                PlannedAction::Skip {
+                    path: copy.path.clone(),
+                }
+            }
+        })
+        .collect();
+    Plan { actions }
+}
+
+/// `(keeper, copy)` pairs of byte-identical files in [`Confidence::Certain`] groups.
+///
+/// The keeper is the source of truth, or the first member outside the
+/// `excluded` roots when the source of truth is excluded. Excluded members (the
+/// pack, offline sources) are never replaced, and members whose catalog hash
+/// differs from the keeper's are left alone.
+pub fn select_linkable<'a>(
+    groups: &'a [DuplicateGroup],
+    excluded: &[PathBuf],
+) -> Vec<(&'a PhotoFile, &'a PhotoFile)> {
+    let is_excluded = |photo: &PhotoFile| excluded.iter().any(|root| photo.path.starts_with(root));
+
+    let mut pairs = Vec::new();
+    for group in groups.iter().filter(|g| g.confidence == Confidence::Certain) {
+        let keeper = group
+            .members
+            .iter()
+            .find(|m| m.id == group.source_of_truth_id && !is_excluded(m))
+            .or_else(|| group.members.iter().find(|m| !is_excluded(m)));
+        let Some(keeper) = keeper else {
+            continue;
+        };
+        pairs.extend(
+            group
+                .members
+                .iter()
+                .filter(|m| m.id != keeper.id && m.sha256 == keeper.sha256 && !is_excluded(m))
+                .map(|m| (keeper, m)),
+        );
+    }
+    pairs
+}
+
+/// Replace each copy with a reflink (or, failing that, a hard link) to its keeper.
+///
+/// Both files are re-hashed first and must still match the catalog. Copies on
+/// another filesystem than their keeper, or already linked to it, are skipped.
+/// The link is made under a temp name and renamed over the copy, so the copy's
+/// path never goes missing.
+pub fn link_copies(
+    pairs: &[(&PhotoFile, &PhotoFile)],
+    mut progress_cb: Option<&mut dyn FnMut(DedupeProgress)>,
+) -> LinkReport {
+    if let Some(ref mut cb) = progress_cb {
+        cb(DedupeProgress::Start { total: pairs.len() });
+    }
+
+    let mut report = LinkReport::default();
+    for (keeper, copy) in pairs {
+        match link_copy(&keeper.path, &copy.path, &copy.sha256) {
+            Ok(Some(kind)) => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(DedupeProgress::Linked {
+                        path: copy.path.clone(),
+                        keeper: keeper.path.clone(),
+                        kind,
+                    });
+                }
+                report.bytes_saved += copy.size;
+                report.linked.push(LinkedFile {
+                    path: copy.path.clone(),
+                    keeper: keeper.path.clone(),
+                    kind,
+                });
+            }
+            Ok(None) | Err(_) => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(DedupeProgress::Skipped {
+                        path: copy.path.clone(),
+                    });
+                }
+                report.skipped.push(copy.path.clone());
+            }
+        }
+    }
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(DedupeProgress::Complete {
+            files: report.linked.len(),
+            bytes: report.bytes_saved,
+        });
+    }
+    report
+}
+
+/// Link `copy` to `keeper` if both still hash to `sha256`.
+/// Returns `None` when there is nothing to do or linking is not possible.
+fn link_copy(keeper: &Path, copy: &Path, sha256: &str) -> Result<Option<LinkKind>> {
+    if !linkable_on_disk(keeper, copy) {
+        return Ok(None);
+    }
+    let copy_meta = fs::symlink_metadata(copy)?;
+    if hasher::compute_sha256(keeper)? != sha256 || hasher::compute_sha256(copy)? != sha256 {
+        return Ok(None);
+    }
+
+    let parent = copy.parent().unwrap_or(Path::new("."));
+    let temp = parent.join(format!(
+        ".{}.photopack-link.tmp",
+        copy.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let _ = fs::remove_file(&temp);
+
+    let kind = if reflink(keeper, &temp).is_ok() {
+        // A clone is a new file: keep the copy's mtime and permissions
+        let clone = fs::File::options().write(true).open(&temp)?;
+        if let Ok(mtime) = copy_meta.modified() {
+            clone.set_modified(mtime)?;
+        }
+        fs::set_permissions(&temp, copy_meta.permissions())?;
+        LinkKind::Reflink
+    } else {
+        let _ = fs::remove_file(&temp);
+        fs::hard_link(keeper, &temp)?;
+        LinkKind::Hardlink
+    };
+
+    if let Err(e) = fs::rename(&temp, copy) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(Some(kind))
+}
+
+/// Whether `copy` is a regular file on the same filesystem as `keeper` and
+/// not already the same file.
+fn linkable_on_disk(keeper: &Path, copy: &Path) -> bool {
+    let (Ok(keeper_meta), Ok(copy_meta)) = (fs::metadata(keeper), fs::symlink_metadata(copy))
+    else {
+        return false;
+    };
+    if !copy_meta.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        keeper_meta.dev() == copy_meta.dev() && keeper_meta.ino() != copy_meta.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = keeper_meta;
+        true
+    }
+}
+
+/// Clone `source` into a new file at `target` sharing its extents (`FICLONE`).
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // _IOW(0x94, 9, int) from <linux/fs.h>
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    let src = fs::File::open(source)?;
+    let dst = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+    // SAFETY: both descriptors are open for the duration of the call
+    let ret = unsafe { libc::ioctl(dst.as_raw_fd(), FICLONE as _, src.as_raw_fd()) };
+    if ret == -1 {
+        let err = std::io::Error::last_os_error();
+        drop(dst);
+        let _ = fs::remove_file(target);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Where `original` goes inside `dest`: its absolute path, mirrored.
+/// `/photos/2023/IMG_1.JPG` → `dest/photos/2023/IMG_1.JPG`.
+pub fn quarantine_path(dest: &Path, original: &Path) -> PathBuf {
+    let mut target = dest.to_path_buf();
+    for component in original.components() {
+        if let Component::Normal(part) = component {
+            target.push(part);
+        }
+    }
+    target
+}
+
+/// Journal file name for a run started at `timestamp` (`YYYYMMDD-HHMMSS`).
+/// Runs started within the same second get a counter suffix (`-2`, `-3`, …)
+/// instead of sharing a journal.
+pub fn journal_path(dest: &Path, timestamp: &str) -> PathBuf {
+    let mut path = dest.join(format!("photopack-quarantine-{timestamp}.journal"));
+    let mut n = 1;
+    while path.symlink_metadata().is_ok() {
+        n += 1;
+        path = dest.join(format!("photopack-quarantine-{timestamp}-{n}.journal"));
+    }
+    path
+}
+
+/// Move `files` (with their sidecars) into `dest`, writing the journal at `journal`.
+///
+/// A photo is skipped if its quarantine path is already taken or the move fails;
+/// its sidecars then stay with it. Each entry is synced to the journal before
+/// its move, and dropped again if the move fails, so a crash mid-move leaves
+/// a journal that still covers the file.
+pub fn quarantine_files(
+    dest: &Path,
+    journal: &Path,
+    files: &[Redundant<'_>],
+    mut progress_cb: Option<&mut dyn FnMut(DedupeProgress)>,
+) -> Result<QuarantineReport> {
+    fs::create_dir_all(dest)?;
+    let mut writer = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(journal)?;
+    writeln!(writer, "{JOURNAL_HEADER}")?;
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(DedupeProgress::Start { total: files.len() });
+    }
+
+    let mut report = QuarantineReport {
+        journal: journal.to_path_buf(),
+        ..Default::default()
+    };
+    for file in files {
+        let photo = file.photo;
+        let target = quarantine_path(dest, &photo.path);
+        let entry = JournalEntry {
+            sha256: photo.sha256.clone(),
+            original: photo.path.clone(),
+            quarantined: target.clone(),
+        };
+        if !journal_safe(&photo.path) || !journaled_move(&mut writer, &entry)? {
+            if let Some(ref mut cb) = progress_cb {
+                cb(DedupeProgress::Skipped {
+                    path: photo.path.clone(),
+                });
+            }
+            report.skipped.push(photo.path.clone());
+            continue;
+        }
+        report.moved.push(entry);
+        report.bytes += photo.size;
+        if let Some(ref mut cb) = progress_cb {
+            cb(DedupeProgress::Quarantined {
+                path: photo.path.clone(),
+                target,
+            });
+        }
+
+        for sidecar in &file.sidecars {
+            let entry = JournalEntry {
+                sha256: sidecar.sha256.clone(),
+                original: sidecar.path.clone(),
+                quarantined: quarantine_path(dest, &sidecar.path),
+            };
+            if !journal_safe(&sidecar.path) || !journaled_move(&mut writer, &entry)? {
+                continue;
+            }
+            report.moved.push(entry);
+            report.bytes += sidecar.size;
+        }
+    }
+    writer.sync_all()?;
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(DedupeProgress::Complete {
+            files: report.moved.len(),
+            bytes: report.bytes,
+        });
+    }
+    Ok(report)
+}
+
+/// Move every file recorded in `journal` back to its original path, last move first.
+/// Original paths taken by another file are reported as conflicts and left alone.
+pub fn undo_quarantine(
+    journal: &Path,
+    mut progress_cb: Option<&mut dyn FnMut(DedupeProgress)>,
+) -> Result<UndoReport> {
+    let entries = read_journal(journal)?;
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(DedupeProgress::Start {
+            total: entries.len(),
+        });
+    }
+
+    let mut report = UndoReport::default();
+    let mut bytes = 0u64;
+    for entry in entries.iter().rev() {
+        if !entry.quarantined.is_file() {
+            report.missing.push(entry.quarantined.clone());
+        } else if entry.original.symlink_metadata().is_ok() {
+            report.conflicts.push(entry.original.clone());
+        } else {
+            let size = fs::metadata(&entry.quarantined).map(|m| m.len()).unwrap_or(0);
+            if move_file(&entry.quarantined, &entry.original, &entry.sha256).is_ok() {
+                bytes += size;
+                if let Some(ref mut cb) = progress_cb {
+                    cb(DedupeProgress::Restored {
+                        path: entry.original.clone(),
+                        from: entry.quarantined.clone(),
+                    });
+                }
+                report.restored.push(entry.original.clone());
+                continue;
+            }
+            report.conflicts.push(entry.original.clone());
+        }
+        if let Some(ref mut cb) = progress_cb {
+            cb(DedupeProgress::Skipped {
+                path: entry.original.clone(),
+            });
+        }
+    }
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(DedupeProgress::Complete {
+            files: report.restored.len(),
+            bytes,
+        });
+    }
+    Ok(report)
+}
+
+/// Parse a quarantine journal into its entries, in move order.
+pub fn read_journal(journal: &Path) -> Result<Vec<JournalEntry>> {
+    let content = fs::read_to_string(journal)?;
+    let invalid = || Error::InvalidJournal(journal.to_path_buf());
+
+    let mut lines = content.lines();
+    if lines.next() != Some(JOURNAL_HEADER) {
+        return Err(invalid());
+    }
+    lines
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some(sha256), Some(original), Some(quarantined), None) => Ok(JournalEntry {
+                    sha256: sha256.to_string(),
+                    original: PathBuf::from(original),
+                    quarantined: PathBuf::from(quarantined),
+                }),
+                _ => Err(invalid()),
+            }
+        })
+        .collect()
+}
+
+/// Sync `entry` to the journal, then make the move it records. A failed move
+/// truncates the entry away again and returns `false`; only journal I/O errors
+/// are returned as errors.
+fn journaled_move(writer: &mut fs::File, entry: &JournalEntry) -> Result<bool> {
+    let before = writer.metadata()?.len();
+    writeln!(
+        writer,
+        "{}\t{}\t{}",
+        entry.sha256,
+        entry.original.display(),
+        entry.quarantined.display()
+    )?;
+    writer.sync_data()?;
+
+    if move_file(&entry.original, &entry.quarantined, &entry.sha256).is_ok() {
+        return Ok(true);
+    }
+    writer.set_len(before)?;
+    writer.seek(SeekFrom::End(0))?;
+    writer.sync_data()?;
+    Ok(false)
+}
+
+/// Whether `path` can be written as a journal field (tab-separated lines).
+fn journal_safe(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|s| !s.contains(['\t', '\n', '\r']))
+}
+
+/// Move `source` to `target`, which must not exist. Falls back to a
+/// hash-verified copy and delete when the two are on different filesystems.
+fn move_file(source: &Path, target: &Path, sha256: &str) -> Result<()> {
+    if target.symlink_metadata().is_ok() {
+        return Err(std::io::Error::from(ErrorKind::AlreadyExists).into());
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(source, target) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            vault_save::replace_pack_file(source, target, sha256)?;
+            fs::remove_file(source)?;
+            Ok(())
+        }
+        result => Ok(result?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::PhotoFormat;
+    use crate::hasher;
+
+    fn photo(id: i64, path: &Path) -> PhotoFile {
+        PhotoFile {
+            id,
+            source_id: 1,
+            path: path.to_path_buf(),
+            size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            format: PhotoFormat::Jpeg,
+            sha256: hasher::compute_sha256(path).unwrap_or_default(),
+            phash: None,
+            dhash: None,
+            exif: None,
+            mtime: 0,
+        }
+    }
+
+    fn group(sot: i64, confidence: Confidence, members: Vec<PhotoFile>) -> DuplicateGroup {
+        DuplicateGroup {
+            id: sot,
+            members,
+            source_of_truth_id: sot,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_quarantine_path_mirrors_absolute_path() {
+        assert_eq!(
+            quarantine_path(Path::new("/q"), Path::new("/photos/2023/IMG_1.JPG")),
+            PathBuf::from("/q/photos/2023/IMG_1.JPG")
+        );
+    }
+
+    #[test]
+    fn test_select_redundant_respects_confidence_and_exclusions() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["a.jpg", "b.jpg", "c.jpg", "d.jpg"] {
+            fs::write(tmp.path().join(name), name).unwrap();
+        }
+        let pack = tmp.path().join("pack");
+        let excluded = [pack.clone()];
+        let groups = [
+            group(
+                1,
+                Confidence::Certain,
+                vec![
+                    photo(1, &tmp.path().join("a.jpg")),
+                    photo(2, &tmp.path().join("b.jpg")),
+                    photo(3, &pack.join("ab/ab.jpg")),
+                ],
+            ),
+            group(
+                4,
+                Confidence::Probable,
+                vec![
+                    photo(4, &tmp.path().join("c.jpg")),
+                    photo(5, &tmp.path().join("d.jpg")),
+                ],
+            ),
+            // Source of truth gone from disk: nothing of this group moves
+            group(
+                6,
+                Confidence::Certain,
+                vec![
+                    photo(6, &tmp.path().join("gone.jpg")),
+                    photo(7, &tmp.path().join("d.jpg")),
+                ],
+            ),
+        ];
+
+        let ids = |min| -> Vec<i64> {
+            select_redundant(&groups, &[], min, &excluded)
+                .iter()
+                .map(|r| r.photo.id)
+                .collect()
+        };
+        assert_eq!(ids(Confidence::Certain), [2]);
+        assert_eq!(ids(Confidence::Low), [2, 5]);
+    }
+
+    #[test]
+    fn test_quarantine_and_undo_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let photos = tmp.path().join("photos");
+        fs::create_dir_all(photos.join("2023")).unwrap();
+        fs::write(photos.join("2023/copy.jpg"), b"photo").unwrap();
+        fs::write(photos.join("2023/copy.xmp"), b"<xmp/>").unwrap();
+        let copy = photo(2, &photos.join("2023/copy.jpg"));
+        let xmp = Sidecar {
+            photo_id: 2,
+            path: photos.join("2023/copy.xmp"),
+            kind: crate::domain::SidecarKind::Xmp,
+            sha256: hasher::compute_sha256(&photos.join("2023/copy.xmp")).unwrap(),
+            size: 6,
+            mtime: 0,
+        };
+
+        let dest = tmp.path().join("quarantine");
+        let files = [Redundant {
+            photo: &copy,
+            sidecars: vec![&xmp],
+        }];
+        let planned = plan_quarantine(&dest, &files);
+        assert_eq!(planned.summary().moves, 2);
+        assert_eq!(planned.summary().bytes_freed, 11);
+        assert!(!dest.exists(), "planning touches nothing");
+
+        let journal = journal_path(&dest, "20240101-000000");
+        let report = quarantine_files(
+            &dest,
+            &journal,
+            &files,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(report.moved.len(), 2);
+        assert_eq!(report.bytes, 11);
+        assert!(!copy.path.exists());
+        assert_eq!(fs::read(quarantine_path(&dest, &copy.path)).unwrap(), b"photo");
+        assert_eq!(read_journal(&journal).unwrap(), report.moved);
+
+        let undo = undo_quarantine(&journal, None).unwrap();
+        assert_eq!(undo.restored.len(), 2);
+        assert_eq!(fs::read(&copy.path).unwrap(), b"photo");
+        assert_eq!(fs::read(&xmp.path).unwrap(), b"<xmp/>");
+
+        // A second undo finds nothing left to move back
+        let again = undo_quarantine(&journal, None).unwrap();
+        assert!(again.restored.is_empty());
+        assert_eq!(again.missing.len(), 2);
+    }
+
+    #[test]
+    fn test_undo_keeps_quarantined_copy_on_conflict() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a.jpg"), b"photo").unwrap();
+        let a = photo(1, &tmp.path().join("a.jpg"));
+        let dest = tmp.path().join("q");
+        let journal = journal_path(&dest, "20240101-000000");
+        quarantine_files(&dest, &journal, &[Redundant { photo: &a, sidecars: vec![] }], None)
+            .unwrap();
+
+        fs::write(&a.path, b"new file").unwrap();
+        let undo = undo_quarantine(&journal, None).unwrap();
+        assert_eq!(undo.conflicts, vec![a.path.clone()]);
+        assert_eq!(fs::read(&a.path).unwrap(), b"new file");
+        assert!(quarantine_path(&dest, &a.path).is_file());
+    }
+
+    #[test]
+    fn test_journal_path_unique_within_a_second() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = journal_path(tmp.path(), "20240101-000000");
+        fs::write(&first, "").unwrap();
+        let second = journal_path(tmp.path(), "20240101-000000");
+        assert_ne!(first, second);
+        assert_eq!(
+            second.file_name().unwrap(),
+            "photopack-quarantine-20240101-000000-2.journal"
+        );
+    }
+
+    #[test]
+    fn test_failed_move_is_dropped_from_journal() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["a.jpg", "b.jpg"] {
+            fs::write(tmp.path().join(name), b"photo").unwrap();
+        }
+        let a = photo(1, &tmp.path().join("a.jpg"));
+        let b = photo(2, &tmp.path().join("b.jpg"));
+        let dest = tmp.path().join("q");
+        // Occupy a's quarantine path so its move fails
+        let taken = quarantine_path(&dest, &a.path);
+        fs::create_dir_all(taken.parent().unwrap()).unwrap();
+        fs::write(&taken, b"other").unwrap();
+
+        let journal = journal_path(&dest, "20240101-000000");
+        let files = [
+            Redundant { photo: &a, sidecars: vec![] },
+            Redundant { photo: &b, sidecars: vec![] },
+        ];
+        let report = quarantine_files(&dest, &journal, &files, None).unwrap();
+
+        assert_eq!(report.skipped, vec![a.path.clone()]);
+        let entries = read_journal(&journal).unwrap();
+        assert_eq!(entries, report.moved);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].original, b.path);
+    }
+
+    #[test]
+    fn test_select_linkable_pairs_copies_with_keeper() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            fs::write(tmp.path().join(name), b"same").unwrap();
+        }
+        let pack = tmp.path().join("pack");
+        fs::create_dir_all(&pack).unwrap();
+        fs::write(pack.join("p.jpg"), b"same").unwrap();
+        let excluded = [pack.clone()];
+        let groups = [
+            // Pack copy elected: the first source copy keeps the bytes
+            group(
+                1,
+                Confidence::Certain,
+                vec![
+                    photo(1, &pack.join("p.jpg")),
+                    photo(2, &tmp.path().join("a.jpg")),
+                    photo(3, &tmp.path().join("b.jpg")),
+                ],
+            ),
+            group(
+                4,
+                Confidence::NearCertain,
+                vec![photo(4, &tmp.path().join("c.jpg")), photo(5, &tmp.path().join("a.jpg"))],
+            ),
+        ];
+
+        let pairs: Vec<(i64, i64)> = select_linkable(&groups, &excluded)
+            .iter()
+            .map(|(k, c)| (k.id, c.id))
+            .collect();
+        assert_eq!(pairs, [(2, 3)]);
+    }
+
+    #[test]
+    fn test_link_copies_shares_bytes_and_skips_changed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("keep.jpg"), b"photo").unwrap();
+        fs::write(tmp.path().join("copy.jpg"), b"photo").unwrap();
+        fs::write(tmp.path().join("edited.jpg"), b"photo").unwrap();
+        let keeper = photo(1, &tmp.path().join("keep.jpg"));
+        let copy = photo(2, &tmp.path().join("copy.jpg"));
+        let edited = photo(3, &tmp.path().join("edited.jpg"));
+        fs::write(&edited.path, b"edited since the scan").unwrap();
+
+        let planned = plan_links(&[(&keeper, &copy), (&keeper, &edited)]);
+        assert_eq!(planned.summary().links, 2, "hashes are checked at link time");
+
+        let report = link_copies(&[(&keeper, &copy), (&keeper, &edited)], None);
+
+        assert_eq!(report.linked.len(), 1);
+        assert_eq!(report.linked[0].path, copy.path);
+        assert_eq!(report.skipped, vec![edited.path.clone()]);
+        assert_eq!(report.bytes_saved, 5);
+        assert_eq!(fs::read(&copy.path).unwrap(), b"photo");
+        #[cfg(unix)]
+        if report.linked[0].kind == LinkKind::Hardlink {
+            use std::os::unix::fs::MetadataExt;
+            let ino = |p: &Path| fs::metadata(p).unwrap().ino();
+            assert_eq!(ino(&keeper.path), ino(&copy.path));
+        }
+
+        // Linking again finds nothing left to do
+        let again = link_copies(&[(&keeper, &copy)], None);
+        if report.linked[0].kind == LinkKind::Hardlink {
+            assert!(again.linked.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_journal_rejects_foreign_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("notes.txt");
+        fs::write(&path, "hello\n").unwrap();
+        assert!(matches!(read_journal(&path), Err(Error::InvalidJournal(_))));
+    }
+}
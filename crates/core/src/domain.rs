@@ -35,9 +35,31 @@ pub enum PhotoFormat {
     Jpeg,
     Heic,
     Webp,
+    // Video (Live Photo motion halves, camera clips)
+    Mov,
+    Mp4,
 }
 
 impl PhotoFormat {
+    /// Every supported format, in quality-tier order.
+    pub const ALL: [PhotoFormat; 15] = [
+        Self::Cr2,
+        Self::Cr3,
+        Self::Nef,
+        Self::Arw,
+        Self::Orf,
+        Self::Raf,
+        Self::Rw2,
+        Self::Dng,
+        Self::Tiff,
+        Self::Png,
+        Self::Jpeg,
+        Self::Heic,
+        Self::Webp,
+        Self::Mov,
+        Self::Mp4,
+    ];
+
     /// Quality tier for ranking (lower = better).
     pub fn quality_tier(&self) -> u8 {
         match self {
@@ -51,6 +73,8 @@ impl PhotoFormat {
             Self::Jpeg => 3,
             Self::Heic => 4,
             Self::Webp => 5,
+            // Video — only ever grouped with byte-identical copies
+            Self::Mov | Self::Mp4 => 6,
         }
     }
 
@@ -70,12 +94,42 @@ impl PhotoFormat {
             Self::Jpeg => "jpg",
             Self::Heic => "heic",
             Self::Webp => "webp",
+            Self::Mov => "mov",
+            Self::Mp4 => "mp4",
         }
     }
 
-    /// Whether the `image` crate can decode this format for perceptual hashing.
+    /// Whether this is a camera RAW format.
+    pub fn is_raw(&self) -> bool {
+        matches!(
+            self,
+            Self::Cr2 | Self::Cr3 | Self::Nef | Self::Arw | Self::Orf | Self::Raf | Self::Rw2 | Self::Dng
+        )
+    }
+
+    /// Format family, as kept side by side by [`PackPolicy::Families`].
+    pub fn family(&self) -> FormatFamily {
+        match self.quality_tier() {
+            0 => FormatFamily::Raw,
+            1 | 2 => FormatFamily::Lossless,
+            3..=5 => FormatFamily::Lossy,
+            _ => FormatFamily::Video,
+        }
+    }
+
+    /// Whether this is a video container (deduplicated by SHA-256 only).
+    pub fn is_video(&self) -> bool {
+        matches!(self, Self::Mov | Self::Mp4)
+    }
+
+    /// Whether perceptual hashes can be computed for this format.
+    /// RAW formats are hashed through their embedded JPEG preview; HEIC needs
+    /// the `heif` feature (built-in HEVC decoder). Videos are never perceptually hashed.
     pub fn supports_perceptual_hash(&self) -> bool {
-        matches!(self, Self::Jpeg | Self::Png | Self::Tiff | Self::Webp)
+        match self {
+            Self::Heic => cfg!(feature = "heif"),
+            _ => !self.is_video(),
+        }
     }
 
     pub fn as_str(&self) -> &'static str {
@@ -93,6 +147,8 @@ impl PhotoFormat {
             Self::Jpeg => "JPEG",
             Self::Heic => "HEIC",
             Self::Webp => "WebP",
+            Self::Mov => "MOV",
+            Self::Mp4 => "MP4",
         }
     }
 }
@@ -132,6 +188,18 @@ impl Confidence {
             Self::Certain => "Certain",
         }
     }
+
+    /// Parse a confidence name, case-insensitively (`near-certain` or `Near-Certain`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "probable" => Some(Self::Probable),
+            "high" => Some(Self::High),
+            "near-certain" => Some(Self::NearCertain),
+            "certain" => Some(Self::Certain),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Confidence {
@@ -140,7 +208,209 @@ impl std::fmt::Display for Confidence {
     }
 }
 
-/// Extracted EXIF metadata.
+/// Matching phase that linked two photos of a duplicate group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MatchPhase {
+    /// Phase 1: byte-identical SHA-256.
+    Sha256,
+    /// Phase 2: same EXIF date and camera, visually validated when both have hashes.
+    Exif,
+    /// Phase 3: aHash/dHash within threshold.
+    Perceptual,
+    /// Phase 4: the visually close pair that allowed two overlapping groups to merge.
+    Merge,
+    /// Phase 5: unhashable photo attached by EXIF key to an existing group.
+    Orphan,
+    /// A manual always-match override (`photopack merge`).
+    Manual,
+}
+
+impl MatchPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Exif => "exif",
+            Self::Perceptual => "perceptual",
+            Self::Merge => "merge",
+            Self::Orphan => "orphan",
+            Self::Manual => "manual",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "sha256" => Some(Self::Sha256),
+            "exif" => Some(Self::Exif),
+            "perceptual" => Some(Self::Perceptual),
+            "merge" => Some(Self::Merge),
+            "orphan" => Some(Self::Orphan),
+            "manual" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MatchPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One edge of a group's evidence graph: why `photo_a` and `photo_b` matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchEvidence {
+    pub photo_a: i64,
+    pub photo_b: i64,
+    pub phase: MatchPhase,
+    /// aHash Hamming distance, when both photos have one.
+    pub phash_distance: Option<u32>,
+    /// dHash Hamming distance, when both photos have one.
+    pub dhash_distance: Option<u32>,
+    /// Shared `date|camera` key for EXIF-based edges.
+    pub exif_key: Option<String>,
+}
+
+/// Direction of a manual matching decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OverrideKind {
+    /// The two files are never in the same group (`photopack split`).
+    NeverMatch,
+    /// The two files are always in the same group (`photopack merge`).
+    AlwaysMatch,
+}
+
+impl OverrideKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NeverMatch => "never",
+            Self::AlwaysMatch => "always",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "never" => Some(Self::NeverMatch),
+            "always" => Some(Self::AlwaysMatch),
+            _ => None,
+        }
+    }
+}
+
+/// A manual matching decision between two files, keyed by SHA-256 so it
+/// survives moves, renames and rescans.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchOverride {
+    /// The smaller of the two hashes.
+    pub sha_a: String,
+    /// The larger of the two hashes.
+    pub sha_b: String,
+    pub kind: OverrideKind,
+}
+
+impl MatchOverride {
+    /// Build an override, ordering the pair so each pair has one key.
+    pub fn new(sha_1: &str, sha_2: &str, kind: OverrideKind) -> Self {
+        let (sha_a, sha_b) = if sha_1 <= sha_2 {
+            (sha_1, sha_2)
+        } else {
+            (sha_2, sha_1)
+        };
+        Self {
+            sha_a: sha_a.to_string(),
+            sha_b: sha_b.to_string(),
+            kind,
+        }
+    }
+}
+
+/// Coarse grouping of formats by how they store the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FormatFamily {
+    Raw,
+    /// TIFF, PNG.
+    Lossless,
+    /// JPEG, HEIC, WebP.
+    Lossy,
+    Video,
+}
+
+impl FormatFamily {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "raw" => Some(Self::Raw),
+            "lossless" => Some(Self::Lossless),
+            "lossy" => Some(Self::Lossy),
+            "video" => Some(Self::Video),
+            _ => None,
+        }
+    }
+}
+
+/// Which members of a duplicate group are packed. Stored per pack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PackPolicy {
+    /// Only the source of truth.
+    #[default]
+    Source,
+    /// The source of truth plus the best copy of every other format family,
+    /// e.g. the camera JPEG next to its RAW.
+    Families,
+}
+
+impl PackPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Source => "source",
+            Self::Families => "families",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "source" => Some(Self::Source),
+            "families" => Some(Self::Families),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PackPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a file is in the pack, as recorded in the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PackRole {
+    /// Not part of any duplicate group.
+    Unique,
+    /// Source of truth of its group.
+    Source,
+    /// Best copy of another format family in its group ([`PackPolicy::Families`]).
+    Alternate,
+}
+
+impl PackRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unique => "unique",
+            Self::Source => "source",
+            Self::Alternate => "alternate",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "unique" => Some(Self::Unique),
+            "source" => Some(Self::Source),
+            "alternate" => Some(Self::Alternate),
+            _ => None,
+        }
+    }
+}
+
+/// Extracted EXIF metadata (QuickTime metadata for videos).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExifData {
     pub date: Option<String>,
@@ -150,6 +420,9 @@ pub struct ExifData {
     pub gps_lon: Option<f64>,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    /// Apple content identifier shared by a Live Photo still and its motion video.
+    #[serde(default)]
+    pub content_id: Option<String>,
 }
 
 /// A registered scan source (directory).
@@ -158,6 +431,13 @@ pub struct Source {
     pub id: i64,
     pub path: PathBuf,
     pub last_scanned: Option<i64>,
+    /// Identity written to the source's marker file, used to tell an unmounted
+    /// volume from an emptied folder. `None` if the marker could not be written.
+    pub volume_id: Option<String>,
+    /// False when the last scan found the volume absent; its photos are kept.
+    pub online: bool,
+    /// Preference for this source's copies when ranking by source (higher wins).
+    pub priority: i64,
 }
 
 /// Summary statistics for the catalog.
@@ -169,6 +449,85 @@ pub struct CatalogStats {
     pub total_duplicates: usize,
 }
 
+/// Kinds of sidecar files kept alongside a photo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SidecarKind {
+    /// Adobe XMP (Lightroom / Camera Raw develop settings, ratings, keywords).
+    Xmp,
+    /// Apple adjustment data (iOS Photos edit recipe).
+    Aae,
+    /// Camera thumbnail.
+    Thm,
+}
+
+impl SidecarKind {
+    /// Map a file extension (lowercase, without dot) to a sidecar kind.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "xmp" => Some(Self::Xmp),
+            "aae" => Some(Self::Aae),
+            "thm" => Some(Self::Thm),
+            _ => None,
+        }
+    }
+
+    /// File extension for this kind (lowercase, no dot).
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Xmp => "xmp",
+            Self::Aae => "aae",
+            Self::Thm => "thm",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Xmp => "XMP",
+            Self::Aae => "AAE",
+            Self::Thm => "THM",
+        }
+    }
+
+    /// Parse the catalog/manifest representation (see [`SidecarKind::as_str`]).
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::from_extension(&s.to_lowercase())
+    }
+}
+
+impl std::fmt::Display for SidecarKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A sidecar file bound to a photo in the catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub photo_id: i64,
+    pub path: PathBuf,
+    pub kind: SidecarKind,
+    pub sha256: String,
+    pub size: u64,
+    pub mtime: i64,
+}
+
+/// A photo that could not be written by a pack save or export, with the reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailedFile {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// A sidecar discovered during scanning (before hashing), with the photo it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedSidecar {
+    pub path: PathBuf,
+    pub photo_path: PathBuf,
+    pub kind: SidecarKind,
+    pub size: u64,
+    pub mtime: i64,
+}
+
 /// A file discovered during scanning (before hashing).
 #[derive(Debug, Clone)]
 pub struct ScannedFile {
@@ -225,6 +584,31 @@ mod tests {
         assert_eq!(format!("{}", Confidence::High), "High");
     }
 
+    #[test]
+    fn test_confidence_parse_round_trips() {
+        for c in [
+            Confidence::Low,
+            Confidence::Probable,
+            Confidence::High,
+            Confidence::NearCertain,
+            Confidence::Certain,
+        ] {
+            assert_eq!(Confidence::parse(c.as_str()), Some(c));
+        }
+        assert_eq!(Confidence::parse("near-certain"), Some(Confidence::NearCertain));
+        assert_eq!(Confidence::parse("sure"), None);
+    }
+
+    #[test]
+    fn test_format_family_parse_covers_all_formats() {
+        for format in PhotoFormat::ALL {
+            let family = format.family();
+            assert_eq!(FormatFamily::parse(&format!("{family:?}")), Some(family));
+        }
+        assert_eq!(FormatFamily::parse("RAW"), Some(FormatFamily::Raw));
+        assert_eq!(FormatFamily::parse("vector"), None);
+    }
+
     #[test]
     fn test_photo_format_extension() {
         assert_eq!(PhotoFormat::Cr2.extension(), "cr2");
@@ -250,15 +634,41 @@ mod tests {
         assert!(PhotoFormat::Tiff.supports_perceptual_hash());
         assert!(PhotoFormat::Webp.supports_perceptual_hash());
 
-        // Formats the image crate cannot decode — must NOT attempt
-        assert!(!PhotoFormat::Heic.supports_perceptual_hash());
-        assert!(!PhotoFormat::Cr2.supports_perceptual_hash());
-        assert!(!PhotoFormat::Cr3.supports_perceptual_hash());
-        assert!(!PhotoFormat::Nef.supports_perceptual_hash());
-        assert!(!PhotoFormat::Arw.supports_perceptual_hash());
-        assert!(!PhotoFormat::Orf.supports_perceptual_hash());
-        assert!(!PhotoFormat::Raf.supports_perceptual_hash());
-        assert!(!PhotoFormat::Rw2.supports_perceptual_hash());
-        assert!(!PhotoFormat::Dng.supports_perceptual_hash());
+        // RAW formats — hashed through their embedded JPEG preview
+        assert!(PhotoFormat::Cr2.supports_perceptual_hash());
+        assert!(PhotoFormat::Cr3.supports_perceptual_hash());
+        assert!(PhotoFormat::Nef.supports_perceptual_hash());
+        assert!(PhotoFormat::Arw.supports_perceptual_hash());
+        assert!(PhotoFormat::Orf.supports_perceptual_hash());
+        assert!(PhotoFormat::Raf.supports_perceptual_hash());
+        assert!(PhotoFormat::Rw2.supports_perceptual_hash());
+        assert!(PhotoFormat::Dng.supports_perceptual_hash());
+
+        // HEIC only with the HEIF decoder compiled in
+        assert_eq!(
+            PhotoFormat::Heic.supports_perceptual_hash(),
+            cfg!(feature = "heif")
+        );
+    }
+
+    #[test]
+    fn test_video_formats() {
+        assert!(PhotoFormat::Mov.is_video());
+        assert!(PhotoFormat::Mp4.is_video());
+        assert!(!PhotoFormat::Heic.is_video());
+        assert!(!PhotoFormat::Mov.supports_perceptual_hash());
+        assert!(!PhotoFormat::Mp4.is_raw());
+        assert_eq!(PhotoFormat::Mov.extension(), "mov");
+        assert_eq!(PhotoFormat::Mp4.as_str(), "MP4");
+        assert!(PhotoFormat::Mov.quality_tier() > PhotoFormat::Webp.quality_tier());
+    }
+
+    #[test]
+    fn test_is_raw() {
+        assert!(PhotoFormat::Cr2.is_raw());
+        assert!(PhotoFormat::Dng.is_raw());
+        assert!(!PhotoFormat::Tiff.is_raw());
+        assert!(!PhotoFormat::Jpeg.is_raw());
+        assert!(!PhotoFormat::Heic.is_raw());
     }
 }
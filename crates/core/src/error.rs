@@ -41,17 +41,72 @@ pub enum Error {
     #[error("vault path does not exist: {}", .0.display())]
     VaultPathNotFound(PathBuf),
 
+    #[error("not a photopack pack (no .photopack/manifest.sqlite): {}", .0.display())]
+    NotAPack(PathBuf),
+
+    #[error("no trashed file matches hash {0}")]
+    NotInTrash(String),
+
+    #[error("hash prefix {0} matches more than one file — use more characters")]
+    AmbiguousHashPrefix(String),
+
     #[error("export path does not exist: {}", .0.display())]
     ExportPathNotFound(PathBuf),
 
     #[error("failed to convert {}: {message}", .path.display())]
     ConversionFailed { path: PathBuf, message: String },
 
-    #[error("sips command not available — this feature requires macOS")]
-    SipsNotAvailable,
+    #[error("export encoder not available on this system: {0}")]
+    EncoderNotAvailable(String),
+
+    #[error("unknown export encoder: {0} (expected auto, sips, heif-enc, magick, vips, jpeg, webp or avif)")]
+    UnknownEncoder(String),
+
+    #[error("photo not in catalog: {} — run `photopack scan` first", .0.display())]
+    PhotoNotInCatalog(PathBuf),
+
+    #[error("{} and {} have identical content and are always grouped", .a.display(), .b.display())]
+    IdenticalContent { a: PathBuf, b: PathBuf },
+
+    #[error("unknown match profile: {0} (expected strict, default or scans)")]
+    UnknownMatchProfile(String),
+
+    #[error("invalid ranking policy: {0}")]
+    InvalidRankingPolicy(String),
+
+    #[error("unknown pack policy: {0} (expected source or families)")]
+    UnknownPackPolicy(String),
+
+    #[error("hash mismatch for {}: expected {expected}, got {actual}", .path.display())]
+    HashMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("invalid ignore pattern: {0}")]
+    InvalidIgnorePattern(String),
 
     #[error("catalog version {db} is newer than supported version {code} — upgrade photopack")]
     SchemaTooNew { db: i64, code: i64 },
+
+    #[error("pack manifest version {db} is newer than supported version {code} — upgrade photopack")]
+    ManifestTooNew { db: i64, code: i64 },
+
+    #[error("unknown confidence: {0} (expected certain, near-certain, high, probable or low)")]
+    UnknownConfidence(String),
+
+    #[error("quarantine folder is inside source {}: quarantined files would be scanned again", .0.display())]
+    QuarantineInsideSource(PathBuf),
+
+    #[error("not a photopack quarantine journal: {}", .0.display())]
+    InvalidJournal(PathBuf),
+
+    #[error("invalid sample percentage: {0} (expected a value in (0, 100])")]
+    InvalidSamplePercent(f64),
+
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
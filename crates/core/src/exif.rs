@@ -4,7 +4,17 @@ use std::path::Path;
 
 use exif::{In, Reader, Tag, Value};
 
-use crate::domain::ExifData;
+use crate::domain::{ExifData, PhotoFormat};
+
+/// Extract capture metadata for a file of the given format: QuickTime metadata
+/// for videos, EXIF for everything else.
+pub fn extract_metadata(path: &Path, format: PhotoFormat) -> Option<ExifData> {
+    if format.is_video() {
+        crate::quicktime::extract_metadata(path)
+    } else {
+        extract_exif(path)
+    }
+}
 
 /// Extract EXIF metadata from a file. Returns None if EXIF data is unavailable or unreadable.
 pub fn extract_exif(path: &Path) -> Option<ExifData> {
@@ -46,12 +56,20 @@ pub fn extract_exif(path: &Path) -> Option<ExifData> {
             _ => None,
         });
 
+    let content_id = exif
+        .get_field(Tag::MakerNote, In::PRIMARY)
+        .and_then(|f| match &f.value {
+            Value::Undefined(bytes, _) => parse_apple_maker_note(bytes),
+            _ => None,
+        });
+
     // Only return Some if we got at least one useful field
     if date.is_some()
         || camera_make.is_some()
         || camera_model.is_some()
         || gps_lat.is_some()
         || width.is_some()
+        || content_id.is_some()
     {
         Some(ExifData {
             date,
@@ -61,12 +79,46 @@ pub fn extract_exif(path: &Path) -> Option<ExifData> {
             gps_lon,
             width,
             height,
+            content_id,
         })
     } else {
         None
     }
 }
 
+/// Apple MakerNote header: "Apple iOS\0", a 2-byte version, then "MM".
+const APPLE_MAKER_NOTE_HEADER: &[u8] = b"Apple iOS\0";
+const APPLE_MAKER_NOTE_IFD_OFFSET: usize = 14;
+/// MakerNote tag holding the content identifier shared with a Live Photo video.
+const APPLE_TAG_CONTENT_ID: u16 = 0x0011;
+
+/// Read the Live Photo content identifier from an Apple MakerNote.
+///
+/// The MakerNote is a big-endian IFD starting after the 14-byte header; value
+/// offsets are relative to the start of the MakerNote.
+fn parse_apple_maker_note(note: &[u8]) -> Option<String> {
+    if !note.starts_with(APPLE_MAKER_NOTE_HEADER) || note.get(12..14) != Some(b"MM") {
+        return None;
+    }
+    let u16_at = |at: usize| Some(u16::from_be_bytes(note.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_be_bytes(note.get(at..at + 4)?.try_into().ok()?));
+
+    let ifd = APPLE_MAKER_NOTE_IFD_OFFSET;
+    let count = u16_at(ifd)? as usize;
+    (0..count).find_map(|i| {
+        let entry = ifd + 2 + i * 12;
+        // ASCII type = 2
+        if u16_at(entry)? != APPLE_TAG_CONTENT_ID || u16_at(entry + 2)? != 2 {
+            return None;
+        }
+        let len = u32_at(entry + 4)? as usize;
+        let start = if len <= 4 { entry + 8 } else { u32_at(entry + 8)? as usize };
+        let raw = note.get(start..start.checked_add(len)?)?;
+        let id = String::from_utf8_lossy(raw).trim_end_matches('\0').trim().to_string();
+        (!id.is_empty()).then_some(id)
+    })
+}
+
 /// Convert GPS DMS (degrees, minutes, seconds) to decimal degrees.
 fn extract_gps_coord(
     exif: &exif::Exif,
@@ -149,4 +201,30 @@ mod tests {
         let result = extract_exif(&path);
         assert!(result.is_none());
     }
+
+    fn apple_maker_note(content_id: &str) -> Vec<u8> {
+        let mut note = b"Apple iOS\0\0\x01MM".to_vec();
+        // One IFD entry; its value lives right after the IFD
+        let value_offset = (note.len() + 2 + 12 + 4) as u32;
+        let value = format!("{content_id}\0");
+        note.extend_from_slice(&1u16.to_be_bytes());
+        note.extend_from_slice(&APPLE_TAG_CONTENT_ID.to_be_bytes());
+        note.extend_from_slice(&2u16.to_be_bytes());
+        note.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        note.extend_from_slice(&value_offset.to_be_bytes());
+        note.extend_from_slice(&0u32.to_be_bytes());
+        note.extend_from_slice(value.as_bytes());
+        note
+    }
+
+    #[test]
+    fn test_parse_apple_maker_note_content_id() {
+        let note = apple_maker_note("A1B2C3D4-0000-1111-2222-333344445555");
+        assert_eq!(
+            parse_apple_maker_note(&note).as_deref(),
+            Some("A1B2C3D4-0000-1111-2222-333344445555")
+        );
+        assert!(parse_apple_maker_note(b"Nikon\0\x02\x10\0\0MM").is_none());
+        assert!(parse_apple_maker_note(&note[..20]).is_none());
+    }
 }
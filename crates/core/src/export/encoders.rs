@@ -0,0 +1,585 @@
+//! Export encoder backends.
+//!
+//! External tools (`sips`, `heif-enc`, `magick`, `vips`) produce HEIC and are
+//! detected at runtime by searching `PATH`. [`ImageEncoder`] is the pure-Rust
+//! fallback built on the `image` crate (JPEG, lossless WebP and, with the `avif`
+//! feature, AVIF) and works everywhere, including on Linux without any tools
+//! installed.
+//!
+//! Every encoder writes to a temp file next to the target and renames it into
+//! place, so an interrupted export never leaves a partial file that a later
+//! run would skip as already exported.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+
+use crate::error::{Error, Result};
+use crate::hasher::raw_preview;
+use crate::scanner::formats::format_from_extension;
+
+/// A backend that converts one photo to a compressed export format.
+pub trait ExportEncoder: Send + Sync {
+    /// Name used to select the encoder (`photopack export --encoder <name>`).
+    fn name(&self) -> &'static str;
+
+    /// File extension of the produced files, without the dot.
+    fn extension(&self) -> &'static str;
+
+    /// Whether the encoder can run on this system.
+    fn is_available(&self) -> bool;
+
+    /// Convert `source` to `target`, creating parent directories as needed.
+    /// Quality: 0–100 (85 recommended for high quality).
+    fn encode(&self, source: &Path, target: &Path, quality: u8) -> Result<()>;
+}
+
+/// Check if a command is available on this system's `PATH`.
+fn command_available(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        let candidate = dir.join(program);
+        #[cfg(windows)]
+        let candidate = candidate.with_extension("exe");
+        is_executable(&candidate)
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Run `write` against a temp path next to `target`, then rename the result
+/// over `target`. The temp file keeps the target's extension, since the
+/// external tools pick the output format from it. On failure the temp file is
+/// removed and `target` is left untouched.
+fn write_atomically(target: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let parent = target.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp = parent.join(format!(
+        ".{}-{}.{}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+        file_name
+    ));
+
+    let result = write(&temp).and_then(|()| Ok(fs::rename(&temp, target)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Run an external conversion tool, mapping a non-zero exit to `ConversionFailed`.
+fn run_tool(command: &mut Command, source: &Path) -> Result<()> {
+    let output = command.output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::ConversionFailed {
+            path: source.to_path_buf(),
+            message: stderr.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// HEIC via the macOS `sips` command (mimics iCloud Photos export).
+pub struct SipsEncoder;
+
+impl ExportEncoder for SipsEncoder {
+    fn name(&self) -> &'static str {
+        "sips"
+    }
+
+    fn extension(&self) -> &'static str {
+        "heic"
+    }
+
+    fn is_available(&self) -> bool {
+        command_available("sips")
+    }
+
+    fn encode(&self, source: &Path, target: &Path, quality: u8) -> Result<()> {
+        write_atomically(target, |temp| {
+            run_tool(
+                Command::new("sips")
+                    .arg("-s")
+                    .arg("format")
+                    .arg("heic")
+                    .arg("-s")
+                    .arg("formatOptions")
+                    .arg(quality.to_string())
+                    .arg(source)
+                    .arg("--out")
+                    .arg(temp),
+                source,
+            )
+        })
+    }
+}
+
+/// HEIC via libheif's `heif-enc` tool (JPEG, PNG and Y4M inputs).
+pub struct HeifEncEncoder;
+
+impl ExportEncoder for HeifEncEncoder {
+    fn name(&self) -> &'static str {
+        "heif-enc"
+    }
+
+    fn extension(&self) -> &'static str {
+        "heic"
+    }
+
+    fn is_available(&self) -> bool {
+        command_available("heif-enc")
+    }
+
+    fn encode(&self, source: &Path, target: &Path, quality: u8) -> Result<()> {
+        write_atomically(target, |temp| {
+            run_tool(
+                Command::new("heif-enc")
+                    .arg("-q")
+                    .arg(quality.to_string())
+                    .arg("-o")
+                    .arg(temp)
+                    .arg(source),
+                source,
+            )
+        })
+    }
+}
+
+/// HEIC via ImageMagick 7 (`magick`, needs its libheif delegate).
+pub struct MagickEncoder;
+
+impl ExportEncoder for MagickEncoder {
+    fn name(&self) -> &'static str {
+        "magick"
+    }
+
+    fn extension(&self) -> &'static str {
+        "heic"
+    }
+
+    fn is_available(&self) -> bool {
+        command_available("magick")
+    }
+
+    fn encode(&self, source: &Path, target: &Path, quality: u8) -> Result<()> {
+        write_atomically(target, |temp| {
+            run_tool(
+                Command::new("magick")
+                    .arg(source)
+                    .arg("-auto-orient")
+                    .arg("-quality")
+                    .arg(quality.to_string())
+                    .arg(temp),
+                source,
+            )
+        })
+    }
+}
+
+/// HEIC via libvips (`vips heifsave`).
+pub struct VipsEncoder;
+
+impl ExportEncoder for VipsEncoder {
+    fn name(&self) -> &'static str {
+        "vips"
+    }
+
+    fn extension(&self) -> &'static str {
+        "heic"
+    }
+
+    fn is_available(&self) -> bool {
+        command_available("vips")
+    }
+
+    fn encode(&self, source: &Path, target: &Path, quality: u8) -> Result<()> {
+        write_atomically(target, |temp| {
+            run_tool(
+                Command::new("vips")
+                    .arg("heifsave")
+                    .arg(source)
+                    .arg(temp)
+                    .arg("--Q")
+                    .arg(quality.to_string()),
+                source,
+            )
+        })
+    }
+}
+
+/// Output format of the pure-Rust [`ImageEncoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    /// Lossless only (the `image` crate has no lossy WebP encoder); quality is ignored.
+    WebP,
+    /// Requires the `avif` feature.
+    Avif,
+}
+
+/// Pure-Rust encoder built on the `image` crate.
+///
+/// Decodes anything the `image` crate reads (applying EXIF orientation, since
+/// the output carries no orientation tag) and RAW files through their embedded
+/// JPEG preview. HEIC sources are not decodable here and fail with
+/// `ConversionFailed`.
+pub struct ImageEncoder {
+    format: ImageFormat,
+}
+
+impl ImageEncoder {
+    pub fn new(format: ImageFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl ExportEncoder for ImageEncoder {
+    fn name(&self) -> &'static str {
+        match self.format {
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.format {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        match self.format {
+            ImageFormat::Jpeg | ImageFormat::WebP => true,
+            ImageFormat::Avif => cfg!(feature = "avif"),
+        }
+    }
+
+    fn encode(&self, source: &Path, target: &Path, quality: u8) -> Result<()> {
+        let img = decode_oriented(source)?;
+        let quality = quality.clamp(1, 100);
+
+        write_atomically(target, |temp| {
+            let mut writer = BufWriter::new(File::create(temp)?);
+            match self.format {
+                ImageFormat::Jpeg => {
+                    let encoder =
+                        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, quality);
+                    DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)?;
+                }
+                ImageFormat::WebP => {
+                    let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut writer);
+                    strip_to_8bit(img).write_with_encoder(encoder)?;
+                }
+                #[cfg(feature = "avif")]
+                ImageFormat::Avif => {
+                    let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                        &mut writer,
+                        6,
+                        quality,
+                    );
+                    strip_to_8bit(img).write_with_encoder(encoder)?;
+                }
+                #[cfg(not(feature = "avif"))]
+                ImageFormat::Avif => {
+                    return Err(Error::EncoderNotAvailable(self.name().to_string()));
+                }
+            }
+            writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            Ok(())
+        })
+    }
+}
+
+/// Convert to 8-bit RGB or RGBA, the layouts the WebP and AVIF encoders accept.
+fn strip_to_8bit(img: DynamicImage) -> DynamicImage {
+    if img.color().has_alpha() {
+        DynamicImage::ImageRgba8(img.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    }
+}
+
+/// Decode a source photo with its EXIF orientation applied to the pixels.
+fn decode_oriented(source: &Path) -> Result<DynamicImage> {
+    let is_raw = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| format_from_extension(&e.to_ascii_lowercase()))
+        .is_some_and(|f| f.is_raw());
+
+    if is_raw {
+        let preview = raw_preview::extract_preview(source).ok_or_else(|| Error::ConversionFailed {
+            path: source.to_path_buf(),
+            message: "no embedded JPEG preview".to_string(),
+        })?;
+        let mut img = image::load_from_memory_with_format(&preview.jpeg, image::ImageFormat::Jpeg)?;
+        if let Some(orientation) = Orientation::from_exif(preview.orientation) {
+            img.apply_orientation(orientation);
+        }
+        return Ok(img);
+    }
+
+    let mut decoder = ImageReader::open(source)?.with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_gradient_jpeg(path: &Path, w: u32, h: u32) {
+        let img = image::RgbImage::from_fn(w, h, |x, y| {
+            image::Rgb([(x * 255 / w) as u8, (y * 255 / h) as u8, ((x + y) % 256) as u8])
+        });
+        img.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_image_encoder_names_and_extensions() {
+        let jpeg = ImageEncoder::new(ImageFormat::Jpeg);
+        assert_eq!((jpeg.name(), jpeg.extension()), ("jpeg", "jpg"));
+        let webp = ImageEncoder::new(ImageFormat::WebP);
+        assert_eq!((webp.name(), webp.extension()), ("webp", "webp"));
+        let avif = ImageEncoder::new(ImageFormat::Avif);
+        assert_eq!((avif.name(), avif.extension()), ("avif", "avif"));
+        assert_eq!(avif.is_available(), cfg!(feature = "avif"));
+    }
+
+    #[test]
+    fn test_jpeg_encoder_writes_decodable_jpeg() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.png");
+        image::RgbImage::from_pixel(64, 48, image::Rgb([10, 200, 30]))
+            .save(&source)
+            .unwrap();
+        let target = tmp.path().join("deep/nested/out.jpg");
+
+        ImageEncoder::new(ImageFormat::Jpeg)
+            .encode(&source, &target, 85)
+            .unwrap();
+
+        let out = image::open(&target).unwrap();
+        assert_eq!((out.width(), out.height()), (64, 48));
+        assert_eq!(
+            image::ImageFormat::from_path(&target).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+    }
+
+    #[test]
+    fn test_jpeg_encoder_quality_affects_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.png");
+        let img = image::RgbImage::from_fn(128, 128, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 2) as u8, ((x * y) % 256) as u8])
+        });
+        img.save(&source).unwrap();
+        let low = tmp.path().join("low.jpg");
+        let high = tmp.path().join("high.jpg");
+
+        let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+        encoder.encode(&source, &low, 10).unwrap();
+        encoder.encode(&source, &high, 100).unwrap();
+
+        let low_size = low.metadata().unwrap().len();
+        let high_size = high.metadata().unwrap().len();
+        assert!(
+            high_size > low_size,
+            "quality 100 ({high_size}) should be larger than quality 10 ({low_size})"
+        );
+    }
+
+    #[test]
+    fn test_webp_encoder_is_lossless() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.png");
+        let img = image::RgbImage::from_fn(32, 32, |x, y| image::Rgb([x as u8 * 8, y as u8 * 8, 77]));
+        img.save(&source).unwrap();
+        let target = tmp.path().join("out.webp");
+
+        ImageEncoder::new(ImageFormat::WebP)
+            .encode(&source, &target, 85)
+            .unwrap();
+
+        let out = image::open(&target).unwrap().to_rgb8();
+        assert_eq!(out, img);
+    }
+
+    #[test]
+    fn test_image_encoder_applies_exif_orientation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("rotated.jpg");
+        // Landscape pixels tagged orientation=6 (rotate 90° CW to display)
+        let img = image::RgbImage::from_pixel(80, 40, image::Rgb([120, 120, 120]));
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 90)
+            .encode_image(&img)
+            .unwrap();
+        fs::write(&source, insert_orientation(&jpeg, 6)).unwrap();
+        let target = tmp.path().join("out.jpg");
+
+        ImageEncoder::new(ImageFormat::Jpeg)
+            .encode(&source, &target, 85)
+            .unwrap();
+
+        let out = image::open(&target).unwrap();
+        assert_eq!((out.width(), out.height()), (40, 80));
+    }
+
+    #[test]
+    fn test_image_encoder_unreadable_source_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("broken.jpg");
+        fs::write(&source, b"not an image").unwrap();
+        let target = tmp.path().join("out.jpg");
+
+        assert!(ImageEncoder::new(ImageFormat::Jpeg)
+            .encode(&source, &target, 85)
+            .is_err());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_failed_write_leaves_no_partial_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let target = tmp.path().join("out/photo.heic");
+
+        let err = write_atomically(&target, |temp| {
+            fs::write(temp, b"half a file")?;
+            Err(Error::ConversionFailed {
+                path: temp.to_path_buf(),
+                message: "interrupted".to_string(),
+            })
+        });
+
+        assert!(err.is_err());
+        assert!(!target.exists());
+        assert_eq!(fs::read_dir(tmp.path().join("out")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_write_atomically_keeps_extension_for_tools() {
+        let tmp = tempfile::tempdir().unwrap();
+        let target = tmp.path().join("photo.heic");
+
+        write_atomically(&target, |temp| {
+            assert_eq!(temp.extension().unwrap(), "heic");
+            assert_eq!(temp.parent(), target.parent());
+            Ok(fs::write(temp, b"encoded")?)
+        })
+        .unwrap();
+
+        assert_eq!(fs::read(&target).unwrap(), b"encoded");
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_image_encoder_decodes_raw_preview() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = tmp.path().join("preview.jpg");
+        create_gradient_jpeg(&preview, 96, 64);
+        let jpeg = fs::read(&preview).unwrap();
+
+        // Minimal little-endian TIFF (DNG) with the preview via JPEGInterchangeFormat
+        let mut raw = b"II*\0".to_vec();
+        raw.extend_from_slice(&8u32.to_le_bytes());
+        let jpeg_offset = 8 + 2 + 2 * 12 + 4;
+        raw.extend_from_slice(&2u16.to_le_bytes());
+        for (tag, value) in [(0x0201u16, jpeg_offset as u32), (0x0202, jpeg.len() as u32)] {
+            raw.extend_from_slice(&tag.to_le_bytes());
+            raw.extend_from_slice(&4u16.to_le_bytes());
+            raw.extend_from_slice(&1u32.to_le_bytes());
+            raw.extend_from_slice(&value.to_le_bytes());
+        }
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        raw.extend_from_slice(&jpeg);
+
+        // Camera exports often carry uppercase extensions
+        for name in ["IMG_0001.dng", "IMG_0002.CR2"] {
+            let source = tmp.path().join(name);
+            fs::write(&source, &raw).unwrap();
+            let target = tmp.path().join("out.jpg");
+
+            ImageEncoder::new(ImageFormat::Jpeg)
+                .encode(&source, &target, 85)
+                .unwrap();
+
+            let out = image::open(&target).unwrap();
+            assert_eq!((out.width(), out.height()), (96, 64), "{name}");
+        }
+    }
+
+    /// Insert an APP1 EXIF segment with the given orientation after SOI.
+    fn insert_orientation(jpeg: &[u8], orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\0*".to_vec();
+        tiff.extend_from_slice(&8u32.to_be_bytes());
+        tiff.extend_from_slice(&1u16.to_be_bytes());
+        tiff.extend_from_slice(&0x0112u16.to_be_bytes());
+        tiff.extend_from_slice(&3u16.to_be_bytes());
+        tiff.extend_from_slice(&1u32.to_be_bytes());
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff);
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    // ── External tools ──────────────────────────────────────────────
+
+    #[test]
+    fn test_external_encoders_produce_heic() {
+        let encoders: [&dyn ExportEncoder; 4] =
+            [&SipsEncoder, &HeifEncEncoder, &MagickEncoder, &VipsEncoder];
+        let names: Vec<_> = encoders.iter().map(|e| e.name()).collect();
+        assert_eq!(names, ["sips", "heif-enc", "magick", "vips"]);
+        assert!(encoders.iter().all(|e| e.extension() == "heic"));
+    }
+
+    #[test]
+    fn test_command_available_missing_program() {
+        assert!(!command_available("photopack-no-such-tool"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_available_finds_shell() {
+        assert!(command_available("sh"));
+    }
+}
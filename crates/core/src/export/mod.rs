@@ -1,72 +1,79 @@
-use std::fs;
+mod encoders;
+
 use std::path::{Path, PathBuf};
-use std::process::Command;
+
+use serde::Serialize;
 
 use crate::error::{Error, Result};
 
+pub use encoders::{
+    ExportEncoder, HeifEncEncoder, ImageEncoder, ImageFormat, MagickEncoder, SipsEncoder,
+    VipsEncoder,
+};
+
 /// Progress callback events for the export operation.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum ExportProgress {
     /// Starting export with total count.
     Start { total: usize },
-    /// A file was converted to HEIC.
+    /// A file was converted to the encoder's format.
     Converted { source: PathBuf, target: PathBuf },
     /// A file was skipped (already exists).
     Skipped { path: PathBuf },
+    /// A file could not be converted.
+    Failed { path: PathBuf, error: String },
     /// Export completed.
-    Complete { converted: usize, skipped: usize },
+    Complete {
+        converted: usize,
+        skipped: usize,
+        failed: usize,
+    },
 }
 
-/// Check if the `sips` command is available on this system.
-pub fn check_sips_available() -> Result<()> {
-    let output = Command::new("which")
-        .arg("sips")
-        .output()
-        .map_err(|_| Error::SipsNotAvailable)?;
-
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err(Error::SipsNotAvailable)
-    }
+/// Encoder names accepted by [`encoder_by_name`], besides `auto`.
+pub const ENCODER_NAMES: &[&str] = &["sips", "heif-enc", "magick", "vips", "jpeg", "webp", "avif"];
+
+/// Look up an encoder by name. `auto` picks the first available one (see [`detect_encoder`]).
+/// The returned encoder may still be unavailable on this system — check
+/// [`ExportEncoder::is_available`] before use.
+pub fn encoder_by_name(name: &str) -> Result<Box<dyn ExportEncoder>> {
+    let encoder: Box<dyn ExportEncoder> = match name {
+        "auto" => detect_encoder(),
+        "sips" => Box::new(SipsEncoder),
+        "heif-enc" => Box::new(HeifEncEncoder),
+        "magick" => Box::new(MagickEncoder),
+        "vips" => Box::new(VipsEncoder),
+        "jpeg" => Box::new(ImageEncoder::new(ImageFormat::Jpeg)),
+        "webp" => Box::new(ImageEncoder::new(ImageFormat::WebP)),
+        "avif" => Box::new(ImageEncoder::new(ImageFormat::Avif)),
+        _ => return Err(Error::UnknownEncoder(name.to_string())),
+    };
+    Ok(encoder)
 }
 
-/// Convert a photo to HEIC format using the macOS `sips` command.
-/// Quality: 0–100 (85 recommended for high quality).
-pub fn convert_to_heic(source: &Path, target: &Path, quality: u8) -> Result<()> {
-    if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    let output = Command::new("sips")
-        .arg("-s")
-        .arg("format")
-        .arg("heic")
-        .arg("-s")
-        .arg("formatOptions")
-        .arg(quality.to_string())
-        .arg(source)
-        .arg("--out")
-        .arg(target)
-        .output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(Error::ConversionFailed {
-            path: source.to_path_buf(),
-            message: stderr.to_string(),
-        });
-    }
-
-    Ok(())
+/// Pick the best available encoder: HEIC tools first (`sips`, `heif-enc`,
+/// `magick`, `vips`), falling back to the pure-Rust JPEG encoder.
+pub fn detect_encoder() -> Box<dyn ExportEncoder> {
+    let heic_tools: [Box<dyn ExportEncoder>; 4] = [
+        Box::new(SipsEncoder),
+        Box::new(HeifEncEncoder),
+        Box::new(MagickEncoder),
+        Box::new(VipsEncoder),
+    ];
+    heic_tools
+        .into_iter()
+        .find(|encoder| encoder.is_available())
+        .unwrap_or_else(|| Box::new(ImageEncoder::new(ImageFormat::Jpeg)))
 }
 
-/// Build the export target path: export_dir/YYYY/MM/DD/stem.heic
+/// Build the export target path: export_dir/YYYY/MM/DD/stem.{extension}
 /// Returns the existing path if a file already exists (enables incremental skip).
-/// Handles collisions with _1, _2, etc. suffixes.
 pub fn build_export_path(
     export_dir: &Path,
     date: (u32, u32, u32),
     original_path: &Path,
+    extension: &str,
 ) -> PathBuf {
     let (year, month, day) = date;
     let dir = export_dir
@@ -79,30 +86,29 @@ pub fn build_export_path(
         .unwrap_or_default()
         .to_string_lossy();
 
-    let base_name = format!("{}.heic", file_stem);
-    let target = dir.join(&base_name);
-
-    if !target.exists() {
-        return target;
-    }
-
-    // Target exists — skip (return existing path for incremental behavior)
-    target
+    dir.join(format!("{}.{}", file_stem, extension))
 }
 
-/// Export a single photo to HEIC.
+/// Export a single photo with the given encoder.
 /// Returns `Ok(false)` if skipped (target exists), `Ok(true)` if converted.
-pub fn export_photo_to_heic(source: &Path, target: &Path, quality: u8) -> Result<bool> {
+pub fn export_photo(
+    encoder: &dyn ExportEncoder,
+    source: &Path,
+    target: &Path,
+    quality: u8,
+) -> Result<bool> {
     if target.exists() {
         return Ok(false);
     }
 
-    convert_to_heic(source, target, quality)?;
+    encoder.encode(source, target, quality)?;
     Ok(true)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use super::*;
 
     // ── build_export_path ───────────────────────────────────────────
@@ -113,6 +119,7 @@ mod tests {
             Path::new("/export"),
             (2024, 6, 15),
             Path::new("/source/photo.jpg"),
+            "heic",
         );
         assert_eq!(target, PathBuf::from("/export/2024/06/15/photo.heic"));
     }
@@ -123,6 +130,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 1),
             Path::new("/photos/IMG_1234.jpg"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
         assert_eq!(target.file_stem().unwrap(), "IMG_1234");
@@ -134,6 +142,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 1),
             Path::new("/photos/IMG_1234.CR2"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
         assert_eq!(target.file_stem().unwrap(), "IMG_1234");
@@ -145,6 +154,7 @@ mod tests {
             Path::new("/export"),
             (2024, 3, 20),
             Path::new("/photos/screenshot.png"),
+            "heic",
         );
         assert_eq!(target, PathBuf::from("/export/2024/03/20/screenshot.heic"));
     }
@@ -155,6 +165,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 1),
             Path::new("/photos/scan.tiff"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
         assert_eq!(target.file_stem().unwrap(), "scan");
@@ -166,6 +177,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 1),
             Path::new("/photos/RAW_5432.DNG"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
         assert_eq!(target.file_stem().unwrap(), "RAW_5432");
@@ -177,6 +189,7 @@ mod tests {
             Path::new("/export"),
             (2024, 5, 10),
             Path::new("/photos/DSC_0001.NEF"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
         assert_eq!(target.file_stem().unwrap(), "DSC_0001");
@@ -188,6 +201,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 1),
             Path::new("/photos/image.webp"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
     }
@@ -198,6 +212,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 1),
             Path::new("/photos/already.heic"),
+            "heic",
         );
         assert_eq!(target.extension().unwrap(), "heic");
         assert_eq!(target.file_stem().unwrap(), "already");
@@ -209,6 +224,7 @@ mod tests {
             Path::new("/export"),
             (2024, 1, 5),
             Path::new("/source/photo.png"),
+            "heic",
         );
         assert_eq!(target, PathBuf::from("/export/2024/01/05/photo.heic"));
     }
@@ -219,6 +235,7 @@ mod tests {
             Path::new("/export"),
             (2024, 6, 15),
             Path::new("/source/noext"),
+            "heic",
         );
         assert_eq!(target.file_name().unwrap(), "noext.heic");
     }
@@ -230,7 +247,12 @@ mod tests {
         fs::create_dir_all(&date_dir).unwrap();
         fs::write(date_dir.join("photo.heic"), b"existing").unwrap();
 
-        let target = build_export_path(tmp.path(), (2024, 6, 15), Path::new("/source/photo.jpg"));
+        let target = build_export_path(
+            tmp.path(),
+            (2024, 6, 15),
+            Path::new("/source/photo.jpg"),
+            "heic",
+        );
         assert_eq!(target.file_name().unwrap(), "photo.heic");
         assert!(target.exists());
     }
@@ -238,8 +260,8 @@ mod tests {
     #[test]
     fn test_build_export_path_different_dates_no_collision() {
         let export_dir = Path::new("/export");
-        let t1 = build_export_path(export_dir, (2024, 1, 1), Path::new("/a/photo.jpg"));
-        let t2 = build_export_path(export_dir, (2024, 1, 2), Path::new("/b/photo.jpg"));
+        let t1 = build_export_path(export_dir, (2024, 1, 1), Path::new("/a/photo.jpg"), "heic");
+        let t2 = build_export_path(export_dir, (2024, 1, 2), Path::new("/b/photo.jpg"), "heic");
         assert_ne!(t1, t2);
         assert_eq!(t1, PathBuf::from("/export/2024/01/01/photo.heic"));
         assert_eq!(t2, PathBuf::from("/export/2024/01/02/photo.heic"));
@@ -248,22 +270,22 @@ mod tests {
     #[test]
     fn test_build_export_path_different_stems_no_collision() {
         let export_dir = Path::new("/export");
-        let t1 = build_export_path(export_dir, (2024, 1, 1), Path::new("/a/sunset.jpg"));
-        let t2 = build_export_path(export_dir, (2024, 1, 1), Path::new("/b/portrait.jpg"));
+        let t1 = build_export_path(export_dir, (2024, 1, 1), Path::new("/a/sunset.jpg"), "heic");
+        let t2 = build_export_path(export_dir, (2024, 1, 1), Path::new("/b/portrait.jpg"), "heic");
         assert_ne!(t1, t2);
     }
 
-    // ── export_photo_to_heic ────────────────────────────────────────
+    // ── export_photo ────────────────────────────────────────
 
     #[test]
-    fn test_export_photo_to_heic_skips_existing_target() {
+    fn test_export_photo_skips_existing_target() {
         let tmp = tempfile::tempdir().unwrap();
         let source = tmp.path().join("source.jpg");
         let target = tmp.path().join("target.heic");
         fs::write(&source, b"jpeg data").unwrap();
         fs::write(&target, b"existing heic").unwrap();
 
-        let result = export_photo_to_heic(&source, &target, 85).unwrap();
+        let result = export_photo(&SipsEncoder, &source, &target, 85).unwrap();
         assert!(!result, "should skip when target exists");
         // Content should NOT change
         assert_eq!(fs::read(&target).unwrap(), b"existing heic");
@@ -271,7 +293,7 @@ mod tests {
 
     #[cfg(target_os = "macos")]
     #[test]
-    fn test_export_photo_to_heic_converts_new_file() {
+    fn test_export_photo_converts_new_file() {
         let tmp = tempfile::tempdir().unwrap();
         let source = tmp.path().join("source.jpg");
         let target = tmp.path().join("output.heic");
@@ -282,17 +304,17 @@ mod tests {
         });
         img.save(&source).unwrap();
 
-        let result = export_photo_to_heic(&source, &target, 85).unwrap();
+        let result = export_photo(&SipsEncoder, &source, &target, 85).unwrap();
         assert!(result, "should convert when target doesn't exist");
         assert!(target.exists());
         assert!(target.metadata().unwrap().len() > 0);
     }
 
-    // ── convert_to_heic ─────────────────────────────────────────────
+    // ── SipsEncoder ─────────────────────────────────────────────
 
     #[cfg(target_os = "macos")]
     #[test]
-    fn test_convert_to_heic_creates_parent_directories() {
+    fn test_sips_encoder_creates_parent_directories() {
         let tmp = tempfile::tempdir().unwrap();
         let source = tmp.path().join("source.jpg");
         let target = tmp.path().join("deep/nested/dir/output.heic");
@@ -302,25 +324,25 @@ mod tests {
         });
         img.save(&source).unwrap();
 
-        convert_to_heic(&source, &target, 85).unwrap();
+        SipsEncoder.encode(&source, &target, 85).unwrap();
         assert!(target.exists());
     }
 
     #[cfg(target_os = "macos")]
     #[test]
-    fn test_convert_to_heic_invalid_source_produces_no_output() {
+    fn test_sips_encoder_invalid_source_produces_no_output() {
         let tmp = tempfile::tempdir().unwrap();
         let source = tmp.path().join("nonexistent.jpg");
         let target = tmp.path().join("output.heic");
 
         // sips exits 0 even for missing files, but produces no output file
-        let _ = convert_to_heic(&source, &target, 85);
+        let _ = SipsEncoder.encode(&source, &target, 85);
         assert!(!target.exists(), "no output should be created for missing source");
     }
 
     #[cfg(target_os = "macos")]
     #[test]
-    fn test_convert_to_heic_output_differs_from_source() {
+    fn test_sips_encoder_output_differs_from_source() {
         let tmp = tempfile::tempdir().unwrap();
         let source = tmp.path().join("source.jpg");
         let target = tmp.path().join("output.heic");
@@ -330,7 +352,7 @@ mod tests {
         });
         img.save(&source).unwrap();
 
-        convert_to_heic(&source, &target, 85).unwrap();
+        SipsEncoder.encode(&source, &target, 85).unwrap();
 
         let source_bytes = fs::read(&source).unwrap();
         let target_bytes = fs::read(&target).unwrap();
@@ -339,7 +361,7 @@ mod tests {
 
     #[cfg(target_os = "macos")]
     #[test]
-    fn test_convert_to_heic_quality_affects_size() {
+    fn test_sips_encoder_quality_affects_size() {
         let tmp = tempfile::tempdir().unwrap();
         let source = tmp.path().join("source.jpg");
         let target_low = tmp.path().join("low.heic");
@@ -350,8 +372,8 @@ mod tests {
         });
         img.save(&source).unwrap();
 
-        convert_to_heic(&source, &target_low, 10).unwrap();
-        convert_to_heic(&source, &target_high, 100).unwrap();
+        SipsEncoder.encode(&source, &target_low, 10).unwrap();
+        SipsEncoder.encode(&source, &target_high, 100).unwrap();
 
         let low_size = target_low.metadata().unwrap().len();
         let high_size = target_high.metadata().unwrap().len();
@@ -361,18 +383,51 @@ mod tests {
         );
     }
 
-    // ── check_sips_available ────────────────────────────────────────
+    #[test]
+    fn test_build_export_path_uses_encoder_extension() {
+        let target = build_export_path(
+            Path::new("/export"),
+            (2024, 6, 15),
+            Path::new("/source/IMG_0001.CR2"),
+            "jpg",
+        );
+        assert_eq!(target, PathBuf::from("/export/2024/06/15/IMG_0001.jpg"));
+    }
 
     #[test]
-    fn test_check_sips_available_on_macos() {
-        #[cfg(target_os = "macos")]
-        {
-            assert!(check_sips_available().is_ok());
-        }
+    fn test_export_photo_converts_with_image_encoder() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.png");
+        let target = tmp.path().join("2024/01/01/source.webp");
+        image::RgbImage::from_pixel(16, 16, image::Rgb([1, 2, 3]))
+            .save(&source)
+            .unwrap();
+
+        let encoder = ImageEncoder::new(ImageFormat::WebP);
+        assert!(export_photo(&encoder, &source, &target, 85).unwrap());
+        assert!(target.exists());
+        assert!(!export_photo(&encoder, &source, &target, 85).unwrap());
+    }
 
-        #[cfg(not(target_os = "macos"))]
-        {
-            assert!(check_sips_available().is_err());
+    // ── encoder_by_name ─────────────────────────────────────────────
+
+    #[test]
+    fn test_encoder_by_name_known_names() {
+        for name in ENCODER_NAMES {
+            assert_eq!(encoder_by_name(name).unwrap().name(), *name);
         }
     }
+
+    #[test]
+    fn test_encoder_by_name_unknown_errors() {
+        let err = encoder_by_name("gimp").err().unwrap();
+        assert!(matches!(err, Error::UnknownEncoder(ref name) if name == "gimp"));
+    }
+
+    #[test]
+    fn test_encoder_by_name_auto_is_available() {
+        let encoder = encoder_by_name("auto").unwrap();
+        assert!(encoder.is_available());
+        assert!(ENCODER_NAMES.contains(&encoder.name()));
+    }
 }
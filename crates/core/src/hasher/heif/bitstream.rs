@@ -0,0 +1,571 @@
+//! HEVC NAL units, RBSP bit reading and parameter sets (VPS is skipped).
+//!
+//! Only what an intra-only Main / Main 10 / Main Still Picture decoder needs is
+//! kept; fields that merely have to be skipped are parsed into locals. Range
+//! extension, tiles, 4:2:2/4:4:4 and separate colour planes are rejected here so
+//! the slice decoder never sees them.
+
+/// NAL unit types used by HEIF image items.
+pub const NAL_SPS: u8 = 33;
+pub const NAL_PPS: u8 = 34;
+
+/// Upper bound on luma samples per picture (guards allocation on corrupt SPS).
+const MAX_PICTURE_SAMPLES: u64 = 64 * 1024 * 1024;
+
+/// Split a NAL unit into its type and emulation-prevention-free payload.
+pub fn nal_payload(nal: &[u8]) -> Option<(u8, Vec<u8>)> {
+    if nal.len() < 2 || nal[0] & 0x80 != 0 {
+        return None;
+    }
+    let nal_type = (nal[0] >> 1) & 0x3F;
+    let mut rbsp = Vec::with_capacity(nal.len() - 2);
+    let mut zeros = 0;
+    for &b in &nal[2..] {
+        if zeros >= 2 && b == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        rbsp.push(b);
+    }
+    Some((nal_type, rbsp))
+}
+
+/// MSB-first bit reader over an RBSP.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Read one bit; past the end of the data the stream reads as zeros.
+    pub fn bit(&mut self) -> u32 {
+        let byte = self.data.get(self.pos >> 3).copied().unwrap_or(0);
+        let bit = (byte >> (7 - (self.pos & 7))) & 1;
+        self.pos += 1;
+        u32::from(bit)
+    }
+
+    /// Read `n` (≤ 32) bits.
+    pub fn bits(&mut self, n: u32) -> u32 {
+        let mut v = 0u32;
+        for _ in 0..n {
+            v = (v << 1) | self.bit();
+        }
+        v
+    }
+
+    pub fn flag(&mut self) -> bool {
+        self.bit() == 1
+    }
+
+    /// ue(v). Returns None for codes longer than 32 bits or reads past the end.
+    pub fn ue(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while self.bit() == 0 {
+            zeros += 1;
+            if zeros > 31 || self.pos > self.data.len() * 8 {
+                return None;
+            }
+        }
+        Some(((1u64 << zeros) - 1 + u64::from(self.bits(zeros))) as u32)
+    }
+
+    /// se(v).
+    pub fn se(&mut self) -> Option<i32> {
+        let k = self.ue()?;
+        let magnitude = k.div_ceil(2) as i32;
+        Some(if k & 1 == 1 { magnitude } else { -magnitude })
+    }
+
+    pub fn skip(&mut self, n: usize) {
+        self.pos += n;
+    }
+
+    pub fn byte_align(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.pos > self.data.len() * 8
+    }
+}
+
+/// Per-size, per-matrix scaling factors (`ScalingFactor` in the spec), stored
+/// as the 8x8 (or 4x4) coefficient grid that larger blocks upsample from.
+#[derive(Debug, Clone)]
+pub struct ScalingList {
+    /// [sizeId][matrixId] → 16 or 64 raster-order coefficients.
+    pub lists: [[[u8; 64]; 6]; 4],
+    /// DC values for 16x16 and 32x32 (sizeId 2 and 3).
+    pub dc: [[u8; 6]; 4],
+}
+
+const DEFAULT_INTRA_8X8: [u8; 64] = [
+    16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 17, 16, 17, 16, 17, 18, 17, 18, 18, 17, 18, 21, 19, 20,
+    21, 20, 19, 21, 24, 22, 22, 24, 24, 22, 22, 24, 25, 25, 27, 30, 27, 25, 25, 29, 31, 35, 35, 31,
+    29, 36, 41, 44, 41, 36, 47, 54, 54, 47, 65, 70, 65, 88, 88, 115,
+];
+
+const DEFAULT_INTER_8X8: [u8; 64] = [
+    16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 17, 17, 17, 17, 17, 18, 18, 18, 18, 18, 18, 20, 20, 20,
+    20, 20, 20, 20, 24, 24, 24, 24, 24, 24, 24, 24, 25, 25, 25, 25, 25, 25, 25, 28, 28, 28, 28, 28,
+    28, 33, 33, 33, 33, 33, 41, 41, 41, 41, 54, 54, 54, 71, 71, 91,
+];
+
+impl ScalingList {
+    /// Table 7-5/7-6 defaults (flat 16 for 4x4), in raster order.
+    pub fn default_lists() -> Self {
+        let mut lists = [[[16u8; 64]; 6]; 4];
+        let diag8 = diagonal_scan(8);
+        for size_lists in lists.iter_mut().skip(1) {
+            for (matrix_id, list) in size_lists.iter_mut().enumerate() {
+                let src = if matrix_id < 3 {
+                    &DEFAULT_INTRA_8X8
+                } else {
+                    &DEFAULT_INTER_8X8
+                };
+                for (i, &(x, y)) in diag8.iter().enumerate() {
+                    list[y as usize * 8 + x as usize] = src[i];
+                }
+            }
+        }
+        Self {
+            lists,
+            dc: [[16; 6]; 4],
+        }
+    }
+
+    /// scaling_list_data() (7.3.4).
+    fn parse(r: &mut BitReader) -> Option<Self> {
+        let mut sl = Self::default_lists();
+        let diag4 = diagonal_scan(4);
+        let diag8 = diagonal_scan(8);
+        for size_id in 0..4 {
+            let step = if size_id == 3 { 3 } else { 1 };
+            let mut matrix_id = 0usize;
+            while matrix_id < 6 {
+                let coef_num = if size_id == 0 { 16 } else { 64 };
+                if !r.flag() {
+                    // scaling_list_pred_matrix_id_delta: 0 = default, else copy
+                    let delta = r.ue()? as usize * step;
+                    if delta > 0 {
+                        let ref_id = matrix_id.checked_sub(delta)?;
+                        sl.lists[size_id][matrix_id] = sl.lists[size_id][ref_id];
+                        sl.dc[size_id][matrix_id] = sl.dc[size_id][ref_id];
+                    } else {
+                        let defaults = Self::default_lists();
+                        sl.lists[size_id][matrix_id] = defaults.lists[size_id][matrix_id];
+                        sl.dc[size_id][matrix_id] = 16;
+                    }
+                } else {
+                    let mut next = 8i32;
+                    if size_id > 1 {
+                        let dc = r.se()? + 8;
+                        next = dc;
+                        sl.dc[size_id][matrix_id] = u8::try_from(dc).ok()?;
+                    }
+                    let scan: &[(u8, u8)] = if size_id == 0 { &diag4 } else { &diag8 };
+                    let width = if size_id == 0 { 4 } else { 8 };
+                    for &(x, y) in scan.iter().take(coef_num) {
+                        next = (next + r.se()? + 256) % 256;
+                        sl.lists[size_id][matrix_id][y as usize * width + x as usize] = next as u8;
+                    }
+                    if size_id <= 1 {
+                        sl.dc[size_id][matrix_id] = sl.lists[size_id][matrix_id][0];
+                    }
+                }
+                matrix_id += step;
+            }
+        }
+        Some(sl)
+    }
+
+    /// Scaling factor m for coefficient (x, y) of a `1 << log2_size` block.
+    pub fn factor(&self, log2_size: u32, matrix_id: usize, x: usize, y: usize) -> i32 {
+        let size_id = log2_size as usize - 2;
+        if size_id == 0 {
+            return i32::from(self.lists[0][matrix_id][y * 4 + x]);
+        }
+        if size_id >= 2 && x == 0 && y == 0 {
+            return i32::from(self.dc[size_id][matrix_id]);
+        }
+        let ratio = 1 << (log2_size - 3);
+        i32::from(self.lists[size_id][matrix_id][(y / ratio) * 8 + x / ratio])
+    }
+}
+
+/// Up-right diagonal scan of a `size`x`size` block (6.5.3) as (x, y) pairs.
+pub fn diagonal_scan(size: usize) -> Vec<(u8, u8)> {
+    let mut out = Vec::with_capacity(size * size);
+    let (mut x, mut y) = (0i32, 0i32);
+    let size = size as i32;
+    while out.len() < (size * size) as usize {
+        while y >= 0 {
+            if x < size && y < size {
+                out.push((x as u8, y as u8));
+            }
+            y -= 1;
+            x += 1;
+        }
+        y = x;
+        x = 0;
+    }
+    out
+}
+
+/// Sequence parameter set fields the intra decoder uses.
+#[derive(Debug, Clone)]
+pub struct Sps {
+    pub id: u32,
+    /// 0 = monochrome, 1 = 4:2:0.
+    pub chroma_format_idc: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Conformance window in luma samples: left, right, top, bottom.
+    pub conf_window: [u32; 4],
+    pub bit_depth: u32,
+    pub bit_depth_chroma: u32,
+    pub log2_max_poc_lsb: u32,
+    pub log2_min_cb_size: u32,
+    pub log2_ctb_size: u32,
+    pub log2_min_tb_size: u32,
+    pub log2_max_tb_size: u32,
+    pub max_transform_hierarchy_depth_intra: u32,
+    pub scaling_list: Option<ScalingList>,
+    pub sao_enabled: bool,
+    pub pcm: Option<PcmParams>,
+    pub num_short_term_ref_pic_sets: u32,
+    /// NumDeltaPocs of each SPS short-term RPS (needed to parse predicted sets).
+    pub st_rps_num_delta_pocs: Vec<u32>,
+    pub long_term_ref_pics_present: bool,
+    pub num_long_term_ref_pics_sps: u32,
+    pub temporal_mvp_enabled: bool,
+    pub strong_intra_smoothing: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PcmParams {
+    pub bit_depth: u32,
+    pub bit_depth_chroma: u32,
+    pub log2_min_size: u32,
+    pub log2_max_size: u32,
+}
+
+impl Sps {
+    pub fn width_in_ctbs(&self) -> u32 {
+        self.width.div_ceil(1 << self.log2_ctb_size)
+    }
+
+    pub fn height_in_ctbs(&self) -> u32 {
+        self.height.div_ceil(1 << self.log2_ctb_size)
+    }
+
+    /// seq_parameter_set_rbsp() up to the VUI (7.3.2.2). Everything after the
+    /// VUI is extension data that Main-family profiles leave disabled.
+    pub fn parse(rbsp: &[u8]) -> Option<Self> {
+        let mut r = BitReader::new(rbsp);
+        r.skip(4); // sps_video_parameter_set_id
+        let max_sub_layers_minus1 = r.bits(3);
+        r.skip(1); // sps_temporal_id_nesting_flag
+        let profile_idc = profile_tier_level(&mut r, max_sub_layers_minus1);
+        // Main, Main 10 and Main Still Picture; range extensions change the syntax
+        if !matches!(profile_idc, 1..=3) {
+            return None;
+        }
+        let id = r.ue()?;
+        let chroma_format_idc = r.ue()?;
+        if chroma_format_idc > 1 {
+            return None;
+        }
+        let width = r.ue()?;
+        let height = r.ue()?;
+        let mut conf_window = [0; 4];
+        if r.flag() {
+            let sub = if chroma_format_idc == 1 { 2 } else { 1 };
+            for v in &mut conf_window {
+                *v = r.ue()?.checked_mul(sub)?;
+            }
+        }
+        let bit_depth = r.ue()? + 8;
+        let bit_depth_chroma = r.ue()? + 8;
+        if bit_depth > 12 || bit_depth_chroma > 12 {
+            return None;
+        }
+        let log2_max_poc_lsb = r.ue()? + 4;
+        let ordering_info = r.flag();
+        let first = if ordering_info {
+            0
+        } else {
+            max_sub_layers_minus1
+        };
+        for _ in first..=max_sub_layers_minus1 {
+            r.ue()?;
+            r.ue()?;
+            r.ue()?;
+        }
+        let log2_min_cb_size = r.ue()? + 3;
+        let log2_ctb_size = log2_min_cb_size + r.ue()?;
+        let log2_min_tb_size = r.ue()? + 2;
+        let log2_max_tb_size = log2_min_tb_size + r.ue()?;
+        r.ue()?; // max_transform_hierarchy_depth_inter
+        let max_transform_hierarchy_depth_intra = r.ue()?;
+        if !(4..=6).contains(&log2_ctb_size)
+            || log2_max_tb_size > 5.min(log2_ctb_size)
+            || log2_min_tb_size >= log2_min_cb_size
+        {
+            return None;
+        }
+        let scaling_list = if r.flag() {
+            if r.flag() {
+                Some(ScalingList::parse(&mut r)?)
+            } else {
+                Some(ScalingList::default_lists())
+            }
+        } else {
+            None
+        };
+        r.skip(1); // amp_enabled_flag
+        let sao_enabled = r.flag();
+        let pcm = if r.flag() {
+            let bit_depth = r.bits(4) + 1;
+            let bit_depth_chroma = r.bits(4) + 1;
+            let log2_min_size = r.ue()? + 3;
+            let log2_max_size = log2_min_size + r.ue()?;
+            r.skip(1); // pcm_loop_filter_disabled_flag
+            Some(PcmParams {
+                bit_depth,
+                bit_depth_chroma,
+                log2_min_size,
+                log2_max_size,
+            })
+        } else {
+            None
+        };
+        let num_short_term_ref_pic_sets = r.ue()?;
+        if num_short_term_ref_pic_sets > 64 {
+            return None;
+        }
+        let mut st_rps_num_delta_pocs = Vec::new();
+        for i in 0..num_short_term_ref_pic_sets {
+            let n = st_ref_pic_set(
+                &mut r,
+                i,
+                num_short_term_ref_pic_sets,
+                &st_rps_num_delta_pocs,
+            )?;
+            st_rps_num_delta_pocs.push(n);
+        }
+        let long_term_ref_pics_present = r.flag();
+        let mut num_long_term_ref_pics_sps = 0;
+        if long_term_ref_pics_present {
+            num_long_term_ref_pics_sps = r.ue()?;
+            if num_long_term_ref_pics_sps > 32 {
+                return None;
+            }
+            for _ in 0..num_long_term_ref_pics_sps {
+                r.skip(log2_max_poc_lsb as usize + 1);
+            }
+        }
+        let temporal_mvp_enabled = r.flag();
+        let strong_intra_smoothing = r.flag();
+        if r.exhausted()
+            || width == 0
+            || height == 0
+            || u64::from(width) * u64::from(height) > MAX_PICTURE_SAMPLES
+            || conf_window[0] + conf_window[1] >= width
+            || conf_window[2] + conf_window[3] >= height
+        {
+            return None;
+        }
+        Some(Self {
+            id,
+            chroma_format_idc,
+            width,
+            height,
+            conf_window,
+            bit_depth,
+            bit_depth_chroma,
+            log2_max_poc_lsb,
+            log2_min_cb_size,
+            log2_ctb_size,
+            log2_min_tb_size,
+            log2_max_tb_size,
+            max_transform_hierarchy_depth_intra,
+            scaling_list,
+            sao_enabled,
+            pcm,
+            num_short_term_ref_pic_sets,
+            st_rps_num_delta_pocs,
+            long_term_ref_pics_present,
+            num_long_term_ref_pics_sps,
+            temporal_mvp_enabled,
+            strong_intra_smoothing,
+        })
+    }
+}
+
+/// profile_tier_level() (7.3.3). Returns general_profile_idc, falling back to
+/// the first compatibility flag set when the idc itself is 0.
+fn profile_tier_level(r: &mut BitReader, max_sub_layers_minus1: u32) -> u32 {
+    r.skip(3); // general_profile_space, general_tier_flag
+    let mut profile_idc = r.bits(5);
+    let compatibility = r.bits(32);
+    if profile_idc == 0 {
+        profile_idc = compatibility.leading_zeros();
+    }
+    r.skip(48); // source flags + constraint flags
+    r.skip(8); // general_level_idc
+    let mut profile_present = [false; 8];
+    let mut level_present = [false; 8];
+    for i in 0..max_sub_layers_minus1 as usize {
+        profile_present[i] = r.flag();
+        level_present[i] = r.flag();
+    }
+    if max_sub_layers_minus1 > 0 {
+        r.skip(2 * (8 - max_sub_layers_minus1 as usize));
+    }
+    for i in 0..max_sub_layers_minus1 as usize {
+        if profile_present[i] {
+            r.skip(88);
+        }
+        if level_present[i] {
+            r.skip(8);
+        }
+    }
+    profile_idc
+}
+
+/// st_ref_pic_set(idx) (7.3.7). Only the syntax is consumed; returns NumDeltaPocs.
+pub fn st_ref_pic_set(r: &mut BitReader, idx: u32, num_sets: u32, previous: &[u32]) -> Option<u32> {
+    let inter_rps_pred = idx != 0 && r.flag();
+    if inter_rps_pred {
+        let delta_idx = if idx == num_sets { r.ue()? + 1 } else { 1 };
+        let ref_idx = idx.checked_sub(delta_idx)?;
+        let ref_num = *previous.get(ref_idx as usize)?;
+        r.skip(1); // delta_rps_sign
+        r.ue()?; // abs_delta_rps_minus1
+        let mut count = 0;
+        for _ in 0..=ref_num {
+            let used_by_curr = r.flag();
+            let use_delta = used_by_curr || r.flag();
+            if use_delta {
+                count += 1;
+            }
+        }
+        Some(count)
+    } else {
+        let negative = r.ue()?;
+        let positive = r.ue()?;
+        if negative > 16 || positive > 16 {
+            return None;
+        }
+        for _ in 0..negative + positive {
+            r.ue()?;
+            r.skip(1);
+        }
+        Some(negative + positive)
+    }
+}
+
+/// Picture parameter set fields the intra decoder uses.
+#[derive(Debug, Clone)]
+pub struct Pps {
+    pub id: u32,
+    pub sps_id: u32,
+    pub dependent_slice_segments_enabled: bool,
+    pub output_flag_present: bool,
+    pub num_extra_slice_header_bits: u32,
+    pub sign_data_hiding: bool,
+    pub init_qp: i32,
+    pub transform_skip_enabled: bool,
+    pub cu_qp_delta_enabled: bool,
+    pub diff_cu_qp_delta_depth: u32,
+    pub slice_chroma_qp_offsets_present: bool,
+    pub transquant_bypass_enabled: bool,
+    pub entropy_coding_sync: bool,
+    pub loop_filter_across_slices: bool,
+    pub deblocking_override_enabled: bool,
+    pub deblocking_disabled: bool,
+    pub scaling_list: Option<ScalingList>,
+    pub slice_header_extension_present: bool,
+}
+
+impl Pps {
+    /// pic_parameter_set_rbsp() (7.3.2.3). Tiles and PPS extensions are rejected.
+    pub fn parse(rbsp: &[u8]) -> Option<Self> {
+        let mut r = BitReader::new(rbsp);
+        let id = r.ue()?;
+        let sps_id = r.ue()?;
+        let dependent_slice_segments_enabled = r.flag();
+        let output_flag_present = r.flag();
+        let num_extra_slice_header_bits = r.bits(3);
+        let sign_data_hiding = r.flag();
+        r.skip(1); // cabac_init_present_flag
+        r.ue()?; // num_ref_idx_l0_default_active_minus1
+        r.ue()?; // num_ref_idx_l1_default_active_minus1
+        let init_qp = 26 + r.se()?;
+        r.skip(1); // constrained_intra_pred_flag: no-op when every CU is intra
+        let transform_skip_enabled = r.flag();
+        let cu_qp_delta_enabled = r.flag();
+        let diff_cu_qp_delta_depth = if cu_qp_delta_enabled { r.ue()? } else { 0 };
+        r.se()?; // pps_cb_qp_offset
+        r.se()?; // pps_cr_qp_offset
+        let slice_chroma_qp_offsets_present = r.flag();
+        r.skip(2); // weighted_pred_flag, weighted_bipred_flag
+        let transquant_bypass_enabled = r.flag();
+        if r.flag() {
+            return None; // tiles_enabled_flag
+        }
+        let entropy_coding_sync = r.flag();
+        let loop_filter_across_slices = r.flag();
+        let mut deblocking_override_enabled = false;
+        let mut deblocking_disabled = false;
+        if r.flag() {
+            deblocking_override_enabled = r.flag();
+            deblocking_disabled = r.flag();
+            if !deblocking_disabled {
+                r.se()?;
+                r.se()?;
+            }
+        }
+        let scaling_list = if r.flag() {
+            Some(ScalingList::parse(&mut r)?)
+        } else {
+            None
+        };
+        r.skip(1); // lists_modification_present_flag
+        r.ue()?; // log2_parallel_merge_level_minus2
+        let slice_header_extension_present = r.flag();
+        if r.flag() {
+            return None; // pps_extension_present_flag
+        }
+        if r.exhausted() || diff_cu_qp_delta_depth > 3 {
+            return None;
+        }
+        Some(Self {
+            id,
+            sps_id,
+            dependent_slice_segments_enabled,
+            output_flag_present,
+            num_extra_slice_header_bits,
+            sign_data_hiding,
+            init_qp,
+            transform_skip_enabled,
+            cu_qp_delta_enabled,
+            diff_cu_qp_delta_depth,
+            slice_chroma_qp_offsets_present,
+            transquant_bypass_enabled,
+            entropy_coding_sync,
+            loop_filter_across_slices,
+            deblocking_override_enabled,
+            deblocking_disabled,
+            scaling_list,
+            slice_header_extension_present,
+        })
+    }
+}
@@ -0,0 +1,321 @@
+//! CABAC arithmetic decoding engine (9.3.4.3) and the I-slice context set.
+//!
+//! The engine follows the spec's 9-bit offset model bit for bit, so after a
+//! terminating bin equal to 1 the reader sits just past the final `1` bit and
+//! the caller only has to byte-align before PCM samples or the next substream.
+
+use super::bitstream::BitReader;
+
+/// Context variable: probability state index and most probable symbol.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Context {
+    pub(super) state: u8,
+    pub(super) mps: u8,
+}
+
+// Offsets of each syntax element's contexts in the context table.
+pub const SAO_MERGE: usize = 0;
+pub const SAO_TYPE: usize = 1;
+pub const SPLIT_CU: usize = 2;
+pub const TRANSQUANT_BYPASS: usize = 5;
+pub const PART_MODE: usize = 6;
+pub const PREV_INTRA_LUMA: usize = 7;
+pub const INTRA_CHROMA: usize = 8;
+pub const SPLIT_TRANSFORM: usize = 9;
+pub const CBF_LUMA: usize = 12;
+pub const CBF_CHROMA: usize = 14;
+pub const CU_QP_DELTA: usize = 19;
+pub const TRANSFORM_SKIP: usize = 21;
+pub const LAST_X_PREFIX: usize = 23;
+pub const LAST_Y_PREFIX: usize = 41;
+pub const CODED_SUB_BLOCK: usize = 59;
+pub const SIG_COEFF: usize = 63;
+pub const GREATER1: usize = 107;
+pub const GREATER2: usize = 131;
+pub const NUM_CONTEXTS: usize = 137;
+
+/// initValue for initType 0 (I slices), in the offset order above.
+const INIT_VALUES: [u8; NUM_CONTEXTS] = [
+    // sao_merge_left_flag / sao_merge_up_flag, sao_type_idx
+    153, 200, //
+    // split_cu_flag
+    139, 141, 157, //
+    // cu_transquant_bypass_flag, part_mode, prev_intra_luma_pred_flag
+    154, 184, 184, //
+    // intra_chroma_pred_mode
+    63, //
+    // split_transform_flag
+    153, 138, 138, //
+    // cbf_luma
+    111, 141, //
+    // cbf_cb / cbf_cr
+    94, 138, 182, 154, 154, //
+    // cu_qp_delta_abs
+    154, 154, //
+    // transform_skip_flag (luma, chroma)
+    139, 139, //
+    // last_sig_coeff_x_prefix
+    110, 110, 124, 125, 140, 153, 125, 127, 140, 109, 111, 143, 127, 111, 79, 108, 123, 63,
+    // last_sig_coeff_y_prefix
+    110, 110, 124, 125, 140, 153, 125, 127, 140, 109, 111, 143, 127, 111, 79, 108, 123, 63,
+    // coded_sub_block_flag
+    91, 171, 134, 141, //
+    // sig_coeff_flag
+    111, 111, 125, 110, 110, 94, 124, 108, 124, 107, 125, 141, 179, 153, 125, 107, 125, 141, 179,
+    153, 125, 107, 125, 141, 179, 153, 125, 140, 139, 182, 182, 152, 136, 152, 136, 153, 136, 139,
+    111, 136, 139, 111, 141, 111, //
+    // coeff_abs_level_greater1_flag
+    140, 92, 137, 138, 140, 152, 138, 139, 153, 74, 149, 92, 139, 107, 122, 152, 140, 179, 166, 182,
+    140, 227, 122, 197, //
+    // coeff_abs_level_greater2_flag
+    138, 153, 136, 167, 152, 152,
+];
+
+/// rangeTabLps (Table 9-46), indexed by [pStateIdx][qRangeIdx].
+pub(super) const RANGE_TAB_LPS: [[u8; 4]; 64] = [
+    [128, 176, 208, 240],
+    [128, 167, 197, 227],
+    [128, 158, 187, 216],
+    [123, 150, 178, 205],
+    [116, 142, 169, 195],
+    [111, 135, 160, 185],
+    [105, 128, 152, 175],
+    [100, 122, 144, 166],
+    [95, 116, 137, 158],
+    [90, 110, 130, 150],
+    [85, 104, 123, 142],
+    [81, 99, 117, 135],
+    [77, 94, 111, 128],
+    [73, 89, 105, 122],
+    [69, 85, 100, 116],
+    [66, 80, 95, 110],
+    [62, 76, 90, 104],
+    [59, 72, 86, 99],
+    [56, 69, 81, 94],
+    [53, 65, 77, 89],
+    [51, 62, 73, 85],
+    [48, 59, 69, 80],
+    [46, 56, 66, 76],
+    [43, 53, 63, 72],
+    [41, 50, 59, 69],
+    [39, 48, 56, 65],
+    [37, 45, 54, 62],
+    [35, 43, 51, 59],
+    [33, 41, 48, 56],
+    [32, 39, 46, 53],
+    [30, 37, 43, 50],
+    [29, 35, 41, 48],
+    [27, 33, 39, 45],
+    [26, 31, 37, 43],
+    [24, 30, 35, 41],
+    [23, 28, 33, 39],
+    [22, 27, 32, 37],
+    [21, 26, 30, 35],
+    [20, 24, 29, 33],
+    [19, 23, 27, 31],
+    [18, 22, 26, 30],
+    [17, 21, 25, 28],
+    [16, 20, 23, 27],
+    [15, 19, 22, 25],
+    [14, 18, 21, 24],
+    [14, 17, 20, 23],
+    [13, 16, 19, 22],
+    [12, 15, 18, 21],
+    [12, 14, 17, 20],
+    [11, 14, 16, 19],
+    [11, 13, 15, 18],
+    [10, 12, 15, 17],
+    [10, 12, 14, 16],
+    [9, 11, 13, 15],
+    [9, 11, 12, 14],
+    [8, 10, 12, 14],
+    [8, 9, 11, 13],
+    [7, 9, 11, 12],
+    [7, 9, 10, 12],
+    [7, 8, 10, 11],
+    [6, 8, 9, 11],
+    [6, 7, 9, 10],
+    [6, 7, 8, 9],
+    [2, 2, 2, 2],
+];
+
+/// transIdxLps (Table 9-47). transIdxMps is min(state + 1, 62).
+pub(super) const TRANS_IDX_LPS: [u8; 64] = [
+    0, 0, 1, 2, 2, 4, 4, 5, 6, 7, 8, 9, 9, 11, 11, 12, 13, 13, 15, 15, 16, 16, 18, 18, 19, 19, 21,
+    21, 22, 22, 23, 24, 24, 25, 26, 26, 27, 27, 28, 29, 29, 30, 30, 30, 31, 32, 32, 33, 33, 33, 34,
+    34, 35, 35, 35, 36, 36, 36, 37, 37, 37, 38, 38, 63,
+];
+
+/// Context table for one slice.
+pub type Contexts = [Context; NUM_CONTEXTS];
+
+/// Initialise every context for the given SliceQpY (9.3.2.2).
+pub fn init_contexts(slice_qp: i32) -> Contexts {
+    let qp = slice_qp.clamp(0, 51);
+    let mut contexts = [Context::default(); NUM_CONTEXTS];
+    for (ctx, &init) in contexts.iter_mut().zip(INIT_VALUES.iter()) {
+        let slope = i32::from(init >> 4) * 5 - 45;
+        let offset = (i32::from(init & 15) << 3) - 16;
+        let pre = (((slope * qp) >> 4) + offset).clamp(1, 126);
+        *ctx = if pre <= 63 {
+            Context {
+                state: (63 - pre) as u8,
+                mps: 0,
+            }
+        } else {
+            Context {
+                state: (pre - 64) as u8,
+                mps: 1,
+            }
+        };
+    }
+    contexts
+}
+
+/// Arithmetic decoder over a slice segment's RBSP.
+pub struct Engine<'a> {
+    reader: BitReader<'a>,
+    range: u32,
+    offset: u32,
+}
+
+impl<'a> Engine<'a> {
+    /// Start decoding at the reader's (byte-aligned) position.
+    pub fn new(reader: BitReader<'a>) -> Self {
+        let mut engine = Self {
+            reader,
+            range: 510,
+            offset: 0,
+        };
+        engine.restart();
+        engine
+    }
+
+    /// Re-initialise the engine at the current position (9.3.2.5).
+    pub fn restart(&mut self) {
+        self.range = 510;
+        self.offset = self.reader.bits(9);
+    }
+
+    pub fn reader(&mut self) -> &mut BitReader<'a> {
+        &mut self.reader
+    }
+
+    /// True once the engine has consumed bits beyond the end of the data.
+    pub fn overrun(&self) -> bool {
+        self.reader.exhausted()
+    }
+
+    pub fn decision(&mut self, ctx: &mut Context) -> bool {
+        let lps = u32::from(RANGE_TAB_LPS[ctx.state as usize][((self.range >> 6) & 3) as usize]);
+        self.range -= lps;
+        let bin = if self.offset >= self.range {
+            self.offset -= self.range;
+            self.range = lps;
+            let bin = ctx.mps == 0;
+            if ctx.state == 0 {
+                ctx.mps = 1 - ctx.mps;
+            }
+            ctx.state = TRANS_IDX_LPS[ctx.state as usize];
+            bin
+        } else {
+            if ctx.state < 62 {
+                ctx.state += 1;
+            }
+            ctx.mps == 1
+        };
+        while self.range < 256 {
+            self.range <<= 1;
+            self.offset = (self.offset << 1) | self.reader.bit();
+        }
+        bin
+    }
+
+    pub fn bypass(&mut self) -> bool {
+        self.offset = (self.offset << 1) | self.reader.bit();
+        if self.offset >= self.range {
+            self.offset -= self.range;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Fixed-length bypass value, most significant bin first.
+    pub fn bypass_bits(&mut self, n: u32) -> u32 {
+        let mut v = 0;
+        for _ in 0..n {
+            v = (v << 1) | u32::from(self.bypass());
+        }
+        v
+    }
+
+    /// Terminating bin (end_of_slice_segment_flag, end_of_subset_one_bit, pcm_flag).
+    pub fn terminate(&mut self) -> bool {
+        self.range -= 2;
+        if self.offset >= self.range {
+            return true;
+        }
+        while self.range < 256 {
+            self.range <<= 1;
+            self.offset = (self.offset << 1) | self.reader.bit();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::writer::{BitWriter, CabacWriter};
+    use super::*;
+
+    #[test]
+    fn test_engine_round_trips_encoded_bins() {
+        let mut enc = CabacWriter::new(BitWriter::default());
+        let mut enc_ctx = init_contexts(30);
+        let mut expected = Vec::new();
+        let mut seed = 0x1234_5678u32;
+        for i in 0..5000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            // Skewed towards 0 so the adaptive contexts move through many states
+            let bin = (seed >> 16).is_multiple_of(7);
+            let kind = (seed >> 8) % 3;
+            if kind == 2 {
+                enc.bypass(bin);
+            } else {
+                enc.decision(&mut enc_ctx[i % 8], bin);
+            }
+            expected.push((kind, bin));
+        }
+        enc.terminate(true);
+        // Trailing marker byte to check the reader position afterwards
+        let mut out = enc.out;
+        out.align_zero();
+        out.bits(0xA5, 8);
+        let data = out.into_bytes();
+
+        let mut dec = Engine::new(BitReader::new(&data));
+        let mut dec_ctx = init_contexts(30);
+        for (i, &(kind, bin)) in expected.iter().enumerate() {
+            let got = if kind == 2 {
+                dec.bypass()
+            } else {
+                dec.decision(&mut dec_ctx[i % 8])
+            };
+            assert_eq!(got, bin, "bin {i}");
+        }
+        assert!(dec.terminate());
+        dec.reader().byte_align();
+        assert_eq!(dec.reader().bits(8), 0xA5);
+    }
+
+    #[test]
+    fn test_context_init_matches_spec_formula() {
+        // initValue 154 is the equiprobable "flat" context at every QP
+        let ctx = init_contexts(26)[CU_QP_DELTA];
+        assert_eq!((ctx.state, ctx.mps), (0, 1));
+        // initValue 63 at QP 26: m = -30, n = 104 → preCtxState 55 → state 8, MPS 0
+        let ctx = init_contexts(26)[INTRA_CHROMA];
+        assert_eq!((ctx.state, ctx.mps), (8, 0));
+    }
+}
@@ -0,0 +1,295 @@
+//! Intra sample prediction (8.4.4.2) for luma transform blocks.
+
+/// intraPredAngle for modes 2..=34 (Table 8-5).
+const INTRA_PRED_ANGLE: [i32; 33] = [
+    32, 26, 21, 17, 13, 9, 5, 2, 0, -2, -5, -9, -13, -17, -21, -26, -32, -26, -21, -17, -13, -9,
+    -5, -2, 0, 2, 5, 9, 13, 17, 21, 26, 32,
+];
+
+/// invAngle for modes 11..=25 (Table 8-6).
+const INV_ANGLE: [i32; 15] = [
+    -4096, -1638, -910, -630, -482, -390, -315, -256, -315, -390, -482, -630, -910, -1638, -4096,
+];
+
+pub const PLANAR: u8 = 0;
+pub const DC: u8 = 1;
+
+/// Neighbouring samples of an N×N block, laid out as one line from the bottom
+/// of the left column (p[-1][2N-1]) up through the corner (index 2N) and along
+/// the top row to p[2N-1][-1]. `None` marks samples that are not available.
+pub struct References {
+    samples: Vec<i32>,
+    size: usize,
+}
+
+impl References {
+    /// Gather references with the availability of every 4-sample group.
+    /// `sample(x, y)` reads reconstructed picture samples relative to the
+    /// block; `available(x, y)` answers for the group containing that sample.
+    pub fn gather(
+        size: usize,
+        bit_depth: u32,
+        sample: impl Fn(i32, i32) -> i32,
+        available: impl Fn(i32, i32) -> bool,
+    ) -> Self {
+        let n = size as i32;
+        let total = 4 * size + 1;
+        let mut samples: Vec<Option<i32>> = vec![None; total];
+        // Left column, bottom to top
+        for y in (0..2 * n).step_by(4) {
+            if available(-1, y) {
+                for dy in y..y + 4 {
+                    samples[(2 * n - 1 - dy) as usize] = Some(sample(-1, dy));
+                }
+            }
+        }
+        if available(-1, -1) {
+            samples[2 * size] = Some(sample(-1, -1));
+        }
+        for x in (0..2 * n).step_by(4) {
+            if available(x, -1) {
+                for dx in x..x + 4 {
+                    samples[(2 * n + 1 + dx) as usize] = Some(sample(dx, -1));
+                }
+            }
+        }
+
+        // Substitution (8.4.4.2.2)
+        let filled = match samples.iter().flatten().next() {
+            None => vec![1 << (bit_depth - 1); total],
+            Some(&first) => {
+                let mut prev = first;
+                samples
+                    .iter()
+                    .map(|s| {
+                        prev = s.unwrap_or(prev);
+                        prev
+                    })
+                    .collect()
+            }
+        };
+        Self {
+            samples: filled,
+            size,
+        }
+    }
+
+    /// p[-1][y] for y in -1..2N.
+    fn left(&self, y: i32) -> i32 {
+        self.samples[(2 * self.size as i32 - 1 - y) as usize]
+    }
+
+    /// p[x][-1] for x in -1..2N.
+    fn top(&self, x: i32) -> i32 {
+        self.samples[(2 * self.size as i32 + 1 + x) as usize]
+    }
+
+    /// Reference smoothing (8.4.4.2.3) for luma.
+    fn filter(&mut self, mode: u8, strong_smoothing: bool, bit_depth: u32) {
+        let n = self.size;
+        if mode == DC || n == 4 {
+            return;
+        }
+        let min_dist = (i32::from(mode) - 26)
+            .abs()
+            .min((i32::from(mode) - 10).abs());
+        let threshold = match n {
+            8 => 7,
+            16 => 1,
+            _ => 0,
+        };
+        if min_dist <= threshold {
+            return;
+        }
+        let last = 4 * n;
+        let s = &self.samples;
+        let corner = s[2 * n];
+        let bottom = s[0];
+        let right = s[last];
+        let limit = 1 << (bit_depth - 5);
+        if strong_smoothing
+            && n == 32
+            && (corner + right - 2 * s[2 * n + n]).abs() < limit
+            && (corner + bottom - 2 * s[n]).abs() < limit
+        {
+            let mut out = self.samples.clone();
+            for i in 0..63 {
+                // p[-1][i] lives at 63 - i, p[i][-1] at 65 + i
+                out[63 - i] = ((63 - i as i32) * corner + (i as i32 + 1) * bottom + 32) >> 6;
+                out[65 + i] = ((63 - i as i32) * corner + (i as i32 + 1) * right + 32) >> 6;
+            }
+            self.samples = out;
+            return;
+        }
+        let mut out = s.clone();
+        for i in 1..last {
+            out[i] = (s[i - 1] + 2 * s[i] + s[i + 1] + 2) >> 2;
+        }
+        self.samples = out;
+    }
+}
+
+/// Predict an N×N luma block into `pred` (row-major).
+pub fn predict(
+    refs: &mut References,
+    mode: u8,
+    strong_smoothing: bool,
+    bit_depth: u32,
+    pred: &mut [i32],
+) {
+    let n = refs.size;
+    let ni = n as i32;
+    refs.filter(mode, strong_smoothing, bit_depth);
+    let max = (1 << bit_depth) - 1;
+
+    match mode {
+        PLANAR => {
+            let shift = n.trailing_zeros() + 1;
+            for y in 0..ni {
+                for x in 0..ni {
+                    pred[(y * ni + x) as usize] = ((ni - 1 - x) * refs.left(y)
+                        + (x + 1) * refs.top(ni)
+                        + (ni - 1 - y) * refs.top(x)
+                        + (y + 1) * refs.left(ni)
+                        + ni)
+                        >> shift;
+                }
+            }
+        }
+        DC => {
+            let sum: i32 = (0..ni).map(|i| refs.top(i) + refs.left(i)).sum();
+            let dc = (sum + ni) >> (n.trailing_zeros() + 1);
+            pred[..n * n].fill(dc);
+            if n < 32 {
+                pred[0] = (refs.left(0) + 2 * dc + refs.top(0) + 2) >> 2;
+                for i in 1..ni {
+                    pred[i as usize] = (refs.top(i) + 3 * dc + 2) >> 2;
+                    pred[(i * ni) as usize] = (refs.left(i) + 3 * dc + 2) >> 2;
+                }
+            }
+        }
+        _ => {
+            let angle = INTRA_PRED_ANGLE[mode as usize - 2];
+            let vertical = mode >= 18;
+            // ref[] indexed from -N..=2N, stored with an offset of N
+            let mut line = vec![0i32; 3 * n + 1];
+            let main = |i: i32| {
+                if vertical {
+                    refs.top(i - 1)
+                } else {
+                    refs.left(i - 1)
+                }
+            };
+            let side = |i: i32| {
+                if vertical {
+                    refs.left(i - 1)
+                } else {
+                    refs.top(i - 1)
+                }
+            };
+            for i in 0..=ni {
+                line[(i + ni) as usize] = main(i);
+            }
+            if angle < 0 {
+                let inv = INV_ANGLE[mode as usize - 11];
+                let lowest = (ni * angle) >> 5;
+                if lowest < -1 {
+                    for i in lowest..0 {
+                        line[(i + ni) as usize] = side((i * inv + 128) >> 8);
+                    }
+                }
+            } else {
+                for i in ni + 1..=2 * ni {
+                    line[(i + ni) as usize] = main(i);
+                }
+            }
+            for j in 0..ni {
+                // j runs along the prediction direction's cross axis
+                let pos = (j + 1) * angle;
+                let idx = pos >> 5;
+                let fact = pos & 31;
+                for i in 0..ni {
+                    let base = (i + idx + 1 + ni) as usize;
+                    let v = if fact != 0 {
+                        ((32 - fact) * line[base] + fact * line[base + 1] + 16) >> 5
+                    } else {
+                        line[base]
+                    };
+                    let (x, y) = if vertical { (i, j) } else { (j, i) };
+                    pred[(y * ni + x) as usize] = v;
+                }
+            }
+            if n < 32 && (mode == 26 || mode == 10) {
+                for i in 0..ni {
+                    let edge = if mode == 26 {
+                        refs.top(0) + ((refs.left(i) - refs.left(-1)) >> 1)
+                    } else {
+                        refs.left(0) + ((refs.top(i) - refs.top(-1)) >> 1)
+                    };
+                    let (x, y) = if mode == 26 { (0, i) } else { (i, 0) };
+                    pred[(y * ni + x) as usize] = edge.clamp(0, max);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_refs(size: usize, value: i32) -> References {
+        References::gather(size, 8, |_, _| value, |_, _| true)
+    }
+
+    #[test]
+    fn test_every_mode_predicts_flat_from_flat_references() {
+        for size in [4, 8, 16, 32] {
+            for mode in 0..=34 {
+                let mut refs = flat_refs(size, 77);
+                let mut pred = vec![0; size * size];
+                predict(&mut refs, mode, true, 8, &mut pred);
+                assert!(pred.iter().all(|&v| v == 77), "size {size} mode {mode}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unavailable_references_use_mid_grey() {
+        let mut refs = References::gather(8, 8, |_, _| 0, |_, _| false);
+        let mut pred = vec![0; 64];
+        predict(&mut refs, DC, false, 8, &mut pred);
+        assert!(pred.iter().all(|&v| v == 128));
+    }
+
+    #[test]
+    fn test_substitution_copies_nearest_available_sample() {
+        // Only the top row is available: the left column takes the corner's
+        // substitute, which is the first top sample
+        let refs = References::gather(4, 8, |x, _| 10 + x, |x, y| y == -1 && x >= 0);
+        assert_eq!(refs.left(3), 10);
+        assert_eq!(refs.left(-1), 10);
+        assert_eq!(refs.top(5), 15);
+    }
+
+    #[test]
+    fn test_horizontal_and_vertical_copy_edges() {
+        let refs = || {
+            References::gather(
+                4,
+                8,
+                |x, y| if y == -1 { 100 + x } else { 50 + y },
+                |_, _| true,
+            )
+        };
+        let mut pred = vec![0; 16];
+        predict(&mut refs(), 26, false, 8, &mut pred);
+        // Column 1..3 copies the top row; column 0 gets the boundary gradient
+        assert_eq!(pred[1], 101);
+        assert_eq!(pred[3 * 4 + 3], 103);
+        let mut pred = vec![0; 16];
+        predict(&mut refs(), 10, false, 8, &mut pred);
+        assert_eq!(pred[4 + 2], 51);
+        assert_eq!(pred[3 * 4 + 1], 53);
+    }
+}
@@ -0,0 +1,1133 @@
+//! Intra slice segment decoding: slice header, coding tree syntax and luma
+//! reconstruction (prediction + residual).
+//!
+//! Chroma syntax is parsed but never reconstructed, and the in-loop filters
+//! (deblocking, SAO) are skipped: intra prediction reads pre-filter samples,
+//! so the luma plane stays exact up to those filters' small edge adjustments,
+//! which vanish when the picture is reduced to a 9x8 hash.
+
+use std::sync::OnceLock;
+
+use super::bitstream::{diagonal_scan, st_ref_pic_set, BitReader, Pps, ScalingList, Sps};
+use super::cabac::{self, Contexts, Engine};
+use super::intra::{self, References, DC, PLANAR};
+use super::transform;
+use super::LumaImage;
+
+/// Slice header fields shared by an independent slice segment and the
+/// dependent segments that follow it.
+#[derive(Debug, Clone)]
+struct SliceHeader {
+    pps_id: u32,
+    /// SliceAddrRs: address of the first CTB of the (independent) slice.
+    slice_addr: u32,
+    sao_luma: bool,
+    sao_chroma: bool,
+    slice_qp: i32,
+}
+
+/// Picture under reconstruction plus the state carried between segments.
+pub struct Picture {
+    width: usize,
+    height: usize,
+    luma: Vec<u16>,
+    sps_id: u32,
+    bit_depth: u32,
+    /// Conformance window: left, right, top, bottom.
+    crop: [u32; 4],
+    log2_ctb_size: u32,
+    width_in_ctbs: usize,
+    /// Per 4x4 block: IntraPredModeY, coding quadtree depth and QpY.
+    intra_mode: Vec<u8>,
+    ct_depth: Vec<u8>,
+    qp_y: Vec<i8>,
+    blocks_w: usize,
+    /// SliceAddrRs of the slice that decoded each CTB, -1 if not decoded yet.
+    ctb_slice: Vec<i64>,
+    decoded_ctbs: usize,
+    header: Option<SliceHeader>,
+    /// Context storage for WPP rows and dependent slice segments (9.3.2.4).
+    wpp_contexts: Option<Contexts>,
+    segment_contexts: Option<Contexts>,
+    /// QpY of the previous coding unit in decoding order.
+    qp_prev: i32,
+}
+
+impl Picture {
+    pub fn new(sps: &Sps) -> Self {
+        let width = sps.width as usize;
+        let height = sps.height as usize;
+        let blocks_w = width.div_ceil(4);
+        let blocks = blocks_w * height.div_ceil(4);
+        let ctbs = (sps.width_in_ctbs() * sps.height_in_ctbs()) as usize;
+        Self {
+            width,
+            height,
+            luma: vec![0; width * height],
+            sps_id: sps.id,
+            bit_depth: sps.bit_depth,
+            crop: sps.conf_window,
+            log2_ctb_size: sps.log2_ctb_size,
+            width_in_ctbs: sps.width_in_ctbs() as usize,
+            intra_mode: vec![DC; blocks],
+            ct_depth: vec![0; blocks],
+            qp_y: vec![0; blocks],
+            blocks_w,
+            ctb_slice: vec![-1; ctbs],
+            decoded_ctbs: 0,
+            header: None,
+            wpp_contexts: None,
+            segment_contexts: None,
+            qp_prev: 0,
+        }
+    }
+
+    /// True once every CTB has been decoded.
+    pub fn complete(&self) -> bool {
+        self.decoded_ctbs == self.ctb_slice.len()
+    }
+
+    /// The conformance-cropped luma plane scaled to 8 bits.
+    pub fn into_luma8(self) -> LumaImage {
+        let [left, right, top, bottom] = self.crop.map(|v| v as usize);
+        let width = self.width - left - right;
+        let height = self.height - top - bottom;
+        let shift = self.bit_depth - 8;
+        let mut data = Vec::with_capacity(width * height);
+        for row in self.luma.chunks_exact(self.width).skip(top).take(height) {
+            data.extend(row[left..left + width].iter().map(|&v| (v >> shift) as u8));
+        }
+        LumaImage {
+            width: width as u32,
+            height: height as u32,
+            data,
+        }
+    }
+
+    fn block(&self, x: usize, y: usize) -> usize {
+        (y >> 2) * self.blocks_w + (x >> 2)
+    }
+
+    fn fill_blocks(map: &mut [u8], blocks_w: usize, x0: usize, y0: usize, size: usize, v: u8) {
+        for by in (y0 >> 2)..((y0 + size) >> 2) {
+            map[by * blocks_w + (x0 >> 2)..by * blocks_w + ((x0 + size) >> 2)].fill(v);
+        }
+    }
+
+    fn ctb_addr(&self, x: usize, y: usize) -> usize {
+        (y >> self.log2_ctb_size) * self.width_in_ctbs + (x >> self.log2_ctb_size)
+    }
+}
+
+/// z-order index of the 4x4 block containing (x, y) within its CTB.
+fn zscan(x: usize, y: usize, log2_ctb_size: u32) -> usize {
+    let mask = (1 << log2_ctb_size) - 1;
+    let (bx, by) = ((x & mask) >> 2, (y & mask) >> 2);
+    let mut z = 0;
+    for bit in 0..4 {
+        z |= ((bx >> bit) & 1) << (2 * bit);
+        z |= ((by >> bit) & 1) << (2 * bit + 1);
+    }
+    z
+}
+
+/// ScanOrder for 1x1..8x8 grids (log2 0..=3) and each scanIdx
+/// (0 = up-right diagonal, 1 = horizontal, 2 = vertical).
+struct ScanTables {
+    orders: [[Vec<(u8, u8)>; 3]; 4],
+}
+
+impl ScanTables {
+    fn new() -> Self {
+        let build = |log2: usize| {
+            let size = 1 << log2;
+            let horizontal = (0..size * size).map(|i| ((i % size) as u8, (i / size) as u8));
+            let vertical = (0..size * size).map(|i| ((i / size) as u8, (i % size) as u8));
+            [
+                diagonal_scan(size),
+                horizontal.collect(),
+                vertical.collect(),
+            ]
+        };
+        Self {
+            orders: [build(0), build(1), build(2), build(3)],
+        }
+    }
+
+    fn get(log2: u32, scan_idx: usize) -> &'static [(u8, u8)] {
+        static SCANS: OnceLock<ScanTables> = OnceLock::new();
+        &SCANS.get_or_init(Self::new).orders[log2 as usize][scan_idx]
+    }
+}
+
+/// ctxIdxMap for sig_coeff_flag in 4x4 blocks (9.3.4.2.5).
+const CTX_IDX_MAP: [usize; 16] = [0, 1, 4, 5, 2, 3, 4, 5, 6, 6, 8, 8, 7, 7, 8, 8];
+
+/// Decode one slice segment NAL into `pic`, allocating the picture from the
+/// referenced SPS on the first segment. Returns None on any syntax the decoder
+/// does not support or on a corrupt bitstream.
+pub fn decode_slice_segment(
+    pic: &mut Option<Picture>,
+    nal_type: u8,
+    rbsp: &[u8],
+    sps_list: &[Sps],
+    pps_list: &[Pps],
+) -> Option<()> {
+    let mut r = BitReader::new(rbsp);
+    let first_in_pic = r.flag();
+    if (16..=23).contains(&nal_type) {
+        r.skip(1); // no_output_of_prior_pics_flag
+    }
+    let pps_id = r.ue()?;
+    let pps = pps_list.iter().find(|p| p.id == pps_id)?;
+    let sps = sps_list.iter().find(|s| s.id == pps.sps_id)?;
+    let pic = match pic {
+        Some(pic) if pic.sps_id == sps.id && !first_in_pic => pic,
+        None if first_in_pic => pic.insert(Picture::new(sps)),
+        _ => return None,
+    };
+    let total_ctbs = pic.ctb_slice.len() as u32;
+    let mut dependent = false;
+    let mut segment_addr = 0;
+    if !first_in_pic {
+        if pps.dependent_slice_segments_enabled {
+            dependent = r.flag();
+        }
+        let bits = 32 - (total_ctbs - 1).leading_zeros();
+        segment_addr = r.bits(bits);
+        if segment_addr >= total_ctbs {
+            return None;
+        }
+    }
+
+    let header = if dependent {
+        let prev = pic.header.clone()?;
+        if prev.pps_id != pps_id {
+            return None;
+        }
+        prev
+    } else {
+        parse_slice_header(&mut r, nal_type, sps, pps, segment_addr)?
+    };
+
+    if pps.entropy_coding_sync {
+        let entry_points = r.ue()?;
+        if entry_points > total_ctbs {
+            return None;
+        }
+        if entry_points > 0 {
+            let len = r.ue()? + 1;
+            if len > 32 {
+                return None;
+            }
+            r.skip(entry_points as usize * len as usize);
+        }
+    }
+    if pps.slice_header_extension_present {
+        let len = r.ue()?;
+        r.skip(len as usize * 8);
+    }
+    // byte_alignment(): a one bit then zeros
+    if !r.flag() {
+        return None;
+    }
+    r.byte_align();
+    if r.exhausted() {
+        return None;
+    }
+
+    if !dependent {
+        pic.qp_prev = header.slice_qp;
+    }
+    let scaling = sps
+        .scaling_list
+        .as_ref()
+        .map(|sps_list| pps.scaling_list.as_ref().unwrap_or(sps_list));
+    let contexts = cabac::init_contexts(header.slice_qp);
+    let mut dec = SliceDecoder {
+        sps,
+        pps,
+        header: &header,
+        pic,
+        engine: Engine::new(r),
+        contexts,
+        scaling,
+        transquant_bypass: false,
+        is_cu_qp_delta_coded: false,
+        qp_pred: header.slice_qp,
+        qp_y: header.slice_qp,
+        chroma_mode: DC,
+    };
+    let result = dec.decode_ctus(segment_addr, dependent);
+    pic.header = Some(header);
+    result
+}
+
+/// slice_segment_header() fields after slice_segment_address for an
+/// independent segment (7.3.6.1). Only I slices are accepted.
+fn parse_slice_header(
+    r: &mut BitReader,
+    nal_type: u8,
+    sps: &Sps,
+    pps: &Pps,
+    segment_addr: u32,
+) -> Option<SliceHeader> {
+    r.skip(pps.num_extra_slice_header_bits as usize);
+    if r.ue()? != 2 {
+        return None; // slice_type: only I slices
+    }
+    if pps.output_flag_present {
+        r.skip(1);
+    }
+    // Not IDR: POC and reference picture set syntax
+    if nal_type != 19 && nal_type != 20 {
+        r.skip(sps.log2_max_poc_lsb as usize);
+        let num_sets = sps.num_short_term_ref_pic_sets;
+        if !r.flag() {
+            st_ref_pic_set(r, num_sets, num_sets, &sps.st_rps_num_delta_pocs)?;
+        } else if num_sets > 1 {
+            r.skip((32 - (num_sets - 1).leading_zeros()) as usize);
+        }
+        if sps.long_term_ref_pics_present {
+            let num_lt_sps = if sps.num_long_term_ref_pics_sps > 0 {
+                r.ue()?
+            } else {
+                0
+            };
+            let num_lt_pics = r.ue()?;
+            if num_lt_sps + num_lt_pics > 32 {
+                return None;
+            }
+            for i in 0..num_lt_sps + num_lt_pics {
+                if i < num_lt_sps {
+                    if sps.num_long_term_ref_pics_sps > 1 {
+                        let bits = 32 - (sps.num_long_term_ref_pics_sps - 1).leading_zeros();
+                        r.skip(bits as usize);
+                    }
+                } else {
+                    r.skip(sps.log2_max_poc_lsb as usize + 1);
+                }
+                if r.flag() {
+                    r.ue()?; // delta_poc_msb_cycle_lt
+                }
+            }
+        }
+        if sps.temporal_mvp_enabled {
+            r.skip(1);
+        }
+    }
+    let mut sao_luma = false;
+    let mut sao_chroma = false;
+    if sps.sao_enabled {
+        sao_luma = r.flag();
+        if sps.chroma_format_idc != 0 {
+            sao_chroma = r.flag();
+        }
+    }
+    let slice_qp = pps.init_qp + r.se()?;
+    let qp_bd_offset = 6 * (sps.bit_depth as i32 - 8);
+    if !(-qp_bd_offset..=51).contains(&slice_qp) {
+        return None;
+    }
+    if pps.slice_chroma_qp_offsets_present {
+        r.se()?;
+        r.se()?;
+    }
+    let mut deblocking_disabled = pps.deblocking_disabled;
+    if pps.deblocking_override_enabled && r.flag() {
+        deblocking_disabled = r.flag();
+        if !deblocking_disabled {
+            r.se()?;
+            r.se()?;
+        }
+    }
+    if pps.loop_filter_across_slices && (sao_luma || sao_chroma || !deblocking_disabled) {
+        r.skip(1);
+    }
+    Some(SliceHeader {
+        pps_id: pps.id,
+        slice_addr: segment_addr,
+        sao_luma,
+        sao_chroma,
+        slice_qp,
+    })
+}
+
+struct SliceDecoder<'a, 'p> {
+    sps: &'a Sps,
+    pps: &'a Pps,
+    header: &'a SliceHeader,
+    pic: &'p mut Picture,
+    engine: Engine<'a>,
+    contexts: Contexts,
+    scaling: Option<&'a ScalingList>,
+    transquant_bypass: bool,
+    is_cu_qp_delta_coded: bool,
+    qp_pred: i32,
+    qp_y: i32,
+    /// IntraPredModeC of the current coding unit (for chroma scan selection).
+    chroma_mode: u8,
+}
+
+impl SliceDecoder<'_, '_> {
+    fn decision(&mut self, ctx: usize) -> bool {
+        self.engine.decision(&mut self.contexts[ctx])
+    }
+
+    /// slice_segment_data() (7.3.8.1) with the context initialisation and
+    /// synchronisation rules of 9.3.1.
+    fn decode_ctus(&mut self, start: u32, dependent: bool) -> Option<()> {
+        let width = self.pic.width_in_ctbs;
+        let total = self.pic.ctb_slice.len();
+        let wpp = self.pps.entropy_coding_sync;
+        let mut addr = start as usize;
+        loop {
+            if self.pic.ctb_slice[addr] != -1 {
+                return None; // CTB decoded twice
+            }
+            self.pic.ctb_slice[addr] = i64::from(self.header.slice_addr);
+            if wpp && addr.is_multiple_of(width) {
+                if addr != start as usize {
+                    // end_of_subset_one_bit, then the next substream
+                    if !self.engine.terminate() {
+                        return None;
+                    }
+                    self.engine.reader().byte_align();
+                    self.engine.restart();
+                }
+                self.pic.qp_prev = self.header.slice_qp;
+                let above_right = addr.checked_sub(width - 1).filter(|_| width > 1);
+                self.contexts = match above_right {
+                    Some(tr) if self.pic.ctb_slice[tr] == i64::from(self.header.slice_addr) => {
+                        self.pic.wpp_contexts?
+                    }
+                    _ => cabac::init_contexts(self.header.slice_qp),
+                };
+            } else if addr == start as usize && dependent {
+                self.contexts = self.pic.segment_contexts?;
+            }
+
+            self.coding_tree_unit(addr)?;
+            self.pic.decoded_ctbs += 1;
+            if wpp && addr % width == 1 {
+                self.pic.wpp_contexts = Some(self.contexts);
+            }
+            let end_of_segment = self.engine.terminate();
+            if self.engine.overrun() {
+                return None;
+            }
+            addr += 1;
+            if end_of_segment {
+                self.pic.segment_contexts = Some(self.contexts);
+                return Some(());
+            }
+            if addr >= total {
+                return None;
+            }
+        }
+    }
+
+    fn coding_tree_unit(&mut self, addr: usize) -> Option<()> {
+        let log2 = self.sps.log2_ctb_size;
+        let width = self.pic.width_in_ctbs;
+        let (rx, ry) = (addr % width, addr / width);
+        if self.header.sao_luma || self.header.sao_chroma {
+            self.sao(addr, rx, ry);
+        }
+        self.coding_quadtree(rx << log2, ry << log2, log2, 0)
+    }
+
+    /// sao() (7.3.8.3): parsed and discarded.
+    fn sao(&mut self, addr: usize, rx: usize, ry: usize) {
+        let slice_addr = self.header.slice_addr as usize;
+        let width = self.pic.width_in_ctbs;
+        if rx > 0 && addr > slice_addr && self.decision(cabac::SAO_MERGE) {
+            return;
+        }
+        if ry > 0 && addr >= slice_addr + width && self.decision(cabac::SAO_MERGE) {
+            return;
+        }
+        let components = if self.sps.chroma_format_idc == 0 {
+            1
+        } else {
+            3
+        };
+        let mut sao_type = 0;
+        for c in 0..components {
+            if (c == 0 && !self.header.sao_luma) || (c > 0 && !self.header.sao_chroma) {
+                continue;
+            }
+            if c < 2 {
+                sao_type = if !self.decision(cabac::SAO_TYPE) {
+                    0
+                } else if self.engine.bypass() {
+                    2
+                } else {
+                    1
+                };
+            }
+            if sao_type == 0 {
+                continue;
+            }
+            let bit_depth = if c == 0 {
+                self.sps.bit_depth
+            } else {
+                self.sps.bit_depth_chroma
+            };
+            let c_max = (1 << (bit_depth.min(10) - 5)) - 1;
+            let mut offsets = [0; 4];
+            for offset in &mut offsets {
+                while *offset < c_max && self.engine.bypass() {
+                    *offset += 1;
+                }
+            }
+            if sao_type == 1 {
+                for _ in offsets.iter().filter(|&&o| o != 0) {
+                    self.engine.bypass();
+                }
+                self.engine.bypass_bits(5);
+            } else if c < 2 {
+                self.engine.bypass_bits(2);
+            }
+        }
+    }
+
+    /// 6.4.1 z-scan availability of (xn, yn) for the block at (xc, yc).
+    fn available(&self, xc: usize, yc: usize, xn: isize, yn: isize) -> bool {
+        if xn < 0 || yn < 0 || xn as usize >= self.pic.width || yn as usize >= self.pic.height {
+            return false;
+        }
+        let (xn, yn) = (xn as usize, yn as usize);
+        let ctb_n = self.pic.ctb_addr(xn, yn);
+        if self.pic.ctb_slice[ctb_n] != i64::from(self.header.slice_addr) {
+            return false;
+        }
+        let ctb_c = self.pic.ctb_addr(xc, yc);
+        if ctb_n != ctb_c {
+            return ctb_n < ctb_c;
+        }
+        let log2 = self.pic.log2_ctb_size;
+        zscan(xn, yn, log2) <= zscan(xc, yc, log2)
+    }
+
+    fn coding_quadtree(&mut self, x0: usize, y0: usize, log2: u32, depth: u8) -> Option<()> {
+        let size = 1 << log2;
+        let split = if x0 + size <= self.pic.width
+            && y0 + size <= self.pic.height
+            && log2 > self.sps.log2_min_cb_size
+        {
+            let (xi, yi) = (x0 as isize, y0 as isize);
+            let mut ctx = cabac::SPLIT_CU;
+            if self.available(x0, y0, xi - 1, yi)
+                && self.pic.ct_depth[self.pic.block(x0 - 1, y0)] > depth
+            {
+                ctx += 1;
+            }
+            if self.available(x0, y0, xi, yi - 1)
+                && self.pic.ct_depth[self.pic.block(x0, y0 - 1)] > depth
+            {
+                ctx += 1;
+            }
+            self.decision(ctx)
+        } else {
+            log2 > self.sps.log2_min_cb_size
+        };
+
+        let qg_log2 = self.sps.log2_ctb_size - self.pps.diff_cu_qp_delta_depth;
+        if self.pps.cu_qp_delta_enabled && log2 >= qg_log2 {
+            self.start_quantization_group(x0, y0);
+        }
+
+        if split {
+            let half = size >> 1;
+            for (dx, dy) in [(0, 0), (half, 0), (0, half), (half, half)] {
+                if x0 + dx < self.pic.width && y0 + dy < self.pic.height {
+                    self.coding_quadtree(x0 + dx, y0 + dy, log2 - 1, depth + 1)?;
+                }
+            }
+            Some(())
+        } else {
+            self.coding_unit(x0, y0, log2, depth)
+        }
+    }
+
+    /// qPY_PRED for the quantization group at (xq, yq) (8.6.1).
+    fn start_quantization_group(&mut self, xq: usize, yq: usize) {
+        self.is_cu_qp_delta_coded = false;
+        let mask = (1 << self.sps.log2_ctb_size) - 1;
+        let prev = self.pic.qp_prev;
+        let qp_a = if xq & mask != 0 {
+            i32::from(self.pic.qp_y[self.pic.block(xq - 1, yq)])
+        } else {
+            prev
+        };
+        let qp_b = if yq & mask != 0 {
+            i32::from(self.pic.qp_y[self.pic.block(xq, yq - 1)])
+        } else {
+            prev
+        };
+        self.qp_pred = (qp_a + qp_b + 1) >> 1;
+        self.qp_y = self.qp_pred;
+    }
+
+    fn coding_unit(&mut self, x0: usize, y0: usize, log2: u32, depth: u8) -> Option<()> {
+        let size = 1usize << log2;
+        let blocks_w = self.pic.blocks_w;
+        Picture::fill_blocks(&mut self.pic.ct_depth, blocks_w, x0, y0, size, depth);
+        self.transquant_bypass =
+            self.pps.transquant_bypass_enabled && self.decision(cabac::TRANSQUANT_BYPASS);
+        let part_nxn = log2 == self.sps.log2_min_cb_size && !self.decision(cabac::PART_MODE);
+        if part_nxn && log2 - 1 < self.sps.log2_min_tb_size {
+            return None;
+        }
+        let pcm = !part_nxn
+            && self
+                .sps
+                .pcm
+                .is_some_and(|p| (p.log2_min_size..=p.log2_max_size).contains(&log2))
+            && self.engine.terminate();
+
+        if pcm {
+            Picture::fill_blocks(&mut self.pic.intra_mode, blocks_w, x0, y0, size, DC);
+            self.pcm_sample(x0, y0, log2)?;
+        } else {
+            self.intra_modes(x0, y0, size, part_nxn);
+            let max_depth = self.sps.max_transform_hierarchy_depth_intra + u32::from(part_nxn);
+            self.transform_tree(x0, y0, log2, 0, 0, max_depth, part_nxn, (false, false))?;
+        }
+
+        let qp = self.qp_y as i8;
+        let qp_map = &mut self.pic.qp_y;
+        for by in (y0 >> 2)..((y0 + size) >> 2) {
+            qp_map[by * blocks_w + (x0 >> 2)..by * blocks_w + ((x0 + size) >> 2)].fill(qp);
+        }
+        self.pic.qp_prev = self.qp_y;
+        Some(())
+    }
+
+    /// Luma intra prediction modes (8.4.2) and the chroma mode (8.4.3).
+    fn intra_modes(&mut self, x0: usize, y0: usize, size: usize, part_nxn: bool) {
+        let parts = if part_nxn { 4 } else { 1 };
+        let pb = if part_nxn { size / 2 } else { size };
+        let mut prev_flags = [false; 4];
+        for flag in prev_flags.iter_mut().take(parts) {
+            *flag = self.decision(cabac::PREV_INTRA_LUMA);
+        }
+        let ctb_mask = (1 << self.sps.log2_ctb_size) - 1;
+        for (i, &prev_flag) in prev_flags.iter().enumerate().take(parts) {
+            let (xp, yp) = (x0 + (i % 2) * pb, y0 + (i / 2) * pb);
+            let (xi, yi) = (xp as isize, yp as isize);
+            let cand_a = if self.available(xp, yp, xi - 1, yi) {
+                self.pic.intra_mode[self.pic.block(xp - 1, yp)]
+            } else {
+                DC
+            };
+            // The above neighbour only counts inside the current CTB row
+            let cand_b = if yp & ctb_mask != 0 && self.available(xp, yp, xi, yi - 1) {
+                self.pic.intra_mode[self.pic.block(xp, yp - 1)]
+            } else {
+                DC
+            };
+            let mut cand = if cand_a == cand_b {
+                if cand_a < 2 {
+                    [PLANAR, DC, 26]
+                } else {
+                    [
+                        cand_a,
+                        2 + ((cand_a + 29) % 32),
+                        2 + ((cand_a - 2 + 1) % 32),
+                    ]
+                }
+            } else {
+                let third = if cand_a != PLANAR && cand_b != PLANAR {
+                    PLANAR
+                } else if cand_a != DC && cand_b != DC {
+                    DC
+                } else {
+                    26
+                };
+                [cand_a, cand_b, third]
+            };
+            let mode = if prev_flag {
+                let idx = if !self.engine.bypass() {
+                    0
+                } else if !self.engine.bypass() {
+                    1
+                } else {
+                    2
+                };
+                cand[idx]
+            } else {
+                let mut mode = self.engine.bypass_bits(5) as u8;
+                cand.sort_unstable();
+                for c in cand {
+                    if mode >= c {
+                        mode += 1;
+                    }
+                }
+                mode
+            };
+            let blocks_w = self.pic.blocks_w;
+            Picture::fill_blocks(&mut self.pic.intra_mode, blocks_w, xp, yp, pb, mode);
+        }
+
+        if self.sps.chroma_format_idc != 0 {
+            let luma = self.pic.intra_mode[self.pic.block(x0, y0)];
+            self.chroma_mode = if !self.decision(cabac::INTRA_CHROMA) {
+                luma
+            } else {
+                let mode = [PLANAR, 26, 10, DC][self.engine.bypass_bits(2) as usize];
+                if mode == luma {
+                    34
+                } else {
+                    mode
+                }
+            };
+        }
+    }
+
+    fn pcm_sample(&mut self, x0: usize, y0: usize, log2: u32) -> Option<()> {
+        let pcm = self.sps.pcm?;
+        if pcm.bit_depth > self.sps.bit_depth {
+            return None;
+        }
+        let shift = self.sps.bit_depth - pcm.bit_depth;
+        let size = 1usize << log2;
+        let width = self.pic.width;
+        let reader = self.engine.reader();
+        reader.byte_align();
+        for y in y0..y0 + size {
+            for x in x0..x0 + size {
+                self.pic.luma[y * width + x] = (reader.bits(pcm.bit_depth) << shift) as u16;
+            }
+        }
+        if self.sps.chroma_format_idc != 0 {
+            reader.skip(2 * (size / 2) * (size / 2) * pcm.bit_depth_chroma as usize);
+        }
+        self.engine.restart();
+        Some(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transform_tree(
+        &mut self,
+        x0: usize,
+        y0: usize,
+        log2: u32,
+        depth: u32,
+        blk_idx: usize,
+        max_depth: u32,
+        intra_split: bool,
+        parent_cbf: (bool, bool),
+    ) -> Option<()> {
+        let split = if log2 <= self.sps.log2_max_tb_size
+            && log2 > self.sps.log2_min_tb_size
+            && depth < max_depth
+            && !(intra_split && depth == 0)
+        {
+            self.decision(cabac::SPLIT_TRANSFORM + (5 - log2 as usize))
+        } else {
+            log2 > self.sps.log2_max_tb_size || (intra_split && depth == 0)
+        };
+
+        let mut cbf = (false, false);
+        if log2 > 2 && self.sps.chroma_format_idc != 0 {
+            if depth == 0 || parent_cbf.0 {
+                cbf.0 = self.decision(cabac::CBF_CHROMA + depth as usize);
+            }
+            if depth == 0 || parent_cbf.1 {
+                cbf.1 = self.decision(cabac::CBF_CHROMA + depth as usize);
+            }
+        }
+
+        if split {
+            let half = 1 << (log2 - 1);
+            for (i, (dx, dy)) in [(0, 0), (half, 0), (0, half), (half, half)]
+                .into_iter()
+                .enumerate()
+            {
+                self.transform_tree(
+                    x0 + dx,
+                    y0 + dy,
+                    log2 - 1,
+                    depth + 1,
+                    i,
+                    max_depth,
+                    intra_split,
+                    cbf,
+                )?;
+            }
+            return Some(());
+        }
+
+        let cbf_luma = self.decision(cabac::CBF_LUMA + usize::from(depth == 0));
+        // 4x4 luma blocks share the parent's chroma block, coded with the last one
+        let chroma_cbf = if log2 == 2 { parent_cbf } else { cbf };
+        if (cbf_luma || chroma_cbf.0 || chroma_cbf.1)
+            && self.pps.cu_qp_delta_enabled
+            && !self.is_cu_qp_delta_coded
+        {
+            self.cu_qp_delta()?;
+        }
+
+        self.reconstruct_luma(x0, y0, log2, cbf_luma)?;
+
+        // Chroma residuals are parsed to stay in sync, then dropped
+        let mut scratch = [0i32; 16 * 16];
+        if log2 > 2 || blk_idx == 3 {
+            let chroma_log2 = (log2 - 1).max(2);
+            for (coded, c_idx) in [(chroma_cbf.0, 1), (chroma_cbf.1, 2)] {
+                if coded {
+                    self.residual_coding(x0, y0, chroma_log2, c_idx, &mut scratch)?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// cu_qp_delta_abs / cu_qp_delta_sign_flag and the resulting QpY (8.6.1).
+    fn cu_qp_delta(&mut self) -> Option<()> {
+        let mut abs = 0i32;
+        while abs < 5 && self.decision(cabac::CU_QP_DELTA + usize::from(abs > 0)) {
+            abs += 1;
+        }
+        if abs == 5 {
+            // EG0 suffix
+            let mut k = 0;
+            while self.engine.bypass() {
+                abs += 1 << k;
+                k += 1;
+                if k > 16 {
+                    return None;
+                }
+            }
+            abs += self.engine.bypass_bits(k) as i32;
+        }
+        let delta = if abs > 0 && self.engine.bypass() {
+            -abs
+        } else {
+            abs
+        };
+        let qp_bd_offset = 6 * (self.sps.bit_depth as i32 - 8);
+        if delta < -(26 + qp_bd_offset / 2) || delta > 25 + qp_bd_offset / 2 {
+            return None;
+        }
+        self.is_cu_qp_delta_coded = true;
+        self.qp_y =
+            (self.qp_pred + delta + 52 + 2 * qp_bd_offset) % (52 + qp_bd_offset) - qp_bd_offset;
+        Some(())
+    }
+
+    /// Predict one luma transform block and add its residual.
+    fn reconstruct_luma(&mut self, x0: usize, y0: usize, log2: u32, cbf: bool) -> Option<()> {
+        let size = 1usize << log2;
+        let mode = self.pic.intra_mode[self.pic.block(x0, y0)];
+        let bit_depth = self.sps.bit_depth;
+
+        let mut residual = [0i32; 32 * 32];
+        if cbf {
+            let mut coeffs = [0i32; 32 * 32];
+            let transform_skip = self.residual_coding(x0, y0, log2, 0, &mut coeffs)?;
+            let coeffs = &mut coeffs[..size * size];
+            if self.transquant_bypass {
+                residual[..size * size].copy_from_slice(coeffs);
+            } else {
+                let qp = self.qp_y + 6 * (bit_depth as i32 - 8);
+                transform::dequantize(coeffs, log2, qp, bit_depth, self.scaling);
+                if transform_skip {
+                    transform::transform_skip(coeffs, bit_depth, &mut residual[..16]);
+                } else {
+                    transform::inverse_transform(coeffs, log2, log2 == 2, bit_depth, &mut residual);
+                }
+            }
+        }
+
+        let width = self.pic.width;
+        let mut refs = {
+            let luma = &self.pic.luma;
+            References::gather(
+                size,
+                bit_depth,
+                |x, y| i32::from(luma[(y0 as i32 + y) as usize * width + (x0 as i32 + x) as usize]),
+                |x, y| self.available(x0, y0, x0 as isize + x as isize, y0 as isize + y as isize),
+            )
+        };
+        let mut pred = [0i32; 32 * 32];
+        intra::predict(
+            &mut refs,
+            mode,
+            self.sps.strong_intra_smoothing,
+            bit_depth,
+            &mut pred,
+        );
+
+        let max = (1 << bit_depth) - 1;
+        for y in 0..size {
+            let row = (y0 + y) * width + x0;
+            for x in 0..size {
+                let v = pred[y * size + x] + residual[y * size + x];
+                self.pic.luma[row + x] = v.clamp(0, max) as u16;
+            }
+        }
+        Some(())
+    }
+
+    /// residual_coding() (7.3.8.11) into raster-order `coeffs` for the
+    /// transform block at (x0, y0). Returns transform_skip_flag.
+    fn residual_coding(
+        &mut self,
+        x0: usize,
+        y0: usize,
+        log2: u32,
+        c_idx: usize,
+        coeffs: &mut [i32],
+    ) -> Option<bool> {
+        let size = 1usize << log2;
+        coeffs[..size * size].fill(0);
+        let chroma = c_idx > 0;
+
+        let transform_skip = self.pps.transform_skip_enabled
+            && !self.transquant_bypass
+            && log2 == 2
+            && self.decision(cabac::TRANSFORM_SKIP + usize::from(chroma));
+
+        // last_sig_coeff_{x,y}_{prefix,suffix}
+        let (ctx_offset, ctx_shift) = if chroma {
+            (15, log2 - 2)
+        } else {
+            (
+                3 * (log2 as usize - 2) + ((log2 as usize - 1) >> 2),
+                (log2 + 1) >> 2,
+            )
+        };
+        let c_max = 2 * log2 - 1;
+        let mut prefixes = [0u32; 2];
+        for (prefix, base) in prefixes
+            .iter_mut()
+            .zip([cabac::LAST_X_PREFIX, cabac::LAST_Y_PREFIX])
+        {
+            while *prefix < c_max
+                && self.decision(base + ctx_offset + (*prefix >> ctx_shift) as usize)
+            {
+                *prefix += 1;
+            }
+        }
+        let [mut last_x, mut last_y] = prefixes.map(|p| p as usize);
+        for (last, prefix) in [(&mut last_x, prefixes[0]), (&mut last_y, prefixes[1])] {
+            if prefix > 3 {
+                let bits = (prefix >> 1) - 1;
+                *last = ((1 << bits) * (2 + (prefix & 1)) + self.engine.bypass_bits(bits)) as usize;
+            }
+        }
+
+        // Mode-dependent scans for 4x4 blocks and 8x8 luma (8.4.4.1)
+        let scan_idx = if log2 == 2 || (log2 == 3 && !chroma) {
+            let mode = if chroma {
+                self.chroma_mode
+            } else {
+                self.pic.intra_mode[self.pic.block(x0, y0)]
+            };
+            match mode {
+                6..=14 => 2,
+                22..=30 => 1,
+                _ => 0,
+            }
+        } else {
+            0
+        };
+        if scan_idx == 2 {
+            std::mem::swap(&mut last_x, &mut last_y);
+        }
+        if last_x >= size || last_y >= size {
+            return None;
+        }
+
+        let log2_sb = log2 - 2;
+        let sb_width = 1usize << log2_sb;
+        let sb_scan = ScanTables::get(log2_sb, scan_idx);
+        let pos_scan = ScanTables::get(2, scan_idx);
+        let last_sb = sb_scan
+            .iter()
+            .position(|&(x, y)| (x as usize, y as usize) == (last_x >> 2, last_y >> 2))?;
+        let last_pos = pos_scan
+            .iter()
+            .position(|&(x, y)| (x as usize, y as usize) == (last_x & 3, last_y & 3))?;
+
+        let sign_hiding = self.pps.sign_data_hiding && !self.transquant_bypass;
+        let mut coded_sb = [[false; 8]; 8];
+        let mut greater1_ctx = 1;
+        for i in (0..=last_sb).rev() {
+            let (xs, ys) = (sb_scan[i].0 as usize, sb_scan[i].1 as usize);
+            let right = xs + 1 < sb_width && coded_sb[xs + 1][ys];
+            let below = ys + 1 < sb_width && coded_sb[xs][ys + 1];
+            let mut infer_dc = false;
+            let coded = if i < last_sb && i > 0 {
+                let ctx = usize::from(right || below) + if chroma { 2 } else { 0 };
+                infer_dc = true;
+                self.decision(cabac::CODED_SUB_BLOCK + ctx)
+            } else {
+                true
+            };
+            coded_sb[xs][ys] = coded;
+
+            // Significant positions in descending scan order
+            let mut sig = [0usize; 16];
+            let mut num_sig = 0;
+            let start = if i == last_sb {
+                sig[0] = last_pos;
+                num_sig = 1;
+                last_pos
+            } else {
+                16
+            };
+            if coded {
+                let prev_csbf = usize::from(right) | (usize::from(below) << 1);
+                for n in (0..start).rev() {
+                    let (xp, yp) = (pos_scan[n].0 as usize, pos_scan[n].1 as usize);
+                    let significant = if n > 0 || !infer_dc {
+                        let ctx = sig_ctx(log2, chroma, scan_idx, xs, ys, xp, yp, prev_csbf);
+                        self.decision(cabac::SIG_COEFF + ctx)
+                    } else {
+                        true
+                    };
+                    if significant {
+                        sig[num_sig] = n;
+                        num_sig += 1;
+                        infer_dc = false;
+                    }
+                }
+            }
+            if num_sig == 0 {
+                continue;
+            }
+
+            // coeff_abs_level_greater1/2_flag
+            let mut ctx_set = if i > 0 && !chroma { 2 } else { 0 };
+            if i != last_sb && greater1_ctx == 0 {
+                ctx_set += 1;
+            }
+            greater1_ctx = 1;
+            let mut greater1 = [false; 8];
+            let mut first_greater1 = None;
+            for (m, flag) in greater1.iter_mut().enumerate().take(num_sig.min(8)) {
+                let ctx = ctx_set * 4 + greater1_ctx + if chroma { 16 } else { 0 };
+                *flag = self.decision(cabac::GREATER1 + ctx);
+                if *flag {
+                    greater1_ctx = 0;
+                    first_greater1.get_or_insert(m);
+                } else if greater1_ctx > 0 && greater1_ctx < 3 {
+                    greater1_ctx += 1;
+                }
+            }
+            let greater2 = first_greater1.is_some_and(|_| {
+                let ctx = ctx_set + if chroma { 4 } else { 0 };
+                self.decision(cabac::GREATER2 + ctx)
+            });
+
+            let hide_sign = sign_hiding && sig[0] - sig[num_sig - 1] > 3;
+            let coded_signs = if hide_sign { num_sig - 1 } else { num_sig };
+            let mut signs = [false; 16];
+            for sign in signs.iter_mut().take(coded_signs) {
+                *sign = self.engine.bypass();
+            }
+
+            // coeff_abs_level_remaining and final levels
+            let mut rice = 0;
+            let mut sum_abs = 0;
+            for m in 0..num_sig {
+                let mut level = 1;
+                let mut threshold = 1;
+                if m < 8 {
+                    level += i32::from(greater1[m]);
+                    threshold = 2;
+                    if first_greater1 == Some(m) {
+                        level += i32::from(greater2);
+                        threshold = 3;
+                    }
+                }
+                if level == threshold {
+                    level += self.coeff_abs_level_remaining(rice)?;
+                    if level > 3 * (1 << rice) {
+                        rice = (rice + 1).min(4);
+                    }
+                }
+                sum_abs += level;
+                let negative = if m < coded_signs {
+                    signs[m]
+                } else {
+                    sum_abs % 2 == 1
+                };
+                let (xp, yp) = pos_scan[sig[m]];
+                let x = (xs << 2) + xp as usize;
+                let y = (ys << 2) + yp as usize;
+                coeffs[y * size + x] = if negative { -level } else { level };
+            }
+        }
+        Some(transform_skip)
+    }
+
+    /// coeff_abs_level_remaining (9.3.3.11): a Rice prefix with an
+    /// Exp-Golomb escape.
+    fn coeff_abs_level_remaining(&mut self, rice: u32) -> Option<i32> {
+        let mut prefix = 0;
+        while prefix < 32 && self.engine.bypass() {
+            prefix += 1;
+        }
+        if prefix <= 3 {
+            return Some(((prefix << rice) + self.engine.bypass_bits(rice)) as i32);
+        }
+        let extra = prefix - 3;
+        if extra + rice > 24 {
+            return None;
+        }
+        let value = (((1 << extra) + 2) << rice) + self.engine.bypass_bits(extra + rice);
+        Some(value as i32)
+    }
+}
+
+/// ctxInc of sig_coeff_flag (9.3.4.2.5) for position (xp, yp) of sub-block
+/// (xs, ys).
+#[allow(clippy::too_many_arguments)]
+fn sig_ctx(
+    log2: u32,
+    chroma: bool,
+    scan_idx: usize,
+    xs: usize,
+    ys: usize,
+    xp: usize,
+    yp: usize,
+    prev_csbf: usize,
+) -> usize {
+    let sig = if log2 == 2 {
+        CTX_IDX_MAP[(yp << 2) + xp]
+    } else if xs + ys + xp + yp == 0 {
+        0
+    } else {
+        let base = match prev_csbf {
+            0 => match xp + yp {
+                0 => 2,
+                1 | 2 => 1,
+                _ => 0,
+            },
+            1 => 2 - yp.min(2),
+            2 => 2 - xp.min(2),
+            _ => 2,
+        };
+        if chroma {
+            base + if log2 == 3 { 9 } else { 12 }
+        } else {
+            let sub_block = if xs + ys > 0 { 3 } else { 0 };
+            let size = match (log2, scan_idx) {
+                (3, 0) => 9,
+                (3, _) => 15,
+                _ => 21,
+            };
+            base + sub_block + size
+        }
+    };
+    if chroma {
+        27 + sig
+    } else {
+        sig
+    }
+}
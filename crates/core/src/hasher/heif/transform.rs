@@ -0,0 +1,188 @@
+//! Scaling (dequantisation) and inverse transforms (8.6.2 – 8.6.4).
+
+use super::bitstream::ScalingList;
+
+const LEVEL_SCALE: [i64; 6] = [40, 45, 51, 57, 64, 72];
+
+/// 4x4 DST-VII basis used for intra luma 4x4 blocks, one row per frequency.
+const DST_4X4: [[i32; 4]; 4] = [
+    [29, 55, 74, 84],
+    [74, 74, 0, -74],
+    [84, -29, -74, 55],
+    [55, -84, 74, -29],
+];
+
+/// 32-point DCT basis (`transMatrix`). Smaller transforms use every
+/// `32 / N`-th row and the first N columns.
+static DCT_32X32: [[i32; 32]; 32] = dct_matrix();
+
+/// Build the DCT basis from its 31 distinct magnitudes: entry (k, n) is the
+/// integer approximation of 64·√2·cos((2n + 1)kπ / 64), with row 0 at 64.
+const fn dct_matrix() -> [[i32; 32]; 32] {
+    // Magnitude for cos(aπ/64), a = 1..=31
+    const MAGNITUDE: [i32; 32] = [
+        0, 90, 90, 90, 89, 88, 87, 85, 83, 82, 80, 78, 75, 73, 70, 67, 64, 61, 57, 54, 50, 46, 43,
+        38, 36, 31, 25, 22, 18, 13, 9, 4,
+    ];
+    let mut m = [[64; 32]; 32];
+    let mut k = 1;
+    while k < 32 {
+        let mut n = 0;
+        while n < 32 {
+            let mut a = ((2 * n + 1) * k) % 128;
+            let mut sign = 1;
+            if a > 64 {
+                a = 128 - a;
+            }
+            if a > 32 {
+                a = 64 - a;
+                sign = -1;
+            }
+            m[k][n] = sign * MAGNITUDE[a];
+            n += 1;
+        }
+        k += 1;
+    }
+    m
+}
+
+/// Scale `TransCoeffLevel` values in place (8.6.3). `qp` is Qp′Y.
+pub fn dequantize(
+    coeffs: &mut [i32],
+    log2_size: u32,
+    qp: i32,
+    bit_depth: u32,
+    scaling: Option<&ScalingList>,
+) {
+    let size = 1usize << log2_size;
+    let bd_shift = bit_depth + log2_size - 5;
+    let round = 1i64 << (bd_shift - 1);
+    let scale = LEVEL_SCALE[(qp % 6) as usize] << (qp / 6);
+    for y in 0..size {
+        for x in 0..size {
+            let c = &mut coeffs[y * size + x];
+            if *c == 0 {
+                continue;
+            }
+            let m = scaling.map_or(16, |s| i64::from(s.factor(log2_size, 0, x, y)));
+            let v = (i64::from(*c) * m * scale + round) >> bd_shift;
+            *c = v.clamp(-32768, 32767) as i32;
+        }
+    }
+}
+
+/// Residual for a transform-skipped 4x4 block (8.6.4.2, rotation/RDPCM off).
+pub fn transform_skip(coeffs: &[i32], bit_depth: u32, residual: &mut [i32]) {
+    let bd_shift = 20 - bit_depth;
+    for (r, &c) in residual.iter_mut().zip(coeffs) {
+        *r = ((c << 7) + (1 << (bd_shift - 1))) >> bd_shift;
+    }
+}
+
+/// Two-stage inverse transform (8.6.4.2). `dst` selects the 4x4 DST-VII.
+pub fn inverse_transform(
+    coeffs: &[i32],
+    log2_size: u32,
+    dst: bool,
+    bit_depth: u32,
+    out: &mut [i32],
+) {
+    let size = 1usize << log2_size;
+    let step = 32 >> log2_size;
+    let basis = |k: usize, n: usize| -> i32 {
+        if dst {
+            DST_4X4[k][n]
+        } else {
+            DCT_32X32[k * step][n]
+        }
+    };
+
+    // Columns and rows beyond the last non-zero coefficient contribute nothing
+    let mut max_x = 0;
+    let mut max_y = 0;
+    for y in 0..size {
+        for x in 0..size {
+            if coeffs[y * size + x] != 0 {
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+
+    // Vertical pass: each column of coefficients → intermediate g[x][y]
+    let mut tmp = [0i32; 32 * 32];
+    for x in 0..=max_x {
+        for y in 0..size {
+            let mut sum = 0i64;
+            for k in 0..=max_y {
+                sum += i64::from(basis(k, y)) * i64::from(coeffs[k * size + x]);
+            }
+            tmp[y * size + x] = ((sum + 64) >> 7).clamp(-32768, 32767) as i32;
+        }
+    }
+
+    // Horizontal pass + final scaling
+    let bd_shift = 20 - bit_depth;
+    let round = 1i64 << (bd_shift - 1);
+    for y in 0..size {
+        for x in 0..size {
+            let mut sum = 0i64;
+            for k in 0..=max_x {
+                sum += i64::from(basis(k, x)) * i64::from(tmp[y * size + k]);
+            }
+            out[y * size + x] = ((sum + round) >> bd_shift) as i32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dct_matrix_matches_known_rows() {
+        // 4-point rows are rows 0, 8, 16, 24 of the 32-point basis
+        assert_eq!(DCT_32X32[8][..4], [83, 36, -36, -83]);
+        assert_eq!(DCT_32X32[16][..4], [64, -64, -64, 64]);
+        assert_eq!(DCT_32X32[4][..8], [89, 75, 50, 18, -18, -50, -75, -89]);
+        assert_eq!(DCT_32X32[2][..8], [90, 87, 80, 70, 57, 43, 25, 9]);
+        assert_eq!(DCT_32X32[1][..4], [90, 90, 88, 85]);
+        assert_eq!(DCT_32X32[1][31], -90);
+        assert_eq!(DCT_32X32[31][..2], [4, -13]);
+    }
+
+    #[test]
+    fn test_dc_coefficient_gives_flat_residual() {
+        for log2 in 2..=5 {
+            let size = 1 << log2;
+            let mut coeffs = vec![0; size * size];
+            coeffs[0] = 1024;
+            let mut out = vec![0; size * size];
+            inverse_transform(&coeffs, log2, false, 8, &mut out);
+            // (1024·64 + 64) >> 7 = 512, then (512·64 + 2048) >> 12 = 8
+            assert!(out.iter().all(|&v| v == 8), "size {size}: {out:?}");
+        }
+    }
+
+    #[test]
+    fn test_dst_first_basis_is_a_ramp() {
+        let mut coeffs = [0; 16];
+        coeffs[0] = 4096;
+        let mut out = [0; 16];
+        inverse_transform(&coeffs, 2, true, 8, &mut out);
+        // Every row is the same increasing ramp along x (and along y)
+        assert!(out[0] < out[1] && out[1] < out[2] && out[2] < out[3]);
+        assert!(out[0] < out[4] && out[4] < out[8] && out[8] < out[12]);
+    }
+
+    #[test]
+    fn test_dequantize_flat_scaling() {
+        // qP 4 → levelScale 64; bdShift 8 + 2 - 5 = 5: 1·16·64 >> 5 = 32
+        let mut coeffs = [0; 16];
+        coeffs[0] = 1;
+        coeffs[5] = -2;
+        dequantize(&mut coeffs, 2, 4, 8, None);
+        assert_eq!(coeffs[0], 32);
+        assert_eq!(coeffs[5], -64);
+    }
+}
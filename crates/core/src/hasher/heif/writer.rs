@@ -0,0 +1,512 @@
+//! Test-only HEIF writer: PCM-coded HEVC inside a minimal HEIF container.
+//!
+//! Every CTB is one 16x16 PCM coding unit, so the output is lossless and the
+//! decoder's parameter set, slice header, CABAC and container paths can be
+//! checked sample-for-sample without a real encoder.
+
+use image::GrayImage;
+
+use super::cabac::{self, Context, RANGE_TAB_LPS, TRANS_IDX_LPS};
+
+/// MSB-first bit writer for RBSPs.
+#[derive(Default)]
+pub struct BitWriter {
+    bits: Vec<bool>,
+}
+
+impl BitWriter {
+    pub fn bits(&mut self, value: u64, n: u32) {
+        for i in (0..n).rev() {
+            self.bits.push((value >> i) & 1 == 1);
+        }
+    }
+
+    pub fn flag(&mut self, b: bool) {
+        self.bits.push(b);
+    }
+
+    pub fn ue(&mut self, value: u32) {
+        let v = u64::from(value) + 1;
+        let len = 64 - v.leading_zeros();
+        self.bits(0, len - 1);
+        self.bits(v, len);
+    }
+
+    pub fn se(&mut self, value: i32) {
+        let k = if value > 0 { 2 * value - 1 } else { -2 * value };
+        self.ue(k as u32);
+    }
+
+    pub fn align_zero(&mut self) {
+        while !self.bits.len().is_multiple_of(8) {
+            self.bits.push(false);
+        }
+    }
+
+    /// rbsp_trailing_bits(): a stop bit, then zeros to the byte boundary.
+    pub fn trailing_bits(&mut self) {
+        self.bits.push(true);
+        self.align_zero();
+    }
+
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.align_zero();
+        self.bits
+            .chunks(8)
+            .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | u8::from(b)))
+            .collect()
+    }
+}
+
+/// Spec arithmetic encoder (9.3.5) writing into a [`BitWriter`].
+pub struct CabacWriter {
+    low: u32,
+    range: u32,
+    outstanding: u32,
+    first: bool,
+    pub out: BitWriter,
+}
+
+impl CabacWriter {
+    pub fn new(out: BitWriter) -> Self {
+        Self {
+            low: 0,
+            range: 510,
+            outstanding: 0,
+            first: true,
+            out,
+        }
+    }
+
+    /// Re-initialise after PCM samples (9.3.2.5).
+    pub fn restart(&mut self) {
+        self.low = 0;
+        self.range = 510;
+        self.outstanding = 0;
+        self.first = true;
+    }
+
+    fn put_bit(&mut self, b: bool) {
+        if self.first {
+            self.first = false;
+        } else {
+            self.out.flag(b);
+        }
+        for _ in 0..self.outstanding {
+            self.out.flag(!b);
+        }
+        self.outstanding = 0;
+    }
+
+    fn renorm(&mut self) {
+        while self.range < 256 {
+            if self.low < 256 {
+                self.put_bit(false);
+            } else if self.low >= 512 {
+                self.low -= 512;
+                self.put_bit(true);
+            } else {
+                self.low -= 256;
+                self.outstanding += 1;
+            }
+            self.range <<= 1;
+            self.low <<= 1;
+        }
+    }
+
+    pub fn decision(&mut self, ctx: &mut Context, bin: bool) {
+        let lps = u32::from(RANGE_TAB_LPS[ctx.state as usize][((self.range >> 6) & 3) as usize]);
+        self.range -= lps;
+        if u8::from(bin) != ctx.mps {
+            self.low += self.range;
+            self.range = lps;
+            if ctx.state == 0 {
+                ctx.mps = 1 - ctx.mps;
+            }
+            ctx.state = TRANS_IDX_LPS[ctx.state as usize];
+        } else if ctx.state < 62 {
+            ctx.state += 1;
+        }
+        self.renorm();
+    }
+
+    pub fn bypass(&mut self, bin: bool) {
+        self.low <<= 1;
+        if bin {
+            self.low += self.range;
+        }
+        if self.low >= 1024 {
+            self.put_bit(true);
+            self.low -= 1024;
+        } else if self.low < 512 {
+            self.put_bit(false);
+        } else {
+            self.low -= 512;
+            self.outstanding += 1;
+        }
+    }
+
+    /// Terminating bin; a 1 flushes the engine, ending with a one bit.
+    pub fn terminate(&mut self, bin: bool) {
+        self.range -= 2;
+        if bin {
+            self.low += self.range;
+            self.range = 2;
+            self.renorm();
+            self.put_bit((self.low >> 9) & 1 == 1);
+            self.out.bits(u64::from(((self.low >> 7) & 3) | 1), 2);
+        } else {
+            self.renorm();
+        }
+    }
+}
+
+// ── HEVC ─────────────────────────────────────────────────────────
+
+const LOG2_CTB_SIZE: u32 = 4;
+const NAL_IDR_W_RADL: u8 = 19;
+
+/// NAL unit with header and emulation prevention bytes.
+fn nal(nal_type: u8, rbsp: &[u8]) -> Vec<u8> {
+    let mut out = vec![nal_type << 1, 1];
+    let mut zeros = 0;
+    for &b in rbsp {
+        if zeros >= 2 && b <= 3 {
+            out.push(3);
+            zeros = 0;
+        }
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        out.push(b);
+    }
+    out
+}
+
+fn sps(width: u32, height: u32) -> Vec<u8> {
+    let ctb = 1 << LOG2_CTB_SIZE;
+    let mut w = BitWriter::default();
+    w.bits(0, 4); // sps_video_parameter_set_id
+    w.bits(0, 3); // sps_max_sub_layers_minus1
+    w.flag(true); // sps_temporal_id_nesting_flag
+
+    // profile_tier_level: Main, level 3.1
+    w.bits(0, 3);
+    w.bits(1, 5);
+    w.bits(0x4000_0000, 32);
+    w.bits(0, 48);
+    w.bits(93, 8);
+    w.ue(0); // sps_seq_parameter_set_id
+    w.ue(0); // chroma_format_idc: monochrome
+    let coded_w = width.div_ceil(ctb) * ctb;
+    let coded_h = height.div_ceil(ctb) * ctb;
+    w.ue(coded_w);
+    w.ue(coded_h);
+    w.flag(true); // conformance_window_flag
+    for offset in [0, coded_w - width, 0, coded_h - height] {
+        w.ue(offset);
+    }
+    w.ue(0); // bit_depth_luma_minus8
+    w.ue(0); // bit_depth_chroma_minus8
+    w.ue(4); // log2_max_pic_order_cnt_lsb_minus4
+    w.flag(true); // sps_sub_layer_ordering_info_present_flag
+    for _ in 0..3 {
+        w.ue(0);
+    }
+    w.ue(LOG2_CTB_SIZE - 3); // log2_min_luma_coding_block_size_minus3
+    w.ue(0); // log2_diff_max_min_luma_coding_block_size
+    w.ue(0); // log2_min_luma_transform_block_size_minus2
+    w.ue(LOG2_CTB_SIZE - 2); // log2_diff_max_min_luma_transform_block_size
+    w.ue(0); // max_transform_hierarchy_depth_inter
+    w.ue(0); // max_transform_hierarchy_depth_intra
+    w.flag(false); // scaling_list_enabled_flag
+    w.flag(false); // amp_enabled_flag
+    w.flag(false); // sample_adaptive_offset_enabled_flag
+    w.flag(true); // pcm_enabled_flag
+    w.bits(7, 4); // pcm_sample_bit_depth_luma_minus1
+    w.bits(7, 4); // pcm_sample_bit_depth_chroma_minus1
+    w.ue(LOG2_CTB_SIZE - 3); // log2_min_pcm_luma_coding_block_size_minus3
+    w.ue(0); // log2_diff_max_min_pcm_luma_coding_block_size
+    w.flag(true); // pcm_loop_filter_disabled_flag
+    w.ue(0); // num_short_term_ref_pic_sets
+    w.flag(false); // long_term_ref_pics_present_flag
+    w.flag(false); // sps_temporal_mvp_enabled_flag
+    w.flag(false); // strong_intra_smoothing_enabled_flag
+    w.flag(false); // vui_parameters_present_flag
+    w.flag(false); // sps_extension_present_flag
+    w.trailing_bits();
+    nal(super::bitstream::NAL_SPS, &w.into_bytes())
+}
+
+fn pps() -> Vec<u8> {
+    let mut w = BitWriter::default();
+    w.ue(0); // pps_pic_parameter_set_id
+    w.ue(0); // pps_seq_parameter_set_id
+    w.bits(0, 7); // dependent slices, output flag, extra bits, sign hiding, cabac_init
+    w.ue(0);
+    w.ue(0);
+    w.se(0); // init_qp_minus26
+    w.bits(0, 3); // constrained intra, transform skip, cu_qp_delta
+    w.se(0);
+    w.se(0);
+    // chroma qp offsets, weighted pred/bipred, transquant bypass, tiles,
+    // entropy sync, loop filter across slices, deblocking control, scaling list,
+    // lists modification
+    w.bits(0, 10);
+    w.ue(0); // log2_parallel_merge_level_minus2
+    w.flag(false); // slice_segment_header_extension_present_flag
+    w.flag(false); // pps_extension_present_flag
+    w.trailing_bits();
+    nal(super::bitstream::NAL_PPS, &w.into_bytes())
+}
+
+/// One IDR slice with every CTB coded as a single PCM coding unit.
+fn slice(image: &GrayImage) -> Vec<u8> {
+    let ctb = 1u32 << LOG2_CTB_SIZE;
+    let mut w = BitWriter::default();
+    w.flag(true); // first_slice_segment_in_pic_flag
+    w.flag(false); // no_output_of_prior_pics_flag
+    w.ue(0); // slice_pic_parameter_set_id
+    w.ue(2); // slice_type: I
+    w.se(0); // slice_qp_delta
+    w.trailing_bits(); // byte_alignment()
+
+    let mut contexts = cabac::init_contexts(26);
+    let mut enc = CabacWriter::new(w);
+    let (cols, rows) = (image.width().div_ceil(ctb), image.height().div_ceil(ctb));
+    for ry in 0..rows {
+        for rx in 0..cols {
+            enc.decision(&mut contexts[cabac::PART_MODE], true); // PART_2Nx2N
+            enc.terminate(true); // pcm_flag
+            enc.out.align_zero();
+            for y in ry * ctb..(ry + 1) * ctb {
+                for x in rx * ctb..(rx + 1) * ctb {
+                    // Padding outside the conformance window repeats the edge
+                    let px = image.get_pixel(x.min(image.width() - 1), y.min(image.height() - 1));
+                    enc.out.bits(u64::from(px.0[0]), 8);
+                }
+            }
+            enc.restart();
+            enc.terminate(rx + 1 == cols && ry + 1 == rows); // end_of_slice_segment_flag
+        }
+    }
+    let mut out = enc.out;
+    out.align_zero();
+    nal(NAL_IDR_W_RADL, &out.into_bytes())
+}
+
+/// `hvcC` with the parameter sets, and the length-prefixed slice data.
+fn encode_hevc(image: &GrayImage) -> (Vec<u8>, Vec<u8>) {
+    let mut hvcc = vec![1, 1];
+    hvcc.extend(0x4000_0000u32.to_be_bytes());
+    hvcc.extend([0; 6]);
+    hvcc.push(93);
+    hvcc.extend([0xF0, 0x00, 0xFC, 0xFC, 0xF8, 0xF8, 0, 0, 0x0F]);
+    hvcc.push(2);
+    for (nal_type, unit) in [(33u8, sps(image.width(), image.height())), (34, pps())] {
+        hvcc.push(0x80 | nal_type);
+        hvcc.extend(1u16.to_be_bytes());
+        hvcc.extend((unit.len() as u16).to_be_bytes());
+        hvcc.extend(unit);
+    }
+    let unit = slice(image);
+    let mut data = (unit.len() as u32).to_be_bytes().to_vec();
+    data.extend(unit);
+    (hvcc, data)
+}
+
+// ── Container ────────────────────────────────────────────────────
+
+fn bmff_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend(kind);
+    out.extend(payload);
+    out
+}
+
+fn full_box(kind: &[u8; 4], version: u8, payload: &[u8]) -> Vec<u8> {
+    bmff_box(kind, &[&[version, 0, 0, 0], payload].concat())
+}
+
+struct CodedItem {
+    id: u16,
+    kind: &'static [u8; 4],
+    data: Vec<u8>,
+    /// Stored in `idat` rather than `mdat`.
+    in_idat: bool,
+    /// 1-based `ipco` indices.
+    properties: Vec<u8>,
+}
+
+/// Write `image` as a HEIC file. `rotation` is the `irot` angle in
+/// anticlockwise quarter turns (the stored pixels are `image` itself);
+/// `grid` splits it into columns x rows tiles; `thumbnail` adds a `thmb` item.
+pub fn heic(
+    image: &GrayImage,
+    rotation: u8,
+    grid: (u32, u32),
+    thumbnail: Option<&GrayImage>,
+) -> Vec<u8> {
+    let mut properties: Vec<Vec<u8>> = Vec::new();
+    let mut add_property = |p: Vec<u8>| -> u8 {
+        properties.push(p);
+        properties.len() as u8
+    };
+    let ispe = |w: u32, h: u32| full_box(b"ispe", 0, &[w.to_be_bytes(), h.to_be_bytes()].concat());
+
+    let mut items = Vec::new();
+    let mut primary_properties = Vec::new();
+    let mut dimg = Vec::new();
+    let (cols, rows) = grid;
+    if (cols, rows) == (1, 1) {
+        let (hvcc, data) = encode_hevc(image);
+        primary_properties.push(add_property(bmff_box(b"hvcC", &hvcc)));
+        primary_properties.push(add_property(ispe(image.width(), image.height())));
+        items.push(CodedItem {
+            id: 1,
+            kind: b"hvc1",
+            data,
+            in_idat: false,
+            properties: Vec::new(),
+        });
+    } else {
+        let tile_w = image.width().div_ceil(cols);
+        let tile_h = image.height().div_ceil(rows);
+        let mut tile_properties = Vec::new();
+        for i in 0..cols * rows {
+            let (x0, y0) = ((i % cols) * tile_w, (i / cols) * tile_h);
+            let tile = GrayImage::from_fn(tile_w, tile_h, |x, y| {
+                let (x, y) = (x0 + x, y0 + y);
+                if x < image.width() && y < image.height() {
+                    *image.get_pixel(x, y)
+                } else {
+                    image::Luma([0])
+                }
+            });
+            let (hvcc, data) = encode_hevc(&tile);
+            if tile_properties.is_empty() {
+                tile_properties.push(add_property(bmff_box(b"hvcC", &hvcc)));
+                tile_properties.push(add_property(ispe(tile_w, tile_h)));
+            }
+            let id = 2 + i as u16;
+            dimg.push(id);
+            items.push(CodedItem {
+                id,
+                kind: b"hvc1",
+                data,
+                in_idat: false,
+                properties: tile_properties.clone(),
+            });
+        }
+        let mut descriptor = vec![0, 0, (rows - 1) as u8, (cols - 1) as u8];
+        descriptor.extend((image.width() as u16).to_be_bytes());
+        descriptor.extend((image.height() as u16).to_be_bytes());
+        primary_properties.push(add_property(ispe(image.width(), image.height())));
+        items.insert(
+            0,
+            CodedItem {
+                id: 1,
+                kind: b"grid",
+                data: descriptor,
+                in_idat: true,
+                properties: Vec::new(),
+            },
+        );
+    }
+    if rotation != 0 {
+        primary_properties.push(add_property(bmff_box(b"irot", &[rotation & 3])));
+    }
+    items[0].properties = primary_properties;
+    if let Some(thumbnail) = thumbnail {
+        let (hvcc, data) = encode_hevc(thumbnail);
+        let properties = vec![
+            add_property(bmff_box(b"hvcC", &hvcc)),
+            add_property(ispe(thumbnail.width(), thumbnail.height())),
+        ];
+        items.push(CodedItem {
+            id: 100,
+            kind: b"hvc1",
+            data,
+            in_idat: false,
+            properties,
+        });
+    }
+
+    let ftyp = bmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+    let build_meta = |mdat_start: u32| -> Vec<u8> {
+        let mut iloc = vec![0x44, 0x00];
+        iloc.extend((items.len() as u16).to_be_bytes());
+        let (mut mdat_offset, mut idat_offset) = (mdat_start, 0u32);
+        for item in &items {
+            iloc.extend(item.id.to_be_bytes());
+            iloc.extend(u16::from(item.in_idat).to_be_bytes());
+            iloc.extend([0, 0, 0, 1]); // data_reference_index, extent_count
+            let offset = if item.in_idat {
+                &mut idat_offset
+            } else {
+                &mut mdat_offset
+            };
+            iloc.extend(offset.to_be_bytes());
+            iloc.extend((item.data.len() as u32).to_be_bytes());
+            *offset += item.data.len() as u32;
+        }
+
+        let mut iinf = (items.len() as u16).to_be_bytes().to_vec();
+        for item in &items {
+            let entry = [&item.id.to_be_bytes()[..], &[0, 0], item.kind, b"\0"].concat();
+            iinf.extend(full_box(b"infe", 2, &entry));
+        }
+
+        let mut iref = Vec::new();
+        if !dimg.is_empty() {
+            let mut refs = [1u16.to_be_bytes(), (dimg.len() as u16).to_be_bytes()].concat();
+            refs.extend(dimg.iter().flat_map(|id| id.to_be_bytes()));
+            iref.extend(bmff_box(b"dimg", &refs));
+        }
+        if thumbnail.is_some() {
+            iref.extend(bmff_box(b"thmb", &[0, 100, 0, 1, 0, 1]));
+        }
+
+        let mut ipma = (items.len() as u32).to_be_bytes().to_vec();
+        for item in &items {
+            ipma.extend(item.id.to_be_bytes());
+            ipma.push(item.properties.len() as u8);
+            ipma.extend(item.properties.iter().map(|&p| p | 0x80));
+        }
+        let iprp = bmff_box(
+            b"iprp",
+            &[
+                bmff_box(b"ipco", &properties.concat()),
+                full_box(b"ipma", 0, &ipma),
+            ]
+            .concat(),
+        );
+
+        let idat: Vec<u8> = items
+            .iter()
+            .filter(|i| i.in_idat)
+            .flat_map(|i| i.data.clone())
+            .collect();
+        let mut children = [
+            full_box(b"hdlr", 0, b"\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0"),
+            full_box(b"pitm", 0, &1u16.to_be_bytes()),
+            full_box(b"iloc", 1, &iloc),
+            full_box(b"iinf", 0, &iinf),
+            iprp,
+        ]
+        .concat();
+        if !iref.is_empty() {
+            children.extend(full_box(b"iref", 0, &iref));
+        }
+        if !idat.is_empty() {
+            children.extend(bmff_box(b"idat", &idat));
+        }
+        full_box(b"meta", 0, &children)
+    };
+    let meta_len = build_meta(0).len();
+    let meta = build_meta((ftyp.len() + meta_len + 8) as u32);
+    let mdat: Vec<u8> = items
+        .iter()
+        .filter(|i| !i.in_idat)
+        .flat_map(|i| i.data.clone())
+        .collect();
+    [ftyp, meta, bmff_box(b"mdat", &mdat)].concat()
+}
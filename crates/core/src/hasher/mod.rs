@@ -1,4 +1,7 @@
+#[cfg(feature = "heif")]
+pub mod heif;
 pub mod perceptual;
+pub mod raw_preview;
 
 use std::io::Read;
 use std::path::Path;
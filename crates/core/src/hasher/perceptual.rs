@@ -3,6 +3,8 @@ use std::path::Path;
 
 use fast_image_resize::{self as fir, images::Image as FirImage};
 
+use crate::scanner::formats::format_from_extension;
+
 /// Compute average hash (aHash) and difference hash (dHash) for an image.
 /// The aHash is stored in the `phash` field for historical reasons.
 /// Returns (ahash, dhash) as u64 values, or None if the image cannot be processed.
@@ -10,9 +12,13 @@ use fast_image_resize::{self as fir, images::Image as FirImage};
 ///
 /// Uses a hybrid decode strategy:
 /// - JPEG: `turbojpeg` full-resolution grayscale decode (feature-gated, skips chroma)
+/// - RAW: the largest embedded JPEG preview (see [`super::raw_preview`]), decoded
+///   like a JPEG with the container's orientation
+/// - HEIC/HEIF: pure-Rust luma decode of the thumbnail or primary item (see
+///   [`super::heif`], feature-gated)
 /// - Other formats: `image` crate decode, RGB resize to 9x8, then grayscale conversion
 ///
-/// Both paths apply EXIF orientation before resizing, so photos with rotation tags
+/// All paths apply EXIF orientation before resizing, so photos with rotation tags
 /// (common on iPhone originals) produce the same hash as physically-rotated exports.
 ///
 /// Both paths produce a 9x8 grayscale buffer for manual aHash + dHash computation.
@@ -27,6 +33,18 @@ pub fn compute_perceptual_hashes(path: &Path) -> Option<(u64, u64)> {
 
 /// Load image and produce a 9x8 grayscale pixel buffer ready for hashing.
 fn load_9x8_grayscale(path: &Path) -> Option<[u8; 72]> {
+    // RAW: embedded JPEG preview → orientation → resize to 9x8
+    if is_raw(path) {
+        let preview = super::raw_preview::extract_preview(path)?;
+        return load_jpeg_bytes_9x8(&preview.jpeg, preview.orientation);
+    }
+
+    // HEIF: luma plane with clap/irot/imir applied → resize to 9x8
+    #[cfg(feature = "heif")]
+    if is_heif(path) {
+        return load_heif_9x8(path);
+    }
+
     // JPEG: turbojpeg full-res grayscale → orientation → resize to 9x8
     #[cfg(feature = "turbojpeg")]
     if is_jpeg(path) {
@@ -39,6 +57,58 @@ fn load_9x8_grayscale(path: &Path) -> Option<[u8; 72]> {
     load_image_crate_9x8(path)
 }
 
+/// Check if a file is a RAW format by extension.
+fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| format_from_extension(&e.to_ascii_lowercase()))
+        .is_some_and(|f| f.is_raw())
+}
+
+/// Decode an in-memory JPEG (e.g. a RAW preview) with a known orientation.
+/// Prefers the turbojpeg grayscale path, falling back to the `image` crate.
+fn load_jpeg_bytes_9x8(jpeg_data: &[u8], orientation: u8) -> Option<[u8; 72]> {
+    #[cfg(feature = "turbojpeg")]
+    if let Some(buf) = decode_jpeg_gray_9x8(jpeg_data, orientation) {
+        return Some(buf);
+    }
+
+    let img = image::load_from_memory_with_format(jpeg_data, image::ImageFormat::Jpeg).ok()?;
+    dynamic_image_9x8(&img, orientation)
+}
+
+/// Check if a file is HEIC/HEIF by extension.
+#[cfg(feature = "heif")]
+fn is_heif(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "heic" | "heif"))
+}
+
+/// Minimum long edge for a HEIF thumbnail item to be hashed instead of the
+/// primary image. iPhone thumbnails are 320px wide: plenty for a 9x8 hash and
+/// far cheaper to decode than the 12-48MP primary.
+#[cfg(feature = "heif")]
+const HEIF_MIN_THUMBNAIL_EDGE: u32 = 256;
+
+/// Decode a HEIF image to its luma plane, then SIMD-resize to 9x8.
+///
+/// Uses the largest thumbnail item when it is at least [`HEIF_MIN_THUMBNAIL_EDGE`],
+/// otherwise the primary image. The decoder applies the item's `irot`/`imir`
+/// (and `clap`) properties, so the EXIF orientation tag must not be applied on
+/// top — in HEIF it only mirrors those transforms.
+#[cfg(feature = "heif")]
+fn load_heif_9x8(path: &Path) -> Option<[u8; 72]> {
+    let luma = super::heif::decode_luma(path, HEIF_MIN_THUMBNAIL_EDGE)?;
+    let src = FirImage::from_vec_u8(luma.width, luma.height, luma.data, fir::PixelType::U8).ok()?;
+    let mut dst = FirImage::new(9, 8, fir::PixelType::U8);
+    fir::Resizer::new().resize(&src, &mut dst, None).ok()?;
+
+    let mut pixels = [0u8; 72];
+    pixels.copy_from_slice(&dst.buffer()[..72]);
+    Some(pixels)
+}
+
 /// Check if a file is JPEG by extension.
 #[cfg(feature = "turbojpeg")]
 fn is_jpeg(path: &Path) -> bool {
@@ -154,8 +224,15 @@ fn apply_orientation(buf: &[u8], w: usize, h: usize, orientation: u8) -> (Vec<u8
 #[cfg(feature = "turbojpeg")]
 fn load_jpeg_9x8(path: &Path) -> Option<[u8; 72]> {
     let jpeg_data = std::fs::read(path).ok()?;
+    let orientation = read_exif_orientation(path);
+    decode_jpeg_gray_9x8(&jpeg_data, orientation)
+}
+
+/// turbojpeg grayscale decode of in-memory JPEG data, then orientation and resize.
+#[cfg(feature = "turbojpeg")]
+fn decode_jpeg_gray_9x8(jpeg_data: &[u8], orientation: u8) -> Option<[u8; 72]> {
     let mut decompressor = turbojpeg::Decompressor::new().ok()?;
-    let header = decompressor.read_header(&jpeg_data).ok()?;
+    let header = decompressor.read_header(jpeg_data).ok()?;
     let w = header.width;
     let h = header.height;
 
@@ -168,10 +245,9 @@ fn load_jpeg_9x8(path: &Path) -> Option<[u8; 72]> {
         height: h,
         format: turbojpeg::PixelFormat::GRAY,
     };
-    decompressor.decompress(&jpeg_data, output).ok()?;
+    decompressor.decompress(jpeg_data, output).ok()?;
 
     // Apply EXIF orientation before resize
-    let orientation = read_exif_orientation(path);
     let (buf, w, h) = apply_orientation(&buf, w, h, orientation);
 
     // SIMD resize grayscale to 9x8
@@ -219,11 +295,16 @@ fn apply_orientation_rgb(buf: &[u8], w: usize, h: usize, orientation: u8) -> (Ve
 /// Avoids full-resolution grayscale conversion (e.g., 12MP × BT.601 per pixel).
 fn load_image_crate_9x8(path: &Path) -> Option<[u8; 72]> {
     let img = image::open(path).ok()?;
+    let orientation = read_exif_orientation(path);
+    dynamic_image_9x8(&img, orientation)
+}
+
+/// Shared tail of the `image` crate path: orientation → RGB resize to 9x8 → grayscale.
+fn dynamic_image_9x8(img: &image::DynamicImage, orientation: u8) -> Option<[u8; 72]> {
     let rgb = img.to_rgb8();
     let (w, h) = (rgb.width() as usize, rgb.height() as usize);
 
     // Apply EXIF orientation before resize
-    let orientation = read_exif_orientation(path);
     let (rgb_data, w, h) = apply_orientation_rgb(rgb.as_raw(), w, h, orientation);
 
     // SIMD resize RGB to 9x8 (216 bytes output instead of millions)
@@ -448,4 +529,69 @@ mod tests {
         assert_eq!((w, h), (2, 3));
         assert_eq!(out, vec![3, 6, 2, 5, 1, 4]);
     }
+
+    /// Grayscale test card with enough structure for stable hashes.
+    #[cfg(feature = "heif")]
+    fn heic_test_card() -> image::GrayImage {
+        image::GrayImage::from_fn(200, 120, |x, y| {
+            let band = if (x / 25 + y / 30) % 2 == 0 { 60 } else { 0 };
+            image::Luma([(x * 150 / 200 + band) as u8 + (y / 4) as u8])
+        })
+    }
+
+    #[cfg(feature = "heif")]
+    #[test]
+    fn test_heic_hash_matches_jpeg_export() {
+        let tmp = tempfile::tempdir().unwrap();
+        let img = heic_test_card();
+        let heic = tmp.path().join("IMG_0001.heic");
+        std::fs::write(&heic, crate::hasher::heif::writer::heic(&img, 0, (1, 1), None)).unwrap();
+        let jpg = tmp.path().join("IMG_0001.jpg");
+        img.save(&jpg).unwrap();
+
+        let (ahash_heic, dhash_heic) = compute_perceptual_hashes(&heic).unwrap();
+        let (ahash_jpg, dhash_jpg) = compute_perceptual_hashes(&jpg).unwrap();
+        assert!(hamming_distance(ahash_heic, ahash_jpg) <= 2);
+        assert!(hamming_distance(dhash_heic, dhash_jpg) <= 2);
+    }
+
+    #[cfg(feature = "heif")]
+    #[test]
+    fn test_heic_irot_matches_upright_jpeg() {
+        // Pixels stored rotated clockwise, with irot turning them back
+        let tmp = tempfile::tempdir().unwrap();
+        let img = heic_test_card();
+        let stored = image::imageops::rotate90(&img);
+        let heic = tmp.path().join("IMG_0002.heic");
+        std::fs::write(&heic, crate::hasher::heif::writer::heic(&stored, 1, (2, 2), None))
+            .unwrap();
+        let jpg = tmp.path().join("IMG_0002.jpg");
+        img.save(&jpg).unwrap();
+
+        let (ahash_heic, dhash_heic) = compute_perceptual_hashes(&heic).unwrap();
+        let (ahash_jpg, dhash_jpg) = compute_perceptual_hashes(&jpg).unwrap();
+        assert!(hamming_distance(ahash_heic, ahash_jpg) <= 2);
+        assert!(hamming_distance(dhash_heic, dhash_jpg) <= 2);
+    }
+
+    #[cfg(feature = "heif")]
+    #[test]
+    fn test_x265_heic_hashes_match_jpeg_exports() {
+        // Encoder output: a single item hashed via its thumbnail, an irot
+        // item and a grid, each against libheif's decode exported as JPEG
+        use crate::matching::confidence::PHASH_NEAR_CERTAIN_THRESHOLD;
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/heif");
+        for name in ["single", "irot", "grid"] {
+            let heic = fixtures.join(format!("{name}.heic"));
+            let jpg = fixtures.join(format!("{name}.jpg"));
+            let (ahash_heic, dhash_heic) = compute_perceptual_hashes(&heic).unwrap();
+            let (ahash_jpg, dhash_jpg) = compute_perceptual_hashes(&jpg).unwrap();
+            let distances = (
+                hamming_distance(ahash_heic, ahash_jpg),
+                hamming_distance(dhash_heic, dhash_jpg),
+            );
+            assert!(distances.0 <= PHASH_NEAR_CERTAIN_THRESHOLD, "{name}: {distances:?}");
+            assert!(distances.1 <= PHASH_NEAR_CERTAIN_THRESHOLD, "{name}: {distances:?}");
+        }
+    }
 }
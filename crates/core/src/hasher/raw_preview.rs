@@ -0,0 +1,963 @@
+//! Embedded JPEG preview extraction for RAW files.
+//!
+//! RAW sensor data cannot be decoded here, but every supported RAW container
+//! carries at least one camera-rendered JPEG (thumbnail, medium preview or a
+//! full-size rendition). Hashing the largest of those gives RAW files real
+//! perceptual hashes that line up with their out-of-camera JPEG siblings.
+//!
+//! Supported containers:
+//! - TIFF-based (CR2, NEF, ARW, DNG, ORF, RW2): IFD chain + SubIFDs, using
+//!   `JPEGInterchangeFormat`, single-strip JPEG-compressed images and the
+//!   Panasonic `JpgFromRaw` tag.
+//! - CR3 (ISO BMFF): the `PRVW` preview box and the full-size JPEG track.
+//! - RAF: the JPEG pointer in the Fujifilm header.
+//!
+//! Files are read with seeks, so only IFDs, box headers and the chosen JPEG are
+//! loaded into memory. If no structured candidate decodes, the first
+//! [`FALLBACK_SCAN_LIMIT`] bytes are scanned for JPEG streams (Olympus keeps its
+//! previews inside the MakerNote).
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Maximum number of leading bytes scanned for JPEG markers when the container
+/// structure yields no usable preview.
+const FALLBACK_SCAN_LIMIT: u64 = 32 * 1024 * 1024;
+
+/// Maximum IFDs visited per file (guards against malformed offset loops).
+const MAX_IFDS: usize = 32;
+
+/// Maximum values read from one IFD entry (guards against corrupt counts).
+const MAX_ENTRY_VALUES: u32 = 4096;
+
+/// A camera-rendered JPEG embedded in a RAW file.
+#[derive(Debug, Clone)]
+pub struct EmbeddedPreview {
+    /// Complete JPEG stream (SOI..EOI).
+    pub jpeg: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// EXIF orientation (1-8) to apply to the preview pixels.
+    pub orientation: u8,
+}
+
+/// Location of a candidate JPEG stream inside the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Candidate {
+    offset: u64,
+    len: u64,
+}
+
+/// Extract the largest decodable embedded JPEG from a RAW file.
+/// Returns `None` if the file has no baseline/progressive JPEG preview.
+pub fn extract_preview(path: &Path) -> Option<EmbeddedPreview> {
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+
+    let mut header = [0u8; 16];
+    let n = read_up_to(&mut file, 0, &mut header)?;
+    let header = &header[..n];
+
+    let (candidates, orientation) = if header.len() >= 16 && &header[..15] == b"FUJIFILMCCD-RAW" {
+        (raf_candidates(&mut file)?, None)
+    } else if header.len() >= 12 && &header[4..12] == b"ftypcrx " {
+        cr3_candidates(&mut file, file_len)
+    } else if let Some(endian) = Endian::from_tiff_header(header) {
+        let mut tiff = Tiff::new(&mut file, 0, endian, file_len);
+        let orientation = tiff.orientation();
+        (tiff.preview_candidates(), orientation)
+    } else {
+        (Vec::new(), None)
+    };
+
+    // Pick the largest baseline/progressive JPEG by pixel area
+    let best = candidates
+        .into_iter()
+        .filter(|c| c.len > 4 && c.offset.saturating_add(c.len) <= file_len)
+        .filter_map(|c| {
+            let head = read_range(&mut file, c.offset, c.len.min(256 * 1024))?;
+            let info = jpeg_info(&head)?;
+            Some((c, info))
+        })
+        .max_by_key(|(c, (w, h))| (u64::from(*w) * u64::from(*h), c.len));
+
+    let (jpeg, width, height) = match best {
+        Some((c, (w, h))) => (read_range(&mut file, c.offset, c.len)?, w, h),
+        None => scan_for_jpeg(&mut file, file_len)?,
+    };
+
+    let orientation = orientation
+        .filter(|o| (1..=8).contains(o))
+        .or_else(|| jpeg_exif_orientation(&jpeg))
+        .unwrap_or(1);
+
+    Some(EmbeddedPreview {
+        jpeg,
+        width,
+        height,
+        orientation,
+    })
+}
+
+// ── Low-level reads ──────────────────────────────────────────────
+
+/// Read as many bytes as available (up to `buf.len()`) at `offset`.
+fn read_up_to<R: Read + Seek>(r: &mut R, offset: u64, buf: &mut [u8]) -> Option<usize> {
+    r.seek(SeekFrom::Start(offset)).ok()?;
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(_) => return None,
+        }
+    }
+    Some(filled)
+}
+
+/// Read exactly `len` bytes at `offset`. Ranges past the end of the stream are
+/// rejected before allocating, so a corrupt length cannot exhaust memory.
+pub(super) fn read_range<R: Read + Seek>(r: &mut R, offset: u64, len: u64) -> Option<Vec<u8>> {
+    let stream_len = r.seek(SeekFrom::End(0)).ok()?;
+    if offset.checked_add(len)? > stream_len {
+        return None;
+    }
+    let mut buf = vec![0u8; usize::try_from(len).ok()?];
+    r.seek(SeekFrom::Start(offset)).ok()?;
+    r.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+// ── TIFF-based containers ────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Recognise a TIFF header, including the ORF (`RO`/`RS`) and RW2 (`0x55`) magics.
+    fn from_tiff_header(header: &[u8]) -> Option<Self> {
+        if header.len() < 8 {
+            return None;
+        }
+        let endian = match &header[..2] {
+            b"II" => Self::Little,
+            b"MM" => Self::Big,
+            _ => return None,
+        };
+        match endian.u16(&header[2..4]) {
+            42 | 0x4F52 | 0x5352 | 0x55 => Some(endian),
+            _ => None,
+        }
+    }
+
+    fn u16(self, b: &[u8]) -> u16 {
+        let bytes = [b[0], b[1]];
+        match self {
+            Self::Little => u16::from_le_bytes(bytes),
+            Self::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(self, b: &[u8]) -> u32 {
+        let bytes = [b[0], b[1], b[2], b[3]];
+        match self {
+            Self::Little => u32::from_le_bytes(bytes),
+            Self::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+/// Panasonic RW2 full-size preview, stored as an UNDEFINED blob.
+const TAG_RW2_JPG_FROM_RAW: u16 = 0x002E;
+
+/// TIFF compression values that denote JPEG data (old-style and new-style).
+const COMPRESSION_OJPEG: u32 = 6;
+const COMPRESSION_JPEG: u32 = 7;
+
+#[derive(Debug, Clone, Copy)]
+struct IfdEntry {
+    tag: u16,
+    typ: u16,
+    count: u32,
+    /// Raw 4-byte value/offset field.
+    value: [u8; 4],
+}
+
+/// Minimal TIFF structure reader. Offsets inside the TIFF are relative to `base`.
+struct Tiff<'a, R> {
+    r: &'a mut R,
+    base: u64,
+    endian: Endian,
+    limit: u64,
+}
+
+impl<'a, R: Read + Seek> Tiff<'a, R> {
+    fn new(r: &'a mut R, base: u64, endian: Endian, limit: u64) -> Self {
+        Self {
+            r,
+            base,
+            endian,
+            limit,
+        }
+    }
+
+    fn first_ifd_offset(&mut self) -> Option<u32> {
+        let mut header = [0u8; 8];
+        if read_up_to(self.r, self.base, &mut header)? < 8 {
+            return None;
+        }
+        Some(self.endian.u32(&header[4..8]))
+    }
+
+    /// Read one IFD, returning its entries and the offset of the next IFD (0 = none).
+    fn read_ifd(&mut self, offset: u32) -> Option<(Vec<IfdEntry>, u32)> {
+        let start = self.base + u64::from(offset);
+        if offset == 0 || start + 2 > self.limit {
+            return None;
+        }
+        let count_bytes = read_range(self.r, start, 2)?;
+        let count = self.endian.u16(&count_bytes) as u64;
+        let body = read_range(self.r, start + 2, count * 12 + 4)?;
+        let entries = body
+            .chunks_exact(12)
+            .take(count as usize)
+            .map(|e| IfdEntry {
+                tag: self.endian.u16(&e[0..2]),
+                typ: self.endian.u16(&e[2..4]),
+                count: self.endian.u32(&e[4..8]),
+                value: [e[8], e[9], e[10], e[11]],
+            })
+            .collect();
+        let next = self.endian.u32(&body[(count * 12) as usize..]);
+        Some((entries, next))
+    }
+
+    /// Integer values of a SHORT/LONG/IFD entry (inline or out-of-line).
+    fn values(&mut self, entry: &IfdEntry) -> Option<Vec<u32>> {
+        let size = match entry.typ {
+            3 => 2,     // SHORT
+            4 | 13 => 4, // LONG, IFD
+            _ => return None,
+        };
+        if entry.count > MAX_ENTRY_VALUES {
+            return None;
+        }
+        let total = size * entry.count as usize;
+        let data = if total <= 4 {
+            entry.value[..total].to_vec()
+        } else {
+            let offset = self.endian.u32(&entry.value);
+            read_range(self.r, self.base + u64::from(offset), total as u64)?
+        };
+        Some(
+            data.chunks_exact(size)
+                .map(|c| {
+                    if size == 2 {
+                        u32::from(self.endian.u16(c))
+                    } else {
+                        self.endian.u32(c)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn first_value(&mut self, entries: &[IfdEntry], tag: u16) -> Option<u32> {
+        let entry = *entries.iter().find(|e| e.tag == tag)?;
+        self.values(&entry)?.first().copied()
+    }
+
+    /// Orientation tag from IFD0, if present.
+    fn orientation(&mut self) -> Option<u8> {
+        let ifd0 = self.first_ifd_offset()?;
+        let (entries, _) = self.read_ifd(ifd0)?;
+        self.first_value(&entries, TAG_ORIENTATION).map(|v| v as u8)
+    }
+
+    /// Collect every embedded JPEG location from the IFD chain and SubIFDs.
+    fn preview_candidates(&mut self) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending: Vec<u32> = self.first_ifd_offset().into_iter().collect();
+
+        while let Some(offset) = pending.pop() {
+            if offset == 0 || visited.len() >= MAX_IFDS || !visited.insert(offset) {
+                continue;
+            }
+            let Some((entries, next)) = self.read_ifd(offset) else {
+                continue;
+            };
+            pending.push(next);
+
+            // JPEGInterchangeFormat / JPEGInterchangeFormatLength
+            if let (Some(off), Some(len)) = (
+                self.first_value(&entries, TAG_JPEG_OFFSET),
+                self.first_value(&entries, TAG_JPEG_LENGTH),
+            ) {
+                candidates.push(self.candidate(off, u64::from(len)));
+            }
+
+            // Single-strip JPEG-compressed image (CR2 full-size, DNG previews)
+            let compression = self.first_value(&entries, TAG_COMPRESSION);
+            if matches!(compression, Some(COMPRESSION_OJPEG | COMPRESSION_JPEG)) {
+                let offsets = entries
+                    .iter()
+                    .find(|e| e.tag == TAG_STRIP_OFFSETS)
+                    .copied()
+                    .and_then(|e| self.values(&e));
+                let counts = entries
+                    .iter()
+                    .find(|e| e.tag == TAG_STRIP_BYTE_COUNTS)
+                    .copied()
+                    .and_then(|e| self.values(&e));
+                if let (Some(offsets), Some(counts)) = (offsets, counts) {
+                    if offsets.len() == 1 && counts.len() == 1 {
+                        candidates.push(self.candidate(offsets[0], u64::from(counts[0])));
+                    }
+                }
+            }
+
+            // Panasonic JpgFromRaw blob
+            if let Some(entry) = entries.iter().find(|e| e.tag == TAG_RW2_JPG_FROM_RAW) {
+                if entry.count > 4 {
+                    let off = self.endian.u32(&entry.value);
+                    candidates.push(self.candidate(off, u64::from(entry.count)));
+                }
+            }
+
+            if let Some(entry) = entries.iter().find(|e| e.tag == TAG_SUB_IFDS).copied() {
+                if let Some(subs) = self.values(&entry) {
+                    pending.extend(subs);
+                }
+            }
+        }
+
+        candidates
+    }
+
+    fn candidate(&self, offset: u32, len: u64) -> Candidate {
+        Candidate {
+            offset: self.base + u64::from(offset),
+            len,
+        }
+    }
+}
+
+// ── RAF ──────────────────────────────────────────────────────────
+
+/// Fujifilm RAF: big-endian JPEG offset and length at bytes 84 and 88.
+fn raf_candidates<R: Read + Seek>(r: &mut R) -> Option<Vec<Candidate>> {
+    let pointers = read_range(r, 84, 8)?;
+    let offset = u32::from_be_bytes([pointers[0], pointers[1], pointers[2], pointers[3]]);
+    let len = u32::from_be_bytes([pointers[4], pointers[5], pointers[6], pointers[7]]);
+    Some(vec![Candidate {
+        offset: u64::from(offset),
+        len: u64::from(len),
+    }])
+}
+
+// ── CR3 (ISO base media file format) ─────────────────────────────
+
+/// Canon metadata container inside `moov` (holds CMT1..CMT4 and THMB).
+const CR3_META_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+/// Top-level container holding the `PRVW` preview box.
+const CR3_PREVIEW_UUID: [u8; 16] = [
+    0xea, 0xf4, 0x2b, 0x5e, 0x1c, 0x98, 0x4b, 0x88, 0xb9, 0xfb, 0xb7, 0xdc, 0x40, 0x6e, 0x4d, 0x16,
+];
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BmffBox {
+    pub(super) kind: [u8; 4],
+    /// Offset of the payload (after the header and, for `uuid`, the user type).
+    pub(super) payload: u64,
+    /// End offset of the box (exclusive).
+    pub(super) end: u64,
+    uuid: Option<[u8; 16]>,
+}
+
+/// List the boxes in `[start, end)`.
+pub(super) fn bmff_children<R: Read + Seek>(r: &mut R, start: u64, end: u64) -> Vec<BmffBox> {
+    let mut boxes = Vec::new();
+    let mut pos = start;
+    while pos + 8 <= end && boxes.len() < 256 {
+        let Some(header) = read_range(r, pos, 8) else {
+            break;
+        };
+        let size32 = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let kind = [header[4], header[5], header[6], header[7]];
+        let (size, header_len) = match size32 {
+            0 => (end - pos, 8),
+            1 => {
+                let Some(large) = read_range(r, pos + 8, 8) else {
+                    break;
+                };
+                let mut b = [0u8; 8];
+                b.copy_from_slice(&large);
+                (u64::from_be_bytes(b), 16)
+            }
+            n => (u64::from(n), 8),
+        };
+        // A box must hold its own header and fit inside its parent
+        let box_end = match pos.checked_add(size) {
+            Some(box_end) if size >= header_len && box_end <= end => box_end,
+            _ => break,
+        };
+        let mut payload = pos + header_len;
+        let uuid = if &kind == b"uuid" {
+            if payload + 16 > box_end {
+                break;
+            }
+            let user_type = read_range(r, payload, 16);
+            payload += 16;
+            user_type.map(|u| {
+                let mut id = [0u8; 16];
+                id.copy_from_slice(&u);
+                id
+            })
+        } else {
+            None
+        };
+        boxes.push(BmffBox {
+            kind,
+            payload,
+            end: box_end,
+            uuid,
+        });
+        pos = box_end;
+    }
+    boxes
+}
+
+pub(super) fn find_box<'b>(boxes: &'b [BmffBox], kind: &[u8; 4]) -> Option<&'b BmffBox> {
+    boxes.iter().find(|b| &b.kind == kind)
+}
+
+/// CR3 candidates (PRVW preview, THMB thumbnail, full-size JPEG track) and the
+/// orientation from the CMT1 TIFF block.
+fn cr3_candidates<R: Read + Seek>(r: &mut R, file_len: u64) -> (Vec<Candidate>, Option<u8>) {
+    let mut candidates = Vec::new();
+    let mut orientation = None;
+    let top = bmff_children(r, 0, file_len);
+
+    // PRVW: a JPEG after a short fixed header inside the preview uuid box
+    for container in top.iter().filter(|b| b.uuid == Some(CR3_PREVIEW_UUID)) {
+        // The uuid payload starts with 8 bytes of padding before the PRVW box
+        let children = bmff_children(r, container.payload + 8, container.end);
+        if let Some(prvw) = find_box(&children, b"PRVW") {
+            candidates.extend(find_soi(r, prvw.payload, prvw.end));
+        }
+    }
+
+    if let Some(moov) = find_box(&top, b"moov") {
+        let moov_children = bmff_children(r, moov.payload, moov.end);
+
+        if let Some(meta) = moov_children.iter().find(|b| b.uuid == Some(CR3_META_UUID)) {
+            let meta_children = bmff_children(r, meta.payload, meta.end);
+            if let Some(cmt1) = find_box(&meta_children, b"CMT1") {
+                let mut header = [0u8; 8];
+                if read_up_to(r, cmt1.payload, &mut header) == Some(8) {
+                    if let Some(endian) = Endian::from_tiff_header(&header) {
+                        orientation =
+                            Tiff::new(r, cmt1.payload, endian, cmt1.end).orientation();
+                    }
+                }
+            }
+            if let Some(thmb) = find_box(&meta_children, b"THMB") {
+                candidates.extend(find_soi(r, thmb.payload, thmb.end));
+            }
+        }
+
+        // The first track holds the full-size JPEG rendition
+        if let Some(trak) = find_box(&moov_children, b"trak") {
+            if let Some(candidate) = cr3_track_sample(r, trak) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    (candidates, orientation)
+}
+
+/// Location of the first sample of a track (`stbl/stsz` + `stbl/co64`).
+fn cr3_track_sample<R: Read + Seek>(r: &mut R, trak: &BmffBox) -> Option<Candidate> {
+    let mut current = *trak;
+    for kind in [b"mdia", b"minf", b"stbl"] {
+        let children = bmff_children(r, current.payload, current.end);
+        current = *find_box(&children, kind)?;
+    }
+    let stbl = bmff_children(r, current.payload, current.end);
+
+    let stsz = find_box(&stbl, b"stsz")?;
+    let sizes = read_range(r, stsz.payload, 16)?;
+    let mut size = u32::from_be_bytes([sizes[4], sizes[5], sizes[6], sizes[7]]);
+    if size == 0 {
+        size = u32::from_be_bytes([sizes[12], sizes[13], sizes[14], sizes[15]]);
+    }
+
+    let co64 = find_box(&stbl, b"co64")?;
+    let offsets = read_range(r, co64.payload, 16)?;
+    let mut offset = [0u8; 8];
+    offset.copy_from_slice(&offsets[8..16]);
+
+    Some(Candidate {
+        offset: u64::from_be_bytes(offset),
+        len: u64::from(size),
+    })
+}
+
+/// Find a JPEG SOI marker near the start of a box payload; the stream runs to
+/// the end of the box (decoders stop at EOI, trailing padding is harmless).
+fn find_soi<R: Read + Seek>(r: &mut R, start: u64, end: u64) -> Option<Candidate> {
+    let len = end.checked_sub(start)?;
+    let head = read_range(r, start, len.min(64))?;
+    let pos = head.windows(3).position(|w| w == [0xFF, 0xD8, 0xFF])? as u64;
+    Some(Candidate {
+        offset: start + pos,
+        len: len - pos,
+    })
+}
+
+// ── JPEG stream inspection ───────────────────────────────────────
+
+/// Width and height of a JPEG stream, if it is baseline or progressive.
+/// Lossless JPEG (SOF3, used for CR2/DNG raw data) and other SOF types are rejected.
+fn jpeg_info(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            // Fill byte
+            pos += 1;
+            continue;
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        match marker {
+            0xC0..=0xC2 => {
+                let sof = data.get(pos + 4..pos + 9)?;
+                let height = u16::from_be_bytes([sof[1], sof[2]]) as u32;
+                let width = u16::from_be_bytes([sof[3], sof[4]]) as u32;
+                return (width > 0 && height > 0).then_some((width, height));
+            }
+            // Other SOF markers (lossless, hierarchical, arithmetic) or SOS before SOF
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => return None,
+            _ => pos += 2 + len,
+        }
+    }
+    None
+}
+
+/// End offset (exclusive) of the JPEG stream starting at `start`, by walking
+/// segments and entropy-coded data up to EOI.
+fn jpeg_end(data: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start + 2;
+    loop {
+        if pos + 2 > data.len() || data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            0xFF => pos += 1,
+            0xD9 => return Some(pos + 2),
+            0xD0..=0xD7 | 0x01 => pos += 2,
+            _ => {
+                let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+                pos += 2 + len;
+                if marker == 0xDA {
+                    // Skip entropy-coded data: stop at the next real marker
+                    while pos + 1 < data.len() {
+                        if data[pos] == 0xFF {
+                            let next = data[pos + 1];
+                            if next != 0x00 && !(0xD0..=0xD7).contains(&next) {
+                                break;
+                            }
+                        }
+                        pos += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fallback: scan the leading bytes of the file for complete JPEG streams and
+/// return the largest baseline/progressive one.
+fn scan_for_jpeg<R: Read + Seek>(r: &mut R, file_len: u64) -> Option<(Vec<u8>, u32, u32)> {
+    let data = read_range(r, 0, file_len.min(FALLBACK_SCAN_LIMIT))?;
+    let mut best: Option<(usize, usize, u32, u32)> = None;
+    let mut pos = 0;
+    while pos + 3 <= data.len() {
+        if data[pos] == 0xFF && data[pos + 1] == 0xD8 && data[pos + 2] == 0xFF {
+            if let Some(end) = jpeg_end(&data, pos) {
+                if let Some((w, h)) = jpeg_info(&data[pos..end]) {
+                    let area = u64::from(w) * u64::from(h);
+                    if best.is_none_or(|(_, _, bw, bh)| area > u64::from(bw) * u64::from(bh)) {
+                        best = Some((pos, end, w, h));
+                    }
+                }
+                // Thumbnails nested in this stream's APP segments are smaller; skip them
+                pos = end;
+                continue;
+            }
+        }
+        pos += 1;
+    }
+    let (start, end, w, h) = best?;
+    Some((data[start..end].to_vec(), w, h))
+}
+
+/// EXIF orientation stored inside the JPEG stream itself (RAF previews carry it).
+fn jpeg_exif_orientation(jpeg: &[u8]) -> Option<u8> {
+    let mut cursor = std::io::Cursor::new(jpeg);
+    let exif = exif::Reader::new().read_from_container(&mut cursor).ok()?;
+    let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?;
+    field.value.get_uint(0).map(|v| v as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an RGB gradient as a baseline JPEG.
+    fn jpeg_bytes(w: u32, h: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(w, h, |x, y| {
+            image::Rgb([(x * 255 / w) as u8, (y * 255 / h) as u8, 128])
+        });
+        let mut out = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 90)
+            .encode_image(&img)
+            .unwrap();
+        out
+    }
+
+    /// A JPEG-looking stream with a lossless (SOF3) frame header, like CR2 raw data.
+    fn lossless_jpeg_bytes() -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8, 0xFF, 0xC3, 0x00, 0x0B, 0x0E, 0x10, 0x00, 0x20, 0x00];
+        out.extend_from_slice(&[0x01, 0x01, 0x11, 0x00, 0xFF, 0xD9]);
+        out.resize(4096, 0);
+        out
+    }
+
+    enum Value {
+        Inline(u32),
+        BlobOffset(usize),
+        BlobLen(usize),
+        IfdOffset(usize),
+    }
+
+    /// Build a little-endian TIFF: blobs right after the header, then a chain of IFDs.
+    fn build_tiff(magic: u16, blobs: &[&[u8]], ifds: &[Vec<(u16, u16, u32, Value)>]) -> Vec<u8> {
+        let mut out = b"II".to_vec();
+        out.extend_from_slice(&magic.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+
+        let mut blob_offsets = Vec::new();
+        for blob in blobs {
+            blob_offsets.push(out.len() as u32);
+            out.extend_from_slice(blob);
+        }
+
+        let mut ifd_offsets = Vec::new();
+        let mut pos = out.len() as u32;
+        for ifd in ifds {
+            ifd_offsets.push(pos);
+            pos += 2 + 12 * ifd.len() as u32 + 4;
+        }
+        out[4..8].copy_from_slice(&ifd_offsets[0].to_le_bytes());
+
+        for (i, ifd) in ifds.iter().enumerate() {
+            out.extend_from_slice(&(ifd.len() as u16).to_le_bytes());
+            for (tag, typ, count, value) in ifd {
+                out.extend_from_slice(&tag.to_le_bytes());
+                out.extend_from_slice(&typ.to_le_bytes());
+                out.extend_from_slice(&count.to_le_bytes());
+                let v = match value {
+                    Value::Inline(v) => *v,
+                    Value::BlobOffset(b) => blob_offsets[*b],
+                    Value::BlobLen(b) => blobs[*b].len() as u32,
+                    Value::IfdOffset(j) => ifd_offsets[*j],
+                };
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            let next = ifd_offsets.get(i + 1).copied().unwrap_or(0);
+            out.extend_from_slice(&next.to_le_bytes());
+        }
+        out
+    }
+
+    fn write(dir: &Path, name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_jpeg_info_baseline_and_lossless() {
+        assert_eq!(jpeg_info(&jpeg_bytes(40, 30)), Some((40, 30)));
+        assert_eq!(jpeg_info(&lossless_jpeg_bytes()), None);
+        assert_eq!(jpeg_info(b"not a jpeg"), None);
+    }
+
+    #[test]
+    fn test_jpeg_end_finds_eoi() {
+        let jpeg = jpeg_bytes(16, 16);
+        let mut data = vec![0u8; 10];
+        data.extend_from_slice(&jpeg);
+        data.extend_from_slice(&[0xAB; 20]);
+        assert_eq!(jpeg_end(&data, 10), Some(10 + jpeg.len()));
+    }
+
+    #[test]
+    fn test_tiff_jpeg_interchange_format_with_orientation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = jpeg_bytes(64, 48);
+        let data = build_tiff(
+            42,
+            &[&preview],
+            &[vec![
+                (TAG_ORIENTATION, 3, 1, Value::Inline(6)),
+                (TAG_JPEG_OFFSET, 4, 1, Value::BlobOffset(0)),
+                (TAG_JPEG_LENGTH, 4, 1, Value::BlobLen(0)),
+            ]],
+        );
+        let path = write(tmp.path(), "photo.arw", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!(extracted.jpeg, preview);
+        assert_eq!((extracted.width, extracted.height), (64, 48));
+        assert_eq!(extracted.orientation, 6);
+    }
+
+    #[test]
+    fn test_cr2_layout_picks_largest_and_skips_lossless() {
+        let tmp = tempfile::tempdir().unwrap();
+        let full = jpeg_bytes(96, 64);
+        let thumb = jpeg_bytes(24, 16);
+        let raw = lossless_jpeg_bytes();
+        let data = build_tiff(
+            42,
+            &[&full, &thumb, &raw],
+            &[
+                // IFD0: full-size JPEG as an old-style JPEG strip
+                vec![
+                    (TAG_COMPRESSION, 3, 1, Value::Inline(COMPRESSION_OJPEG)),
+                    (TAG_STRIP_OFFSETS, 4, 1, Value::BlobOffset(0)),
+                    (TAG_STRIP_BYTE_COUNTS, 4, 1, Value::BlobLen(0)),
+                ],
+                // IFD1: thumbnail
+                vec![
+                    (TAG_JPEG_OFFSET, 4, 1, Value::BlobOffset(1)),
+                    (TAG_JPEG_LENGTH, 4, 1, Value::BlobLen(1)),
+                ],
+                // IFD2: lossless raw data, must be ignored
+                vec![
+                    (TAG_COMPRESSION, 3, 1, Value::Inline(COMPRESSION_OJPEG)),
+                    (TAG_STRIP_OFFSETS, 4, 1, Value::BlobOffset(2)),
+                    (TAG_STRIP_BYTE_COUNTS, 4, 1, Value::BlobLen(2)),
+                ],
+            ],
+        );
+        let path = write(tmp.path(), "photo.cr2", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!((extracted.width, extracted.height), (96, 64));
+        assert_eq!(extracted.jpeg, full);
+        assert_eq!(extracted.orientation, 1);
+    }
+
+    #[test]
+    fn test_nef_preview_in_sub_ifd() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = jpeg_bytes(80, 60);
+        let data = build_tiff(
+            42,
+            &[&preview],
+            &[
+                vec![
+                    (TAG_ORIENTATION, 3, 1, Value::Inline(8)),
+                    (TAG_SUB_IFDS, 13, 1, Value::IfdOffset(1)),
+                ],
+                vec![
+                    (TAG_JPEG_OFFSET, 4, 1, Value::BlobOffset(0)),
+                    (TAG_JPEG_LENGTH, 4, 1, Value::BlobLen(0)),
+                ],
+            ],
+        );
+        let path = write(tmp.path(), "photo.nef", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!((extracted.width, extracted.height), (80, 60));
+        assert_eq!(extracted.orientation, 8);
+    }
+
+    #[test]
+    fn test_rw2_jpg_from_raw() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = jpeg_bytes(72, 48);
+        let data = build_tiff(
+            0x55,
+            &[&preview],
+            &[vec![(
+                TAG_RW2_JPG_FROM_RAW,
+                7,
+                preview.len() as u32,
+                Value::BlobOffset(0),
+            )]],
+        );
+        let path = write(tmp.path(), "photo.rw2", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!(extracted.jpeg, preview);
+    }
+
+    #[test]
+    fn test_raf_header_pointer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = jpeg_bytes(64, 64);
+        let mut data = b"FUJIFILMCCD-RAW 0201FF383501".to_vec();
+        data.resize(128, 0);
+        data[84..88].copy_from_slice(&128u32.to_be_bytes());
+        data[88..92].copy_from_slice(&(preview.len() as u32).to_be_bytes());
+        data.extend_from_slice(&preview);
+        let path = write(tmp.path(), "photo.raf", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!(extracted.jpeg, preview);
+    }
+
+    fn bmff_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn uuid_box(uuid: &[u8; 16], payload: &[u8]) -> Vec<u8> {
+        let mut body = uuid.to_vec();
+        body.extend_from_slice(payload);
+        bmff_box(b"uuid", &body)
+    }
+
+    #[test]
+    fn test_cr3_prvw_and_cmt1_orientation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let thumb = jpeg_bytes(16, 12);
+        let preview = jpeg_bytes(96, 64);
+
+        let cmt1 = build_tiff(42, &[], &[vec![(TAG_ORIENTATION, 3, 1, Value::Inline(3))]]);
+        let mut thmb_payload = vec![0u8; 16];
+        thmb_payload.extend_from_slice(&thumb);
+        let mut meta = bmff_box(b"CMT1", &cmt1);
+        meta.extend(bmff_box(b"THMB", &thmb_payload));
+        let moov = bmff_box(b"moov", &uuid_box(&CR3_META_UUID, &meta));
+
+        let mut prvw_payload = vec![0u8; 16];
+        prvw_payload.extend_from_slice(&preview);
+        let mut preview_container = vec![0u8; 8];
+        preview_container.extend(bmff_box(b"PRVW", &prvw_payload));
+
+        let mut data = bmff_box(b"ftyp", b"crx \x00\x00\x00\x01crx isom");
+        data.extend(moov);
+        data.extend(uuid_box(&CR3_PREVIEW_UUID, &preview_container));
+        let path = write(tmp.path(), "photo.cr3", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!((extracted.width, extracted.height), (96, 64));
+        assert_eq!(extracted.orientation, 3);
+    }
+
+    #[test]
+    fn test_read_range_rejects_ranges_past_end_before_allocating() {
+        let mut data = std::io::Cursor::new(vec![0u8; 64]);
+        assert_eq!(read_range(&mut data, 60, 4).map(|b| b.len()), Some(4));
+        assert!(read_range(&mut data, 60, 8).is_none());
+        assert!(read_range(&mut data, 0, 16 << 30).is_none());
+        assert!(read_range(&mut data, u64::MAX, 2).is_none());
+    }
+
+    #[test]
+    fn test_tiff_entry_with_huge_count_is_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data = build_tiff(
+            42,
+            &[],
+            &[vec![
+                (TAG_SUB_IFDS, 4, u32::MAX, Value::Inline(8)),
+                (TAG_STRIP_OFFSETS, 4, 0x4000_0000, Value::Inline(8)),
+            ]],
+        );
+        let path = write(tmp.path(), "corrupt.nef", &data);
+        assert!(extract_preview(&path).is_none());
+    }
+
+    #[test]
+    fn test_bmff_box_smaller_than_its_header_is_rejected() {
+        // size32 == 1 with a largesize of 8: payload would start past the box end
+        let mut bad = 1u32.to_be_bytes().to_vec();
+        bad.extend_from_slice(b"PRVW");
+        bad.extend_from_slice(&8u64.to_be_bytes());
+        bad.extend_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        let len = bad.len() as u64;
+        assert!(bmff_children(&mut std::io::Cursor::new(&bad), 0, len).is_empty());
+
+        // A largesize that overflows `pos + size`
+        bad[8..16].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(bmff_children(&mut std::io::Cursor::new(&bad), 0, len).is_empty());
+
+        let mut data = bmff_box(b"ftyp", b"crx \x00\x00\x00\x01crx isom");
+        data.extend(uuid_box(&CR3_PREVIEW_UUID, &[vec![0u8; 8], bad].concat()));
+        let tmp = tempfile::tempdir().unwrap();
+        let path = write(tmp.path(), "corrupt.cr3", &data);
+        assert!(extract_preview(&path).is_none());
+    }
+
+    #[test]
+    fn test_find_soi_rejects_inverted_range() {
+        let mut data = std::io::Cursor::new(vec![0xFFu8, 0xD8, 0xFF, 0xE0]);
+        assert!(find_soi(&mut data, 4, 2).is_none());
+    }
+
+    #[test]
+    fn test_fallback_scan_finds_unreferenced_jpeg() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = jpeg_bytes(48, 32);
+        // ORF-style: preview only reachable through the MakerNote, not the IFDs
+        let mut data = build_tiff(0x4F52, &[], &[vec![(TAG_ORIENTATION, 3, 1, Value::Inline(1))]]);
+        data.extend_from_slice(&[0x00; 100]);
+        data.extend_from_slice(&preview);
+        data.extend_from_slice(&[0x00; 100]);
+        let path = write(tmp.path(), "photo.orf", &data);
+
+        let extracted = extract_preview(&path).unwrap();
+        assert_eq!(extracted.jpeg, preview);
+    }
+
+    #[test]
+    fn test_no_preview_returns_none() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = write(tmp.path(), "fake.cr2", b"fake raw data");
+        assert!(extract_preview(&path).is_none());
+
+        let path = write(tmp.path(), "lossless.dng", &lossless_jpeg_bytes());
+        assert!(extract_preview(&path).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod catalog;
+pub mod dedupe;
 pub mod domain;
 pub mod error;
 pub mod exif;
@@ -6,23 +7,42 @@ pub mod export;
 pub mod hasher;
 pub mod manifest;
 pub mod matching;
+pub mod plan;
+pub mod query;
+pub mod quicktime;
 pub mod ranking;
+pub mod repair;
+pub mod restore;
 pub mod scanner;
+pub mod trash;
 pub mod vault_save;
+pub mod verify;
+pub mod views;
+pub mod volume;
+
+#[cfg(test)]
+pub(crate) mod test_support;
 
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use rayon::prelude::*;
+use serde::Serialize;
 
 use catalog::Catalog;
 use domain::*;
 use error::{Error, Result};
+use scanner::ignore_rules::IgnoreRules;
 
 /// Callback for reporting scan progress.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum ScanProgress {
     /// Starting scan of a source directory.
     SourceStart { source: String, file_count: usize },
+    /// A source's volume is absent (unmounted, or another volume at its path).
+    /// It is skipped and its photos are kept.
+    SourceOffline { source: String },
     /// A file has been hashed (SHA-256 + EXIF).
     FileHashed { path: PathBuf },
     /// Starting perceptual analysis of unique images.
@@ -31,6 +51,11 @@ pub enum ScanProgress {
     AnalysisDone { path: PathBuf },
     /// Stale catalog entries removed (files deleted from disk).
     FilesRemoved { count: usize },
+    /// A cataloged file was found at a new path (same size, mtime and SHA-256);
+    /// its catalog entry now points there and keeps its identity.
+    FileMoved { from: PathBuf, to: PathBuf },
+    /// A path was skipped by ignore rules (directories are pruned, not descended).
+    Excluded { path: PathBuf },
     /// Scan phase completed.
     PhaseComplete { phase: String },
 }
@@ -55,12 +80,121 @@ impl Vault {
         if !path.is_dir() {
             return Err(Error::SourceNotDirectory(path.to_path_buf()));
         }
-        self.catalog.add_source(path)
+        let mut source = self.catalog.add_source(path)?;
+        // Volume identity, so an unmounted volume is not mistaken for deleted files
+        if let Some(volume_id) = volume::ensure_marker(&source.path) {
+            self.catalog.set_source_volume_id(source.id, &volume_id)?;
+            source.volume_id = Some(volume_id);
+        }
+        Ok(source)
+    }
+
+    /// Set how strongly copies in the source at `path` are preferred when the
+    /// ranking policy includes `source` (higher wins, default 0), then re-rank.
+    pub fn set_source_priority(&mut self, path: &Path, priority: i64) -> Result<Source> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut source = self
+            .catalog
+            .list_sources()?
+            .into_iter()
+            .find(|s| s.path == canonical)
+            .ok_or_else(|| Error::SourceNotRegistered(path.to_path_buf()))?;
+        self.catalog.set_source_priority(source.id, priority)?;
+        source.priority = priority;
+        self.rebuild_groups()?;
+        Ok(source)
     }
 
     /// Remove a source and all its photos from the catalog.
     pub fn remove_source(&self, path: &Path) -> Result<(Source, usize)> {
-        self.catalog.remove_source(path)
+        let (source, removed) = self.catalog.remove_source(path)?;
+        if let Some(volume_id) = &source.volume_id {
+            volume::remove_marker(&source.path, volume_id);
+        }
+        Ok((source, removed))
+    }
+
+    /// Global ignore patterns applied to every source, in gitignore syntax.
+    pub fn ignore_patterns(&self) -> Result<Vec<String>> {
+        Ok(self
+            .catalog
+            .get_config("ignore_patterns")?
+            .map(|v| v.lines().map(String::from).collect())
+            .unwrap_or_default())
+    }
+
+    /// Add a global ignore pattern. Returns `false` if it was already present.
+    pub fn add_ignore_pattern(&self, pattern: &str) -> Result<bool> {
+        let pattern = pattern.trim();
+        scanner::ignore_rules::validate_pattern(pattern)?;
+        let mut patterns = self.ignore_patterns()?;
+        if pattern.is_empty() || patterns.iter().any(|p| p == pattern) {
+            return Ok(false);
+        }
+        patterns.push(pattern.to_string());
+        self.catalog
+            .set_config("ignore_patterns", &patterns.join("\n"))?;
+        Ok(true)
+    }
+
+    /// Remove a global ignore pattern. Returns `false` if it was not present.
+    pub fn remove_ignore_pattern(&self, pattern: &str) -> Result<bool> {
+        let pattern = pattern.trim();
+        let mut patterns = self.ignore_patterns()?;
+        let before = patterns.len();
+        patterns.retain(|p| p != pattern);
+        if patterns.len() == before {
+            return Ok(false);
+        }
+        self.catalog
+            .set_config("ignore_patterns", &patterns.join("\n"))?;
+        Ok(true)
+    }
+
+    /// Matching thresholds used by [`Vault::scan`] (the `default` profile unless set).
+    pub fn match_config(&self) -> Result<matching::MatchConfig> {
+        Ok(self
+            .catalog
+            .get_config("match_config")?
+            .map(|v| matching::MatchConfig::from_config_value(&v))
+            .unwrap_or_default())
+    }
+
+    /// Persist custom matching thresholds. Takes effect on the next scan.
+    pub fn set_match_config(&self, config: &matching::MatchConfig) -> Result<()> {
+        self.catalog
+            .set_config("match_config", &config.to_config_value())
+    }
+
+    /// Persist the named matching profile (`strict`, `default` or `scans`).
+    pub fn set_match_profile(&self, name: &str) -> Result<matching::MatchConfig> {
+        let config = matching::MatchConfig::profile(name)
+            .ok_or_else(|| Error::UnknownMatchProfile(name.to_string()))?;
+        self.set_match_config(&config)?;
+        Ok(config)
+    }
+
+    /// Criteria used to elect each group's source of truth, with the current
+    /// source priorities filled in.
+    pub fn ranking_policy(&self) -> Result<ranking::RankingPolicy> {
+        let mut policy = match self.catalog.get_config("ranking_policy")? {
+            Some(v) => ranking::RankingPolicy::parse(&v)?,
+            None => ranking::RankingPolicy::default(),
+        };
+        policy.source_priority = self
+            .catalog
+            .list_sources()?
+            .into_iter()
+            .map(|s| (s.id, s.priority))
+            .collect();
+        Ok(policy)
+    }
+
+    /// Persist the ranking criteria and re-elect every group's source of truth.
+    pub fn set_ranking_policy(&mut self, policy: &ranking::RankingPolicy) -> Result<()> {
+        self.catalog
+            .set_config("ranking_policy", &policy.to_config_value())?;
+        self.rebuild_groups()
     }
 
     /// Scan all registered sources, hash files, find duplicates, and rank them.
@@ -73,7 +207,9 @@ impl Vault {
     /// Current perceptual hash algorithm version. Bump this whenever the hash
     /// computation changes (decode strategy, resize, coefficients) to invalidate
     /// cached hashes and force recomputation on next scan.
-    const PHASH_VERSION: &str = "4";
+    /// Builds with the `heif` feature use a distinct version, so switching a
+    /// catalog to (or from) a HEIF-capable build rehashes everything once.
+    const PHASH_VERSION: &str = if cfg!(feature = "heif") { "5+heif" } else { "5" };
 
     pub fn scan(&mut self, mut progress_cb: Option<&mut dyn FnMut(ScanProgress)>) -> Result<()> {
         // Invalidate cached hashes if algorithm version changed.
@@ -87,17 +223,49 @@ impl Vault {
         }
 
         let sources = self.catalog.list_sources()?;
+        let global_ignores = self.ignore_patterns()?;
         let now = chrono::Utc::now().timestamp();
 
         for source in &sources {
-            // Discover files
-            let scanned_files = scanner::scan_directory(&source.path)?;
+            // An absent volume keeps its photos: they still take part in
+            // matching and ranking, they just can't be rescanned.
+            match volume::check(source) {
+                volume::VolumeState::Offline => {
+                    self.catalog.set_source_online(source.id, false)?;
+                    if let Some(ref mut cb) = progress_cb {
+                        cb(ScanProgress::SourceOffline {
+                            source: source.path.to_string_lossy().to_string(),
+                        });
+                    }
+                    continue;
+                }
+                volume::VolumeState::Unmarked => {
+                    if let Some(volume_id) = volume::ensure_marker(&source.path) {
+                        self.catalog.set_source_volume_id(source.id, &volume_id)?;
+                    }
+                }
+                volume::VolumeState::Online => {}
+            }
+            if !source.online {
+                self.catalog.set_source_online(source.id, true)?;
+            }
+
+            // Discover files, pruning anything matched by ignore rules
+            let rules = IgnoreRules::for_source(&source.path, &global_ignores)?;
+            let scanner::DirectoryScan {
+                files: scanned_files,
+                excluded,
+                sidecars,
+            } = scanner::scan_directory_with_rules(&source.path, &rules)?;
 
             if let Some(ref mut cb) = progress_cb {
                 cb(ScanProgress::SourceStart {
                     source: source.path.to_string_lossy().to_string(),
                     file_count: scanned_files.len(),
                 });
+                for path in excluded {
+                    cb(ScanProgress::Excluded { path });
+                }
             }
 
             // Batch mtime check: one query instead of N
@@ -119,21 +287,6 @@ impl Vault {
                 }
             }
 
-            // ── Remove stale entries (deleted from disk but still in catalog)
-            let scanned_paths: HashSet<&PathBuf> =
-                scanned_files.iter().map(|sf| &sf.path).collect();
-            let stale_paths: Vec<&Path> = known_mtimes
-                .keys()
-                .filter(|path| !scanned_paths.contains(path))
-                .map(|p| p.as_path())
-                .collect();
-            if !stale_paths.is_empty() {
-                let count = self.catalog.remove_photos_by_paths(&stale_paths)?;
-                if let Some(ref mut cb) = progress_cb {
-                    cb(ScanProgress::FilesRemoved { count });
-                }
-            }
-
             // ── Phase 1: Fast fingerprint (SHA-256 + EXIF) ──────────────
             // Uses a background thread + channel so progress streams in real-time.
             type Fingerprint = (PathBuf, PhotoFormat, u64, i64, String, Option<ExifData>);
@@ -147,7 +300,7 @@ impl Vault {
                 work.into_par_iter()
                     .for_each_with(tx, |tx, (path, format, size, mtime)| {
                         let data = hasher::compute_sha256(&path).ok().map(|sha256| {
-                            let exif_data = exif::extract_exif(&path);
+                            let exif_data = exif::extract_metadata(&path, format);
                             (path.clone(), format, size, mtime, sha256, exif_data)
                         });
                         let _ = tx.send((path, data));
@@ -164,6 +317,49 @@ impl Vault {
                 }
             }
 
+            // ── Moves: vanished paths whose file reappeared elsewhere ───
+            // Matched rows take the new path and keep their id; the rest of
+            // the vanished paths are stale (deleted from disk).
+            let scanned_paths: HashSet<&PathBuf> =
+                scanned_files.iter().map(|sf| &sf.path).collect();
+            let mut stale_paths: Vec<&Path> = known_mtimes
+                .keys()
+                .filter(|path| !scanned_paths.contains(path))
+                .map(|p| p.as_path())
+                .collect();
+            if !stale_paths.is_empty() {
+                let arrived = fingerprints
+                    .iter()
+                    .filter(|(path, ..)| !known_mtimes.contains_key(path))
+                    .map(|(path, _, size, mtime, sha256, _)| {
+                        (path.as_path(), *size, *mtime, sha256.as_str())
+                    });
+                let vanished = self.catalog.get_identities_by_paths(&stale_paths)?;
+                let moves = scanner::moves::detect_moves(vanished, arrived);
+                if !moves.is_empty() {
+                    let updates: Vec<(i64, &Path)> =
+                        moves.iter().map(|m| (m.id, m.to.as_path())).collect();
+                    self.catalog.move_photos(&updates)?;
+                    let moved_from: HashSet<&Path> =
+                        moves.iter().map(|m| m.from.as_path()).collect();
+                    stale_paths.retain(|path| !moved_from.contains(path));
+                }
+                if let Some(ref mut cb) = progress_cb {
+                    for m in moves {
+                        cb(ScanProgress::FileMoved {
+                            from: m.from,
+                            to: m.to,
+                        });
+                    }
+                }
+            }
+            if !stale_paths.is_empty() {
+                let count = self.catalog.remove_photos_by_paths(&stale_paths)?;
+                if let Some(ref mut cb) = progress_cb {
+                    cb(ScanProgress::FilesRemoved { count });
+                }
+            }
+
             // ── SHA-256 dedup: skip perceptual hashing for duplicates ───
             let mut sha_groups: HashMap<&str, Vec<usize>> = HashMap::new();
             for (i, (_, _, _, _, sha, _)) in fingerprints.iter().enumerate() {
@@ -255,6 +451,28 @@ impl Vault {
 
             // Batch insert into catalog (single transaction)
             self.catalog.upsert_photos_batch(&processed)?;
+
+            // ── Sidecars: hash new or changed ones, reuse the rest ──────
+            let known_sidecars: HashMap<PathBuf, Sidecar> = self
+                .catalog
+                .get_sidecars_for_source(source.id)?
+                .into_iter()
+                .map(|s| (s.path.clone(), s))
+                .collect();
+            let hashed_sidecars: Vec<(ScannedSidecar, String)> = sidecars
+                .into_par_iter()
+                .filter_map(|sidecar| {
+                    let sha256 = match known_sidecars.get(&sidecar.path) {
+                        Some(known) if known.mtime == sidecar.mtime && known.size == sidecar.size => {
+                            known.sha256.clone()
+                        }
+                        _ => hasher::compute_sha256(&sidecar.path).ok()?,
+                    };
+                    Some((sidecar, sha256))
+                })
+                .collect();
+            self.catalog
+                .replace_sidecars_for_source(source.id, &hashed_sidecars)?;
             self.catalog.update_source_scanned(source.id, now)?;
         }
 
@@ -265,8 +483,25 @@ impl Vault {
         }
 
         // Matching phase
+        self.rebuild_groups()?;
+
+        if let Some(ref mut cb) = progress_cb {
+            cb(ScanProgress::PhaseComplete {
+                phase: "matching".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Run the matching pipeline over the catalog and replace all groups.
+    fn rebuild_groups(&mut self) -> Result<()> {
         let all_photos = self.catalog.list_all_photos()?;
-        let match_groups = matching::find_duplicates(&all_photos);
+        let overrides = self.catalog.list_match_overrides()?;
+        let pins = self.catalog.list_pins()?;
+        let policy = self.ranking_policy()?;
+        let match_groups =
+            matching::find_duplicates(&all_photos, &self.match_config()?, &overrides);
 
         // Build a lookup map for ranking
         let photo_map: std::collections::HashMap<i64, &PhotoFile> =
@@ -285,23 +520,93 @@ impl Vault {
                 continue;
             }
 
-            let sot = ranking::elect_source_of_truth(&members);
+            let sot = ranking::elect_with_pins(&members, &pins, &policy);
             group_tuples.push((sot.id, group.clone()));
         }
+        ranking::pair_live_photos(&mut group_tuples, &photo_map, &policy);
 
-        let batch: Vec<(i64, domain::Confidence, Vec<i64>)> = group_tuples
-            .into_iter()
-            .map(|(sot_id, g)| (sot_id, g.confidence, g.member_ids))
-            .collect();
-        self.catalog.replace_groups_batch(&batch)?;
+        self.catalog.replace_groups_batch(&group_tuples)?;
+        Ok(())
+    }
 
-        if let Some(ref mut cb) = progress_cb {
-            cb(ScanProgress::PhaseComplete {
-                phase: "matching".to_string(),
+    /// Never group the files at `a` and `b` (and any copies of them) again,
+    /// then regroup. The decision is keyed by content and survives rescans.
+    pub fn split_photos(&mut self, a: &Path, b: &Path) -> Result<()> {
+        self.set_match_override(a, b, OverrideKind::NeverMatch)
+    }
+
+    /// Always group the files at `a` and `b` (and any copies of them), then regroup.
+    pub fn merge_photos(&mut self, a: &Path, b: &Path) -> Result<()> {
+        self.set_match_override(a, b, OverrideKind::AlwaysMatch)
+    }
+
+    /// Forget the split or merge decision for `a` and `b`, then regroup.
+    /// Returns `false` if there was none.
+    pub fn clear_match_override(&mut self, a: &Path, b: &Path) -> Result<bool> {
+        let (sha_a, sha_b) = self.override_pair(a, b)?;
+        let removed = self.catalog.remove_match_override(&sha_a, &sha_b)?;
+        if removed {
+            self.rebuild_groups()?;
+        }
+        Ok(removed)
+    }
+
+    /// All manual split and merge decisions.
+    pub fn match_overrides(&self) -> Result<Vec<MatchOverride>> {
+        self.catalog.list_match_overrides()
+    }
+
+    fn set_match_override(&mut self, a: &Path, b: &Path, kind: OverrideKind) -> Result<()> {
+        let (sha_a, sha_b) = self.override_pair(a, b)?;
+        self.catalog
+            .set_match_override(&MatchOverride::new(&sha_a, &sha_b, kind))?;
+        self.rebuild_groups()
+    }
+
+    /// Content hashes of two cataloged files, which must differ.
+    fn override_pair(&self, a: &Path, b: &Path) -> Result<(String, String)> {
+        let (sha_a, sha_b) = (self.photo_sha(a)?, self.photo_sha(b)?);
+        if sha_a == sha_b {
+            return Err(Error::IdenticalContent {
+                a: a.to_path_buf(),
+                b: b.to_path_buf(),
             });
         }
+        Ok((sha_a, sha_b))
+    }
 
-        Ok(())
+    /// Content hash of a cataloged file.
+    fn photo_sha(&self, path: &Path) -> Result<String> {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.catalog
+            .get_identities_by_paths(&[&path])?
+            .pop()
+            .map(|(_, _, _, _, sha256)| sha256)
+            .ok_or(Error::PhotoNotInCatalog(path))
+    }
+
+    /// Pin the file at `path` (and any copy of it) as the source of truth of
+    /// its group, then regroup. Pins are keyed by content and survive rescans.
+    pub fn pin_photo(&mut self, path: &Path) -> Result<()> {
+        let sha256 = self.photo_sha(path)?;
+        self.catalog.add_pin(&sha256)?;
+        self.rebuild_groups()
+    }
+
+    /// Remove the pin on the file at `path`, then regroup.
+    /// Returns `false` if it was not pinned.
+    pub fn unpin_photo(&mut self, path: &Path) -> Result<bool> {
+        let sha256 = self.photo_sha(path)?;
+        let removed = self.catalog.remove_pin(&sha256)?;
+        if removed {
+            self.rebuild_groups()?;
+        }
+        Ok(removed)
+    }
+
+    /// Hashes pinned as source of truth.
+    pub fn pinned_hashes(&self) -> Result<HashSet<String>> {
+        self.catalog.list_pins()
     }
 
     /// List all registered sources.
@@ -314,6 +619,11 @@ impl Vault {
         self.catalog.list_all_photos()
     }
 
+    /// Photos matching a query's filter, sorted and limited as it asks.
+    pub fn query_photos(&self, query: &query::Query) -> Result<Vec<PhotoFile>> {
+        self.catalog.query_photos(query)
+    }
+
     /// Get catalog summary statistics (single query for photos/groups/duplicates).
     pub fn status(&self) -> Result<CatalogStats> {
         let (total_photos, total_groups, total_duplicates) = self.catalog.stats_summary()?;
@@ -335,6 +645,12 @@ impl Vault {
         self.catalog.get_group(id)
     }
 
+    /// Evidence edges explaining why each member joined a group, recorded at the last scan.
+    pub fn group_evidence(&self, id: i64) -> Result<Vec<MatchEvidence>> {
+        self.catalog.get_group(id)?;
+        self.catalog.get_group_evidence(id)
+    }
+
     /// Set the vault export destination path.
     pub fn set_vault_path(&self, path: &Path) -> Result<()> {
         let canonical = path
@@ -357,15 +673,104 @@ impl Vault {
         Ok(self.catalog.get_config("vault_path")?.map(PathBuf::from))
     }
 
+    /// Days a stale pack file stays in `.photopack/trash/` before `vault_save` deletes it.
+    pub fn trash_retention_days(&self) -> Result<u32> {
+        Ok(self
+            .catalog
+            .get_config("trash_retention_days")?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(trash::DEFAULT_RETENTION_DAYS))
+    }
+
+    /// Set the trash retention window. `0` purges trashed files on the next `vault_save`.
+    pub fn set_trash_retention_days(&self, days: u32) -> Result<()> {
+        self.catalog
+            .set_config("trash_retention_days", &days.to_string())
+    }
+
+    /// List pack files waiting in the trash, oldest first.
+    pub fn trash_entries(&self) -> Result<Vec<manifest::TrashEntry>> {
+        let pack_path = self.existing_pack_path()?;
+        manifest::Manifest::open(&pack_path)?.list_trash()
+    }
+
+    /// Move the trashed file whose hash starts with `hash_prefix` out to
+    /// `dest/original_filename`. Returns the restored path.
+    pub fn restore_from_trash(&self, hash_prefix: &str, dest: &Path) -> Result<PathBuf> {
+        let pack_path = self.existing_pack_path()?;
+        let pack_manifest = manifest::Manifest::open(&pack_path)?;
+        let entry = trash::find_entry(&pack_manifest, hash_prefix)?;
+        trash::restore_to(&pack_path, &pack_manifest, &entry, dest)
+    }
+
+    /// Delete trashed files past the retention window, or all of them with `all`.
+    /// Returns the purged entries.
+    pub fn purge_trash(&self, all: bool) -> Result<Vec<manifest::TrashEntry>> {
+        let pack_path = self.existing_pack_path()?;
+        let pack_manifest = manifest::Manifest::open(&pack_path)?;
+        let older_than = if all {
+            None
+        } else {
+            Some(self.trash_retention_days()?)
+        };
+        trash::purge(&pack_path, &pack_manifest, older_than)
+    }
+
+    /// Enable or disable the `by-date/` and `by-camera/` link views inside the pack.
+    /// Takes effect on the next [`Vault::vault_save`].
+    pub fn set_pack_views(&self, enabled: bool) -> Result<()> {
+        self.catalog
+            .set_config("pack_views", if enabled { "on" } else { "off" })
+    }
+
+    /// Which group members the pack keeps, stored in the pack's own manifest
+    /// so each pack can have its own. Takes effect on the next [`Vault::vault_save`].
+    pub fn set_pack_policy(&self, policy: PackPolicy) -> Result<()> {
+        let pack_path = self.existing_pack_path()?;
+        manifest::Manifest::open(&pack_path)?.set_pack_policy(policy)
+    }
+
+    pub fn pack_policy(&self) -> Result<PackPolicy> {
+        let pack_path = self.existing_pack_path()?;
+        manifest::Manifest::open(&pack_path)?.pack_policy()
+    }
+
+    /// Whether `vault_save` maintains the pack's link views (off by default).
+    pub fn pack_views_enabled(&self) -> Result<bool> {
+        Ok(self.catalog.get_config("pack_views")?.as_deref() == Some("on"))
+    }
+
     /// Copy deduplicated photos to the pack directory using content-addressable storage.
     /// For each duplicate group, only the source-of-truth is copied.
     /// Ungrouped photos are copied as-is.
     /// Files are named by their SHA-256 hash with 2-char prefix sharding.
     /// An embedded manifest tracks all pack entries for cleanup and integrity verification.
+    /// With [`Vault::set_pack_views`] enabled, `by-date/` and `by-camera/` link views are kept in sync.
+    ///
+    /// A photo that cannot be copied does not stop the save: it is reported as
+    /// [`vault_save::VaultSaveProgress::Failed`] and returned.
     pub fn vault_save(
         &mut self,
+        progress_cb: Option<&mut dyn FnMut(vault_save::VaultSaveProgress)>,
+    ) -> Result<Vec<FailedFile>> {
+        self.save_pack(None, progress_cb)
+    }
+
+    /// [`Vault::vault_save`] restricted to the photos matching `filter`.
+    /// Pack files outside the filter are left alone rather than trashed.
+    pub fn vault_save_matching(
+        &mut self,
+        filter: &query::Filter,
+        progress_cb: Option<&mut dyn FnMut(vault_save::VaultSaveProgress)>,
+    ) -> Result<Vec<FailedFile>> {
+        self.save_pack(Some(filter), progress_cb)
+    }
+
+    fn save_pack(
+        &mut self,
+        filter: Option<&query::Filter>,
         mut progress_cb: Option<&mut dyn FnMut(vault_save::VaultSaveProgress)>,
-    ) -> Result<()> {
+    ) -> Result<Vec<FailedFile>> {
         let pack_path = self
             .catalog
             .get_config("vault_path")?
@@ -380,7 +785,24 @@ impl Vault {
 
         let all_photos = self.catalog.list_all_photos()?;
         let groups = self.catalog.list_groups()?;
-        let to_save = vault_save::select_photos_to_export(&all_photos, &groups);
+        let pins = self.catalog.list_pins()?;
+        let policy = self.ranking_policy()?;
+        let mut selection = vault_save::select_pack_files(
+            &all_photos,
+            &groups,
+            &pins,
+            &policy,
+            pack_manifest.pack_policy()?,
+        );
+        if let Some(filter) = filter {
+            let matching = self.catalog.matching_photo_ids(filter)?;
+            selection.retain(|s| matching.contains(&s.photo.id));
+        }
+        let to_save: Vec<&PhotoFile> = selection.iter().map(|s| s.photo).collect();
+        let roles: HashMap<&str, (Option<&str>, PackRole)> = selection
+            .iter()
+            .map(|s| (s.photo.sha256.as_str(), (s.group_sha256, s.role)))
+            .collect();
 
         if let Some(ref mut cb) = progress_cb {
             cb(vault_save::VaultSaveProgress::Start {
@@ -392,6 +814,13 @@ impl Vault {
         let desired_hashes: HashSet<String> =
             to_save.iter().map(|p| p.sha256.clone()).collect();
 
+        // Files wanted again come back from the trash instead of being re-copied
+        for entry in pack_manifest.list_trash()? {
+            if desired_hashes.contains(&entry.sha256) {
+                trash::untrash(&pack_path, &pack_manifest, &entry.sha256)?;
+            }
+        }
+
         // Build targets — pure function, no I/O needed
         let targets: Vec<(&PhotoFile, PathBuf)> = to_save
             .iter()
@@ -403,20 +832,41 @@ impl Vault {
             .collect();
 
         // Parallel file copy, collect results
-        let results: Vec<(bool, &PhotoFile, PathBuf)> = targets
+        let results: Vec<(&PhotoFile, PathBuf, Result<bool>)> = targets
             .par_iter()
-            .filter_map(|(photo, target)| {
-                match vault_save::copy_photo_to_pack(&photo.path, target) {
-                    Ok(did_copy) => Some((did_copy, *photo, target.clone())),
-                    Err(_) => None,
-                }
+            .map(|(photo, target)| {
+                let result = vault_save::copy_photo_to_pack(
+                    &photo.path,
+                    target,
+                    &photo.sha256,
+                    photo.size,
+                );
+                (*photo, target.clone(), result)
             })
             .collect();
 
         // Report progress + insert into manifest sequentially (callback is not Send, Connection is not Sync)
         let mut copied = 0usize;
         let mut skipped = 0usize;
-        for (did_copy, photo, target) in &results {
+        let mut failed = Vec::new();
+        for (photo, target, result) in &results {
+            let did_copy = match result {
+                Ok(did_copy) => did_copy,
+                Err(e) => {
+                    let failure = FailedFile {
+                        path: photo.path.clone(),
+                        error: e.to_string(),
+                    };
+                    if let Some(ref mut cb) = progress_cb {
+                        cb(vault_save::VaultSaveProgress::Failed {
+                            path: failure.path.clone(),
+                            error: failure.error.clone(),
+                        });
+                    }
+                    failed.push(failure);
+                    continue;
+                }
+            };
             if *did_copy {
                 copied += 1;
                 // Insert into manifest
@@ -436,6 +886,7 @@ impl Vault {
                     exif_date,
                     camera_make,
                     camera_model,
+                    photo.mtime,
                 );
                 if let Some(ref mut cb) = progress_cb {
                     cb(vault_save::VaultSaveProgress::Copied {
@@ -464,6 +915,7 @@ impl Vault {
                         exif_date,
                         camera_make,
                         camera_model,
+                        photo.mtime,
                     );
                 }
                 if let Some(ref mut cb) = progress_cb {
@@ -472,11 +924,38 @@ impl Vault {
                     });
                 }
             }
+            // Live Photo pairing (re-applied to entries packed before manifest v7)
+            if let Some(content_id) = photo.exif.as_ref().and_then(|e| e.content_id.as_deref()) {
+                let _ = pack_manifest.set_content_id(&photo.sha256, Some(content_id));
+            }
+            if let Some(&(group_sha256, role)) = roles.get(photo.sha256.as_str()) {
+                let _ = pack_manifest.set_group_role(&photo.sha256, group_sha256, role);
+            }
         }
 
-        // Clean up stale pack files (entries in manifest not in desired set)
-        let removed_files =
-            vault_save::cleanup_pack_files(&pack_path, &desired_hashes, &pack_manifest);
+        // Sidecars follow their pack file; edited sidecars are re-packed
+        let catalog_sidecars = self.catalog.list_sidecars()?;
+        let wanted_sidecars =
+            vault_save::select_sidecars(&to_save, &all_photos, &catalog_sidecars);
+        let sidecars_copied = vault_save::sync_sidecars(
+            &pack_path,
+            &pack_manifest,
+            &wanted_sidecars,
+            &desired_hashes,
+        )?;
+        for (source, target) in sidecars_copied {
+            if let Some(ref mut cb) = progress_cb {
+                cb(vault_save::VaultSaveProgress::SidecarCopied { source, target });
+            }
+        }
+
+        // Move stale pack files (entries in manifest not in desired set) to the trash.
+        // A filtered save only sees part of the selection, so it trashes nothing.
+        let removed_files = if filter.is_none() {
+            vault_save::cleanup_pack_files(&pack_path, &desired_hashes, &pack_manifest)
+        } else {
+            Vec::new()
+        };
         let removed = removed_files.len();
         for removed_path in &removed_files {
             if let Some(ref mut cb) = progress_cb {
@@ -486,82 +965,400 @@ impl Vault {
             }
         }
 
+        let purged = trash::purge(
+            &pack_path,
+            &pack_manifest,
+            Some(self.trash_retention_days()?),
+        )?;
+        if !purged.is_empty() {
+            if let Some(ref mut cb) = progress_cb {
+                cb(vault_save::VaultSaveProgress::TrashPurged {
+                    count: purged.len(),
+                });
+            }
+        }
+
+        // Disabled views still run once, to tear down links from an earlier run
+        let views_enabled = self.pack_views_enabled()?;
+        let view_sync = views::sync_views(&pack_path, &pack_manifest, views_enabled)?;
+        if views_enabled || view_sync.removed > 0 {
+            if let Some(ref mut cb) = progress_cb {
+                cb(vault_save::VaultSaveProgress::ViewsSynced {
+                    linked: view_sync.linked,
+                    removed: view_sync.removed,
+                });
+            }
+        }
+
         if let Some(ref mut cb) = progress_cb {
             cb(vault_save::VaultSaveProgress::Complete {
                 copied,
                 skipped,
                 removed,
+                failed: failed.len(),
             });
         }
 
-        Ok(())
+        Ok(failed)
+    }
+
+    /// What [`Vault::vault_save`] would do to the pack: files copied or skipped,
+    /// trashed files put back, superseded files moved to the trash, and trashed
+    /// files past the retention window purged.
+    /// Sidecars and link views are not planned.
+    pub fn plan_vault_save(&self) -> Result<plan::Plan> {
+        self.plan_pack(None)
+    }
+
+    /// What [`Vault::vault_save_matching`] would do to the pack.
+    pub fn plan_vault_save_matching(&self, filter: &query::Filter) -> Result<plan::Plan> {
+        self.plan_pack(Some(filter))
+    }
+
+    fn plan_pack(&self, filter: Option<&query::Filter>) -> Result<plan::Plan> {
+        let pack_path = self.existing_pack_path()?;
+        let pack_manifest = manifest::Manifest::open(&pack_path)?;
+
+        let all_photos = self.catalog.list_all_photos()?;
+        let groups = self.catalog.list_groups()?;
+        let pins = self.catalog.list_pins()?;
+        let policy = self.ranking_policy()?;
+        let mut selection = vault_save::select_pack_files(
+            &all_photos,
+            &groups,
+            &pins,
+            &policy,
+            pack_manifest.pack_policy()?,
+        );
+        if let Some(filter) = filter {
+            let matching = self.catalog.matching_photo_ids(filter)?;
+            selection.retain(|s| matching.contains(&s.photo.id));
+        }
+        let desired_hashes: HashSet<&str> =
+            selection.iter().map(|s| s.photo.sha256.as_str()).collect();
+
+        let mut plan = plan::Plan::default();
+        let mut untrashed: HashSet<String> = HashSet::new();
+        for entry in pack_manifest.list_trash()? {
+            if desired_hashes.contains(entry.sha256.as_str()) {
+                plan.actions.push(plan::PlannedAction::Untrash {
+                    path: trash::content_path(&pack_path, &entry.sha256, &entry.format),
+                });
+                untrashed.insert(entry.sha256);
+            }
+        }
+        for photo in selection.iter().map(|s| s.photo) {
+            let target = vault_save::build_content_path(&pack_path, &photo.sha256, photo.format);
+            if untrashed.contains(&photo.sha256) || !vault_save::needs_copy(&target, photo.size) {
+                plan.actions.push(plan::PlannedAction::Skip {
+                    path: photo.path.clone(),
+                });
+            } else {
+                plan.actions.push(plan::PlannedAction::Copy {
+                    source: photo.path.clone(),
+                    target,
+                    bytes: photo.size,
+                });
+            }
+        }
+        let mut removed = Vec::new();
+        for entry in pack_manifest.list_files()? {
+            if filter.is_none() && !desired_hashes.contains(entry.sha256.as_str()) {
+                plan.actions.push(plan::PlannedAction::Remove {
+                    path: trash::content_path(&pack_path, &entry.sha256, &entry.format),
+                    bytes: entry.size,
+                });
+                removed.push(entry);
+            }
+        }
+
+        // The save ends by purging the trash past the retention window
+        let retention = self.trash_retention_days()?;
+        for entry in pack_manifest.list_trash_older_than(retention)? {
+            if !untrashed.contains(&entry.sha256) {
+                plan.actions.push(plan::PlannedAction::Purge {
+                    path: trash::trash_path(&pack_path, &entry.sha256, &entry.format),
+                    bytes: entry.size,
+                });
+            }
+        }
+        // With no retention, files trashed by this save are purged straight
+        // away; their bytes are already counted by the `Remove`
+        if retention == 0 {
+            for entry in removed {
+                plan.actions.push(plan::PlannedAction::Purge {
+                    path: trash::trash_path(&pack_path, &entry.sha256, &entry.format),
+                    bytes: 0,
+                });
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Verify the pack's integrity: re-hash content-addressed files and report
+    /// corrupted, missing, orphaned and misnamed files.
+    /// `sample_percent` limits re-hashing to that share of entries, least recently
+    /// verified first, so repeated sampled runs eventually cover the whole pack.
+    pub fn verify_pack(
+        &self,
+        sample_percent: Option<f64>,
+        progress_cb: Option<&mut dyn FnMut(verify::VerifyProgress)>,
+    ) -> Result<verify::VerifyReport> {
+        let pack_path = self.existing_pack_path()?;
+        verify::verify_pack(&pack_path, sample_percent, progress_cb)
+    }
+
+    /// Verify the whole pack, then restore corrupted or missing pack files from
+    /// any source photo the catalog knows with the same SHA-256.
+    /// Orphaned and misnamed files are reported but left alone.
+    pub fn repair_pack(
+        &self,
+        progress_cb: Option<&mut dyn FnMut(repair::RepairProgress)>,
+    ) -> Result<repair::RepairReport> {
+        let pack_path = self.existing_pack_path()?;
+        let verification = verify::verify_pack(&pack_path, None, None)?;
+        let damaged = verification.damaged_entries();
+
+        let shas: Vec<&str> = damaged.iter().map(|(sha, _)| sha.as_str()).collect();
+        let mut candidates = self.catalog.get_paths_by_sha256s(&shas)?;
+        // The pack is itself a registered source — never repair it from itself
+        for paths in candidates.values_mut() {
+            paths.retain(|path| !path.starts_with(&pack_path));
+        }
+
+        let pack_manifest = manifest::Manifest::open(&pack_path)?;
+        let mut report = repair::repair_pack(&pack_manifest, &damaged, &candidates, progress_cb);
+        // Replacing a pack file breaks its hard-linked view entries
+        if !report.repaired.is_empty() && self.pack_views_enabled()? {
+            views::sync_views(&pack_path, &pack_manifest, true)?;
+        }
+        report.orphaned = verification.orphaned;
+        report.misnamed = verification.misnamed;
+        Ok(report)
+    }
+
+    /// Rebuild a `YYYY/MM/DD/original_filename` tree from the pack into `dest`.
+    /// Only the pack's manifest is read; see [`restore::restore_pack`] to unpack
+    /// a pack without a catalog.
+    pub fn restore_pack(
+        &self,
+        dest: &Path,
+        mode: restore::RestoreMode,
+        progress_cb: Option<&mut dyn FnMut(restore::RestoreProgress)>,
+    ) -> Result<restore::RestoreReport> {
+        let pack_path = self.existing_pack_path()?;
+        restore::restore_pack(&pack_path, dest, mode, progress_cb)
+    }
+
+    /// Move redundant copies out of the sources into `dest`: every member of a
+    /// group at or above `min_confidence` except its source of truth, with its
+    /// sidecars. Original paths are mirrored under `dest` and recorded in a
+    /// journal for [`Vault::undo_quarantine`]. Moved photos leave the catalog.
+    /// Pack files and photos of offline sources are never moved.
+    pub fn quarantine_duplicates(
+        &mut self,
+        dest: &Path,
+        min_confidence: Confidence,
+        progress_cb: Option<&mut dyn FnMut(dedupe::DedupeProgress)>,
+    ) -> Result<dedupe::QuarantineReport> {
+        let dest = self.quarantine_dest(dest)?;
+        std::fs::create_dir_all(&dest)?;
+        let groups = self.catalog.list_groups()?;
+        let sidecars = self.catalog.list_sidecars()?;
+        let files =
+            dedupe::select_redundant(&groups, &sidecars, min_confidence, &self.dedupe_exclusions()?);
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
+        let journal = dedupe::journal_path(&dest, &timestamp);
+        let report = dedupe::quarantine_files(&dest, &journal, &files, progress_cb)?;
+
+        let moved: Vec<&Path> = report.moved.iter().map(|e| e.original.as_path()).collect();
+        if !moved.is_empty() {
+            self.catalog.remove_photos_by_paths(&moved)?;
+            self.rebuild_groups()?;
+        }
+        Ok(report)
+    }
+
+    /// What [`Vault::quarantine_duplicates`] would move, without moving anything
+    /// or creating `dest`.
+    pub fn plan_quarantine(&self, dest: &Path, min_confidence: Confidence) -> Result<plan::Plan> {
+        let dest = self.quarantine_dest(dest)?;
+        let groups = self.catalog.list_groups()?;
+        let sidecars = self.catalog.list_sidecars()?;
+        let files =
+            dedupe::select_redundant(&groups, &sidecars, min_confidence, &self.dedupe_exclusions()?);
+        Ok(dedupe::plan_quarantine(&dest, &files))
+    }
+
+    /// Absolute quarantine folder, which must not be inside a source: it would be
+    /// cataloged again on the next scan.
+    fn quarantine_dest(&self, dest: &Path) -> Result<PathBuf> {
+        let dest = std::path::absolute(dest)?;
+        let dest = dest.canonicalize().unwrap_or(dest);
+        if let Some(source) = self
+            .catalog
+            .list_sources()?
+            .into_iter()
+            .find(|s| dest.starts_with(&s.path))
+        {
+            return Err(Error::QuarantineInsideSource(source.path));
+        }
+        Ok(dest)
+    }
+
+    /// Replace byte-identical copies (groups of [`Confidence::Certain`]) with
+    /// reflinks or hard links to their source of truth, keeping every path.
+    /// Both files are re-hashed first; copies on another filesystem are skipped.
+    /// Pack files and photos of offline sources are never replaced.
+    pub fn link_duplicates(
+        &self,
+        progress_cb: Option<&mut dyn FnMut(dedupe::DedupeProgress)>,
+    ) -> Result<dedupe::LinkReport> {
+        let excluded = self.dedupe_exclusions()?;
+        let groups = self.catalog.list_groups()?;
+        let pairs = dedupe::select_linkable(&groups, &excluded);
+        Ok(dedupe::link_copies(&pairs, progress_cb))
+    }
+
+    /// What [`Vault::link_duplicates`] would link, without touching any file.
+    pub fn plan_link(&self) -> Result<plan::Plan> {
+        let groups = self.catalog.list_groups()?;
+        let pairs = dedupe::select_linkable(&groups, &self.dedupe_exclusions()?);
+        Ok(dedupe::plan_links(&pairs))
+    }
+
+    /// Roots whose files dedupe never touches: the pack and offline sources.
+    fn dedupe_exclusions(&self) -> Result<Vec<PathBuf>> {
+        let mut excluded: Vec<PathBuf> = self
+            .catalog
+            .list_sources()?
+            .into_iter()
+            .filter(|s| !s.online)
+            .map(|s| s.path)
+            .collect();
+        excluded.extend(self.get_vault_path()?);
+        Ok(excluded)
+    }
+
+    /// Move everything recorded in a quarantine journal back to its original path.
+    /// Restored files are cataloged again by the next [`Vault::scan`].
+    pub fn undo_quarantine(
+        &self,
+        journal: &Path,
+        progress_cb: Option<&mut dyn FnMut(dedupe::DedupeProgress)>,
+    ) -> Result<dedupe::UndoReport> {
+        dedupe::undo_quarantine(journal, progress_cb)
+    }
+
+    /// The configured pack path, which must exist on disk.
+    fn existing_pack_path(&self) -> Result<PathBuf> {
+        let pack_path = self
+            .catalog
+            .get_config("vault_path")?
+            .map(PathBuf::from)
+            .ok_or(Error::VaultPathNotSet)?;
+
+        if !pack_path.is_dir() {
+            return Err(Error::VaultPathNotFound(pack_path));
+        }
+        Ok(pack_path)
     }
 
-    /// Export deduplicated photos as HEIC files.
+    /// Export deduplicated photos as compressed files using `encoder`.
     /// For each duplicate group, only the source-of-truth is exported.
-    /// Ungrouped photos are exported as-is.
-    /// Photos are organized into YYYY/MM/DD folders and converted to HEIC.
+    /// Ungrouped photos are exported as-is. Videos are not exported.
+    /// Photos are organized into YYYY/MM/DD folders with the encoder's extension.
+    ///
+    /// A photo that cannot be converted does not stop the export: it is reported as
+    /// [`export::ExportProgress::Failed`] and returned.
     pub fn export(
         &self,
         export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
+        quality: u8,
+        progress_cb: Option<&mut dyn FnMut(export::ExportProgress)>,
+    ) -> Result<Vec<FailedFile>> {
+        self.export_photos(export_path, encoder, quality, None, progress_cb)
+    }
+
+    /// [`Vault::export`] restricted to the photos matching `filter`.
+    pub fn export_matching(
+        &self,
+        export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
         quality: u8,
+        filter: &query::Filter,
+        progress_cb: Option<&mut dyn FnMut(export::ExportProgress)>,
+    ) -> Result<Vec<FailedFile>> {
+        self.export_photos(export_path, encoder, quality, Some(filter), progress_cb)
+    }
+
+    fn export_photos(
+        &self,
+        export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
+        quality: u8,
+        filter: Option<&query::Filter>,
         mut progress_cb: Option<&mut dyn FnMut(export::ExportProgress)>,
-    ) -> Result<()> {
-        export::check_sips_available()?;
+    ) -> Result<Vec<FailedFile>> {
+        if !encoder.is_available() {
+            return Err(Error::EncoderNotAvailable(encoder.name().to_string()));
+        }
 
         if !export_path.is_dir() {
             return Err(Error::ExportPathNotFound(export_path.to_path_buf()));
         }
 
         let all_photos = self.catalog.list_all_photos()?;
-        let groups = self.catalog.list_groups()?;
-        let to_export = vault_save::select_photos_to_export(&all_photos, &groups);
+        let targets = self.export_targets(&all_photos, export_path, encoder, filter)?;
 
         if let Some(ref mut cb) = progress_cb {
             cb(export::ExportProgress::Start {
-                total: to_export.len(),
+                total: targets.len(),
             });
         }
 
-        // Pre-compute targets sequentially (needs filesystem checks)
-        let targets: Vec<(&PhotoFile, PathBuf)> = to_export
-            .iter()
-            .map(|photo| {
-                let date = vault_save::date_for_photo(photo);
-                let target = export::build_export_path(export_path, date, &photo.path);
-                (*photo, target)
-            })
-            .collect();
-
-        // Parallel HEIC conversion, collect results
-        let results: Vec<(bool, PathBuf, PathBuf)> = targets
+        // Parallel conversion, collect results
+        let results: Vec<(PathBuf, PathBuf, Result<bool>)> = targets
             .par_iter()
-            .filter_map(|(photo, target)| {
-                match export::export_photo_to_heic(&photo.path, target, quality) {
-                    Ok(did_convert) => Some((did_convert, photo.path.clone(), target.clone())),
-                    Err(_) => None,
-                }
+            .map(|(photo, target)| {
+                let result = export::export_photo(encoder, &photo.path, target, quality);
+                (photo.path.clone(), target.clone(), result)
             })
             .collect();
 
         // Report progress sequentially (callback is not Send)
         let mut converted = 0usize;
         let mut skipped = 0usize;
-        for (did_convert, source, target) in &results {
-            if *did_convert {
+        let mut failed = Vec::new();
+        for (source, target, result) in results {
+            let did_convert = match result {
+                Ok(did_convert) => did_convert,
+                Err(e) => {
+                    let failure = FailedFile {
+                        path: source,
+                        error: e.to_string(),
+                    };
+                    if let Some(ref mut cb) = progress_cb {
+                        cb(export::ExportProgress::Failed {
+                            path: failure.path.clone(),
+                            error: failure.error.clone(),
+                        });
+                    }
+                    failed.push(failure);
+                    continue;
+                }
+            };
+            if did_convert {
                 converted += 1;
                 if let Some(ref mut cb) = progress_cb {
-                    cb(export::ExportProgress::Converted {
-                        source: source.clone(),
-                        target: target.clone(),
-                    });
+                    cb(export::ExportProgress::Converted { source, target });
                 }
             } else {
                 skipped += 1;
                 if let Some(ref mut cb) = progress_cb {
-                    cb(export::ExportProgress::Skipped {
-                        path: source.clone(),
-                    });
+                    cb(export::ExportProgress::Skipped { path: source });
                 }
             }
         }
@@ -570,9 +1367,99 @@ impl Vault {
             cb(export::ExportProgress::Complete {
                 converted,
                 skipped,
+                failed: failed.len(),
             });
         }
 
-        Ok(())
+        Ok(failed)
+    }
+
+    /// What [`Vault::export`] would convert or skip, without encoding anything.
+    pub fn plan_export(
+        &self,
+        export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
+    ) -> Result<plan::Plan> {
+        self.plan_export_photos(export_path, encoder, None)
+    }
+
+    /// What [`Vault::export_matching`] would convert or skip.
+    pub fn plan_export_matching(
+        &self,
+        export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
+        filter: &query::Filter,
+    ) -> Result<plan::Plan> {
+        self.plan_export_photos(export_path, encoder, Some(filter))
+    }
+
+    fn plan_export_photos(
+        &self,
+        export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
+        filter: Option<&query::Filter>,
+    ) -> Result<plan::Plan> {
+        if !encoder.is_available() {
+            return Err(Error::EncoderNotAvailable(encoder.name().to_string()));
+        }
+
+        if !export_path.is_dir() {
+            return Err(Error::ExportPathNotFound(export_path.to_path_buf()));
+        }
+
+        let all_photos = self.catalog.list_all_photos()?;
+        let actions = self
+            .export_targets(&all_photos, export_path, encoder, filter)?
+            .into_iter()
+            .map(|(photo, target)| {
+                if target.exists() {
+                    plan::PlannedAction::Skip {
+                        path: photo.path.clone(),
+                    }
+                } else {
+                    plan::PlannedAction::Convert {
+                        source: photo.path.clone(),
+                        target,
+                        bytes: photo.size,
+                    }
+                }
+            })
+            .collect();
+        Ok(plan::Plan { actions })
+    }
+
+    /// Photos to export (source of truth or ungrouped, no videos, matching
+    /// `filter` if any) with their `YYYY/MM/DD` targets under `export_path`.
+    fn export_targets<'a>(
+        &self,
+        all_photos: &'a [PhotoFile],
+        export_path: &Path,
+        encoder: &dyn export::ExportEncoder,
+        filter: Option<&query::Filter>,
+    ) -> Result<Vec<(&'a PhotoFile, PathBuf)>> {
+        let groups = self.catalog.list_groups()?;
+        let pins = self.catalog.list_pins()?;
+        let policy = self.ranking_policy()?;
+        let mut to_export =
+            vault_save::select_photos_to_export(all_photos, &groups, &pins, &policy);
+        to_export.retain(|photo| !photo.format.is_video());
+        if let Some(filter) = filter {
+            let matching = self.catalog.matching_photo_ids(filter)?;
+            to_export.retain(|photo| matching.contains(&photo.id));
+        }
+
+        Ok(to_export
+            .into_iter()
+            .map(|photo| {
+                let date = vault_save::date_for_photo(photo);
+                let target = export::build_export_path(
+                    export_path,
+                    date,
+                    &photo.path,
+                    encoder.extension(),
+                );
+                (photo, target)
+            })
+            .collect())
     }
 }
@@ -0,0 +1,831 @@
+pub mod schema;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::domain::{PackPolicy, PackRole};
+use crate::error::{Error, Result};
+
+/// A pack file entry as stored in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub sha256: String,
+    pub original_filename: String,
+    pub format: String,
+    pub size: u64,
+    pub exif_date: Option<String>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// Source file mtime (Unix seconds); `None` for entries packed before manifest v3.
+    pub mtime: Option<i64>,
+    /// Last successful verification (`YYYY-MM-DD HH:MM:SS`, UTC), if any.
+    pub verified_at: Option<String>,
+    /// Apple content identifier pairing a Live Photo still with its motion video.
+    pub content_id: Option<String>,
+    /// Hash of the source of truth of the duplicate group the file was packed
+    /// from, if any. Unlike catalog group ids, it survives rescans.
+    pub group_sha256: Option<String>,
+    /// Why the file was packed; `None` for entries packed before manifest v8.
+    pub role: Option<PackRole>,
+}
+
+const ENTRY_COLUMNS: &str = "sha256, original_filename, format, size, exif_date, \
+     camera_make, camera_model, mtime, verified_at, content_id, group_sha256, role";
+
+/// A pack file moved to the trash, as stored in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrashEntry {
+    pub sha256: String,
+    pub original_filename: String,
+    pub format: String,
+    pub size: u64,
+    /// When the file was trashed (`YYYY-MM-DD HH:MM:SS`, UTC).
+    pub trashed_at: String,
+}
+
+/// Columns shared by `pack_files` and `trash`, for moving rows between them.
+const MOVABLE_COLUMNS: &str = "sha256, original_filename, format, size, exif_date, \
+     camera_make, camera_model, added_at, verified_at, mtime, content_id, group_sha256, role";
+
+/// A sidecar file packed next to a pack file, as stored in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSidecar {
+    /// Hash of the pack file the sidecar belongs to.
+    pub sha256: String,
+    /// Sidecar kind (`XMP`, `AAE`, `THM`).
+    pub kind: String,
+    pub sidecar_sha256: String,
+    pub size: u64,
+    pub original_filename: String,
+}
+
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<ManifestEntry> {
+    Ok(ManifestEntry {
+        sha256: row.get(0)?,
+        original_filename: row.get(1)?,
+        format: row.get(2)?,
+        size: row.get::<_, i64>(3)? as u64,
+        exif_date: row.get(4)?,
+        camera_make: row.get(5)?,
+        camera_model: row.get(6)?,
+        mtime: row.get(7)?,
+        verified_at: row.get(8)?,
+        content_id: row.get(9)?,
+        group_sha256: row.get(10)?,
+        role: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|r| PackRole::parse(&r)),
+    })
+}
+
+/// Embedded manifest stored inside the pack directory at `.photopack/manifest.sqlite`.
+/// Maps SHA-256 hashes to file metadata, enabling integrity verification and cleanup.
+pub struct Manifest {
+    conn: Connection,
+}
+
+impl Manifest {
+    /// Open (or create) the manifest database inside `pack_path/.photopack/`.
+    /// Creates the `.photopack/` directory, `manifest.sqlite`, and `version` file.
+    pub fn open(pack_path: &Path) -> Result<Self> {
+        let meta_dir = pack_path.join(".photopack");
+        fs::create_dir_all(&meta_dir)?;
+
+        let db_path = meta_dir.join("manifest.sqlite");
+        let conn = Connection::open(&db_path)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+
+        schema::initialize(&conn)?;
+        schema::migrate(&conn)?;
+
+        // Write version text file
+        fs::write(meta_dir.join("version"), schema::MANIFEST_VERSION.to_string())?;
+
+        Ok(Self { conn })
+    }
+
+    /// Insert or replace a pack file entry.
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_file(
+        &self,
+        sha256: &str,
+        original_filename: &str,
+        format: &str,
+        size: u64,
+        exif_date: Option<&str>,
+        camera_make: Option<&str>,
+        camera_model: Option<&str>,
+        mtime: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO pack_files
+                (sha256, original_filename, format, size, exif_date, camera_make, camera_model, added_at, mtime)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'), ?8)",
+            rusqlite::params![sha256, original_filename, format, size as i64, exif_date, camera_make, camera_model, mtime],
+        )?;
+        Ok(())
+    }
+
+    /// Record the Live Photo content identifier of a pack file entry.
+    pub fn set_content_id(&self, sha256: &str, content_id: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE pack_files SET content_id = ?2 WHERE sha256 = ?1",
+            rusqlite::params![sha256, content_id],
+        )?;
+        Ok(())
+    }
+
+    /// Record the duplicate group and role a pack file entry was packed with.
+    pub fn set_group_role(
+        &self,
+        sha256: &str,
+        group_sha256: Option<&str>,
+        role: PackRole,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE pack_files SET group_sha256 = ?2, role = ?3 WHERE sha256 = ?1",
+            rusqlite::params![sha256, group_sha256, role.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Which group members this pack keeps ([`PackPolicy::Source`] unless set).
+    pub fn pack_policy(&self) -> Result<PackPolicy> {
+        let value: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'pack_policy'",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        match value {
+            Some(v) => PackPolicy::parse(&v).ok_or(Error::UnknownPackPolicy(v)),
+            None => Ok(PackPolicy::default()),
+        }
+    }
+
+    pub fn set_pack_policy(&self, policy: PackPolicy) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('pack_policy', ?1)",
+            [policy.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Check if a SHA-256 hash exists in the manifest.
+    pub fn contains(&self, sha256: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM pack_files WHERE sha256 = ?1",
+            [sha256],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Remove a pack file entry. Returns true if a row was deleted.
+    pub fn remove(&self, sha256: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM pack_files WHERE sha256 = ?1",
+            [sha256],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// List all entries as `(sha256, format)` pairs.
+    pub fn list_entries(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT sha256, format FROM pack_files")?;
+        let entries = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(entries)
+    }
+
+    /// List all entries, least recently verified first (never-verified entries lead).
+    pub fn list_entries_by_verification(&self) -> Result<Vec<ManifestEntry>> {
+        self.query_entries("ORDER BY verified_at IS NOT NULL, verified_at, sha256")
+    }
+
+    /// List all entries with full metadata, ordered by hash.
+    pub fn list_files(&self) -> Result<Vec<ManifestEntry>> {
+        self.query_entries("ORDER BY sha256")
+    }
+
+    fn query_entries(&self, order_by: &str) -> Result<Vec<ManifestEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM pack_files {order_by}"
+        ))?;
+        let entries = stmt
+            .query_map([], entry_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(entries)
+    }
+
+    /// Record a successful verification of the given entries at the current time.
+    pub fn mark_verified(&self, sha256s: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE pack_files SET verified_at = datetime('now') WHERE sha256 = ?1",
+            )?;
+            for sha256 in sha256s {
+                stmt.execute([sha256])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Move an entry from `pack_files` to `trash`, stamped with the current time.
+    /// Returns false if there was no such entry.
+    pub fn trash_file(&self, sha256: &str) -> Result<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+        let moved = tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO trash ({MOVABLE_COLUMNS}, trashed_at)
+                 SELECT {MOVABLE_COLUMNS}, datetime('now') FROM pack_files WHERE sha256 = ?1"
+            ),
+            [sha256],
+        )?;
+        tx.execute("DELETE FROM pack_files WHERE sha256 = ?1", [sha256])?;
+        tx.commit()?;
+        Ok(moved > 0)
+    }
+
+    /// Move an entry from `trash` back to `pack_files`.
+    /// Returns false if there was no such trash entry.
+    pub fn untrash_file(&self, sha256: &str) -> Result<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+        let moved = tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO pack_files ({MOVABLE_COLUMNS})
+                 SELECT {MOVABLE_COLUMNS} FROM trash WHERE sha256 = ?1"
+            ),
+            [sha256],
+        )?;
+        tx.execute("DELETE FROM trash WHERE sha256 = ?1", [sha256])?;
+        tx.commit()?;
+        Ok(moved > 0)
+    }
+
+    /// Forget a trash entry. Returns true if a row was deleted.
+    pub fn remove_trash(&self, sha256: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM trash WHERE sha256 = ?1", [sha256])?;
+        Ok(deleted > 0)
+    }
+
+    /// List trash entries, oldest first.
+    pub fn list_trash(&self) -> Result<Vec<TrashEntry>> {
+        self.query_trash("", rusqlite::params![])
+    }
+
+    /// List trash entries trashed at least `days` days ago, oldest first.
+    pub fn list_trash_older_than(&self, days: u32) -> Result<Vec<TrashEntry>> {
+        self.query_trash(
+            "WHERE trashed_at <= datetime('now', ?1)",
+            rusqlite::params![format!("-{days} days")],
+        )
+    }
+
+    fn query_trash(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<TrashEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT sha256, original_filename, format, size, trashed_at FROM trash {filter}
+             ORDER BY trashed_at, sha256"
+        ))?;
+        let entries = stmt
+            .query_map(params, |row| {
+                Ok(TrashEntry {
+                    sha256: row.get(0)?,
+                    original_filename: row.get(1)?,
+                    format: row.get(2)?,
+                    size: row.get::<_, i64>(3)? as u64,
+                    trashed_at: row.get(4)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(entries)
+    }
+
+    /// List all view links as `(path relative to the pack, sha256)`, ordered by path.
+    pub fn list_view_links(&self) -> Result<Vec<(PathBuf, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, sha256 FROM view_links ORDER BY path")?;
+        let links = stmt
+            .query_map([], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(links)
+    }
+
+    /// List the view links (relative paths) pointing at one pack file.
+    pub fn view_links_for(&self, sha256: &str) -> Result<Vec<PathBuf>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM view_links WHERE sha256 = ?1 ORDER BY path")?;
+        let links = stmt
+            .query_map([sha256], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(links)
+    }
+
+    /// Record (or retarget) a view link.
+    pub fn insert_view_link(&self, path: &Path, sha256: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO view_links (path, sha256) VALUES (?1, ?2)",
+            rusqlite::params![path.to_string_lossy(), sha256],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a view link. Returns true if a row was deleted.
+    pub fn remove_view_link(&self, path: &Path) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM view_links WHERE path = ?1",
+            [path.to_string_lossy()],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Record (or replace) the sidecar of one kind for a pack file.
+    pub fn upsert_sidecar(&self, sidecar: &ManifestSidecar) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sidecars (sha256, kind, sidecar_sha256, size, original_filename)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                sidecar.sha256,
+                sidecar.kind,
+                sidecar.sidecar_sha256,
+                sidecar.size as i64,
+                sidecar.original_filename,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// List all recorded sidecars, ordered by parent hash then kind.
+    pub fn list_sidecars(&self) -> Result<Vec<ManifestSidecar>> {
+        self.query_sidecars("", rusqlite::params![])
+    }
+
+    /// List the sidecars recorded for one pack file (kept while it is trashed).
+    pub fn sidecars_for(&self, sha256: &str) -> Result<Vec<ManifestSidecar>> {
+        self.query_sidecars("WHERE sha256 = ?1", rusqlite::params![sha256])
+    }
+
+    /// Forget one sidecar of a pack file. Returns true if a row was deleted.
+    pub fn remove_sidecar(&self, sha256: &str, kind: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM sidecars WHERE sha256 = ?1 AND kind = ?2",
+            [sha256, kind],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Forget every sidecar of a pack file. Returns the number of rows deleted.
+    pub fn remove_sidecars_for(&self, sha256: &str) -> Result<usize> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM sidecars WHERE sha256 = ?1", [sha256])?;
+        Ok(deleted)
+    }
+
+    fn query_sidecars(
+        &self,
+        filter: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<ManifestSidecar>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT sha256, kind, sidecar_sha256, size, original_filename FROM sidecars {filter}
+             ORDER BY sha256, kind"
+        ))?;
+        let sidecars = stmt
+            .query_map(params, |row| {
+                Ok(ManifestSidecar {
+                    sha256: row.get(0)?,
+                    kind: row.get(1)?,
+                    sidecar_sha256: row.get(2)?,
+                    size: row.get::<_, i64>(3)? as u64,
+                    original_filename: row.get(4)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(sidecars)
+    }
+
+    /// Get the manifest version string.
+    pub fn version(&self) -> Result<String> {
+        let version: String = self.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'version'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_open_creates_db() {
+        let tmp = tempfile::tempdir().unwrap();
+        let _manifest = Manifest::open(tmp.path()).unwrap();
+        assert!(tmp.path().join(".photopack/manifest.sqlite").exists());
+        assert!(tmp.path().join(".photopack/version").exists());
+    }
+
+    #[test]
+    fn test_manifest_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        assert_eq!(manifest.version().unwrap(), "8");
+        assert_eq!(
+            fs::read_to_string(tmp.path().join(".photopack/version")).unwrap(),
+            "8"
+        );
+    }
+
+    #[test]
+    fn test_manifest_insert_and_contains() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+
+        assert!(!manifest.contains("abc123").unwrap());
+        manifest
+            .insert_file("abc123", "photo.jpg", "JPEG", 1024, None, None, None, 0)
+            .unwrap();
+        assert!(manifest.contains("abc123").unwrap());
+    }
+
+    #[test]
+    fn test_manifest_remove() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+
+        manifest
+            .insert_file("abc123", "photo.jpg", "JPEG", 1024, None, None, None, 0)
+            .unwrap();
+        assert!(manifest.contains("abc123").unwrap());
+
+        let removed = manifest.remove("abc123").unwrap();
+        assert!(removed);
+        assert!(!manifest.contains("abc123").unwrap());
+
+        // Removing again returns false
+        let removed_again = manifest.remove("abc123").unwrap();
+        assert!(!removed_again);
+    }
+
+    #[test]
+    fn test_manifest_list_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+
+        manifest
+            .insert_file("aaa", "a.jpg", "JPEG", 100, None, None, None, 0)
+            .unwrap();
+        manifest
+            .insert_file("bbb", "b.cr2", "CR2", 200, None, None, None, 0)
+            .unwrap();
+
+        let entries = manifest.list_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let sha_set: std::collections::HashSet<&str> =
+            entries.iter().map(|(s, _)| s.as_str()).collect();
+        assert!(sha_set.contains("aaa"));
+        assert!(sha_set.contains("bbb"));
+    }
+
+    #[test]
+    fn test_manifest_insert_idempotent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+
+        manifest
+            .insert_file("abc123", "photo.jpg", "JPEG", 1024, None, None, None, 0)
+            .unwrap();
+        // Insert again with different metadata — should succeed (OR REPLACE)
+        manifest
+            .insert_file(
+                "abc123",
+                "renamed.jpg",
+                "JPEG",
+                2048,
+                Some("2024-01-01"),
+                Some("Canon"),
+                Some("EOS R5"),
+                1_700_000_000,
+            )
+            .unwrap();
+
+        let entries = manifest.list_entries().unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    // ── Schema safety ───────────────────────────────────────────
+
+    #[test]
+    fn test_manifest_tables_exist() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        let mut stmt = manifest
+            .conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+            .unwrap();
+        let tables: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(tables, vec!["metadata", "pack_files", "sidecars", "trash", "view_links"]);
+    }
+
+    #[test]
+    fn test_manifest_pack_files_columns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        let mut stmt = manifest
+            .conn
+            .prepare("SELECT name FROM pragma_table_info('pack_files') ORDER BY cid")
+            .unwrap();
+        let columns: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                "sha256", "original_filename", "format", "size",
+                "exif_date", "camera_make", "camera_model", "added_at",
+                "verified_at", "mtime", "content_id", "group_sha256", "role",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manifest_data_survives_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        {
+            let manifest = Manifest::open(tmp.path()).unwrap();
+            manifest
+                .insert_file("abc123", "photo.jpg", "JPEG", 1024, None, None, None, 0)
+                .unwrap();
+        }
+        {
+            let manifest = Manifest::open(tmp.path()).unwrap();
+            assert!(manifest.contains("abc123").unwrap());
+            let entries = manifest.list_entries().unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].0, "abc123");
+        }
+    }
+
+    // ── Migrations ──────────────────────────────────────────────
+
+    #[test]
+    fn test_manifest_v1_migrated_to_latest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let meta_dir = tmp.path().join(".photopack");
+        fs::create_dir_all(&meta_dir).unwrap();
+        {
+            // A v1 manifest as written by earlier releases
+            let conn = Connection::open(meta_dir.join("manifest.sqlite")).unwrap();
+            schema::initialize(&conn).unwrap();
+            conn.execute(
+                "INSERT INTO pack_files (sha256, original_filename, format, size, added_at)
+                 VALUES ('abc123', 'photo.jpg', 'JPEG', 1024, datetime('now'))",
+                [],
+            )
+            .unwrap();
+        }
+
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        assert_eq!(manifest.version().unwrap(), "8");
+        let entries = manifest.list_entries_by_verification().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].sha256, "abc123");
+        assert_eq!(entries[0].verified_at, None);
+        assert_eq!(entries[0].mtime, None);
+        assert_eq!(entries[0].content_id, None);
+        assert_eq!(entries[0].role, None);
+    }
+
+    #[test]
+    fn test_group_role_survives_trash_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        manifest
+            .insert_file("aaa", "IMG_1.JPG", "JPEG", 10, None, None, None, 0)
+            .unwrap();
+        manifest.set_group_role("aaa", Some("bbb"), PackRole::Alternate).unwrap();
+
+        assert!(manifest.trash_file("aaa").unwrap());
+        assert!(manifest.untrash_file("aaa").unwrap());
+
+        let entries = manifest.list_files().unwrap();
+        assert_eq!(entries[0].group_sha256.as_deref(), Some("bbb"));
+        assert_eq!(entries[0].role, Some(PackRole::Alternate));
+    }
+
+    #[test]
+    fn test_pack_policy_defaults_to_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        assert_eq!(manifest.pack_policy().unwrap(), PackPolicy::Source);
+
+        manifest.set_pack_policy(PackPolicy::Families).unwrap();
+        let reopened = Manifest::open(tmp.path()).unwrap();
+        assert_eq!(reopened.pack_policy().unwrap(), PackPolicy::Families);
+    }
+
+    #[test]
+    fn test_content_id_survives_trash_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        manifest
+            .insert_file("aaa", "IMG_1.MOV", "MOV", 10, None, None, None, 0)
+            .unwrap();
+        manifest.set_content_id("aaa", Some("cid")).unwrap();
+
+        assert!(manifest.trash_file("aaa").unwrap());
+        assert!(manifest.untrash_file("aaa").unwrap());
+
+        let entries = manifest.list_files().unwrap();
+        assert_eq!(entries[0].content_id.as_deref(), Some("cid"));
+    }
+
+    #[test]
+    fn test_manifest_rejects_newer_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        {
+            let manifest = Manifest::open(tmp.path()).unwrap();
+            manifest
+                .conn
+                .execute("UPDATE metadata SET value = '999' WHERE key = 'version'", [])
+                .unwrap();
+        }
+        let err = Manifest::open(tmp.path()).err().unwrap();
+        assert!(matches!(
+            err,
+            crate::error::Error::ManifestTooNew { db: 999, code: 8 }
+        ));
+    }
+
+    // ── Verification bookkeeping ────────────────────────────────
+
+    #[test]
+    fn test_mark_verified_orders_least_recent_first() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        for sha in ["aaa", "bbb", "ccc"] {
+            manifest
+                .insert_file(sha, "p.jpg", "JPEG", 10, None, None, None, 0)
+                .unwrap();
+        }
+        manifest
+            .conn
+            .execute(
+                "UPDATE pack_files SET verified_at = '2020-01-01 00:00:00' WHERE sha256 = 'ccc'",
+                [],
+            )
+            .unwrap();
+        manifest.mark_verified(&["aaa".to_string()]).unwrap();
+
+        let order: Vec<String> = manifest
+            .list_entries_by_verification()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.sha256)
+            .collect();
+        // Never verified, then oldest verification, then just verified
+        assert_eq!(order, ["bbb", "ccc", "aaa"]);
+    }
+
+    // ── Trash ───────────────────────────────────────────────────
+
+    #[test]
+    fn test_trash_and_untrash_move_rows() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        manifest
+            .insert_file("aaa", "a.jpg", "JPEG", 10, Some("2024:01:01 00:00:00"), None, None, 7)
+            .unwrap();
+
+        assert!(manifest.trash_file("aaa").unwrap());
+        assert!(!manifest.contains("aaa").unwrap());
+        let trash = manifest.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].original_filename, "a.jpg");
+        assert!(!manifest.trash_file("aaa").unwrap());
+
+        assert!(manifest.untrash_file("aaa").unwrap());
+        assert!(manifest.list_trash().unwrap().is_empty());
+        let entries = manifest.list_files().unwrap();
+        assert_eq!(entries[0].exif_date.as_deref(), Some("2024:01:01 00:00:00"));
+        assert_eq!(entries[0].mtime, Some(7));
+    }
+
+    #[test]
+    fn test_list_trash_older_than() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        for sha in ["old", "new"] {
+            manifest
+                .insert_file(sha, "p.jpg", "JPEG", 10, None, None, None, 0)
+                .unwrap();
+            manifest.trash_file(sha).unwrap();
+        }
+        manifest
+            .conn
+            .execute(
+                "UPDATE trash SET trashed_at = datetime('now', '-40 days') WHERE sha256 = 'old'",
+                [],
+            )
+            .unwrap();
+
+        let expired: Vec<String> = manifest
+            .list_trash_older_than(30)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.sha256)
+            .collect();
+        assert_eq!(expired, ["old"]);
+        assert_eq!(manifest.list_trash_older_than(0).unwrap().len(), 2);
+        assert!(manifest.remove_trash("old").unwrap());
+        assert_eq!(manifest.list_trash().unwrap().len(), 1);
+    }
+
+    // ── View links ──────────────────────────────────────────────
+
+    #[test]
+    fn test_view_links_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        let a = Path::new("by-date/2024/01/01/a.jpg");
+        let b = Path::new("by-camera/Canon EOS R5/a.jpg");
+        manifest.insert_view_link(a, "aaa").unwrap();
+        manifest.insert_view_link(b, "aaa").unwrap();
+        manifest.insert_view_link(b, "bbb").unwrap(); // retarget
+
+        assert_eq!(manifest.view_links_for("aaa").unwrap(), vec![a.to_path_buf()]);
+        assert_eq!(
+            manifest.list_view_links().unwrap(),
+            vec![
+                (b.to_path_buf(), "bbb".to_string()),
+                (a.to_path_buf(), "aaa".to_string()),
+            ]
+        );
+
+        assert!(manifest.remove_view_link(a).unwrap());
+        assert!(!manifest.remove_view_link(a).unwrap());
+        assert!(manifest.view_links_for("aaa").unwrap().is_empty());
+    }
+
+    // ── Sidecars ────────────────────────────────────────────────
+
+    #[test]
+    fn test_sidecars_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        let sidecar = |sha: &str, kind: &str, sidecar_sha: &str| ManifestSidecar {
+            sha256: sha.to_string(),
+            kind: kind.to_string(),
+            sidecar_sha256: sidecar_sha.to_string(),
+            size: 12,
+            original_filename: "IMG_1.xmp".to_string(),
+        };
+        manifest.upsert_sidecar(&sidecar("aaa", "XMP", "x1")).unwrap();
+        manifest.upsert_sidecar(&sidecar("aaa", "AAE", "e1")).unwrap();
+        manifest.upsert_sidecar(&sidecar("bbb", "XMP", "x2")).unwrap();
+        manifest.upsert_sidecar(&sidecar("aaa", "XMP", "x3")).unwrap(); // replace
+
+        assert_eq!(
+            manifest.sidecars_for("aaa").unwrap(),
+            vec![sidecar("aaa", "AAE", "e1"), sidecar("aaa", "XMP", "x3")]
+        );
+        assert_eq!(manifest.list_sidecars().unwrap().len(), 3);
+
+        assert!(manifest.remove_sidecar("aaa", "AAE").unwrap());
+        assert!(!manifest.remove_sidecar("aaa", "AAE").unwrap());
+        assert_eq!(manifest.remove_sidecars_for("aaa").unwrap(), 1);
+        assert_eq!(manifest.list_sidecars().unwrap(), vec![sidecar("bbb", "XMP", "x2")]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Manifest database schema and migration framework.
+//!
+//! Mirrors the catalog's strategy (see `catalog::schema`): the version lives in
+//! the `metadata` table under the `version` key (and in the `.photopack/version`
+//! text file for tools that don't speak SQLite). On every open, [`migrate`]
+//! compares it against [`MANIFEST_VERSION`]:
+//!
+//! - **DB version == code version** → no-op.
+//! - **DB version < code version** → run pending migrations in a transaction.
+//! - **DB version > code version** → fail with [`Error::ManifestTooNew`], since
+//!   an older photopack must not write to a pack created by a newer one.
+//!
+//! ## Adding a migration
+//!
+//! 1. Increment [`MANIFEST_VERSION`].
+//! 2. Write a `fn(conn: &Connection) -> Result<()>` that performs the DDL/DML.
+//! 3. Append it to [`MIGRATIONS`]. `MIGRATIONS[0]` = v1→v2, `MIGRATIONS[1]` = v2→v3, etc.
+
+use rusqlite::{params, Connection};
+
+use crate::error::{Error, Result};
+
+/// Current manifest version. Bump when adding a migration.
+pub const MANIFEST_VERSION: i64 = 8;
+
+/// Ordered list of migrations. `MIGRATIONS[i]` migrates from version `i+1` to `i+2`.
+pub const MIGRATIONS: &[fn(&Connection) -> Result<()>] = &[
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+];
+
+/// Create the v1 tables. Newer columns are added by [`migrate`].
+pub fn initialize(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS metadata (
+            key   TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS pack_files (
+            sha256            TEXT PRIMARY KEY,
+            original_filename TEXT NOT NULL,
+            format            TEXT NOT NULL,
+            size              INTEGER NOT NULL,
+            exif_date         TEXT,
+            camera_make       TEXT,
+            camera_model      TEXT,
+            added_at          TEXT NOT NULL
+        );",
+    )?;
+
+    // Seed version metadata if missing (a brand-new manifest starts at v1)
+    conn.execute(
+        "INSERT OR IGNORE INTO metadata (key, value) VALUES ('version', '1')",
+        [],
+    )?;
+    conn.execute(
+        "INSERT OR IGNORE INTO metadata (key, value) VALUES ('created_at', datetime('now'))",
+        [],
+    )?;
+    Ok(())
+}
+
+/// v2: `verified_at` records the last successful integrity check of each entry.
+fn migrate_v1_to_v2(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE pack_files ADD COLUMN verified_at TEXT;")?;
+    Ok(())
+}
+
+/// v3: `mtime` of the packed source, the date fallback when EXIF has none
+/// (needed to rebuild a date tree from the manifest alone).
+fn migrate_v2_to_v3(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE pack_files ADD COLUMN mtime INTEGER;")?;
+    Ok(())
+}
+
+/// v4: `view_links` records every link of the browsable views (`by-date/`,
+/// `by-camera/`) so stale links can be removed without walking the tree.
+fn migrate_v3_to_v4(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE view_links (
+            path   TEXT PRIMARY KEY,
+            sha256 TEXT NOT NULL
+        );
+        CREATE INDEX idx_view_links_sha256 ON view_links(sha256);",
+    )?;
+    Ok(())
+}
+
+/// v5: `trash` holds the entries of pack files moved to `.photopack/trash/`
+/// (same columns as `pack_files`, plus when they were trashed).
+fn migrate_v4_to_v5(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE trash (
+            sha256            TEXT PRIMARY KEY,
+            original_filename TEXT NOT NULL,
+            format            TEXT NOT NULL,
+            size              INTEGER NOT NULL,
+            exif_date         TEXT,
+            camera_make       TEXT,
+            camera_model      TEXT,
+            added_at          TEXT NOT NULL,
+            verified_at       TEXT,
+            mtime             INTEGER,
+            trashed_at        TEXT NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+/// v6: `sidecars` records the sidecar files (XMP, AAE, THM) packed next to a
+/// pack file as `{sha256}.{ext}`, keyed by the parent hash and sidecar kind.
+fn migrate_v5_to_v6(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE sidecars (
+            sha256            TEXT NOT NULL,
+            kind              TEXT NOT NULL,
+            sidecar_sha256    TEXT NOT NULL,
+            size              INTEGER NOT NULL,
+            original_filename TEXT NOT NULL,
+            PRIMARY KEY (sha256, kind)
+        );",
+    )?;
+    Ok(())
+}
+
+/// v7: `content_id` pairs a Live Photo still with its motion video, so both
+/// are unpacked side by side. Trashed entries keep it too.
+fn migrate_v6_to_v7(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE pack_files ADD COLUMN content_id TEXT;
+         ALTER TABLE trash ADD COLUMN content_id TEXT;",
+    )?;
+    Ok(())
+}
+
+/// v8: `group_sha256` and `role` record which duplicate group a pack file came
+/// from (named by the hash of its source of truth) and why it was kept
+/// (`unique`, `source` or `alternate`).
+fn migrate_v7_to_v8(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE pack_files ADD COLUMN group_sha256 TEXT;
+         ALTER TABLE pack_files ADD COLUMN role TEXT;
+         ALTER TABLE trash ADD COLUMN group_sha256 TEXT;
+         ALTER TABLE trash ADD COLUMN role TEXT;",
+    )?;
+    Ok(())
+}
+
+fn get_version(conn: &Connection) -> Result<i64> {
+    let version: String = conn.query_row(
+        "SELECT value FROM metadata WHERE key = 'version'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(version.parse().unwrap_or(1))
+}
+
+fn set_version(conn: &Connection, version: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE metadata SET value = ?1 WHERE key = 'version'",
+        params![version.to_string()],
+    )?;
+    Ok(())
+}
+
+/// Run pending migrations and update the stored version.
+///
+/// Must be called **after** [`initialize`] so the metadata table exists.
+pub fn migrate(conn: &Connection) -> Result<()> {
+    let db_version = get_version(conn)?;
+
+    if db_version > MANIFEST_VERSION {
+        return Err(Error::ManifestTooNew {
+            db: db_version,
+            code: MANIFEST_VERSION,
+        });
+    }
+
+    if db_version < MANIFEST_VERSION {
+        let tx = conn.unchecked_transaction()?;
+        for migration in MIGRATIONS.iter().skip((db_version - 1) as usize) {
+            migration(&tx)?;
+        }
+        set_version(&tx, MANIFEST_VERSION)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
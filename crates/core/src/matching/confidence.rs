@@ -1,20 +1,23 @@
 use crate::domain::Confidence;
 
+use super::config::MatchConfig;
+
 /// Perceptual hash Hamming distance thresholds (for 64-bit hashes).
 /// Super-safe thresholds: true cross-format duplicates (RAW↔JPEG of the SAME photo)
 /// have distance 0-2. Different photos (even similar scenes) have distance 3+.
 /// These thresholds prioritize zero false positives over catching edge-case duplicates.
+/// They are the `default` profile of [`MatchConfig`].
 pub const PHASH_NEAR_CERTAIN_THRESHOLD: u32 = 2;
 pub const PHASH_HIGH_THRESHOLD: u32 = 2;
 pub const PHASH_PROBABLE_THRESHOLD: u32 = 3;
 
 /// Determine confidence from a perceptual hash Hamming distance.
-pub fn confidence_from_hamming(distance: u32) -> Option<Confidence> {
-    if distance <= PHASH_NEAR_CERTAIN_THRESHOLD {
+pub fn confidence_from_hamming(distance: u32, config: &MatchConfig) -> Option<Confidence> {
+    if distance <= config.near_certain_threshold {
         Some(Confidence::NearCertain)
-    } else if distance <= PHASH_HIGH_THRESHOLD {
+    } else if distance <= config.high_threshold {
         Some(Confidence::High)
-    } else if distance <= PHASH_PROBABLE_THRESHOLD {
+    } else if distance <= config.probable_threshold {
         Some(Confidence::Probable)
     } else {
         None
@@ -32,13 +35,27 @@ mod tests {
 
     #[test]
     fn test_confidence_from_hamming() {
-        assert_eq!(confidence_from_hamming(0), Some(Confidence::NearCertain));
-        assert_eq!(confidence_from_hamming(2), Some(Confidence::NearCertain));
-        assert_eq!(confidence_from_hamming(3), Some(Confidence::Probable));
-        assert_eq!(confidence_from_hamming(4), None);
-        assert_eq!(confidence_from_hamming(5), None);
-        assert_eq!(confidence_from_hamming(6), None);
-        assert_eq!(confidence_from_hamming(10), None);
+        let config = MatchConfig::default();
+        assert_eq!(confidence_from_hamming(0, &config), Some(Confidence::NearCertain));
+        assert_eq!(confidence_from_hamming(2, &config), Some(Confidence::NearCertain));
+        assert_eq!(confidence_from_hamming(3, &config), Some(Confidence::Probable));
+        assert_eq!(confidence_from_hamming(4, &config), None);
+        assert_eq!(confidence_from_hamming(5, &config), None);
+        assert_eq!(confidence_from_hamming(6, &config), None);
+        assert_eq!(confidence_from_hamming(10, &config), None);
+    }
+
+    #[test]
+    fn test_confidence_from_hamming_follows_config() {
+        let scans = MatchConfig::profile("scans").unwrap();
+        assert_eq!(confidence_from_hamming(4, &scans), Some(Confidence::NearCertain));
+        assert_eq!(confidence_from_hamming(5, &scans), Some(Confidence::High));
+        assert_eq!(confidence_from_hamming(8, &scans), Some(Confidence::Probable));
+        assert_eq!(confidence_from_hamming(9, &scans), None);
+
+        let strict = MatchConfig::profile("strict").unwrap();
+        assert_eq!(confidence_from_hamming(2, &strict), Some(Confidence::Probable));
+        assert_eq!(confidence_from_hamming(3, &strict), None);
     }
 
     #[test]
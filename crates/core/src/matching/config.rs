@@ -0,0 +1,145 @@
+use super::confidence::{
+    PHASH_HIGH_THRESHOLD, PHASH_NEAR_CERTAIN_THRESHOLD, PHASH_PROBABLE_THRESHOLD,
+};
+
+/// Tunable parameters of the matching pipeline.
+///
+/// Thresholds are Hamming distances between 64-bit perceptual hashes and must
+/// satisfy `near_certain <= high <= probable`. Persisted in the catalog `config`
+/// table; see [`MatchConfig::to_config_value`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchConfig {
+    /// Max distance for NearCertain, and for visual validation of EXIF groups.
+    pub near_certain_threshold: u32,
+    /// Max distance for High; also the limit when one side lacks a dHash.
+    pub high_threshold: u32,
+    /// Max distance for Probable: the widest perceptual-only match.
+    pub probable_threshold: u32,
+    /// Same-camera shots up to this many seconds apart are sequential shots,
+    /// never duplicates. `0` disables the filter.
+    pub sequential_window_secs: u64,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            near_certain_threshold: PHASH_NEAR_CERTAIN_THRESHOLD,
+            high_threshold: PHASH_HIGH_THRESHOLD,
+            probable_threshold: PHASH_PROBABLE_THRESHOLD,
+            sequential_window_secs: 60,
+        }
+    }
+}
+
+impl MatchConfig {
+    /// Names accepted by [`MatchConfig::profile`].
+    pub const PROFILES: &'static [&'static str] = &["strict", "default", "scans"];
+
+    /// Look up a named profile:
+    ///
+    /// - `strict`: tighter thresholds and a 10-minute sequential window, for
+    ///   bursts and continuous shooting where frames differ by a few bits.
+    /// - `default`: the built-in thresholds, tuned for zero false positives.
+    /// - `scans`: looser thresholds for rescanned film and prints, whose copies
+    ///   differ by scanner noise; the sequential filter is off because scanner
+    ///   dates record when a frame was scanned, not when it was shot.
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "strict" => Some(Self {
+                near_certain_threshold: 1,
+                high_threshold: 1,
+                probable_threshold: 2,
+                sequential_window_secs: 600,
+            }),
+            "default" => Some(Self::default()),
+            "scans" => Some(Self {
+                near_certain_threshold: 4,
+                high_threshold: 6,
+                probable_threshold: 8,
+                sequential_window_secs: 0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Name of the profile equal to this config, if any.
+    pub fn profile_name(&self) -> Option<&'static str> {
+        Self::PROFILES
+            .iter()
+            .copied()
+            .find(|name| Self::profile(name).as_ref() == Some(self))
+    }
+
+    /// Serialize as `key=value` pairs for the catalog `config` table.
+    pub fn to_config_value(&self) -> String {
+        format!(
+            "near_certain={} high={} probable={} sequential_window={}",
+            self.near_certain_threshold,
+            self.high_threshold,
+            self.probable_threshold,
+            self.sequential_window_secs
+        )
+    }
+
+    /// Parse a value written by [`MatchConfig::to_config_value`]. Missing or
+    /// unreadable keys keep their default.
+    pub fn from_config_value(value: &str) -> Self {
+        let mut config = Self::default();
+        for (key, val) in value.split_whitespace().filter_map(|kv| kv.split_once('=')) {
+            match key {
+                "near_certain" => {
+                    config.near_certain_threshold =
+                        val.parse().unwrap_or(config.near_certain_threshold)
+                }
+                "high" => config.high_threshold = val.parse().unwrap_or(config.high_threshold),
+                "probable" => {
+                    config.probable_threshold = val.parse().unwrap_or(config.probable_threshold)
+                }
+                "sequential_window" => {
+                    config.sequential_window_secs =
+                        val.parse().unwrap_or(config.sequential_window_secs)
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        assert_eq!(MatchConfig::profile("default"), Some(MatchConfig::default()));
+        assert!(MatchConfig::profile("loose").is_none());
+        for name in MatchConfig::PROFILES {
+            let config = MatchConfig::profile(name).unwrap();
+            assert!(config.near_certain_threshold <= config.high_threshold);
+            assert!(config.high_threshold <= config.probable_threshold);
+            assert_eq!(config.profile_name(), Some(*name));
+        }
+        let strict = MatchConfig::profile("strict").unwrap();
+        let scans = MatchConfig::profile("scans").unwrap();
+        assert!(strict.probable_threshold < MatchConfig::default().probable_threshold);
+        assert!(scans.probable_threshold > MatchConfig::default().probable_threshold);
+    }
+
+    #[test]
+    fn test_config_value_roundtrip() {
+        let custom = MatchConfig {
+            near_certain_threshold: 3,
+            high_threshold: 4,
+            probable_threshold: 5,
+            sequential_window_secs: 30,
+        };
+        assert_eq!(MatchConfig::from_config_value(&custom.to_config_value()), custom);
+        assert_eq!(custom.profile_name(), None);
+
+        // Missing keys fall back to defaults
+        let partial = MatchConfig::from_config_value("probable=5 bogus=1");
+        assert_eq!(partial.probable_threshold, 5);
+        assert_eq!(partial.near_certain_threshold, PHASH_NEAR_CERTAIN_THRESHOLD);
+    }
+}
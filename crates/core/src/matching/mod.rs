@@ -1,10 +1,13 @@
 pub mod confidence;
+pub mod config;
+pub mod overrides;
 
 use std::collections::{HashMap, HashSet};
 
-use crate::domain::{Confidence, PhotoFile};
+use crate::domain::{Confidence, MatchEvidence, MatchOverride, MatchPhase, PhotoFile};
 use crate::hasher::perceptual::hamming_distance;
 use confidence::confidence_from_hamming;
+pub use config::MatchConfig;
 
 /// BK-tree for efficient Hamming distance nearest-neighbor search.
 /// Allows finding all items within a given distance in O(n^α) where α < 1,
@@ -81,11 +84,32 @@ impl BkTree {
 pub struct MatchGroup {
     pub member_ids: Vec<i64>,
     pub confidence: Confidence,
+    /// Why members joined: pairwise edges recorded by each phase.
+    pub evidence: Vec<MatchEvidence>,
+}
+
+/// Evidence edge between two photos, with their aHash/dHash distances when both have them.
+fn edge(phase: MatchPhase, a: &PhotoFile, b: &PhotoFile, exif_key: Option<String>) -> MatchEvidence {
+    let distance = |x: Option<u64>, y: Option<u64>| Some(hamming_distance(x?, y?));
+    MatchEvidence {
+        photo_a: a.id,
+        photo_b: b.id,
+        phase,
+        phash_distance: distance(a.phash, b.phash),
+        dhash_distance: distance(a.dhash, b.dhash),
+        exif_key,
+    }
 }
 
 /// Run the full matching pipeline on a set of photos.
 /// Returns groups of duplicate photos with confidence levels.
-pub fn find_duplicates(photos: &[PhotoFile]) -> Vec<MatchGroup> {
+/// Perceptual thresholds and the sequential-shot window come from `config`;
+/// manual `overrides` are applied last and win over every phase.
+pub fn find_duplicates(
+    photos: &[PhotoFile],
+    config: &MatchConfig,
+    overrides: &[MatchOverride],
+) -> Vec<MatchGroup> {
     if photos.len() < 2 {
         return Vec::new();
     }
@@ -99,6 +123,10 @@ pub fn find_duplicates(photos: &[PhotoFile]) -> Vec<MatchGroup> {
             groups.push(MatchGroup {
                 member_ids: members.iter().map(|p| p.id).collect(),
                 confidence: Confidence::Certain,
+                evidence: members[1..]
+                    .iter()
+                    .map(|p| edge(MatchPhase::Sha256, members[0], p, None))
+                    .collect(),
             });
         }
     }
@@ -111,7 +139,8 @@ pub fn find_duplicates(photos: &[PhotoFile]) -> Vec<MatchGroup> {
     let exif_groups = group_by_exif(photos, &empty_set);
     let photo_map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
     for group in exif_groups {
-        let validated = validate_with_perceptual_hash(&group.member_ids, photos);
+        let pairs = validate_with_perceptual_hash(&group.member_ids, photos, config);
+        let validated: HashSet<i64> = pairs.iter().flat_map(|&(a, b)| [a, b]).collect();
 
         // Filter: keep members that either (a) passed visual validation, or
         // (b) lack perceptual hashes entirely (HEIC/RAW — EXIF is our best signal), or
@@ -155,9 +184,11 @@ pub fn find_duplicates(photos: &[PhotoFile]) -> Vec<MatchGroup> {
             } else {
                 Confidence::NearCertain
             };
+            let evidence = exif_evidence(&filtered, &pairs, &photo_map);
             groups.push(MatchGroup {
                 member_ids: filtered,
                 confidence,
+                evidence,
             });
         }
     }
@@ -171,7 +202,7 @@ pub fn find_duplicates(photos: &[PhotoFile]) -> Vec<MatchGroup> {
     }
 
     // Phase 3: pHash/dHash Hamming distance → Probable
-    let perceptual_groups = group_by_perceptual_hash(photos, &grouped_ids);
+    let perceptual_groups = group_by_perceptual_hash(photos, &grouped_ids, config);
     for group in perceptual_groups {
         for &id in &group.member_ids {
             grouped_ids.insert(id);
@@ -180,13 +211,14 @@ pub fn find_duplicates(photos: &[PhotoFile]) -> Vec<MatchGroup> {
     }
 
     // Phase 4: Merge overlapping groups (with cross-group visual validation)
-    let mut merged = merge_overlapping(&mut groups, photos);
+    let mut merged = merge_overlapping(&mut groups, photos, config);
 
     // Phase 5: Attach orphaned non-phash photos to groups by EXIF match
     let final_grouped: HashSet<i64> = merged.iter().flat_map(|g| &g.member_ids).copied().collect();
     attach_orphaned_by_exif(&mut merged, photos, &final_grouped);
 
-    merged
+    // Phase 6: Manual always-match / never-match overrides
+    overrides::apply_overrides(merged, photos, overrides)
 }
 
 /// Phase 1: Group photos by identical SHA-256 hash.
@@ -200,6 +232,11 @@ fn group_by_sha256(photos: &[PhotoFile]) -> HashMap<String, Vec<&PhotoFile>> {
 
 /// Build an EXIF key (date + camera model) for grouping.
 fn exif_key(photo: &PhotoFile) -> Option<String> {
+    // Videos only ever match byte-identical copies: a Live Photo's motion clip
+    // shares its still's date and camera but is not a duplicate of it.
+    if photo.format.is_video() {
+        return None;
+    }
     let exif = photo.exif.as_ref()?;
     let date = exif.date.as_ref()?;
     Some(format!(
@@ -229,19 +266,23 @@ fn group_by_exif(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Vec<MatchGrou
         .map(|member_ids| MatchGroup {
             member_ids,
             confidence: Confidence::High,
+            evidence: Vec::new(),
         })
         .collect()
 }
 
 /// Validate a group of photo IDs using perceptual hash distance (strict dual-hash consensus).
-/// Returns IDs of photos that are perceptually close to at least one other member.
-/// Uses NEAR_CERTAIN threshold (≤2 bits) for EXIF validation — only true duplicates pass.
-/// Sequential/burst shots (distance 3+) are rejected.
-fn validate_with_perceptual_hash(ids: &[i64], photos: &[PhotoFile]) -> HashSet<i64> {
-    use confidence::PHASH_NEAR_CERTAIN_THRESHOLD;
-
+/// Returns the pairs of members that are perceptually close to each other.
+/// Uses the NEAR_CERTAIN threshold (≤2 bits by default) for EXIF validation — only
+/// true duplicates pass. Sequential/burst shots (distance 3+) are rejected.
+fn validate_with_perceptual_hash(
+    ids: &[i64],
+    photos: &[PhotoFile],
+    config: &MatchConfig,
+) -> Vec<(i64, i64)> {
+    let threshold = config.near_certain_threshold;
     let photo_map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
-    let mut valid = HashSet::new();
+    let mut valid = Vec::new();
 
     for (i, &id_a) in ids.iter().enumerate() {
         for &id_b in &ids[i + 1..] {
@@ -251,14 +292,12 @@ fn validate_with_perceptual_hash(ids: &[i64], photos: &[PhotoFile]) -> HashSet<i
                     let is_match = match (pa.dhash, pb.dhash) {
                         (Some(da), Some(db)) => {
                             let dhash_dist = hamming_distance(da, db);
-                            phash_dist <= PHASH_NEAR_CERTAIN_THRESHOLD
-                                && dhash_dist <= PHASH_NEAR_CERTAIN_THRESHOLD
+                            phash_dist <= threshold && dhash_dist <= threshold
                         }
-                        _ => phash_dist <= PHASH_NEAR_CERTAIN_THRESHOLD,
+                        _ => phash_dist <= threshold,
                     };
                     if is_match {
-                        valid.insert(id_a);
-                        valid.insert(id_b);
+                        valid.push((id_a, id_b));
                     }
                 }
             }
@@ -268,6 +307,44 @@ fn validate_with_perceptual_hash(ids: &[i64], photos: &[PhotoFile]) -> HashSet<i
     valid
 }
 
+/// Evidence for a Phase 2 group: its visually validated pairs, plus an EXIF-only
+/// edge to the anchor for members no pair covers (no hash, or no partner).
+fn exif_evidence(
+    members: &[i64],
+    pairs: &[(i64, i64)],
+    photo_map: &HashMap<i64, &PhotoFile>,
+) -> Vec<MatchEvidence> {
+    let member_set: HashSet<i64> = members.iter().copied().collect();
+    let kept: Vec<(i64, i64)> = pairs
+        .iter()
+        .copied()
+        .filter(|(a, b)| member_set.contains(a) && member_set.contains(b))
+        .collect();
+    let paired: HashSet<i64> = kept.iter().flat_map(|&(a, b)| [a, b]).collect();
+    let anchor = members
+        .iter()
+        .copied()
+        .find(|id| paired.contains(id))
+        .unwrap_or(members[0]);
+    let key = exif_key(photo_map[&anchor]);
+
+    let mut evidence: Vec<MatchEvidence> = kept
+        .iter()
+        .map(|(a, b)| edge(MatchPhase::Exif, photo_map[a], photo_map[b], key.clone()))
+        .collect();
+    for id in members {
+        if *id != anchor && !paired.contains(id) {
+            evidence.push(edge(
+                MatchPhase::Exif,
+                photo_map[&anchor],
+                photo_map[id],
+                key.clone(),
+            ));
+        }
+    }
+    evidence
+}
+
 /// Parse an EXIF datetime string into an approximate seconds value (for comparison only).
 /// Handles "YYYY:MM:DD HH:MM:SS" and "YYYY-MM-DD HH:MM:SS".
 fn parse_exif_seconds(date_str: &str) -> Option<i64> {
@@ -291,9 +368,12 @@ fn parse_exif_seconds(date_str: &str) -> Option<i64> {
 }
 
 /// Check if two photos are sequential shots from the same camera.
-/// Sequential shots: same camera model, EXIF dates 1-60 seconds apart (not identical).
-/// True duplicates always have identical EXIF dates.
-fn is_sequential_shot(a: &PhotoFile, b: &PhotoFile) -> bool {
+/// Sequential shots: same camera model, EXIF dates 1 to `sequential_window_secs`
+/// seconds apart (not identical). True duplicates always have identical EXIF dates.
+fn is_sequential_shot(a: &PhotoFile, b: &PhotoFile, config: &MatchConfig) -> bool {
+    if config.sequential_window_secs == 0 {
+        return false;
+    }
     let (exif_a, exif_b) = match (&a.exif, &b.exif) {
         (Some(ea), Some(eb)) => (ea, eb),
         _ => return false,
@@ -320,7 +400,7 @@ fn is_sequential_shot(a: &PhotoFile, b: &PhotoFile) -> bool {
     match (parse_exif_seconds(date_a), parse_exif_seconds(date_b)) {
         (Some(sa), Some(sb)) => {
             let diff = (sa - sb).unsigned_abs();
-            diff <= 60
+            diff <= config.sequential_window_secs
         }
         _ => false,
     }
@@ -336,11 +416,13 @@ fn is_sequential_shot(a: &PhotoFile, b: &PhotoFile) -> bool {
 /// accepted only at the stricter HIGH threshold.
 ///
 /// Sequential shot filter: rejects matches where both photos have the same camera
-/// model and EXIF dates 1-60 seconds apart (but not identical). True duplicates
-/// always have identical EXIF dates.
-fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Vec<MatchGroup> {
-    use confidence::{PHASH_HIGH_THRESHOLD, PHASH_PROBABLE_THRESHOLD};
-
+/// model and EXIF dates within the sequential window (60s by default) but not
+/// identical. True duplicates always have identical EXIF dates.
+fn group_by_perceptual_hash(
+    photos: &[PhotoFile],
+    excluded: &HashSet<i64>,
+    config: &MatchConfig,
+) -> Vec<MatchGroup> {
     // Build lookup map for dhash access
     let photo_map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
 
@@ -367,9 +449,10 @@ fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Ve
         }
 
         let phash_a = photo_a.phash.unwrap();
-        let neighbors = tree.find_within(phash_a, PHASH_PROBABLE_THRESHOLD);
+        let neighbors = tree.find_within(phash_a, config.probable_threshold);
 
         let mut members = vec![photo_a.id];
+        let mut evidence = Vec::new();
         let mut worst_confidence = Confidence::Certain;
 
         for (neighbor_id, phash_dist) in &neighbors {
@@ -377,7 +460,7 @@ fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Ve
                 continue;
             }
 
-            let phash_conf = match confidence_from_hamming(*phash_dist) {
+            let phash_conf = match confidence_from_hamming(*phash_dist, config) {
                 Some(c) => c,
                 None => continue,
             };
@@ -387,14 +470,14 @@ fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Ve
             let conf = match (photo_a.dhash, neighbor.and_then(|p| p.dhash)) {
                 (Some(da), Some(db)) => {
                     let dhash_dist = hamming_distance(da, db);
-                    match confidence_from_hamming(dhash_dist) {
+                    match confidence_from_hamming(dhash_dist, config) {
                         Some(dc) => confidence::combine_confidence(phash_conf, dc),
                         None => continue, // dhash too far → reject
                     }
                 }
                 _ => {
                     // One or both lack dhash (cross-format) — require stricter phash
-                    if *phash_dist > PHASH_HIGH_THRESHOLD {
+                    if *phash_dist > config.high_threshold {
                         continue;
                     }
                     phash_conf
@@ -402,14 +485,17 @@ fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Ve
             };
 
             // Sequential shot filter: reject matches from the same camera
-            // with EXIF dates 1-60 seconds apart (not identical).
+            // with EXIF dates inside the sequential window (not identical).
             if let Some(neighbor_photo) = neighbor {
-                if is_sequential_shot(photo_a, neighbor_photo) {
+                if is_sequential_shot(photo_a, neighbor_photo, config) {
                     continue;
                 }
             }
 
             members.push(*neighbor_id);
+            if let Some(neighbor_photo) = neighbor {
+                evidence.push(edge(MatchPhase::Perceptual, photo_a, neighbor_photo, None));
+            }
             if conf < worst_confidence {
                 worst_confidence = conf;
             }
@@ -422,6 +508,7 @@ fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Ve
             groups.push(MatchGroup {
                 member_ids: members,
                 confidence: worst_confidence,
+                evidence,
             });
         }
     }
@@ -433,7 +520,11 @@ fn group_by_perceptual_hash(photos: &[PhotoFile], excluded: &HashSet<i64>) -> Ve
 /// Before merging, validates that the groups are visually related — at least one
 /// pair of exclusive members (one from each group) must have perceptual hashes
 /// within threshold. This prevents cascading false merges through bridge photos.
-fn merge_overlapping(groups: &mut Vec<MatchGroup>, photos: &[PhotoFile]) -> Vec<MatchGroup> {
+fn merge_overlapping(
+    groups: &mut Vec<MatchGroup>,
+    photos: &[PhotoFile],
+    config: &MatchConfig,
+) -> Vec<MatchGroup> {
     let photo_map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
     let mut merged: Vec<MatchGroup> = Vec::new();
 
@@ -455,9 +546,12 @@ fn merge_overlapping(groups: &mut Vec<MatchGroup>, photos: &[PhotoFile]) -> Vec<
             // For each overlapping group, check that at least one pair of
             // exclusive members (one from each side) are perceptually close.
             let mut to_merge: Vec<usize> = Vec::new();
+            let mut links: Vec<MatchEvidence> = Vec::new();
             for &idx in &overlap_indices {
-                if cross_group_validated(&group_set, &merged[idx], &photo_map) {
+                if let Some(link) = cross_group_validated(&group_set, &merged[idx], &photo_map, config)
+                {
                     to_merge.push(idx);
+                    links.extend(link);
                 }
             }
 
@@ -467,10 +561,13 @@ fn merge_overlapping(groups: &mut Vec<MatchGroup>, photos: &[PhotoFile]) -> Vec<
             } else {
                 let mut combined_ids: HashSet<i64> = group_set;
                 let mut worst_confidence = group.confidence;
+                let mut evidence = group.evidence;
+                evidence.extend(links);
 
                 for &idx in to_merge.iter().rev() {
                     let removed = merged.remove(idx);
                     combined_ids.extend(removed.member_ids);
+                    evidence.extend(removed.evidence);
                     if removed.confidence < worst_confidence {
                         worst_confidence = removed.confidence;
                     }
@@ -479,6 +576,7 @@ fn merge_overlapping(groups: &mut Vec<MatchGroup>, photos: &[PhotoFile]) -> Vec<
                 merged.push(MatchGroup {
                     member_ids: combined_ids.into_iter().collect(),
                     confidence: worst_confidence,
+                    evidence,
                 });
             }
         }
@@ -490,11 +588,15 @@ fn merge_overlapping(groups: &mut Vec<MatchGroup>, photos: &[PhotoFile]) -> Vec<
 /// Check if two groups have at least one pair of perceptually similar exclusive members.
 /// "Exclusive" means members not in the overlap (i.e., unique to each group).
 /// If there are no exclusive members on one side, allow the merge (pure subset).
+///
+/// Returns `None` when the groups are visually unrelated, otherwise the merge
+/// evidence: the validating pair, or nothing when the merge needed no check.
 fn cross_group_validated(
     new_set: &HashSet<i64>,
     existing: &MatchGroup,
     photo_map: &HashMap<i64, &PhotoFile>,
-) -> bool {
+    config: &MatchConfig,
+) -> Option<Option<MatchEvidence>> {
     let existing_set: HashSet<i64> = existing.member_ids.iter().copied().collect();
 
     // Members exclusive to each group
@@ -503,7 +605,7 @@ fn cross_group_validated(
 
     // If either side has no exclusive members, it's a pure subset — allow merge
     if new_exclusive.is_empty() || existing_exclusive.is_empty() {
-        return true;
+        return Some(None);
     }
 
     // If either side lacks photos with phash, can't validate — allow merge
@@ -514,7 +616,7 @@ fn cross_group_validated(
         .iter()
         .any(|id| photo_map.get(id).and_then(|p| p.phash).is_some());
     if !new_has_phash || !existing_has_phash {
-        return true;
+        return Some(None);
     }
 
     // Check if at least one cross-group pair is perceptually close
@@ -523,15 +625,15 @@ fn cross_group_validated(
             if let (Some(pa), Some(pb)) = (photo_map.get(&id_a), photo_map.get(&id_b)) {
                 if let (Some(phash_a), Some(phash_b)) = (pa.phash, pb.phash) {
                     let dist = hamming_distance(phash_a, phash_b);
-                    if confidence_from_hamming(dist).is_some() {
-                        return true;
+                    if confidence_from_hamming(dist, config).is_some() {
+                        return Some(Some(edge(MatchPhase::Merge, pa, pb, None)));
                     }
                 }
             }
         }
     }
 
-    false
+    None
 }
 
 /// Phase 5: Attach ungrouped non-phash photos to existing groups by EXIF match.
@@ -545,14 +647,14 @@ fn attach_orphaned_by_exif(
     photos: &[PhotoFile],
     grouped_ids: &HashSet<i64>,
 ) {
-    // Build: EXIF key → first group index containing a photo with that key
+    // Build: EXIF key → first group index (and member) with that key
     let photo_map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
-    let mut exif_to_group: HashMap<String, usize> = HashMap::new();
+    let mut exif_to_group: HashMap<String, (usize, &PhotoFile)> = HashMap::new();
     for (idx, group) in groups.iter().enumerate() {
         for &id in &group.member_ids {
             if let Some(photo) = photo_map.get(&id) {
                 if let Some(key) = exif_key(photo) {
-                    exif_to_group.entry(key).or_insert(idx);
+                    exif_to_group.entry(key).or_insert((idx, *photo));
                 }
             }
         }
@@ -564,8 +666,11 @@ fn attach_orphaned_by_exif(
             continue;
         }
         if let Some(key) = exif_key(photo) {
-            if let Some(&group_idx) = exif_to_group.get(&key) {
+            if let Some(&(group_idx, anchor)) = exif_to_group.get(&key) {
                 groups[group_idx].member_ids.push(photo.id);
+                groups[group_idx]
+                    .evidence
+                    .push(edge(MatchPhase::Orphan, anchor, photo, Some(key)));
             }
         }
     }
@@ -617,6 +722,7 @@ mod tests {
             gps_lon: None,
             width: None,
             height: None,
+            content_id: None,
         });
         p
     }
@@ -639,6 +745,7 @@ mod tests {
             gps_lon: None,
             width: None,
             height: None,
+            content_id: None,
         });
         p
     }
@@ -653,7 +760,7 @@ mod tests {
             make_photo(3, "bbb", Some(u64::MAX)), // far from 100 in Hamming distance
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].confidence, Confidence::Certain);
         assert_eq!(groups[0].member_ids.len(), 2);
@@ -667,7 +774,7 @@ mod tests {
             make_photo(3, "aaa", Some(100)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].member_ids.len(), 3);
     }
@@ -681,7 +788,7 @@ mod tests {
             make_photo(4, "bbb", Some(u64::MAX)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 2);
         assert!(groups.iter().all(|g| g.confidence == Confidence::Certain));
     }
@@ -696,7 +803,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", None, "2024-01-15 12:00:00", "iPhone 16"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].confidence, Confidence::NearCertain);
         assert_eq!(groups[0].member_ids.len(), 2);
@@ -710,7 +817,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(101), "2024-01-15 12:00:00", "Canon R5"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].confidence, Confidence::High);
     }
@@ -722,7 +829,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", None, "2024-01-16 12:00:00", "iPhone 16"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
@@ -733,7 +840,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", None, "2024-01-15 12:00:00", "Canon R5"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
@@ -747,7 +854,7 @@ mod tests {
             make_photo(2, "bbb", Some(0b1111_0001)), // 1 bit different
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert!(groups[0].confidence >= Confidence::Probable);
     }
@@ -760,7 +867,7 @@ mod tests {
             make_photo(2, "bbb", Some(u64::MAX)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
@@ -772,7 +879,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0001), Some(u64::MAX)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Dual-hash: close phash + far dhash should reject");
     }
 
@@ -784,7 +891,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0001), Some(0b1010_0001)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Dual-hash: both close should group");
     }
 
@@ -808,7 +915,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(
             groups.is_empty(),
             "Sequential shots with divergent dHash must NOT be grouped (birthday photo bug)"
@@ -827,7 +934,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0b1111_0111), "2024-01-15 12:00:00", "iPhone 16"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         // Phase 3 catches these because both phash AND dhash are distance 3
         // (dhash defaults to same as phash in make_photo). In reality, different photos
         // have divergent dHash values (gradient patterns differ), so dual-hash consensus blocks them.
@@ -842,7 +949,7 @@ mod tests {
             make_photo(2, "bbb", Some(0b1111_1111)), // 4 bits different
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(
             groups.is_empty(),
             "Phase 3 should reject photos with phash distance 4"
@@ -858,7 +965,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0011), Some(0b0000_1111)), // phash=2, dhash=4
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(
             groups.is_empty(),
             "Dual-hash should reject when dhash exceeds threshold even if phash passes"
@@ -875,7 +982,7 @@ mod tests {
             make_photo_with_exif(3, "ccc", Some(u64::MAX), "2024-01-15 12:00:00", "Canon R5"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].member_ids.len(), 2, "Visually different member should be filtered");
         assert!(!groups[0].member_ids.contains(&3));
@@ -889,7 +996,7 @@ mod tests {
             make_photo(2, "bbb", None),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
@@ -907,14 +1014,16 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![2, 3],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1);
         assert_eq!(merged[0].member_ids.len(), 3);
         assert_eq!(merged[0].confidence, Confidence::High);
@@ -932,14 +1041,16 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![3, 4],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 2);
     }
 
@@ -952,20 +1063,20 @@ mod tests {
             make_photo(2, "bbb", Some(u64::MAX)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
     #[test]
     fn test_single_photo_no_group() {
         let photos = vec![make_photo(1, "aaa", Some(100))];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
     #[test]
     fn test_empty_input() {
-        let groups = find_duplicates(&[]);
+        let groups = find_duplicates(&[], &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
@@ -980,7 +1091,7 @@ mod tests {
         p1.sha256 = "aaa".to_string();
 
         let photos = vec![p1, p2, p3];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         // All three should end up in a single merged group
         assert_eq!(groups.len(), 1);
@@ -998,12 +1109,33 @@ mod tests {
         p2.format = PhotoFormat::Heic;
 
         let photos = vec![p1, p2];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 1, "JPEG+HEIC with same EXIF should group");
         assert_eq!(groups[0].member_ids.len(), 2);
     }
 
+    #[test]
+    fn test_live_photo_video_not_grouped_with_still() {
+        // A Live Photo's HEIC and MOV share date+camera but only identical MOVs group.
+        let mut still = make_photo_with_exif(1, "sha_heic", None, "2024-01-15 12:00:00", "iPhone 16 Pro");
+        still.format = PhotoFormat::Heic;
+        let mut mov1 = make_photo_with_exif(2, "sha_mov", None, "2024-01-15 12:00:00", "iPhone 16 Pro");
+        let mut mov2 = make_photo_with_exif(3, "sha_mov", None, "2024-01-15 12:00:00", "iPhone 16 Pro");
+        let mut other = make_photo_with_exif(4, "sha_mov_2", None, "2024-01-15 12:00:00", "iPhone 16 Pro");
+        for p in [&mut mov1, &mut mov2, &mut other] {
+            p.format = PhotoFormat::Mov;
+        }
+
+        let groups = find_duplicates(&[still, mov1, mov2, other], &MatchConfig::default(), &[]);
+
+        assert_eq!(groups.len(), 1);
+        let mut ids = groups[0].member_ids.clone();
+        ids.sort();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(groups[0].confidence, Confidence::Certain);
+    }
+
     #[test]
     fn test_cross_format_cross_directory_all_merge() {
         // Real-world scenario: IMG_3234.jpeg and IMG_3234.heic in both test/ and test2/.
@@ -1018,7 +1150,7 @@ mod tests {
         p4.format = PhotoFormat::Heic;
 
         let photos = vec![p1, p2, p3, p4];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 1, "All 4 files should merge into one group");
         assert_eq!(groups[0].member_ids.len(), 4);
@@ -1036,7 +1168,7 @@ mod tests {
             make_photo_with_exif(6, "sha_c_heic", None, "2024-01-12 12:00:00", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 3, "Should have 3 separate groups");
         for group in &groups {
@@ -1054,7 +1186,7 @@ mod tests {
             make_photo_with_exif(3, "ccc", None, "2024-01-15 12:00:00", "Canon R5"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].member_ids.len(), 3, "All EXIF-matching members should be kept");
     }
@@ -1074,18 +1206,21 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![2, 3],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![3, 4],
                 confidence: Confidence::NearCertain,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1, "Transitive chain should collapse to 1 group");
         assert_eq!(merged[0].member_ids.len(), 4);
         assert_eq!(merged[0].confidence, Confidence::High, "Worst confidence wins");
@@ -1103,18 +1238,21 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![3, 4],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![2, 3],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1, "Bridge group should merge the two disjoint groups");
         assert_eq!(merged[0].member_ids.len(), 4);
     }
@@ -1133,22 +1271,26 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![3, 4],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![5, 6],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![2, 4, 6],
                 confidence: Confidence::Probable,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1, "Single bridge touching all groups should merge everything");
         assert_eq!(merged[0].member_ids.len(), 6);
         assert_eq!(merged[0].confidence, Confidence::Probable);
@@ -1168,22 +1310,26 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![2, 3],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![10, 11],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![11, 12],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 2, "Two independent chains should stay separate");
     }
 
@@ -1200,14 +1346,16 @@ mod tests {
             MatchGroup {
                 member_ids: vec![1, 2],
                 confidence: Confidence::Certain,
+                evidence: Vec::new(),
             },
             MatchGroup {
                 member_ids: vec![2, 3],
                 confidence: Confidence::High,
+                evidence: Vec::new(),
             },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 2, "Visually unrelated groups should NOT merge");
     }
 
@@ -1217,84 +1365,84 @@ mod tests {
     fn test_is_sequential_shot_2_seconds_same_camera() {
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:45", "iPhone 16 Pro Max");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:47", "iPhone 16 Pro Max");
-        assert!(is_sequential_shot(&a, &b), "2s apart, same camera → sequential");
+        assert!(is_sequential_shot(&a, &b, &MatchConfig::default()), "2s apart, same camera → sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_identical_dates_not_sequential() {
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:45", "iPhone 16 Pro Max");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:45", "iPhone 16 Pro Max");
-        assert!(!is_sequential_shot(&a, &b), "Identical dates = true duplicate, not sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "Identical dates = true duplicate, not sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_different_cameras_not_sequential() {
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:45", "iPhone 16 Pro Max");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:47", "Canon R5");
-        assert!(!is_sequential_shot(&a, &b), "Different cameras → not sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "Different cameras → not sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_no_exif_a() {
         let a = make_photo(1, "a", Some(0));
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:47", "iPhone");
-        assert!(!is_sequential_shot(&a, &b), "No EXIF on A → not sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No EXIF on A → not sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_no_exif_b() {
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:45", "iPhone");
         let b = make_photo(2, "b", Some(0));
-        assert!(!is_sequential_shot(&a, &b), "No EXIF on B → not sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No EXIF on B → not sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_no_exif_both() {
         let a = make_photo(1, "a", Some(0));
         let b = make_photo(2, "b", Some(0));
-        assert!(!is_sequential_shot(&a, &b), "No EXIF on either → not sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No EXIF on either → not sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_no_camera_model_a() {
         let a = make_photo_exif_full(1, "a", Some(0), Some(0), Some("2024-12-24 20:43:45"), None);
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:47", "iPhone");
-        assert!(!is_sequential_shot(&a, &b), "No camera on A → can't confirm sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No camera on A → can't confirm sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_no_camera_model_both() {
         let a = make_photo_exif_full(1, "a", Some(0), Some(0), Some("2024-12-24 20:43:45"), None);
         let b = make_photo_exif_full(2, "b", Some(0), Some(0), Some("2024-12-24 20:43:47"), None);
-        assert!(!is_sequential_shot(&a, &b), "No camera on either → can't confirm sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No camera on either → can't confirm sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_no_date_a() {
         let a = make_photo_exif_full(1, "a", Some(0), Some(0), None, Some("iPhone"));
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:47", "iPhone");
-        assert!(!is_sequential_shot(&a, &b), "No date on A → can't determine");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No date on A → can't determine");
     }
 
     #[test]
     fn test_is_sequential_shot_no_date_both() {
         let a = make_photo_exif_full(1, "a", Some(0), Some(0), None, Some("iPhone"));
         let b = make_photo_exif_full(2, "b", Some(0), Some(0), None, Some("iPhone"));
-        assert!(!is_sequential_shot(&a, &b), "No dates on either → can't determine");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "No dates on either → can't determine");
     }
 
     #[test]
     fn test_is_sequential_shot_boundary_60s() {
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:00", "iPhone");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:44:00", "iPhone");
-        assert!(is_sequential_shot(&a, &b), "Exactly 60s → sequential");
+        assert!(is_sequential_shot(&a, &b, &MatchConfig::default()), "Exactly 60s → sequential");
     }
 
     #[test]
     fn test_is_sequential_shot_boundary_61s_not_sequential() {
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:00", "iPhone");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:44:01", "iPhone");
-        assert!(!is_sequential_shot(&a, &b), "61s apart → NOT sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "61s apart → NOT sequential");
     }
 
     #[test]
@@ -1302,7 +1450,7 @@ mod tests {
         // Date-only (no time component) → parse_exif_seconds returns None → not sequential
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24", "iPhone");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-25", "iPhone");
-        assert!(!is_sequential_shot(&a, &b), "Date-only EXIF can't determine seconds");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "Date-only EXIF can't determine seconds");
     }
 
     #[test]
@@ -1310,7 +1458,7 @@ mod tests {
         // 23:59:59 → 00:00:01 is 2 seconds apart. Formula handles midnight rollover correctly.
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 23:59:59", "iPhone");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-25 00:00:01", "iPhone");
-        assert!(is_sequential_shot(&a, &b), "Midnight boundary: 2s apart → sequential");
+        assert!(is_sequential_shot(&a, &b, &MatchConfig::default()), "Midnight boundary: 2s apart → sequential");
     }
 
     #[test]
@@ -1318,7 +1466,7 @@ mod tests {
         // Photos 24 hours apart → not sequential.
         let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 12:00:00", "iPhone");
         let b = make_photo_with_exif(2, "b", Some(0), "2024-12-25 12:00:00", "iPhone");
-        assert!(!is_sequential_shot(&a, &b), "24h apart → not sequential");
+        assert!(!is_sequential_shot(&a, &b, &MatchConfig::default()), "24h apart → not sequential");
     }
 
     // ── parse_exif_seconds unit tests ───────────────────────────────
@@ -1389,7 +1537,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:43:47", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(
             groups.is_empty(),
             "Sequential shots (2s apart, same camera) must NOT be grouped"
@@ -1405,7 +1553,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:43:45", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "True duplicates with identical dates should group");
     }
 
@@ -1417,7 +1565,7 @@ mod tests {
             make_photo(2, "bbb", Some(0xFF00)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Photos without EXIF should still group by hash");
     }
 
@@ -1429,7 +1577,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:43:47", "Canon R5"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Different cameras should not trigger sequential shot filter");
     }
 
@@ -1440,7 +1588,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:43:46", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Burst shots (1s apart) must NOT be grouped");
     }
 
@@ -1451,7 +1599,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:44:00", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Shots 60s apart on same camera must NOT be grouped");
     }
 
@@ -1463,7 +1611,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:44:01", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "61s apart → just outside window, should group");
     }
 
@@ -1475,10 +1623,53 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:45:00", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Photos >60s apart should still group by hash");
     }
 
+    #[test]
+    fn test_sequential_window_follows_config() {
+        let photos = vec![
+            make_photo_with_exif(1, "aaa", Some(0xFF00), "2024-12-24 20:43:00", "iPhone 16 Pro Max"),
+            make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:45:00", "iPhone 16 Pro Max"),
+        ];
+
+        let strict = MatchConfig::profile("strict").unwrap();
+        assert!(
+            find_duplicates(&photos, &strict, &[]).is_empty(),
+            "2 minutes apart is inside the strict window"
+        );
+
+        let scans = MatchConfig::profile("scans").unwrap();
+        let a = make_photo_with_exif(1, "a", Some(0), "2024-12-24 20:43:00", "Nikon LS-5000");
+        let b = make_photo_with_exif(2, "b", Some(0), "2024-12-24 20:43:02", "Nikon LS-5000");
+        assert!(!is_sequential_shot(&a, &b, &scans), "window 0 disables the filter");
+    }
+
+    #[test]
+    fn test_phase3_thresholds_follow_config() {
+        // 5 bits apart on both hashes: beyond the default threshold
+        let photos = vec![
+            make_photo(1, "aaa", Some(0xFF00)),
+            make_photo(2, "bbb", Some(0xFF00 ^ 0b11111)),
+        ];
+        assert!(find_duplicates(&photos, &MatchConfig::default(), &[]).is_empty());
+
+        let scans = MatchConfig::profile("scans").unwrap();
+        let groups = find_duplicates(&photos, &scans, &[]);
+        assert_eq!(groups.len(), 1, "Rescans differ by a few bits of scanner noise");
+        assert_eq!(groups[0].confidence, Confidence::High);
+
+        // 3 bits apart: Probable by default, rejected by the strict profile
+        let photos = vec![
+            make_photo(1, "aaa", Some(0xFF00)),
+            make_photo(2, "bbb", Some(0xFF00 ^ 0b111)),
+        ];
+        assert_eq!(find_duplicates(&photos, &MatchConfig::default(), &[]).len(), 1);
+        let strict = MatchConfig::profile("strict").unwrap();
+        assert!(find_duplicates(&photos, &strict, &[]).is_empty());
+    }
+
     #[test]
     fn test_phase3_accepts_one_photo_no_exif() {
         // One has EXIF (with camera), other has none → can't confirm sequential, allow.
@@ -1487,7 +1678,7 @@ mod tests {
             make_photo(2, "bbb", Some(0xFF00)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "One missing EXIF → can't determine sequential, allow grouping");
     }
 
@@ -1499,7 +1690,7 @@ mod tests {
             make_photo_exif_full(2, "bbb", Some(0xFF00), Some(0xFF00), Some("2024-12-24 20:43:47"), None),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "No camera model → can't confirm sequential, allow grouping");
     }
 
@@ -1511,7 +1702,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24", "iPhone 16 Pro Max"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Date-only EXIF should group (identical strings)");
     }
 
@@ -1526,7 +1717,7 @@ mod tests {
             make_photo_with_exif(5, "e", Some(0xFF00), "2024-12-24 20:43:04", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Burst of 5 sequential shots must NOT be grouped");
     }
 
@@ -1541,7 +1732,7 @@ mod tests {
             make_photo_with_exif(3, "ccc", Some(0xFF00), "2024-12-24 20:43:00", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         // Phase 2 groups {1,3} (same date+camera, phash validated).
         // Phase 3 checks photo 2 vs all: sequential with both → rejected.
         assert_eq!(groups.len(), 1, "Only the true duplicate pair should group");
@@ -1561,7 +1752,7 @@ mod tests {
             make_photo_with_exif(3, "sha_b", Some(0xFF00), "2024-12-24 20:43:02", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         // Photo 2 and 3: exact SHA match → Certain group.
         // Photo 1: ungrouped, Phase 3 finds hash match with 2 and 3, but sequential with both → rejected.
         assert_eq!(groups.len(), 1, "Only SHA duplicate pair should group");
@@ -1578,7 +1769,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0b1111_0011), "2024-12-24 20:43:47", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Sequential shots with phash distance 2 must NOT be grouped");
     }
 
@@ -1592,7 +1783,7 @@ mod tests {
             make_photo_exif_full(2, "bbb", None, None, Some("2024-01-15 12:00:00"), None),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Same date, no camera → should group under 'unknown'");
     }
 
@@ -1603,7 +1794,7 @@ mod tests {
         let b = make_photo_exif_full(2, "bbb", None, None, Some("2024-01-15 12:00:00"), None);
 
         let photos = vec![a, b];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Different camera keys should NOT group");
     }
 
@@ -1615,7 +1806,7 @@ mod tests {
             make_photo_exif_full(2, "bbb", None, None, None, Some("iPhone")),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "No EXIF date → can't group by EXIF");
     }
 
@@ -1641,7 +1832,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].member_ids.len(), 2, "Visually different photo rejected");
         assert!(!groups[0].member_ids.contains(&3));
@@ -1656,7 +1847,7 @@ mod tests {
         heic.format = PhotoFormat::Heic;
 
         let photos = vec![jpeg, heic];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].member_ids.len(), 2);
         assert_eq!(groups[0].confidence, Confidence::NearCertain,
@@ -1677,7 +1868,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].member_ids.len(), 3, "All three should be in one group");
         assert_eq!(groups[0].confidence, Confidence::High);
@@ -1695,7 +1886,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0001), None), // no dhash
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Cross-format phash dist 1 should group");
     }
 
@@ -1707,7 +1898,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0111), None), // phash dist 3, no dhash
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Cross-format phash dist 3 > HIGH → rejected");
     }
 
@@ -1719,7 +1910,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0011), None), // phash dist 2, no dhash
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Cross-format phash dist 2 = HIGH → accepted");
     }
 
@@ -1731,7 +1922,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0001), None), // phash dist 1
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Both dhash=None, phash dist 1 → should group");
     }
 
@@ -1742,7 +1933,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0b1111_0111), None), // phash dist 3
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Both dhash=None, phash dist 3 > HIGH → rejected");
     }
 
@@ -1754,7 +1945,7 @@ mod tests {
             make_photo_full(2, "bbb", Some(0xFF00), Some(0xAA00)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].confidence, Confidence::NearCertain);
     }
@@ -1769,7 +1960,7 @@ mod tests {
             make_photo(3, "different", Some(0xFF01)), // phash dist 1 from photo 1
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Bridge should merge all three");
         assert_eq!(groups[0].member_ids.len(), 3);
     }
@@ -1782,7 +1973,7 @@ mod tests {
             make_photo(2, "bbb", None),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty());
     }
 
@@ -1795,7 +1986,7 @@ mod tests {
             make_photo(2, "bbb", None),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Can't match when one has no phash");
     }
 
@@ -1810,11 +2001,11 @@ mod tests {
             make_photo(3, "c", Some(102)),
         ];
         let mut groups = vec![
-            MatchGroup { member_ids: vec![1, 2, 3], confidence: Confidence::High },
-            MatchGroup { member_ids: vec![1, 2], confidence: Confidence::Certain },
+            MatchGroup { member_ids: vec![1, 2, 3], confidence: Confidence::High, evidence: Vec::new() },
+            MatchGroup { member_ids: vec![1, 2], confidence: Confidence::Certain, evidence: Vec::new() },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1);
         assert_eq!(merged[0].member_ids.len(), 3);
     }
@@ -1829,12 +2020,12 @@ mod tests {
             make_photo(4, "d", Some(100)),
         ];
         let mut groups = vec![
-            MatchGroup { member_ids: vec![1, 2], confidence: Confidence::Certain },
-            MatchGroup { member_ids: vec![2, 3], confidence: Confidence::High },
-            MatchGroup { member_ids: vec![2, 4], confidence: Confidence::NearCertain },
+            MatchGroup { member_ids: vec![1, 2], confidence: Confidence::Certain, evidence: Vec::new() },
+            MatchGroup { member_ids: vec![2, 3], confidence: Confidence::High, evidence: Vec::new() },
+            MatchGroup { member_ids: vec![2, 4], confidence: Confidence::NearCertain, evidence: Vec::new() },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1, "Single bridge photo merges all");
         assert_eq!(merged[0].member_ids.len(), 4);
     }
@@ -1849,11 +2040,11 @@ mod tests {
             make_photo(3, "c", None),
         ];
         let mut groups = vec![
-            MatchGroup { member_ids: vec![1, 2], confidence: Confidence::Certain },
-            MatchGroup { member_ids: vec![2, 3], confidence: Confidence::High },
+            MatchGroup { member_ids: vec![1, 2], confidence: Confidence::Certain, evidence: Vec::new() },
+            MatchGroup { member_ids: vec![2, 3], confidence: Confidence::High, evidence: Vec::new() },
         ];
 
-        let merged = merge_overlapping(&mut groups, &photos);
+        let merged = merge_overlapping(&mut groups, &photos, &MatchConfig::default());
         assert_eq!(merged.len(), 1, "No phash on exclusive side → allow merge");
     }
 
@@ -1870,7 +2061,7 @@ mod tests {
         h2.format = PhotoFormat::Heic;
 
         let photos = vec![h1, h2];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Sequential HEICs have different dates → no EXIF group");
     }
 
@@ -1885,7 +2076,7 @@ mod tests {
         c.format = PhotoFormat::Heic;
 
         let photos = vec![a, b, c];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Only A+C should group");
         assert!(groups[0].member_ids.contains(&1));
         assert!(groups[0].member_ids.contains(&3));
@@ -1901,7 +2092,7 @@ mod tests {
         let c = make_photo_with_exif(3, "sha_c", Some(0xFF00), "2024-12-24 20:43:02", "iPhone");
 
         let photos = vec![a, b, c];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Only SHA group should exist");
         assert_eq!(groups[0].member_ids.len(), 2, "Sequential C must not join SHA group");
         assert!(!groups[0].member_ids.contains(&3));
@@ -1930,7 +2121,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "All 4 files should merge into one group");
         assert_eq!(groups[0].member_ids.len(), 4);
     }
@@ -1961,7 +2152,7 @@ mod tests {
         }
 
         assert_eq!(photos.len(), 12);
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 3, "Should have exactly 3 groups");
         for group in &groups {
             assert_eq!(group.member_ids.len(), 4, "Each group should have 4 members");
@@ -1993,7 +2184,7 @@ mod tests {
         };
 
         let photos = vec![original, export, heic];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Original+export+HEIC should all group");
         assert_eq!(groups[0].member_ids.len(), 3);
     }
@@ -2010,7 +2201,7 @@ mod tests {
         }
 
         assert_eq!(photos.len(), 20);
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 10, "Should have 10 separate groups");
         for group in &groups {
             assert_eq!(group.member_ids.len(), 2);
@@ -2034,7 +2225,7 @@ mod tests {
             make_photo_with_exif(6, "sha_4", Some(0xCC00), "2024-12-24 12:00:02", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 2, "Only 2 true duplicate groups");
         for group in &groups {
             assert_eq!(group.member_ids.len(), 2);
@@ -2052,7 +2243,7 @@ mod tests {
             make_photo_with_exif(2, "bbb", Some(0xFF00), "2024-12-24 20:43:45", "iPhone"),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Different days, same hash → should group (not sequential)");
     }
 
@@ -2074,7 +2265,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert!(groups.is_empty(), "Same scene, different composition → must NOT group");
     }
 
@@ -2094,7 +2285,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].confidence, Confidence::Certain);
     }
@@ -2116,7 +2307,7 @@ mod tests {
             },
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 1, "Recompressed JPEG should group by perceptual hash");
     }
 
@@ -2133,7 +2324,7 @@ mod tests {
             make_photo(4, "sha_b", Some(u64::MAX)),
         ];
 
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
         assert_eq!(groups.len(), 2, "Visually unrelated SHA groups must stay separate");
     }
 
@@ -2171,7 +2362,7 @@ mod tests {
         };
 
         let photos = vec![jpeg1, jpeg2, heic];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 1, "All 3 photos should be in one group");
         let ids: HashSet<i64> = groups[0].member_ids.iter().copied().collect();
@@ -2194,7 +2385,7 @@ mod tests {
         };
 
         let photos = vec![jpeg1, jpeg2, heic];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 1, "Only one group from JPEGs");
         let ids: HashSet<i64> = groups[0].member_ids.iter().copied().collect();
@@ -2214,7 +2405,7 @@ mod tests {
         };
 
         let photos = vec![jpeg1, jpeg2, heic];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 1, "Only one group from JPEGs");
         let ids: HashSet<i64> = groups[0].member_ids.iter().copied().collect();
@@ -2243,11 +2434,73 @@ mod tests {
         };
 
         let photos = vec![jpeg1, jpeg2, heic1, heic2];
-        let groups = find_duplicates(&photos);
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
 
         assert_eq!(groups.len(), 1, "All 4 photos should be in one group");
         let ids: HashSet<i64> = groups[0].member_ids.iter().copied().collect();
         assert!(ids.contains(&3), "First HEIC should be attached");
         assert!(ids.contains(&4), "Second HEIC should be attached");
     }
+
+    // ── Match evidence ─────────────────────────────────────────
+
+    #[test]
+    fn test_evidence_records_sha_and_exif_edges() {
+        let date = "2024-12-24 15:08:18";
+        let camera = "iPhone 16 Pro Max";
+        let photos = vec![
+            make_photo_with_exif(1, "aaa", Some(0xFF00), date, camera),
+            make_photo_with_exif(2, "aaa", Some(0xFF00), date, camera),
+            make_photo_with_exif(3, "bbb", Some(0xFF01), date, camera),
+        ];
+        let groups = find_duplicates(&photos, &MatchConfig::default(), &[]);
+        assert_eq!(groups.len(), 1);
+        let evidence = &groups[0].evidence;
+
+        assert!(evidence.iter().any(|e| e.phase == MatchPhase::Sha256
+            && [e.photo_a, e.photo_b].contains(&1)
+            && [e.photo_a, e.photo_b].contains(&2)));
+        let exif_edge = evidence
+            .iter()
+            .find(|e| e.phase == MatchPhase::Exif && e.photo_a == 1 && e.photo_b == 3)
+            .expect("validated EXIF pair recorded");
+        assert_eq!(exif_edge.phash_distance, Some(1));
+        assert_eq!(exif_edge.dhash_distance, Some(1));
+        assert_eq!(exif_edge.exif_key.as_deref(), Some("2024-12-24 15:08:18|iPhone 16 Pro Max"));
+    }
+
+    #[test]
+    fn test_evidence_records_perceptual_and_orphan_edges() {
+        let date = "2024-12-24 15:08:18";
+        let camera = "iPhone 16 Pro Max";
+        let jpeg1 = {
+            let mut p = make_photo_with_exif(1, "sha_j1", Some(0b1111_0000), date, camera);
+            p.dhash = Some(0b1010_0000);
+            p
+        };
+        let jpeg2 = {
+            let mut p = make_photo_with_exif(2, "sha_j2", Some(0b1111_0001), date, camera);
+            p.dhash = Some(0b1010_0111);
+            p
+        };
+        let heic = {
+            let mut p = make_photo_with_exif(3, "sha_heic", None, date, camera);
+            p.dhash = None;
+            p.format = PhotoFormat::Heic;
+            p
+        };
+
+        let groups = find_duplicates(&[jpeg1, jpeg2, heic], &MatchConfig::default(), &[]);
+        assert_eq!(groups.len(), 1);
+        let evidence = &groups[0].evidence;
+        assert_eq!(evidence.len(), 2, "{evidence:?}");
+
+        let perceptual = evidence.iter().find(|e| e.phase == MatchPhase::Perceptual).unwrap();
+        assert_eq!((perceptual.phash_distance, perceptual.dhash_distance), (Some(1), Some(3)));
+
+        let orphan = evidence.iter().find(|e| e.phase == MatchPhase::Orphan).unwrap();
+        assert_eq!(orphan.photo_b, 3);
+        assert_eq!(orphan.phash_distance, None);
+        assert!(orphan.exif_key.is_some());
+    }
 }
@@ -0,0 +1,277 @@
+//! Manual match overrides, applied after the automatic phases.
+//!
+//! Overrides are keyed by SHA-256, so they apply to every copy of a file and
+//! survive moves and rescans. Always-match pairs are merged first; never-match
+//! pairs then split any group holding both sides, following the group's
+//! evidence graph so each member stays with the side it was matched to.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::domain::{Confidence, MatchOverride, MatchPhase, OverrideKind, PhotoFile};
+
+use super::{edge, MatchGroup};
+
+/// Apply `overrides` to the pipeline's groups.
+pub fn apply_overrides(
+    mut groups: Vec<MatchGroup>,
+    photos: &[PhotoFile],
+    overrides: &[MatchOverride],
+) -> Vec<MatchGroup> {
+    if overrides.is_empty() {
+        return groups;
+    }
+
+    let mut by_sha: HashMap<&str, Vec<&PhotoFile>> = HashMap::new();
+    for photo in photos {
+        by_sha.entry(photo.sha256.as_str()).or_default().push(photo);
+    }
+    let sha_of: HashMap<i64, &str> = photos.iter().map(|p| (p.id, p.sha256.as_str())).collect();
+
+    for o in overrides.iter().filter(|o| o.kind == OverrideKind::AlwaysMatch) {
+        let side_a = by_sha.get(o.sha_a.as_str());
+        let side_b = by_sha.get(o.sha_b.as_str());
+        let (Some(side_a), Some(side_b)) = (side_a, side_b) else {
+            continue;
+        };
+        groups = force_merge(groups, side_a, side_b);
+    }
+
+    let never: HashSet<(&str, &str)> = overrides
+        .iter()
+        .filter(|o| o.kind == OverrideKind::NeverMatch)
+        .map(|o| (o.sha_a.as_str(), o.sha_b.as_str()))
+        .collect();
+    if never.is_empty() {
+        return groups;
+    }
+
+    // Split until no group holds both sides of a never-match pair. Each split
+    // separates one pair, so this ends after at most one round per pair.
+    let mut done = Vec::new();
+    while let Some(group) = groups.pop() {
+        let shas: HashSet<&str> = group
+            .member_ids
+            .iter()
+            .filter_map(|id| sha_of.get(id).copied())
+            .collect();
+        let conflict = never.iter().find(|(a, b)| shas.contains(a) && shas.contains(b));
+        match conflict {
+            Some(&(a, b)) => groups.extend(split(group, a, b, &sha_of, &never)),
+            None => done.push(group),
+        }
+    }
+    done.reverse();
+    done
+}
+
+/// Put every copy of both sides into one group, merging the groups they are in.
+fn force_merge(
+    groups: Vec<MatchGroup>,
+    side_a: &[&PhotoFile],
+    side_b: &[&PhotoFile],
+) -> Vec<MatchGroup> {
+    let ids: HashSet<i64> = side_a.iter().chain(side_b).map(|p| p.id).collect();
+    let (touched, mut rest): (Vec<MatchGroup>, Vec<MatchGroup>) = groups
+        .into_iter()
+        .partition(|g| g.member_ids.iter().any(|id| ids.contains(id)));
+
+    let mut member_ids: Vec<i64> = Vec::new();
+    let mut evidence = vec![edge(MatchPhase::Manual, side_a[0], side_b[0], None)];
+    // A manual decision stands for a confirmed duplicate on its own
+    let mut confidence = Confidence::NearCertain;
+    for group in touched {
+        member_ids.extend(group.member_ids);
+        evidence.extend(group.evidence);
+        confidence = confidence.min(group.confidence);
+    }
+    for p in side_a.iter().chain(side_b) {
+        if !member_ids.contains(&p.id) {
+            member_ids.push(p.id);
+        }
+    }
+    // Copies of one side that were not grouped yet
+    for copies in [side_a, side_b] {
+        for p in &copies[1..] {
+            if !evidence.iter().any(|e| e.photo_b == p.id || e.photo_a == p.id) {
+                evidence.push(edge(MatchPhase::Sha256, copies[0], p, None));
+            }
+        }
+    }
+
+    rest.push(MatchGroup {
+        member_ids,
+        confidence,
+        evidence,
+    });
+    rest
+}
+
+/// Split `group` between the members with hash `sha_a` and those with `sha_b`.
+/// Every other member joins the side it is closest to in the evidence graph
+/// (ties and unconnected members go to `sha_a`). Edges between never-match
+/// pairs are ignored. Parts with fewer than two members are dropped.
+fn split(
+    group: MatchGroup,
+    sha_a: &str,
+    sha_b: &str,
+    sha_of: &HashMap<i64, &str>,
+    never: &HashSet<(&str, &str)>,
+) -> Vec<MatchGroup> {
+    let sha = |id: &i64| sha_of.get(id).copied().unwrap_or_default();
+    let forbidden = |a: &i64, b: &i64| {
+        let (x, y) = (sha(a), sha(b));
+        never.contains(&(x.min(y), x.max(y)))
+    };
+
+    let mut adjacency: HashMap<i64, Vec<i64>> = HashMap::new();
+    for e in &group.evidence {
+        if !forbidden(&e.photo_a, &e.photo_b) {
+            adjacency.entry(e.photo_a).or_default().push(e.photo_b);
+            adjacency.entry(e.photo_b).or_default().push(e.photo_a);
+        }
+    }
+
+    // Multi-source BFS: side 0 from the `sha_a` copies, side 1 from `sha_b`
+    let mut side: HashMap<i64, usize> = HashMap::new();
+    let mut queue: VecDeque<i64> = VecDeque::new();
+    for (label, seed) in [(0, sha_a), (1, sha_b)] {
+        for id in group.member_ids.iter().filter(|id| sha(id) == seed) {
+            side.insert(*id, label);
+            queue.push_back(*id);
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        let label = side[&id];
+        for next in adjacency.get(&id).into_iter().flatten() {
+            if group.member_ids.contains(next) && !side.contains_key(next) {
+                side.insert(*next, label);
+                queue.push_back(*next);
+            }
+        }
+    }
+
+    (0..2)
+        .filter_map(|label| {
+            let members: Vec<i64> = group
+                .member_ids
+                .iter()
+                .copied()
+                .filter(|id| side.get(id).copied().unwrap_or(0) == label)
+                .collect();
+            if members.len() < 2 {
+                return None;
+            }
+            let evidence = group
+                .evidence
+                .iter()
+                .filter(|e| members.contains(&e.photo_a) && members.contains(&e.photo_b))
+                .cloned()
+                .collect();
+            Some(MatchGroup {
+                member_ids: members,
+                confidence: group.confidence,
+                evidence,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::PhotoFormat;
+    use std::path::PathBuf;
+
+    fn photo(id: i64, sha: &str) -> PhotoFile {
+        PhotoFile {
+            id,
+            source_id: 1,
+            path: PathBuf::from(format!("/photos/{id}.jpg")),
+            size: 1000,
+            format: PhotoFormat::Jpeg,
+            sha256: sha.to_string(),
+            phash: Some(0),
+            dhash: Some(0),
+            exif: None,
+            mtime: 0,
+        }
+    }
+
+    fn chain(ids: &[i64], photos: &[PhotoFile]) -> MatchGroup {
+        let by_id: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
+        MatchGroup {
+            member_ids: ids.to_vec(),
+            confidence: Confidence::Probable,
+            evidence: ids
+                .windows(2)
+                .map(|w| edge(MatchPhase::Perceptual, by_id[&w[0]], by_id[&w[1]], None))
+                .collect(),
+        }
+    }
+
+    fn sorted(groups: &[MatchGroup]) -> Vec<Vec<i64>> {
+        let mut out: Vec<Vec<i64>> = groups
+            .iter()
+            .map(|g| {
+                let mut ids = g.member_ids.clone();
+                ids.sort();
+                ids
+            })
+            .collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn test_never_match_splits_along_evidence() {
+        // 1 - 2 - 3 - 4: splitting 1 from 4 keeps 2 with 1 and 3 with 4
+        let photos: Vec<PhotoFile> = (1..=4).map(|id| photo(id, &format!("s{id}"))).collect();
+        let groups = vec![chain(&[1, 2, 3, 4], &photos)];
+        let overrides = [MatchOverride::new("s4", "s1", OverrideKind::NeverMatch)];
+
+        let result = apply_overrides(groups, &photos, &overrides);
+        assert_eq!(sorted(&result), vec![vec![1, 2], vec![3, 4]]);
+        assert!(result.iter().all(|g| g.evidence.len() == 1));
+    }
+
+    #[test]
+    fn test_never_match_drops_singletons_and_covers_copies() {
+        // Two copies of s1 and one s2: the s2 side is a singleton and is dropped
+        let photos = vec![photo(1, "s1"), photo(2, "s1"), photo(3, "s2")];
+        let groups = vec![chain(&[1, 2, 3], &photos)];
+        let overrides = [MatchOverride::new("s1", "s2", OverrideKind::NeverMatch)];
+
+        let result = apply_overrides(groups, &photos, &overrides);
+        assert_eq!(sorted(&result), vec![vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_always_match_merges_groups_and_ungrouped() {
+        let photos: Vec<PhotoFile> = (1..=5).map(|id| photo(id, &format!("s{id}"))).collect();
+        let groups = vec![chain(&[1, 2], &photos), chain(&[3, 4], &photos)];
+        let overrides = [
+            MatchOverride::new("s2", "s3", OverrideKind::AlwaysMatch),
+            MatchOverride::new("s5", "s1", OverrideKind::AlwaysMatch),
+        ];
+
+        let result = apply_overrides(groups, &photos, &overrides);
+        assert_eq!(sorted(&result), vec![vec![1, 2, 3, 4, 5]]);
+        assert_eq!(result[0].confidence, Confidence::Probable);
+        let manual = result[0]
+            .evidence
+            .iter()
+            .filter(|e| e.phase == MatchPhase::Manual)
+            .count();
+        assert_eq!(manual, 2);
+    }
+
+    #[test]
+    fn test_always_match_creates_group() {
+        let photos = vec![photo(1, "s1"), photo(2, "s2"), photo(3, "s3")];
+        let overrides = [MatchOverride::new("s1", "s2", OverrideKind::AlwaysMatch)];
+
+        let result = apply_overrides(Vec::new(), &photos, &overrides);
+        assert_eq!(sorted(&result), vec![vec![1, 2]]);
+        assert_eq!(result[0].confidence, Confidence::NearCertain);
+    }
+}
@@ -0,0 +1,172 @@
+//! Dry-run plans: what a mutating operation would do, computed without
+//! touching the pack, the export folder or the sources.
+//!
+//! Each `Vault::plan_*` method mirrors the selection and skip rules of the
+//! operation it previews, so the plan lists the same copies, conversions,
+//! removals and moves the real run would make against the current catalog.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// One file-level effect of an operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlannedAction {
+    /// `source` will be copied to `target` (pack).
+    Copy {
+        source: PathBuf,
+        target: PathBuf,
+        bytes: u64,
+    },
+    /// `source` will be converted to `target` (export). `bytes` is the source size;
+    /// the encoded size is only known after conversion.
+    Convert {
+        source: PathBuf,
+        target: PathBuf,
+        bytes: u64,
+    },
+    /// `path` is already up to date and will be left alone.
+    Skip { path: PathBuf },
+    /// A pack file no longer wanted will be moved to the trash.
+    Remove { path: PathBuf, bytes: u64 },
+    /// A trashed pack file is wanted again and will be put back.
+    Untrash { path: PathBuf },
+    /// A trashed pack file past the retention window will be deleted for good.
+    Purge { path: PathBuf, bytes: u64 },
+    /// A source file will be moved out to `target` (quarantine).
+    Move {
+        source: PathBuf,
+        target: PathBuf,
+        bytes: u64,
+    },
+    /// A copy will be replaced by a link to `keeper` (in-place dedupe).
+    /// Both files are re-hashed when the link is made.
+    Link {
+        path: PathBuf,
+        keeper: PathBuf,
+        bytes: u64,
+    },
+}
+
+/// Counts and byte totals of a [`Plan`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PlanSummary {
+    pub copies: usize,
+    pub conversions: usize,
+    pub skips: usize,
+    pub removals: usize,
+    pub untrashed: usize,
+    pub purges: usize,
+    pub moves: usize,
+    pub links: usize,
+    /// Bytes the operation will write (copies, plus sources of conversions).
+    pub bytes_to_write: u64,
+    /// Bytes freed where they are today (removals, purges, moves out of
+    /// sources, links).
+    pub bytes_freed: u64,
+}
+
+/// The effects of an operation, in the order it would apply them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    pub actions: Vec<PlannedAction>,
+}
+
+impl Plan {
+    pub fn summary(&self) -> PlanSummary {
+        let mut summary = PlanSummary::default();
+        for action in &self.actions {
+            match action {
+                PlannedAction::Copy { bytes, .. } => {
+                    summary.copies += 1;
+                    summary.bytes_to_write += bytes;
+                }
+                PlannedAction::Convert { bytes, .. } => {
+                    summary.conversions += 1;
+                    summary.bytes_to_write += bytes;
+                }
+                PlannedAction::Skip { .. } => summary.skips += 1,
+                PlannedAction::Remove { bytes, .. } => {
+                    summary.removals += 1;
+                    summary.bytes_freed += bytes;
+                }
+                PlannedAction::Untrash { .. } => summary.untrashed += 1,
+                PlannedAction::Purge { bytes, .. } => {
+                    summary.purges += 1;
+                    summary.bytes_freed += bytes;
+                }
+                PlannedAction::Move { bytes, .. } => {
+                    summary.moves += 1;
+                    summary.bytes_freed += bytes;
+                }
+                PlannedAction::Link { bytes, .. } => {
+                    summary.links += 1;
+                    summary.bytes_freed += bytes;
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions
+            .iter()
+            .all(|a| matches!(a, PlannedAction::Skip { .. }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_counts_actions_and_bytes() {
+        let plan = Plan {
+            actions: vec![
+                PlannedAction::Copy {
+                    source: "/a.jpg".into(),
+                    target: "/pack/ab/ab.jpg".into(),
+                    bytes: 100,
+                },
+                PlannedAction::Skip {
+                    path: "/b.jpg".into(),
+                },
+                PlannedAction::Remove {
+                    path: "/pack/cd/cd.jpg".into(),
+                    bytes: 40,
+                },
+                PlannedAction::Link {
+                    path: "/c.jpg".into(),
+                    keeper: "/a.jpg".into(),
+                    bytes: 100,
+                },
+                PlannedAction::Purge {
+                    path: "/pack/.photopack/trash/ef/ef.jpg".into(),
+                    bytes: 10,
+                },
+            ],
+        };
+
+        let summary = plan.summary();
+        assert_eq!(summary.copies, 1);
+        assert_eq!(summary.skips, 1);
+        assert_eq!(summary.removals, 1);
+        assert_eq!(summary.links, 1);
+        assert_eq!(summary.purges, 1);
+        assert_eq!(summary.bytes_to_write, 100);
+        assert_eq!(summary.bytes_freed, 150);
+        assert!(!plan.is_empty());
+    }
+
+    #[test]
+    fn test_plan_of_skips_only_is_empty() {
+        let plan = Plan {
+            actions: vec![PlannedAction::Skip {
+                path: "/b.jpg".into(),
+            }],
+        };
+        assert!(plan.is_empty());
+        assert!(Plan::default().is_empty());
+    }
+}
@@ -0,0 +1,812 @@
+//! Filter language for `ls --where` and selective pack and export.
+//!
+//! ```text
+//! camera_model = 'ILCE-7M3' and date >= 2023-01 and format in (raw) and role = duplicate
+//! ```
+//!
+//! A [`Filter`] is parsed and compiled once into a SQL condition over
+//! `photos p`; `group`, `role` and `confidence` are read from `group_members`
+//! and `duplicate_groups` through subqueries. Values are always bound as
+//! parameters, never spliced into the SQL.
+//!
+//! Comparisons are `field op value` with `=`, `!=`, `<`, `<=`, `>`, `>=`, `~`
+//! (glob, `*` and `?`) or `field [not] in (v1, v2, …)`, combined with `and`,
+//! `or`, `not` and parentheses. Values are bare words (`2023-01`, `raw`) or
+//! quoted strings (`'ILCE-7M3'`).
+
+use rusqlite::types::Value;
+
+use crate::domain::{Confidence, FormatFamily, PhotoFormat};
+use crate::error::{Error, Result};
+
+/// Normalized `YYYY-MM-DD` date: EXIF date, falling back to mtime like the pack layout.
+const DATE_SQL: &str =
+    "COALESCE(replace(substr(p.exif_date, 1, 10), ':', '-'), date(p.mtime, 'unixepoch'))";
+
+/// Role of a photo in its duplicate group, as shown by `ls`.
+const ROLE_SQL: &str = "(CASE \
+     WHEN EXISTS (SELECT 1 FROM duplicate_groups g WHERE g.source_of_truth_id = p.id) \
+     THEN 'best_copy' \
+     WHEN EXISTS (SELECT 1 FROM group_members m WHERE m.photo_id = p.id) THEN 'duplicate' \
+     ELSE 'unique' END)";
+
+/// Confidence of the photo's group as its [`Confidence`] rank (`low` = 0 … `certain` = 4).
+const CONFIDENCE_SQL: &str = "(SELECT CASE g.confidence \
+     WHEN 'Low' THEN 0 WHEN 'Probable' THEN 1 WHEN 'High' THEN 2 \
+     WHEN 'Near-Certain' THEN 3 WHEN 'Certain' THEN 4 END \
+     FROM group_members m JOIN duplicate_groups g ON g.id = m.group_id WHERE m.photo_id = p.id)";
+
+/// Roles accepted by `role = …`.
+const ROLES: [&str; 3] = ["best_copy", "duplicate", "unique"];
+
+/// A photo attribute that can be filtered and sorted on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Path,
+    /// Path of the photo's source directory.
+    Source,
+    Format,
+    Size,
+    /// EXIF date, or the file's mtime when it has none.
+    Date,
+    CameraMake,
+    CameraModel,
+    Width,
+    Height,
+    /// Duplicate group ID.
+    Group,
+    /// `best_copy`, `duplicate` or `unique`.
+    Role,
+    /// Confidence of the photo's duplicate group.
+    Confidence,
+    Sha256,
+}
+
+impl Field {
+    pub const NAMES: &'static [&'static str] = &[
+        "path",
+        "source",
+        "format",
+        "size",
+        "date",
+        "camera_make",
+        "camera_model",
+        "width",
+        "height",
+        "group",
+        "role",
+        "confidence",
+        "sha256",
+    ];
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "path" => Ok(Self::Path),
+            "source" => Ok(Self::Source),
+            "format" => Ok(Self::Format),
+            "size" => Ok(Self::Size),
+            "date" => Ok(Self::Date),
+            "camera_make" => Ok(Self::CameraMake),
+            "camera_model" => Ok(Self::CameraModel),
+            "width" => Ok(Self::Width),
+            "height" => Ok(Self::Height),
+            "group" => Ok(Self::Group),
+            "role" => Ok(Self::Role),
+            "confidence" => Ok(Self::Confidence),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(Error::InvalidQuery(format!(
+                "unknown field '{s}' (expected {})",
+                Self::NAMES.join(", ")
+            ))),
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Path => "p.path",
+            Self::Source => "(SELECT s.path FROM sources s WHERE s.id = p.source_id)",
+            Self::Format => "p.format",
+            Self::Size => "p.size",
+            Self::Date => DATE_SQL,
+            Self::CameraMake => "p.exif_camera_make",
+            Self::CameraModel => "p.exif_camera_model",
+            Self::Width => "p.exif_width",
+            Self::Height => "p.exif_height",
+            Self::Group => "(SELECT m.group_id FROM group_members m WHERE m.photo_id = p.id)",
+            Self::Role => ROLE_SQL,
+            Self::Confidence => CONFIDENCE_SQL,
+            Self::Sha256 => "p.sha256",
+        }
+    }
+
+    /// Whether values compare as free text (case-insensitive, `~` allowed).
+    fn is_text(self) -> bool {
+        matches!(
+            self,
+            Self::Path | Self::Source | Self::CameraMake | Self::CameraModel | Self::Sha256
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+}
+
+impl Op {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Glob => "GLOB",
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        matches!(self, Self::Lt | Self::Le | Self::Gt | Self::Ge)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    LParen,
+    RParen,
+    Comma,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        field: Field,
+        op: Op,
+        value: String,
+    },
+    In {
+        field: Field,
+        values: Vec<String>,
+        negated: bool,
+    },
+}
+
+/// A parsed `--where` expression, compiled to a SQL condition over `photos p`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    sql: String,
+    params: Vec<Value>,
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(Error::InvalidQuery(format!(
+                "unexpected {} after a complete expression",
+                describe(token)
+            )));
+        }
+        let mut params = Vec::new();
+        let sql = compile(&expr, &mut params)?;
+        Ok(Self {
+            source: s.trim().to_string(),
+            sql,
+            params,
+        })
+    }
+
+    /// The SQL condition, with one `?` per parameter.
+    pub(crate) fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub(crate) fn params(&self) -> &[Value] {
+        &self.params
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// One `--sort` key: `field` or `field:desc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: Field,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (name, direction) = s.trim().split_once(':').unwrap_or((s.trim(), "asc"));
+        let descending = match direction.to_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            _ => {
+                return Err(Error::InvalidQuery(format!(
+                    "unknown sort direction '{direction}' (expected asc or desc)"
+                )))
+            }
+        };
+        Ok(Self {
+            field: Field::parse(name)?,
+            descending,
+        })
+    }
+}
+
+/// Which photos `ls` lists: an optional filter, sort keys (path by default) and a limit.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub filter: Option<Filter>,
+    pub sort: Vec<SortKey>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// `WHERE … ORDER BY … LIMIT …` clauses and their parameters.
+    pub(crate) fn sql(&self) -> (String, Vec<Value>) {
+        let mut sql = String::new();
+        let mut params = Vec::new();
+        if let Some(filter) = &self.filter {
+            sql.push_str(&format!(" WHERE {}", filter.sql()));
+            params.extend_from_slice(filter.params());
+        }
+        let mut order: Vec<String> = self
+            .sort
+            .iter()
+            .map(|key| {
+                let direction = if key.descending { "DESC" } else { "ASC" };
+                format!("{} {direction}", key.field.sql())
+            })
+            .collect();
+        order.push("p.path ASC".to_string());
+        sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        if let Some(limit) = self.limit {
+            sql.push_str(" LIMIT ?");
+            params.push(Value::Integer(limit as i64));
+        }
+        (sql, params)
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                });
+            }
+            '=' | '~' => {
+                chars.next();
+                tokens.push(Token::Op(if c == '=' { Op::Eq } else { Op::Glob }));
+            }
+            '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                tokens.push(Token::Op(match (c, eq) {
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return Err(Error::InvalidQuery("expected '!='".to_string())),
+                }));
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote stands for itself
+                        Some(q) if q == c && chars.next_if_eq(&c).is_some() => value.push(c),
+                        Some(q) if q == c => break,
+                        Some(other) => value.push(other),
+                        None => {
+                            return Err(Error::InvalidQuery(format!(
+                                "unterminated string {c}{value}"
+                            )))
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !"()=,~!<>'\"".contains(*c))
+                {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(w) => format!("'{w}'"),
+        Token::Quoted(q) => format!("'{q}'"),
+        Token::Op(op) => format!("'{}'", if *op == Op::Glob { "~" } else { op.sql() }),
+        Token::LParen => "'('".to_string(),
+        Token::RParen => "')'".to_string(),
+        Token::Comma => "','".to_string(),
+    }
+}
+
+/// Recursive-descent parser: `or` binds loosest, then `and`, then `not`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(Error::InvalidQuery(format!(
+                "expected {}, found {}",
+                describe(&expected),
+                describe(&token)
+            ))),
+            None => Err(Error::InvalidQuery(format!(
+                "expected {} at end of query",
+                describe(&expected)
+            ))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Word(name)) => {
+                let field = Field::parse(&name)?;
+                let negated = self.keyword("not");
+                if self.keyword("in") {
+                    return Ok(Expr::In {
+                        field,
+                        values: self.list()?,
+                        negated,
+                    });
+                }
+                if negated {
+                    return Err(Error::InvalidQuery(format!("expected 'in' after '{name} not'")));
+                }
+                match self.next() {
+                    Some(Token::Op(op)) => Ok(Expr::Compare {
+                        field,
+                        op,
+                        value: self.value()?,
+                    }),
+                    Some(token) => Err(Error::InvalidQuery(format!(
+                        "expected an operator after '{name}', found {}",
+                        describe(&token)
+                    ))),
+                    None => Err(Error::InvalidQuery(format!(
+                        "expected an operator after '{name}'"
+                    ))),
+                }
+            }
+            Some(token) => Err(Error::InvalidQuery(format!(
+                "expected a field name, found {}",
+                describe(&token)
+            ))),
+            None => Err(Error::InvalidQuery("expected a field name".to_string())),
+        }
+    }
+
+    fn value(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(w)) | Some(Token::Quoted(w)) => Ok(w),
+            Some(token) => Err(Error::InvalidQuery(format!(
+                "expected a value, found {}",
+                describe(&token)
+            ))),
+            None => Err(Error::InvalidQuery("expected a value at end of query".to_string())),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<String>> {
+        self.expect(Token::LParen)?;
+        let mut values = vec![self.value()?];
+        while matches!(self.peek(), Some(Token::Comma)) {
+            self.pos += 1;
+            values.push(self.value()?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(values)
+    }
+}
+
+fn compile(expr: &Expr, params: &mut Vec<Value>) -> Result<String> {
+    Ok(match expr {
+        Expr::And(a, b) => format!("({} AND {})", compile(a, params)?, compile(b, params)?),
+        Expr::Or(a, b) => format!("({} OR {})", compile(a, params)?, compile(b, params)?),
+        Expr::Not(e) => format!("(NOT {})", compile(e, params)?),
+        Expr::Compare { field, op, value } => compile_compare(*field, *op, value, params)?,
+        Expr::In {
+            field,
+            values,
+            negated,
+        } => compile_in(*field, values, *negated, params)?,
+    })
+}
+
+fn compile_compare(field: Field, op: Op, value: &str, params: &mut Vec<Value>) -> Result<String> {
+    if op == Op::Glob && !field.is_text() {
+        return Err(Error::InvalidQuery(format!(
+            "'~' only applies to path, source, camera_make, camera_model and sha256, not '{}'",
+            field_name(field)
+        )));
+    }
+    match field {
+        Field::Format | Field::Role => {
+            if op.is_ordering() {
+                return Err(Error::InvalidQuery(format!(
+                    "'{}' only supports =, != and in",
+                    field_name(field)
+                )));
+            }
+            compile_in(field, &[value.to_string()], op == Op::Ne, params)
+        }
+        Field::Date => {
+            let date = parse_date(value)?;
+            let sql = format!("substr({DATE_SQL}, 1, {}) {} ?", date.len(), op.sql());
+            params.push(Value::Text(date));
+            Ok(sql)
+        }
+        Field::Size | Field::Width | Field::Height | Field::Group => {
+            params.push(Value::Integer(parse_number(field, value)?));
+            Ok(format!("{} {} ?", field.sql(), op.sql()))
+        }
+        Field::Confidence => {
+            params.push(Value::Integer(parse_confidence(value)?));
+            Ok(format!("{} {} ?", field.sql(), op.sql()))
+        }
+        Field::Path | Field::Source | Field::CameraMake | Field::CameraModel | Field::Sha256 => {
+            params.push(Value::Text(value.to_string()));
+            if op == Op::Glob {
+                Ok(format!("{} GLOB ?", field.sql()))
+            } else {
+                Ok(format!("{} {} ? COLLATE NOCASE", field.sql(), op.sql()))
+            }
+        }
+    }
+}
+
+fn compile_in(
+    field: Field,
+    values: &[String],
+    negated: bool,
+    params: &mut Vec<Value>,
+) -> Result<String> {
+    let mut resolved: Vec<Value> = Vec::new();
+    for value in values {
+        match field {
+            Field::Format => resolved.extend(parse_formats(value)?.into_iter().map(Value::Text)),
+            Field::Role => resolved.push(Value::Text(parse_role(value)?)),
+            Field::Date => {
+                return Err(Error::InvalidQuery(
+                    "'date' does not support in; use >= and <".to_string(),
+                ))
+            }
+            Field::Size | Field::Width | Field::Height | Field::Group => {
+                resolved.push(Value::Integer(parse_number(field, value)?))
+            }
+            Field::Confidence => resolved.push(Value::Integer(parse_confidence(value)?)),
+            _ => resolved.push(Value::Text(value.to_string())),
+        }
+    }
+    let placeholders = vec!["?"; resolved.len()].join(", ");
+    let collate = if field.is_text() { " COLLATE NOCASE" } else { "" };
+    let not = if negated { "NOT " } else { "" };
+    params.extend(resolved);
+    Ok(format!("{}{collate} {not}IN ({placeholders})", field.sql()))
+}
+
+fn field_name(field: Field) -> &'static str {
+    match field {
+        Field::Path => "path",
+        Field::Source => "source",
+        Field::Format => "format",
+        Field::Size => "size",
+        Field::Date => "date",
+        Field::CameraMake => "camera_make",
+        Field::CameraModel => "camera_model",
+        Field::Width => "width",
+        Field::Height => "height",
+        Field::Group => "group",
+        Field::Role => "role",
+        Field::Confidence => "confidence",
+        Field::Sha256 => "sha256",
+    }
+}
+
+/// A format name (`jpeg`, `jpg`, `CR3`) or family (`raw`, `lossless`, `lossy`, `video`),
+/// as the catalog's format strings.
+fn parse_formats(value: &str) -> Result<Vec<String>> {
+    if let Some(family) = FormatFamily::parse(value) {
+        return Ok(PhotoFormat::ALL
+            .iter()
+            .filter(|f| f.family() == family)
+            .map(|f| f.as_str().to_string())
+            .collect());
+    }
+    PhotoFormat::ALL
+        .iter()
+        .find(|f| {
+            f.as_str().eq_ignore_ascii_case(value) || f.extension().eq_ignore_ascii_case(value)
+        })
+        .map(|f| vec![f.as_str().to_string()])
+        .ok_or_else(|| {
+            Error::InvalidQuery(format!(
+                "unknown format '{value}' (expected a format such as jpeg or cr3, \
+                 or raw, lossless, lossy, video)"
+            ))
+        })
+}
+
+fn parse_role(value: &str) -> Result<String> {
+    let role = value.to_lowercase();
+    if ROLES.contains(&role.as_str()) {
+        Ok(role)
+    } else {
+        Err(Error::InvalidQuery(format!(
+            "unknown role '{value}' (expected {})",
+            ROLES.join(", ")
+        )))
+    }
+}
+
+fn parse_confidence(value: &str) -> Result<i64> {
+    Confidence::parse(value)
+        .map(|c| c as i64)
+        .ok_or_else(|| Error::UnknownConfidence(value.to_string()))
+}
+
+/// `YYYY`, `YYYY-MM` or `YYYY-MM-DD` (`:` separators accepted, as in EXIF).
+fn parse_date(value: &str) -> Result<String> {
+    let date = value.replace(':', "-");
+    let valid = matches!(date.len(), 4 | 7 | 10)
+        && date.chars().enumerate().all(|(i, c)| {
+            if i == 4 || i == 7 {
+                c == '-'
+            } else {
+                c.is_ascii_digit()
+            }
+        });
+    if valid {
+        Ok(date)
+    } else {
+        Err(Error::InvalidQuery(format!(
+            "invalid date '{value}' (expected YYYY, YYYY-MM or YYYY-MM-DD)"
+        )))
+    }
+}
+
+/// An integer; `size` also takes `B`, `KB`, `MB`, `GB` or `TB` suffixes (1 KB = 1024 bytes).
+fn parse_number(field: Field, value: &str) -> Result<i64> {
+    let invalid = || Error::InvalidQuery(format!("invalid {} '{value}'", field_name(field)));
+    if field != Field::Size {
+        return value.parse().map_err(|_| invalid());
+    }
+    let upper = value.to_uppercase();
+    let digits_end = upper
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(upper.len());
+    let (number, unit) = upper.split_at(digits_end);
+    let multiplier: f64 = match unit.trim() {
+        "" | "B" => 1.0,
+        "KB" | "K" => 1024.0,
+        "MB" | "M" => 1024.0 * 1024.0,
+        "GB" | "G" => 1024.0 * 1024.0 * 1024.0,
+        "TB" | "T" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(invalid()),
+    };
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    Ok((number * multiplier).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled(s: &str) -> (String, Vec<Value>) {
+        let filter = Filter::parse(s).unwrap();
+        (filter.sql().to_string(), filter.params().to_vec())
+    }
+
+    fn error(s: &str) -> String {
+        Filter::parse(s).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse_example_query() {
+        let (sql, params) = compiled(
+            "camera_model = 'ILCE-7M3' and date >= 2023-01 and format in (raw) and role = duplicate",
+        );
+        assert!(sql.starts_with("(((p.exif_camera_model = ? COLLATE NOCASE AND substr("));
+        assert_eq!(params[0], Value::Text("ILCE-7M3".into()));
+        assert_eq!(params[1], Value::Text("2023-01".into()));
+        // 8 RAW formats, then the role
+        assert_eq!(params.len(), 2 + 8 + 1);
+        assert_eq!(params[2], Value::Text("CR2".into()));
+        assert_eq!(params[10], Value::Text("duplicate".into()));
+    }
+
+    #[test]
+    fn test_and_binds_tighter_than_or() {
+        let (sql, _) = compiled("size > 1 or size < 2 and width = 3");
+        assert_eq!(sql, "(p.size > ? OR (p.size < ? AND p.exif_width = ?))");
+        let (sql, _) = compiled("(size > 1 or size < 2) and width = 3");
+        assert_eq!(sql, "((p.size > ? OR p.size < ?) AND p.exif_width = ?)");
+    }
+
+    #[test]
+    fn test_not_and_not_in() {
+        let (sql, params) = compiled("not format = jpg and format not in (heic, png)");
+        assert_eq!(sql, "((NOT p.format IN (?)) AND p.format NOT IN (?, ?))");
+        assert_eq!(
+            params,
+            vec![
+                Value::Text("JPEG".into()),
+                Value::Text("HEIC".into()),
+                Value::Text("PNG".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keywords_and_fields_are_case_insensitive() {
+        assert!(Filter::parse("Format IN (RAW) AND Role = Best_Copy").is_ok());
+    }
+
+    #[test]
+    fn test_quoted_strings_keep_spaces_and_doubled_quotes() {
+        let (_, params) = compiled("path ~ '*/It''s a trip/*'");
+        assert_eq!(params, vec![Value::Text("*/It's a trip/*".into())]);
+        let (sql, _) = compiled("camera_make = \"Canon\"");
+        assert_eq!(sql, "p.exif_camera_make = ? COLLATE NOCASE");
+    }
+
+    #[test]
+    fn test_date_compares_by_prefix() {
+        let (sql, params) = compiled("date = 2023");
+        assert_eq!(sql, format!("substr({DATE_SQL}, 1, 4) = ?"));
+        assert_eq!(params, vec![Value::Text("2023".into())]);
+        let (_, params) = compiled("date < 2023:06:01");
+        assert_eq!(params, vec![Value::Text("2023-06-01".into())]);
+    }
+
+    #[test]
+    fn test_size_units() {
+        assert_eq!(parse_number(Field::Size, "512").unwrap(), 512);
+        assert_eq!(parse_number(Field::Size, "10KB").unwrap(), 10 * 1024);
+        assert_eq!(parse_number(Field::Size, "1.5mb").unwrap(), 1536 * 1024);
+        assert!(parse_number(Field::Size, "10 parsecs").is_err());
+        assert!(parse_number(Field::Width, "10KB").is_err());
+    }
+
+    #[test]
+    fn test_confidence_compares_by_rank() {
+        let (_, params) = compiled("confidence >= near-certain");
+        assert_eq!(params, vec![Value::Integer(3)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(error("colour = red").contains("unknown field 'colour'"));
+        assert!(error("format = bmp").contains("unknown format 'bmp'"));
+        assert!(error("format > raw").contains("only supports =, != and in"));
+        assert!(error("role = keeper").contains("unknown role 'keeper'"));
+        assert!(error("date >= last-week").contains("invalid date"));
+        assert!(error("size ~ 1*").contains("'~' only applies"));
+        assert!(error("size >").contains("expected a value"));
+        assert!(error("(size > 1").contains("expected ')'"));
+        assert!(error("size > 1 size < 2").contains("unexpected 'size'"));
+        assert!(error("path = 'open").contains("unterminated string"));
+        assert!(error("").contains("expected a field name"));
+    }
+
+    #[test]
+    fn test_sort_key_parse() {
+        assert_eq!(
+            SortKey::parse("date:desc").unwrap(),
+            SortKey {
+                field: Field::Date,
+                descending: true
+            }
+        );
+        assert!(!SortKey::parse("size").unwrap().descending);
+        assert!(SortKey::parse("size:sideways").is_err());
+        assert!(SortKey::parse("colour").is_err());
+    }
+
+    #[test]
+    fn test_query_sql_orders_and_limits() {
+        let query = Query {
+            filter: Some(Filter::parse("size > 1").unwrap()),
+            sort: vec![SortKey::parse("size:desc").unwrap()],
+            limit: Some(5),
+        };
+        let (sql, params) = query.sql();
+        assert_eq!(sql, " WHERE p.size > ? ORDER BY p.size DESC, p.path ASC LIMIT ?");
+        assert_eq!(params, vec![Value::Integer(1), Value::Integer(5)]);
+        assert_eq!(Query::default().sql().0, " ORDER BY p.path ASC");
+    }
+}
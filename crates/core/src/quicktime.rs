@@ -0,0 +1,341 @@
+//! Metadata from QuickTime / MP4 (ISO base media) containers.
+//!
+//! Reads only the `moov` box — the media data is skipped by seeking — and
+//! extracts the creation date, camera, GPS location, frame size and the Apple
+//! content identifier that pairs a Live Photo motion video with its still.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::domain::ExifData;
+
+/// Largest `moov` box read into memory; real ones are a few hundred KB at most.
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Seconds between the QuickTime epoch (1904-01-01) and the Unix epoch.
+const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+const KEY_CONTENT_ID: &str = "com.apple.quicktime.content.identifier";
+const KEY_CREATION_DATE: &str = "com.apple.quicktime.creationdate";
+const KEY_MAKE: &str = "com.apple.quicktime.make";
+const KEY_MODEL: &str = "com.apple.quicktime.model";
+const KEY_LOCATION: &str = "com.apple.quicktime.location.ISO6709";
+
+/// Extract metadata from a QuickTime/MP4 file. Returns None if the file has no
+/// readable `moov` box or no useful field in it.
+pub fn extract_metadata(path: &Path) -> Option<ExifData> {
+    let file = File::open(path).ok()?;
+    let moov = read_moov(BufReader::new(file))?;
+    parse_moov(&moov)
+}
+
+/// Find the top-level `moov` box and return its payload.
+fn read_moov<R: Read + Seek>(mut reader: R) -> Option<Vec<u8>> {
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let size32 = u32::from_be_bytes(header[..4].try_into().ok()?) as u64;
+        let kind = &header[4..8];
+
+        let (size, header_len) = match size32 {
+            1 => {
+                let mut large = [0u8; 8];
+                reader.read_exact(&mut large).ok()?;
+                (u64::from_be_bytes(large), 16)
+            }
+            // Box extends to the end of the file
+            0 => (u64::MAX, 8),
+            n => (n, 8),
+        };
+        if size < header_len {
+            return None;
+        }
+
+        if kind == b"moov" {
+            let len = if size == u64::MAX {
+                MAX_MOOV_SIZE
+            } else {
+                size - header_len
+            };
+            if len > MAX_MOOV_SIZE {
+                return None;
+            }
+            let mut payload = Vec::new();
+            reader.take(len).read_to_end(&mut payload).ok()?;
+            return Some(payload);
+        }
+        if size == u64::MAX {
+            return None;
+        }
+        reader.seek(SeekFrom::Current((size - header_len) as i64)).ok()?;
+    }
+}
+
+/// Iterate the child boxes of a payload as `(type, payload)`.
+fn boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let header = data.get(pos..pos.checked_add(8)?)?;
+        let size32 = u32::from_be_bytes(header[..4].try_into().ok()?) as usize;
+        let kind = &header[4..];
+        let (size, header_len) = match size32 {
+            1 => {
+                let large = data.get(pos + 8..pos.checked_add(16)?)?;
+                let size = u64::from_be_bytes(large.try_into().ok()?);
+                (usize::try_from(size).ok()?, 16)
+            }
+            0 => (data.len() - pos, 8),
+            n => (n, 8),
+        };
+        if size < header_len {
+            return None;
+        }
+        // Malformed sizes end the walk instead of panicking on the slice
+        let end = pos.checked_add(size).filter(|&end| end <= data.len())?;
+        let payload = data.get(pos + header_len..end)?;
+        pos = end;
+        Some((kind, payload))
+    })
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    boxes(data).find(|(k, _)| *k == kind).map(|(_, p)| p)
+}
+
+fn parse_moov(moov: &[u8]) -> Option<ExifData> {
+    let keys = child(moov, b"meta").map(metadata_items).unwrap_or_default();
+    let key = |name: &str| {
+        keys.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let date = key(KEY_CREATION_DATE)
+        .and_then(|d| iso8601_to_exif_date(&d))
+        .or_else(|| child(moov, b"mvhd").and_then(mvhd_creation_date));
+    let (gps_lat, gps_lon) = key(KEY_LOCATION)
+        .and_then(|l| parse_iso6709(&l))
+        .map_or((None, None), |(lat, lon)| (Some(lat), Some(lon)));
+    let (width, height) = boxes(moov)
+        .filter(|(k, _)| *k == b"trak")
+        .filter_map(|(_, trak)| child(trak, b"tkhd").and_then(tkhd_dimensions))
+        .find(|&(w, h)| w > 0 && h > 0)
+        .map_or((None, None), |(w, h)| (Some(w), Some(h)));
+
+    let data = ExifData {
+        date,
+        camera_make: key(KEY_MAKE),
+        camera_model: key(KEY_MODEL),
+        gps_lat,
+        gps_lon,
+        width,
+        height,
+        content_id: key(KEY_CONTENT_ID),
+    };
+    let useful = data.date.is_some()
+        || data.camera_make.is_some()
+        || data.camera_model.is_some()
+        || data.gps_lat.is_some()
+        || data.width.is_some()
+        || data.content_id.is_some();
+    useful.then_some(data)
+}
+
+/// `mvhd` creation time (UTC) as an EXIF-style date; None when unset.
+fn mvhd_creation_date(mvhd: &[u8]) -> Option<String> {
+    let version = *mvhd.first()?;
+    let seconds = if version == 1 {
+        u64::from_be_bytes(mvhd.get(4..12)?.try_into().ok()?) as i64
+    } else {
+        u32::from_be_bytes(mvhd.get(4..8)?.try_into().ok()?) as i64
+    };
+    if seconds == 0 {
+        return None;
+    }
+    let dt = chrono::DateTime::from_timestamp(seconds - QUICKTIME_EPOCH_OFFSET, 0)?;
+    Some(dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Track width and height: the last two 16.16 fixed-point fields of `tkhd`.
+fn tkhd_dimensions(tkhd: &[u8]) -> Option<(u32, u32)> {
+    let end = tkhd.len();
+    let field = |at: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(tkhd.get(at..at + 4)?.try_into().ok()?) >> 16)
+    };
+    Some((field(end.checked_sub(8)?)?, field(end - 4)?))
+}
+
+/// String items of a QuickTime `meta` box (`keys` + `ilst`) as `(key, value)`.
+fn metadata_items(meta: &[u8]) -> Vec<(String, String)> {
+    // QuickTime `meta` has no version/flags; the ISO (MP4) full box does
+    let meta = if meta.get(4..8) == Some(b"hdlr") || meta.len() < 4 {
+        meta
+    } else {
+        &meta[4..]
+    };
+
+    let Some(keys) = child(meta, b"keys") else {
+        return Vec::new();
+    };
+    // Key entries are boxes whose "type" is the namespace (`mdta`)
+    let names: Vec<String> = boxes(keys.get(8..).unwrap_or_default())
+        .map(|(_, name)| String::from_utf8_lossy(name).into_owned())
+        .collect();
+
+    let Some(ilst) = child(meta, b"ilst") else {
+        return Vec::new();
+    };
+    boxes(ilst)
+        .filter_map(|(index, item)| {
+            let index = u32::from_be_bytes(index.try_into().ok()?) as usize;
+            let name = names.get(index.checked_sub(1)?)?;
+            let data = child(item, b"data")?;
+            // type indicator (4) + locale (4); type 1 = UTF-8
+            let type_indicator = u32::from_be_bytes(data.get(..4)?.try_into().ok()?);
+            let value = data.get(8..)?;
+            (type_indicator == 1)
+                .then(|| (name.clone(), String::from_utf8_lossy(value).into_owned()))
+        })
+        .collect()
+}
+
+/// `2024-06-15T10:30:00+0200` → `2024-06-15 10:30:00` (local capture time).
+fn iso8601_to_exif_date(value: &str) -> Option<String> {
+    let head = value.get(..19)?;
+    let (date, time) = head.split_once('T')?;
+    Some(format!("{date} {time}"))
+}
+
+/// `+37.7749-122.4194+010.000/` → (37.7749, -122.4194).
+fn parse_iso6709(value: &str) -> Option<(f64, f64)> {
+    let starts: Vec<usize> = value
+        .char_indices()
+        .filter(|(_, c)| *c == '+' || *c == '-')
+        .map(|(i, _)| i)
+        .collect();
+    let lat = value.get(*starts.first()?..*starts.get(1)?)?.parse().ok()?;
+    let lon_end = starts
+        .get(2)
+        .copied()
+        .unwrap_or_else(|| value.find('/').unwrap_or(value.len()));
+    let lon = value.get(starts[1]..lon_end)?.parse().ok()?;
+    Some((lat, lon))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// A minimal QuickTime file: `ftyp`, `mdat`, then `moov` with `mvhd` and
+    /// `meta` string items.
+    pub(crate) fn quicktime_bytes(creation_time: u32, items: &[(&str, &str)], media: &[u8]) -> Vec<u8> {
+        let mut mvhd = vec![0u8; 4];
+        mvhd.extend_from_slice(&creation_time.to_be_bytes());
+        mvhd.extend_from_slice(&[0u8; 92]);
+
+        let mut keys = vec![0u8; 4];
+        keys.extend_from_slice(&(items.len() as u32).to_be_bytes());
+        let mut ilst = Vec::new();
+        for (i, (key, value)) in items.iter().enumerate() {
+            keys.extend(mp4_box(b"mdta", key.as_bytes()));
+            let mut data = 1u32.to_be_bytes().to_vec();
+            data.extend_from_slice(&[0u8; 4]);
+            data.extend_from_slice(value.as_bytes());
+            let index = ((i + 1) as u32).to_be_bytes();
+            ilst.extend(mp4_box(&index, &mp4_box(b"data", &data)));
+        }
+        let mut meta = mp4_box(b"hdlr", &[0u8; 24]);
+        meta.extend(mp4_box(b"keys", &keys));
+        meta.extend(mp4_box(b"ilst", &ilst));
+
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend(mp4_box(b"meta", &meta));
+
+        let mut file = mp4_box(b"ftyp", b"qt  \0\0\0\0qt  ");
+        file.extend(mp4_box(b"mdat", media));
+        file.extend(mp4_box(b"moov", &moov));
+        file
+    }
+
+    fn parse(bytes: &[u8]) -> Option<ExifData> {
+        read_moov(std::io::Cursor::new(bytes)).and_then(|moov| parse_moov(&moov))
+    }
+
+    #[test]
+    fn test_apple_metadata_items() {
+        let bytes = quicktime_bytes(
+            0,
+            &[
+                (KEY_CONTENT_ID, "A1B2C3D4-0000-1111-2222-333344445555"),
+                (KEY_CREATION_DATE, "2024-06-15T10:30:00+0200"),
+                (KEY_MAKE, "Apple"),
+                (KEY_MODEL, "iPhone 15 Pro"),
+                (KEY_LOCATION, "+37.7749-122.4194+010.000/"),
+            ],
+            b"motion",
+        );
+        let data = parse(&bytes).unwrap();
+        assert_eq!(data.content_id.as_deref(), Some("A1B2C3D4-0000-1111-2222-333344445555"));
+        assert_eq!(data.date.as_deref(), Some("2024-06-15 10:30:00"));
+        assert_eq!(data.camera_make.as_deref(), Some("Apple"));
+        assert_eq!(data.camera_model.as_deref(), Some("iPhone 15 Pro"));
+        assert_eq!(data.gps_lat, Some(37.7749));
+        assert_eq!(data.gps_lon, Some(-122.4194));
+    }
+
+    #[test]
+    fn test_mvhd_creation_time_fallback() {
+        // 2024-01-01 00:00:00 UTC
+        let bytes = quicktime_bytes((1_704_067_200 + QUICKTIME_EPOCH_OFFSET) as u32, &[], b"clip");
+        let data = parse(&bytes).unwrap();
+        assert_eq!(data.date.as_deref(), Some("2024-01-01 00:00:00"));
+        assert!(data.content_id.is_none());
+    }
+
+    #[test]
+    fn test_no_moov_or_no_metadata() {
+        assert!(parse(b"not a movie at all").is_none());
+        assert!(parse(&quicktime_bytes(0, &[], b"clip")).is_none());
+    }
+
+    #[test]
+    fn test_malformed_boxes_end_the_walk() {
+        // 64-bit size that overflows `pos + size`
+        let mut huge = 1u32.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"free");
+        huge.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(boxes(&huge).count(), 0);
+
+        // A valid box followed by one that claims more bytes than remain
+        let mut data = mp4_box(b"free", b"ok");
+        data.extend_from_slice(&64u32.to_be_bytes());
+        data.extend_from_slice(b"skip");
+        assert_eq!(boxes(&data).count(), 1);
+
+        // `data` atom shorter than its type + locale header
+        let mut item = mp4_box(b"data", &1u32.to_be_bytes());
+        item = mp4_box(&1u32.to_be_bytes(), &item);
+        let keys = [&0u32.to_be_bytes()[..], &1u32.to_be_bytes(), &mp4_box(b"mdta", b"k")]
+            .concat();
+        let mut meta = mp4_box(b"hdlr", &[0u8; 24]);
+        meta.extend(mp4_box(b"keys", &keys));
+        meta.extend(mp4_box(b"ilst", &item));
+        assert!(metadata_items(&meta).is_empty());
+    }
+
+    #[test]
+    fn test_parse_iso6709() {
+        assert_eq!(parse_iso6709("+48.8584+002.2945/"), Some((48.8584, 2.2945)));
+        assert_eq!(parse_iso6709("-33.8688+151.2093+005.000/"), Some((-33.8688, 151.2093)));
+        assert_eq!(parse_iso6709("garbage"), None);
+    }
+}
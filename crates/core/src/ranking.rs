@@ -1,30 +1,286 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
 use crate::domain::PhotoFile;
+use crate::error::{Error, Result};
+use crate::matching::MatchGroup;
 
-/// Elect the source of truth from a group of duplicate photo references.
-///
-/// Priority:
-/// 1. Lowest format quality tier (RAW > TIFF > PNG > JPEG > HEIC > WebP)
-/// 2. Largest file size
-/// 3. Oldest mtime (earliest capture is likely the original)
-pub fn elect_source_of_truth<'a>(members: &[&'a PhotoFile]) -> &'a PhotoFile {
+/// One criterion of a [`RankingPolicy`]. Each prefers the photo named in its doc.
+#[derive(Debug, Clone)]
+pub enum RankCriterion {
+    /// Lowest format quality tier (RAW > TIFF > PNG > JPEG > HEIC > WebP).
+    FormatTier,
+    /// Most pixels, from the EXIF dimensions (unknown counts as zero).
+    Megapixels,
+    /// Largest file size.
+    FileSize,
+    /// Highest priority of the photo's source (`photopack add --priority`).
+    SourcePriority,
+    /// Most EXIF fields present: date, make, model, GPS, dimensions.
+    ExifCompleteness,
+    /// Oldest mtime (earliest capture is likely the original).
+    OldestMtime,
+    /// Path matching a gitignore-style pattern, e.g. `edited/`.
+    PathPattern(String, Gitignore),
+}
+
+impl RankCriterion {
+    /// Parse one criterion as written in a policy string.
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(pattern) = s.strip_prefix("path:") {
+            let mut builder = GitignoreBuilder::new("/");
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| Error::InvalidRankingPolicy(e.to_string()))?;
+            let matcher = builder
+                .build()
+                .map_err(|e| Error::InvalidRankingPolicy(e.to_string()))?;
+            return Ok(Self::PathPattern(pattern.to_string(), matcher));
+        }
+        match s {
+            "format" => Ok(Self::FormatTier),
+            "megapixels" => Ok(Self::Megapixels),
+            "size" => Ok(Self::FileSize),
+            "source" => Ok(Self::SourcePriority),
+            "exif" => Ok(Self::ExifCompleteness),
+            "mtime" => Ok(Self::OldestMtime),
+            _ => Err(Error::InvalidRankingPolicy(format!("unknown criterion '{s}'"))),
+        }
+    }
+
+    /// Compare two photos; `Less` means `a` ranks higher.
+    fn compare(&self, a: &PhotoFile, b: &PhotoFile, source_priority: &HashMap<i64, i64>) -> Ordering {
+        match self {
+            Self::FormatTier => a.format.quality_tier().cmp(&b.format.quality_tier()),
+            Self::Megapixels => pixels(b).cmp(&pixels(a)),
+            Self::FileSize => b.size.cmp(&a.size),
+            Self::SourcePriority => {
+                let priority = |p: &PhotoFile| source_priority.get(&p.source_id).copied().unwrap_or(0);
+                priority(b).cmp(&priority(a))
+            }
+            Self::ExifCompleteness => exif_fields(b).cmp(&exif_fields(a)),
+            Self::OldestMtime => a.mtime.cmp(&b.mtime),
+            Self::PathPattern(_, matcher) => {
+                let matches = |p: &PhotoFile| {
+                    p.path.has_root()
+                        && matcher
+                            .matched_path_or_any_parents(&p.path, false)
+                            .is_ignore()
+                };
+                matches(b).cmp(&matches(a))
+            }
+        }
+    }
+}
+
+impl fmt::Display for RankCriterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FormatTier => write!(f, "format"),
+            Self::Megapixels => write!(f, "megapixels"),
+            Self::FileSize => write!(f, "size"),
+            Self::SourcePriority => write!(f, "source"),
+            Self::ExifCompleteness => write!(f, "exif"),
+            Self::OldestMtime => write!(f, "mtime"),
+            Self::PathPattern(pattern, _) => write!(f, "path:{pattern}"),
+        }
+    }
+}
+
+impl PartialEq for RankCriterion {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+fn pixels(p: &PhotoFile) -> u64 {
+    let dims = p.exif.as_ref().and_then(|e| Some((e.width?, e.height?)));
+    dims.map(|(w, h)| w as u64 * h as u64).unwrap_or(0)
+}
+
+fn exif_fields(p: &PhotoFile) -> usize {
+    let Some(e) = &p.exif else {
+        return 0;
+    };
+    [
+        e.date.is_some(),
+        e.camera_make.is_some(),
+        e.camera_model.is_some(),
+        e.gps_lat.is_some() && e.gps_lon.is_some(),
+        e.width.is_some() && e.height.is_some(),
+    ]
+    .into_iter()
+    .filter(|present| *present)
+    .count()
+}
+
+/// Ordered criteria used to elect a group's source of truth. Earlier criteria
+/// win; later ones only break ties. Stored in the catalog as a comma-separated
+/// list, e.g. `format,megapixels,size,source,mtime`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingPolicy {
+    pub criteria: Vec<RankCriterion>,
+    /// Priority of each source id (missing = 0). Loaded from the sources
+    /// table at election time, not part of the stored policy.
+    pub source_priority: HashMap<i64, i64>,
+}
+
+impl Default for RankingPolicy {
+    fn default() -> Self {
+        Self {
+            criteria: vec![
+                RankCriterion::FormatTier,
+                RankCriterion::Megapixels,
+                RankCriterion::FileSize,
+                RankCriterion::SourcePriority,
+                RankCriterion::OldestMtime,
+            ],
+            source_priority: HashMap::new(),
+        }
+    }
+}
+
+impl RankingPolicy {
+    /// Parse a comma-separated list of criteria.
+    pub fn parse(s: &str) -> Result<Self> {
+        let criteria = s
+            .split(',')
+            .filter(|c| !c.trim().is_empty())
+            .map(RankCriterion::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if criteria.is_empty() {
+            return Err(Error::InvalidRankingPolicy("no criteria given".to_string()));
+        }
+        Ok(Self {
+            criteria,
+            source_priority: HashMap::new(),
+        })
+    }
+
+    pub fn to_config_value(&self) -> String {
+        self.criteria
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Compare two photos under this policy; `Less` means `a` ranks higher.
+    pub fn compare(&self, a: &PhotoFile, b: &PhotoFile) -> Ordering {
+        self.criteria
+            .iter()
+            .map(|c| c.compare(a, b, &self.source_priority))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl fmt::Display for RankingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.criteria.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}", names.join(" > "))
+    }
+}
+
+/// Elect the source of truth from a group of duplicate photo references,
+/// ranking them by `policy` (the first best member wins full ties).
+pub fn elect_source_of_truth<'a>(members: &[&'a PhotoFile], policy: &RankingPolicy) -> &'a PhotoFile {
     assert!(!members.is_empty(), "cannot elect from empty group");
 
     members
         .iter()
-        .min_by(|a, b| {
-            a.format
-                .quality_tier()
-                .cmp(&b.format.quality_tier())
-                .then(b.size.cmp(&a.size))
-                .then(a.mtime.cmp(&b.mtime))
-        })
+        .min_by(|a, b| policy.compare(a, b))
         .unwrap()
 }
 
+/// Elect the source of truth, honouring pins: when any member's hash is in
+/// `pins`, the election only runs among the pinned members.
+pub fn elect_with_pins<'a>(
+    members: &[&'a PhotoFile],
+    pins: &HashSet<String>,
+    policy: &RankingPolicy,
+) -> &'a PhotoFile {
+    let pinned: Vec<&PhotoFile> = members
+        .iter()
+        .copied()
+        .filter(|p| pins.contains(&p.sha256))
+        .collect();
+    if pinned.is_empty() {
+        elect_source_of_truth(members, policy)
+    } else {
+        elect_source_of_truth(&pinned, policy)
+    }
+}
+
+/// Keep Live Photo pairs together: when copies of a motion video are grouped,
+/// prefer the copy sitting in the same directory as the still that is kept
+/// (its group's source of truth, or the still itself when ungrouped).
+///
+/// `elected` holds `(source_of_truth_id, group)` pairs and is updated in place.
+/// Videos are only grouped with byte-identical copies, so any copy is as good
+/// as another; this only changes which path is considered the original.
+pub fn pair_live_photos(
+    elected: &mut [(i64, MatchGroup)],
+    photos: &HashMap<i64, &PhotoFile>,
+    policy: &RankingPolicy,
+) {
+    let content_id = |p: &PhotoFile| p.exif.as_ref().and_then(|e| e.content_id.clone());
+
+    let grouped: HashMap<i64, i64> = elected
+        .iter()
+        .flat_map(|(sot, g)| g.member_ids.iter().map(move |&id| (id, *sot)))
+        .collect();
+
+    // content id -> directory of the kept still (lowest id wins for determinism)
+    let mut still_dirs: HashMap<String, (i64, &Path)> = HashMap::new();
+    for photo in photos.values() {
+        if photo.format.is_video() || grouped.get(&photo.id).is_some_and(|&sot| sot != photo.id) {
+            continue;
+        }
+        let (Some(cid), Some(dir)) = (content_id(photo), photo.path.parent()) else {
+            continue;
+        };
+        let slot = still_dirs.entry(cid).or_insert((photo.id, dir));
+        if photo.id < slot.0 {
+            *slot = (photo.id, dir);
+        }
+    }
+    if still_dirs.is_empty() {
+        return;
+    }
+
+    for (sot_id, group) in elected.iter_mut() {
+        let Some(sot) = photos.get(sot_id) else {
+            continue;
+        };
+        if !sot.format.is_video() {
+            continue;
+        }
+        let Some((_, still_dir)) = content_id(sot).and_then(|cid| still_dirs.get(&cid)) else {
+            continue;
+        };
+        let beside: Vec<&PhotoFile> = group
+            .member_ids
+            .iter()
+            .filter_map(|id| photos.get(id).copied())
+            .filter(|p| p.path.parent() == Some(*still_dir))
+            .collect();
+        if !beside.is_empty() {
+            *sot_id = elect_source_of_truth(&beside, policy).id;
+        }
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
-    use crate::domain::{PhotoFile, PhotoFormat};
+    use crate::domain::{Confidence, ExifData, PhotoFile, PhotoFormat};
     use std::path::PathBuf;
 
     fn make_photo(id: i64, format: PhotoFormat, size: u64, mtime: i64) -> PhotoFile {
@@ -49,7 +305,7 @@ mod tests {
             make_photo(2, PhotoFormat::Cr2, 20_000_000, 1000),
         ];
         let members: Vec<&PhotoFile> = photos.iter().collect();
-        let winner = elect_source_of_truth(&members);
+        let winner = elect_source_of_truth(&members, &RankingPolicy::default());
         assert_eq!(winner.id, 2);
     }
 
@@ -60,7 +316,7 @@ mod tests {
             make_photo(2, PhotoFormat::Jpeg, 5_000_000, 1000),
         ];
         let members: Vec<&PhotoFile> = photos.iter().collect();
-        let winner = elect_source_of_truth(&members);
+        let winner = elect_source_of_truth(&members, &RankingPolicy::default());
         assert_eq!(winner.id, 2);
     }
 
@@ -71,7 +327,159 @@ mod tests {
             make_photo(2, PhotoFormat::Jpeg, 5_000_000, 1000),
         ];
         let members: Vec<&PhotoFile> = photos.iter().collect();
-        let winner = elect_source_of_truth(&members);
+        let winner = elect_source_of_truth(&members, &RankingPolicy::default());
         assert_eq!(winner.id, 2);
     }
+
+    fn with_dims(mut photo: PhotoFile, width: u32, height: u32) -> PhotoFile {
+        photo.exif = Some(ExifData {
+            date: None,
+            camera_make: None,
+            camera_model: None,
+            gps_lat: None,
+            gps_lon: None,
+            width: Some(width),
+            height: Some(height),
+            content_id: None,
+        });
+        photo
+    }
+
+    #[test]
+    fn test_megapixels_beat_file_size() {
+        let photos = [
+            with_dims(make_photo(1, PhotoFormat::Jpeg, 9_000_000, 1000), 2000, 1500),
+            with_dims(make_photo(2, PhotoFormat::Jpeg, 5_000_000, 1000), 4000, 3000),
+        ];
+        let members: Vec<&PhotoFile> = photos.iter().collect();
+        assert_eq!(elect_source_of_truth(&members, &RankingPolicy::default()).id, 2);
+
+        let by_size = RankingPolicy::parse("format,size").unwrap();
+        assert_eq!(elect_source_of_truth(&members, &by_size).id, 1);
+    }
+
+    #[test]
+    fn test_source_priority_breaks_ties() {
+        let mut photos = [
+            make_photo(1, PhotoFormat::Jpeg, 5_000_000, 1000),
+            make_photo(2, PhotoFormat::Jpeg, 5_000_000, 2000),
+        ];
+        photos[1].source_id = 2;
+        let members: Vec<&PhotoFile> = photos.iter().collect();
+
+        let mut policy = RankingPolicy::default();
+        assert_eq!(elect_source_of_truth(&members, &policy).id, 1);
+        policy.source_priority = HashMap::from([(2, 10)]);
+        assert_eq!(elect_source_of_truth(&members, &policy).id, 2);
+    }
+
+    #[test]
+    fn test_path_pattern_and_exif_completeness() {
+        let mut photos = [
+            make_photo(1, PhotoFormat::Cr2, 20_000_000, 1000),
+            with_dims(make_photo(2, PhotoFormat::Jpeg, 5_000_000, 1000), 10, 10),
+        ];
+        photos[1].path = PathBuf::from("/photos/edited/2.jpg");
+        let members: Vec<&PhotoFile> = photos.iter().collect();
+
+        let policy = RankingPolicy::parse("path:edited/, format").unwrap();
+        assert_eq!(elect_source_of_truth(&members, &policy).id, 2);
+        let policy = RankingPolicy::parse("exif,format").unwrap();
+        assert_eq!(elect_source_of_truth(&members, &policy).id, 2);
+    }
+
+    #[test]
+    fn test_policy_round_trips_and_rejects_unknown() {
+        let policy = RankingPolicy::parse("source, path:*_edit.jpg,mtime").unwrap();
+        assert_eq!(policy.to_config_value(), "source,path:*_edit.jpg,mtime");
+        assert_eq!(policy.to_string(), "source > path:*_edit.jpg > mtime");
+        assert_eq!(
+            RankingPolicy::parse(&RankingPolicy::default().to_config_value()).unwrap(),
+            RankingPolicy::default()
+        );
+        assert!(matches!(
+            RankingPolicy::parse("format,color"),
+            Err(Error::InvalidRankingPolicy(_))
+        ));
+        assert!(RankingPolicy::parse(" , ").is_err());
+    }
+
+    #[test]
+    fn test_pinned_member_beats_raw() {
+        let mut photos = [
+            make_photo(1, PhotoFormat::Cr2, 20_000_000, 1000),
+            make_photo(2, PhotoFormat::Jpeg, 5_000_000, 1000),
+            make_photo(3, PhotoFormat::Jpeg, 6_000_000, 1000),
+        ];
+        photos[1].sha256 = "edited".to_string();
+        let members: Vec<&PhotoFile> = photos.iter().collect();
+
+        let pins = HashSet::from(["edited".to_string()]);
+        assert_eq!(elect_with_pins(&members, &pins, &RankingPolicy::default()).id, 2);
+        // Pins on other content leave the usual election alone
+        let pins = HashSet::from(["elsewhere".to_string()]);
+        assert_eq!(elect_with_pins(&members, &pins, &RankingPolicy::default()).id, 1);
+    }
+
+    fn live(id: i64, path: &str, format: PhotoFormat, cid: &str) -> PhotoFile {
+        let mut photo = make_photo(id, format, 1_000, 1000);
+        photo.path = PathBuf::from(path);
+        photo.exif = Some(ExifData {
+            date: None,
+            camera_make: None,
+            camera_model: None,
+            gps_lat: None,
+            gps_lon: None,
+            width: None,
+            height: None,
+            content_id: Some(cid.to_string()),
+        });
+        photo
+    }
+
+    #[test]
+    fn test_live_photo_video_follows_its_still() {
+        let photos = [
+            live(1, "/backup/IMG_1.HEIC", PhotoFormat::Heic, "cid"),
+            live(2, "/phone/IMG_1.MOV", PhotoFormat::Mov, "cid"),
+            live(3, "/backup/IMG_1.MOV", PhotoFormat::Mov, "cid"),
+        ];
+        let map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
+        let group = MatchGroup {
+            member_ids: vec![2, 3],
+            confidence: Confidence::Certain,
+            evidence: Vec::new(),
+        };
+        // Equal size and mtime: the plain election keeps the first copy
+        let mut elected = vec![(2, group)];
+
+        pair_live_photos(&mut elected, &map, &RankingPolicy::default());
+        assert_eq!(elected[0].0, 3);
+    }
+
+    #[test]
+    fn test_live_photo_video_ignores_non_kept_still() {
+        let photos = [
+            live(1, "/phone/IMG_1.JPG", PhotoFormat::Jpeg, "cid"),
+            live(2, "/phone/IMG_1.MOV", PhotoFormat::Mov, "cid"),
+            live(3, "/backup/IMG_1.MOV", PhotoFormat::Mov, "cid"),
+            live(4, "/backup/IMG_1.DNG", PhotoFormat::Dng, "cid"),
+        ];
+        let map: HashMap<i64, &PhotoFile> = photos.iter().map(|p| (p.id, p)).collect();
+        let stills = MatchGroup {
+            member_ids: vec![1, 4],
+            confidence: Confidence::NearCertain,
+            evidence: Vec::new(),
+        };
+        let videos = MatchGroup {
+            member_ids: vec![2, 3],
+            confidence: Confidence::Certain,
+            evidence: Vec::new(),
+        };
+        let mut elected = vec![(4, stills), (2, videos)];
+
+        pair_live_photos(&mut elected, &map, &RankingPolicy::default());
+        assert_eq!(elected[0].0, 4);
+        assert_eq!(elected[1].0, 3, "the DNG is kept, so the video beside it is");
+    }
 }
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::hasher;
+use crate::manifest::Manifest;
+use crate::vault_save;
+
+/// Progress callback events for the repair operation.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RepairProgress {
+    /// Starting repair with the number of damaged pack files.
+    Start { total: usize },
+    /// A damaged pack file was restored from a source copy.
+    Repaired { path: PathBuf, source: PathBuf },
+    /// No intact source copy exists for a damaged pack file.
+    Unrecoverable { path: PathBuf },
+    /// Repair completed.
+    Complete { repaired: usize, unrecoverable: usize },
+}
+
+/// A pack file restored from a source copy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepairedFile {
+    pub path: PathBuf,
+    pub source: PathBuf,
+}
+
+/// Outcome of a pack repair.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RepairReport {
+    pub repaired: Vec<RepairedFile>,
+    /// Damaged pack files with no intact source copy.
+    pub unrecoverable: Vec<PathBuf>,
+    /// Orphaned and misnamed files found by verification. Repair leaves them alone.
+    pub orphaned: Vec<PathBuf>,
+    pub misnamed: Vec<PathBuf>,
+}
+
+/// Restore damaged pack files from source copies with the same SHA-256.
+///
+/// `damaged` lists `(sha256, pack path)` pairs; `candidates` maps each hash to the
+/// catalog paths known to hold that content. Each candidate is re-hashed before
+/// use (sources change too) and the first intact one atomically replaces the pack
+/// file. Restored entries are marked verified in the manifest.
+pub fn repair_pack(
+    manifest: &Manifest,
+    damaged: &[(String, PathBuf)],
+    candidates: &HashMap<String, Vec<PathBuf>>,
+    mut progress_cb: Option<&mut dyn FnMut(RepairProgress)>,
+) -> RepairReport {
+    if let Some(ref mut cb) = progress_cb {
+        cb(RepairProgress::Start {
+            total: damaged.len(),
+        });
+    }
+
+    let mut report = RepairReport::default();
+    let mut restored_hashes = Vec::new();
+    for (sha256, target) in damaged {
+        let source = candidates
+            .get(sha256)
+            .into_iter()
+            .flatten()
+            .find(|source| restore_from(source, target, sha256));
+
+        match source {
+            Some(source) => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(RepairProgress::Repaired {
+                        path: target.clone(),
+                        source: source.clone(),
+                    });
+                }
+                restored_hashes.push(sha256.clone());
+                report.repaired.push(RepairedFile {
+                    path: target.clone(),
+                    source: source.clone(),
+                });
+            }
+            None => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(RepairProgress::Unrecoverable {
+                        path: target.clone(),
+                    });
+                }
+                report.unrecoverable.push(target.clone());
+            }
+        }
+    }
+    let _ = manifest.mark_verified(&restored_hashes);
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(RepairProgress::Complete {
+            repaired: report.repaired.len(),
+            unrecoverable: report.unrecoverable.len(),
+        });
+    }
+
+    report
+}
+
+/// Try to restore `target` from `source`. Returns false if the source is gone,
+/// no longer matches the hash, or the replacement fails.
+fn restore_from(source: &Path, target: &Path, sha256: &str) -> bool {
+    match hasher::compute_sha256(source) {
+        Ok(actual) if actual == sha256 => {
+            vault_save::replace_pack_file(source, target, sha256).is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write_with_sha(path: &Path, content: &[u8]) -> String {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        hasher::compute_sha256(path).unwrap()
+    }
+
+    #[test]
+    fn test_repair_restores_from_first_intact_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+
+        let good = tmp.path().join("photos/good.jpg");
+        let sha = write_with_sha(&good, b"photo data");
+        // An edited copy the catalog still lists under the old hash
+        let stale = tmp.path().join("photos/edited.jpg");
+        write_with_sha(&stale, b"edited photo data");
+        manifest
+            .insert_file(&sha, "good.jpg", "JPEG", 10, None, None, None, 0)
+            .unwrap();
+
+        let target = pack.join(&sha[..2]).join(format!("{sha}.jpg"));
+        write_with_sha(&target, b"photo dat\0");
+
+        let candidates = HashMap::from([(sha.clone(), vec![stale.clone(), good.clone()])]);
+        let report = repair_pack(&manifest, &[(sha.clone(), target.clone())], &candidates, None);
+
+        assert_eq!(
+            report.repaired,
+            vec![RepairedFile {
+                path: target.clone(),
+                source: good,
+            }]
+        );
+        assert!(report.unrecoverable.is_empty());
+        assert_eq!(fs::read(&target).unwrap(), b"photo data");
+        let entries = manifest.list_entries_by_verification().unwrap();
+        assert!(entries[0].verified_at.is_some());
+    }
+
+    #[test]
+    fn test_repair_recreates_missing_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+
+        let source = tmp.path().join("photos/a.jpg");
+        let sha = write_with_sha(&source, b"photo data");
+        let target = pack.join(&sha[..2]).join(format!("{sha}.jpg"));
+
+        let candidates = HashMap::from([(sha.clone(), vec![source])]);
+        let report = repair_pack(&manifest, &[(sha, target.clone())], &candidates, None);
+
+        assert_eq!(report.repaired.len(), 1);
+        assert_eq!(fs::read(&target).unwrap(), b"photo data");
+    }
+
+    #[test]
+    fn test_repair_reports_unrecoverable() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+
+        let sha = "ab".repeat(32);
+        let target = pack.join("ab").join(format!("{sha}.jpg"));
+        write_with_sha(&target, b"damaged");
+        let gone = tmp.path().join("photos/deleted.jpg");
+
+        let mut events = Vec::new();
+        let candidates = HashMap::from([(sha.clone(), vec![gone])]);
+        let report = repair_pack(
+            &manifest,
+            &[(sha, target.clone())],
+            &candidates,
+            Some(&mut |progress| match progress {
+                RepairProgress::Start { total } => events.push(format!("start:{total}")),
+                RepairProgress::Repaired { .. } => events.push("repaired".to_string()),
+                RepairProgress::Unrecoverable { .. } => events.push("unrecoverable".to_string()),
+                RepairProgress::Complete {
+                    repaired,
+                    unrecoverable,
+                } => events.push(format!("complete:{repaired}:{unrecoverable}")),
+            }),
+        );
+
+        assert!(report.repaired.is_empty());
+        assert_eq!(report.unrecoverable, vec![target.clone()]);
+        assert_eq!(events, ["start:1", "unrecoverable", "complete:0:1"]);
+        // Damaged file is left in place for manual recovery
+        assert_eq!(fs::read(&target).unwrap(), b"damaged");
+    }
+}
@@ -0,0 +1,600 @@
+//! Rebuild a browsable `YYYY/MM/DD/original_filename` tree from a pack.
+//!
+//! Works from the pack's embedded manifest alone — no catalog, no sources —
+//! so a pack copied to another machine can still be unpacked. Packed sidecars
+//! (XMP, AAE, THM) are materialized next to their photo.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::domain::SidecarKind;
+use crate::error::{Error, Result};
+use crate::manifest::{Manifest, ManifestEntry, ManifestSidecar};
+use crate::vault_save::{self, format_str_to_extension};
+
+/// Directory for entries with neither an EXIF date nor an mtime.
+pub const UNKNOWN_DATE_DIR: &str = "unknown-date";
+
+/// How restored files are materialized in the destination tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Independent copies (safe to edit, uses extra space).
+    Copy,
+    /// Hard links into the pack (no extra space; destination must be on the same filesystem).
+    Hardlink,
+    /// Symbolic links to the pack files (no extra space; breaks if the pack moves).
+    Symlink,
+}
+
+/// Progress callback events for the restore operation.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RestoreProgress {
+    /// Starting restore with total entry count.
+    Start { total: usize },
+    /// A pack file was materialized in the destination tree.
+    Restored { source: PathBuf, target: PathBuf },
+    /// The target already exists (previous restore).
+    Skipped { path: PathBuf },
+    /// The manifest lists a pack file that isn't on disk.
+    Missing { path: PathBuf },
+    /// Restore completed.
+    Complete {
+        restored: usize,
+        skipped: usize,
+        missing: usize,
+    },
+}
+
+/// Outcome of a restore.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RestoreReport {
+    pub restored: usize,
+    pub skipped: usize,
+    /// Pack files listed in the manifest but absent on disk.
+    pub missing: Vec<PathBuf>,
+}
+
+/// Date directory for an entry: EXIF date, then mtime, then [`UNKNOWN_DATE_DIR`].
+pub(crate) fn date_dir(entry: &ManifestEntry) -> PathBuf {
+    let date = entry
+        .exif_date
+        .as_deref()
+        .and_then(vault_save::parse_exif_date)
+        .or_else(|| {
+            use chrono::Datelike;
+            let dt = chrono::DateTime::from_timestamp(entry.mtime?, 0)?;
+            Some((dt.year() as u32, dt.month(), dt.day()))
+        });
+
+    match date {
+        Some((year, month, day)) => PathBuf::from(format!("{:04}", year))
+            .join(format!("{:02}", month))
+            .join(format!("{:02}", day)),
+        None => PathBuf::from(UNKNOWN_DATE_DIR),
+    }
+}
+
+/// Insert `_{sha[..8]}` before the extension: `IMG_0001.jpg` → `IMG_0001_1a2b3c4d.jpg`.
+pub(crate) fn suffixed_name(original_filename: &str, sha256: &str) -> String {
+    let short = &sha256[..8.min(sha256.len())];
+    match original_filename.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem}_{short}.{ext}"),
+        _ => format!("{original_filename}_{short}"),
+    }
+}
+
+/// Name of a sidecar placed next to a photo restored as `photo_name`, keeping
+/// the sidecar's original convention: `IMG_1.CR2.xmp` (full name) or `IMG_1.xmp` (stem).
+pub(crate) fn sidecar_name(
+    photo_name: &str,
+    original_photo_name: &str,
+    sidecar: &ManifestSidecar,
+) -> String {
+    let original = Path::new(&sidecar.original_filename);
+    let ext = original
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_else(|| sidecar.kind.to_lowercase());
+    let full_name_form = original
+        .file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(original_photo_name));
+    if full_name_form {
+        format!("{photo_name}.{ext}")
+    } else {
+        let stem = Path::new(photo_name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| photo_name.to_string());
+        format!("{stem}.{ext}")
+    }
+}
+
+/// Plan the relative target path of every entry in the date tree.
+pub fn plan_restore_paths(entries: &[ManifestEntry]) -> Vec<PathBuf> {
+    plan_paths(entries, date_dir)
+}
+
+/// Plan `dir_for(entry)/original_filename` for every entry.
+///
+/// Entries that would share a path (compared case-insensitively, for macOS and
+/// Windows destinations) all get a `_{sha[..8]}` suffix, so names depend only on
+/// the colliding entries themselves — never on iteration order. Live Photo
+/// motion videos are then placed next to their still (see [`pair_live_photos`]).
+pub(crate) fn plan_paths(
+    entries: &[ManifestEntry],
+    dir_for: impl Fn(&ManifestEntry) -> PathBuf,
+) -> Vec<PathBuf> {
+    let natural: Vec<PathBuf> = entries
+        .iter()
+        .map(|e| dir_for(e).join(&e.original_filename))
+        .collect();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for path in &natural {
+        *counts
+            .entry(path.to_string_lossy().to_lowercase())
+            .or_default() += 1;
+    }
+
+    let planned: Vec<PathBuf> = entries
+        .iter()
+        .zip(natural)
+        .map(|(entry, path)| {
+            if counts[&path.to_string_lossy().to_lowercase()] > 1 {
+                path.with_file_name(suffixed_name(&entry.original_filename, &entry.sha256))
+            } else {
+                path
+            }
+        })
+        .collect();
+    pair_live_photos(entries, planned)
+}
+
+fn is_video_format(format: &str) -> bool {
+    matches!(format, "MOV" | "MP4")
+}
+
+/// Move each Live Photo motion video next to its still, named after the
+/// still's planned file name: `2024/06/15/IMG_1.HEIC` → `2024/06/15/IMG_1.MOV`.
+///
+/// A motion is paired with the still sharing its content identifier, preferring
+/// one with the same original stem, then the lowest hash. A motion whose paired
+/// path is already taken keeps its planned path.
+fn pair_live_photos(entries: &[ManifestEntry], mut planned: Vec<PathBuf>) -> Vec<PathBuf> {
+    let stem = |name: &str| {
+        Path::new(name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+
+    let mut stills: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if let (Some(cid), false) = (entry.content_id.as_deref(), is_video_format(&entry.format)) {
+            stills.entry(cid).or_default().push(i);
+        }
+    }
+    if stills.is_empty() {
+        return planned;
+    }
+
+    // motion index -> paired path
+    let mut paired: HashMap<usize, PathBuf> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if !is_video_format(&entry.format) {
+            continue;
+        }
+        let Some(candidates) = entry.content_id.as_deref().and_then(|cid| stills.get(cid)) else {
+            continue;
+        };
+        let motion_stem = stem(&entry.original_filename);
+        let Some(&still) = candidates.iter().min_by_key(|&&s| {
+            (stem(&entries[s].original_filename) != motion_stem, &entries[s].sha256)
+        }) else {
+            continue;
+        };
+        let ext = Path::new(&entry.original_filename)
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format_str_to_extension(&entry.format).to_string());
+        paired.insert(i, planned[still].with_extension(ext));
+    }
+
+    // Drop pairings that collide with each other or with any path not being
+    // replaced, until the remaining ones are all free.
+    loop {
+        let key = |p: &PathBuf| p.to_string_lossy().to_lowercase();
+        let mut taken: HashMap<String, usize> = HashMap::new();
+        for (i, path) in planned.iter().enumerate() {
+            if !paired.contains_key(&i) {
+                *taken.entry(key(path)).or_default() += 1;
+            }
+        }
+        for path in paired.values() {
+            *taken.entry(key(path)).or_default() += 1;
+        }
+        let before = paired.len();
+        paired.retain(|_, path| taken[&key(path)] == 1);
+        if paired.len() == before {
+            break;
+        }
+    }
+
+    for (i, path) in paired {
+        planned[i] = path;
+    }
+    planned
+}
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Materialize one pack file at `target`.
+///
+/// Copies go to a temp file next to `target` and are renamed into place, so
+/// an interrupted restore never leaves a truncated file that a re-run would
+/// skip as already restored.
+fn materialize(source: &Path, target: &Path, mode: RestoreMode) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match mode {
+        RestoreMode::Copy => {
+            let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+            let temp = target.with_file_name(format!(
+                ".{}.{}-{}.tmp",
+                file_name,
+                std::process::id(),
+                TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let result = fs::copy(source, &temp).and_then(|_| fs::rename(&temp, target));
+            if result.is_err() {
+                let _ = fs::remove_file(&temp);
+            }
+            result?;
+        }
+        RestoreMode::Hardlink => fs::hard_link(source, target)?,
+        RestoreMode::Symlink => {
+            let source = source.canonicalize()?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&source, target)?;
+            #[cfg(windows)]
+            std::os::windows::fs::symlink_file(&source, target)?;
+        }
+    }
+    Ok(())
+}
+
+enum Outcome {
+    Restored,
+    Skipped,
+    Missing,
+}
+
+/// Materialize the packed sidecars of `entry` next to its restored `target`.
+/// Sidecars already present at the destination are left alone.
+fn restore_sidecars(
+    pack_path: &Path,
+    entry: &ManifestEntry,
+    target: &Path,
+    sidecars: Option<&Vec<ManifestSidecar>>,
+    mode: RestoreMode,
+) -> Result<()> {
+    let photo_name = target.file_name().unwrap_or_default().to_string_lossy();
+    for sidecar in sidecars.into_iter().flatten() {
+        let Some(kind) = SidecarKind::parse(&sidecar.kind) else {
+            continue;
+        };
+        let source = vault_save::build_sidecar_path(pack_path, &entry.sha256, kind);
+        let sidecar_target =
+            target.with_file_name(sidecar_name(&photo_name, &entry.original_filename, sidecar));
+        if source.is_file() && sidecar_target.symlink_metadata().is_err() {
+            materialize(&source, &sidecar_target, mode)?;
+        }
+    }
+    Ok(())
+}
+
+/// Restore every pack file listed in the manifest of `pack_path` into `dest`.
+///
+/// Re-running into the same destination skips targets that already exist.
+/// Pack files missing on disk are reported, not fatal; any other I/O error
+/// (e.g. hard-linking across filesystems) aborts the restore.
+pub fn restore_pack(
+    pack_path: &Path,
+    dest: &Path,
+    mode: RestoreMode,
+    mut progress_cb: Option<&mut dyn FnMut(RestoreProgress)>,
+) -> Result<RestoreReport> {
+    if !pack_path.join(".photopack").join("manifest.sqlite").is_file() {
+        return Err(Error::NotAPack(pack_path.to_path_buf()));
+    }
+    let manifest = Manifest::open(pack_path)?;
+    let entries = manifest.list_files()?;
+    let targets = plan_restore_paths(&entries);
+    let mut sidecars: HashMap<String, Vec<ManifestSidecar>> = HashMap::new();
+    for sidecar in manifest.list_sidecars()? {
+        sidecars.entry(sidecar.sha256.clone()).or_default().push(sidecar);
+    }
+    fs::create_dir_all(dest)?;
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(RestoreProgress::Start {
+            total: entries.len(),
+        });
+    }
+
+    // Parallel materialization, collect results
+    let results: Vec<Result<(Outcome, PathBuf, PathBuf)>> = entries
+        .par_iter()
+        .zip(targets.par_iter())
+        .map(|(entry, relative)| {
+            let source = pack_path
+                .join(&entry.sha256[..2])
+                .join(format!("{}.{}", entry.sha256, format_str_to_extension(&entry.format)));
+            let target = dest.join(relative);
+            let outcome = if target.symlink_metadata().is_ok() {
+                Outcome::Skipped
+            } else if !source.is_file() {
+                Outcome::Missing
+            } else {
+                materialize(&source, &target, mode)?;
+                Outcome::Restored
+            };
+            if !matches!(outcome, Outcome::Missing) {
+                restore_sidecars(pack_path, entry, &target, sidecars.get(&entry.sha256), mode)?;
+            }
+            Ok((outcome, source, target))
+        })
+        .collect();
+
+    // Report progress sequentially (callback is not Send)
+    let mut report = RestoreReport::default();
+    for result in results {
+        let (outcome, source, target) = result?;
+        match outcome {
+            Outcome::Restored => {
+                report.restored += 1;
+                if let Some(ref mut cb) = progress_cb {
+                    cb(RestoreProgress::Restored { source, target });
+                }
+            }
+            Outcome::Skipped => {
+                report.skipped += 1;
+                if let Some(ref mut cb) = progress_cb {
+                    cb(RestoreProgress::Skipped { path: target });
+                }
+            }
+            Outcome::Missing => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(RestoreProgress::Missing {
+                        path: source.clone(),
+                    });
+                }
+                report.missing.push(source);
+            }
+        }
+    }
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(RestoreProgress::Complete {
+            restored: report.restored,
+            skipped: report.skipped,
+            missing: report.missing.len(),
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{add_pack_file, PackEntry};
+
+    /// A pack entry with an EXIF date, or only the mtime 2023-11-14 22:13:20 UTC.
+    fn packed<'a>(name: &'a str, exif_date: Option<&'a str>) -> PackEntry<'a> {
+        PackEntry {
+            exif_date,
+            mtime: 1_700_000_000,
+            ..PackEntry::named(name)
+        }
+    }
+
+    fn entry(sha256: &str, name: &str, exif_date: Option<&str>, mtime: Option<i64>) -> ManifestEntry {
+        ManifestEntry {
+            sha256: sha256.to_string(),
+            original_filename: name.to_string(),
+            format: "JPEG".to_string(),
+            size: 10,
+            exif_date: exif_date.map(str::to_string),
+            camera_make: None,
+            camera_model: None,
+            mtime,
+            verified_at: None,
+            content_id: None,
+            group_sha256: None,
+            role: None,
+        }
+    }
+
+    #[test]
+    fn test_date_dir_prefers_exif_then_mtime() {
+        let exif = entry("aa", "a.jpg", Some("2024:06:15 12:00:00"), Some(0));
+        assert_eq!(date_dir(&exif), PathBuf::from("2024/06/15"));
+        // 2023-11-14 22:13:20 UTC
+        let mtime_only = entry("bb", "b.jpg", None, Some(1_700_000_000));
+        assert_eq!(date_dir(&mtime_only), PathBuf::from("2023/11/14"));
+        let bad_exif = entry("cc", "c.jpg", Some("0000:00:00 00:00:00"), Some(1_700_000_000));
+        assert_eq!(date_dir(&bad_exif), PathBuf::from("2023/11/14"));
+        let neither = entry("dd", "d.jpg", None, None);
+        assert_eq!(date_dir(&neither), PathBuf::from(UNKNOWN_DATE_DIR));
+    }
+
+    #[test]
+    fn test_suffixed_name() {
+        let sha = "1a2b3c4d5e6f";
+        assert_eq!(suffixed_name("IMG_0001.jpg", sha), "IMG_0001_1a2b3c4d.jpg");
+        assert_eq!(suffixed_name("archive.tar.gz", sha), "archive.tar_1a2b3c4d.gz");
+        assert_eq!(suffixed_name("noext", sha), "noext_1a2b3c4d");
+        assert_eq!(suffixed_name(".hidden", sha), ".hidden_1a2b3c4d");
+    }
+
+    #[test]
+    fn test_sidecar_name_keeps_convention() {
+        let sidecar = |name: &str| ManifestSidecar {
+            sha256: "a".repeat(64),
+            kind: "XMP".to_string(),
+            sidecar_sha256: "b".repeat(64),
+            size: 1,
+            original_filename: name.to_string(),
+        };
+        assert_eq!(sidecar_name("IMG_1.CR2", "IMG_1.CR2", &sidecar("IMG_1.CR2.xmp")), "IMG_1.CR2.xmp");
+        assert_eq!(sidecar_name("IMG_1.CR2", "IMG_1.CR2", &sidecar("IMG_1.xmp")), "IMG_1.xmp");
+        assert_eq!(
+            sidecar_name("IMG_1_1a2b3c4d.HEIC", "IMG_1.HEIC", &sidecar("IMG_1.AAE")),
+            "IMG_1_1a2b3c4d.AAE"
+        );
+    }
+
+    #[test]
+    fn test_plan_restore_paths_suffixes_all_collisions() {
+        let date = Some("2024:01:01 00:00:00");
+        let entries = vec![
+            entry("11111111aaaa", "IMG_0001.JPG", date, None),
+            entry("22222222bbbb", "img_0001.jpg", date, None),
+            entry("33333333cccc", "IMG_0002.jpg", date, None),
+            entry("44444444dddd", "IMG_0001.JPG", Some("2024:01:02 00:00:00"), None),
+        ];
+
+        let paths = plan_restore_paths(&entries);
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("2024/01/01/IMG_0001_11111111.JPG"),
+                PathBuf::from("2024/01/01/img_0001_22222222.jpg"),
+                PathBuf::from("2024/01/01/IMG_0002.jpg"),
+                PathBuf::from("2024/01/02/IMG_0001.JPG"),
+            ]
+        );
+
+        // Order-independent
+        let mut reversed = entries.clone();
+        reversed.reverse();
+        let mut reversed_paths = plan_restore_paths(&reversed);
+        reversed_paths.reverse();
+        assert_eq!(reversed_paths, paths);
+    }
+
+    #[test]
+    fn test_plan_restore_paths_pairs_live_photos() {
+        let live = |sha: &str, name: &str, format: &str, date: &str, cid: &str| ManifestEntry {
+            format: format.to_string(),
+            content_id: Some(cid.to_string()),
+            ..entry(sha, name, Some(date), None)
+        };
+        let entries = vec![
+            // Still restored with a suffix; motion dated a day later (UTC)
+            live("11111111aaaa", "IMG_0001.HEIC", "HEIC", "2024:01:01 23:30:00", "cid-1"),
+            entry("22222222bbbb", "IMG_0001.HEIC", Some("2024:01:01 08:00:00"), None),
+            live("33333333cccc", "IMG_0001.MOV", "MOV", "2024:01:02 07:30:00", "cid-1"),
+            // Motion without its still keeps its own place
+            live("44444444dddd", "IMG_0002.MOV", "MOV", "2024:01:03 10:00:00", "cid-2"),
+        ];
+
+        let paths = plan_restore_paths(&entries);
+        assert_eq!(paths[0], PathBuf::from("2024/01/01/IMG_0001_11111111.HEIC"));
+        assert_eq!(paths[2], PathBuf::from("2024/01/01/IMG_0001_11111111.MOV"));
+        assert_eq!(paths[3], PathBuf::from("2024/01/03/IMG_0002.MOV"));
+    }
+
+    #[test]
+    fn test_restore_copy_builds_date_tree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let dest = tmp.path().join("restored");
+        let manifest = Manifest::open(&pack).unwrap();
+        let date = Some("2024:06:15 10:00:00");
+        add_pack_file(&pack, &manifest, b"one", packed("IMG_0001.jpg", date));
+        add_pack_file(&pack, &manifest, b"two", packed("IMG_0002.jpg", None));
+
+        let report = restore_pack(&pack, &dest, RestoreMode::Copy, None).unwrap();
+        assert_eq!(report.restored, 2);
+        assert_eq!(fs::read(dest.join("2024/06/15/IMG_0001.jpg")).unwrap(), b"one");
+        assert_eq!(fs::read(dest.join("2023/11/14/IMG_0002.jpg")).unwrap(), b"two");
+
+        // Re-running skips everything
+        let again = restore_pack(&pack, &dest, RestoreMode::Copy, None).unwrap();
+        assert_eq!((again.restored, again.skipped), (0, 2));
+        // No temp files left next to the copies
+        assert_eq!(fs::read_dir(dest.join("2024/06/15")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_failed_copy_leaves_no_partial_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("not-a-file");
+        fs::create_dir_all(&source).unwrap();
+        let target = tmp.path().join("out/IMG_0001.jpg");
+
+        assert!(materialize(&source, &target, RestoreMode::Copy).is_err());
+        assert!(!target.exists());
+        assert_eq!(fs::read_dir(tmp.path().join("out")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_restore_hardlink_and_symlink() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        let date = Some("2024:06:15 10:00:00");
+        let (_, pack_file) = add_pack_file(&pack, &manifest, b"one", packed("IMG_0001.jpg", date));
+
+        let hard = tmp.path().join("hard");
+        restore_pack(&pack, &hard, RestoreMode::Hardlink, None).unwrap();
+        let restored = hard.join("2024/06/15/IMG_0001.jpg");
+        assert_eq!(fs::read(&restored).unwrap(), b"one");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(
+                fs::metadata(&restored).unwrap().ino(),
+                fs::metadata(&pack_file).unwrap().ino()
+            );
+        }
+
+        let soft = tmp.path().join("soft");
+        restore_pack(&pack, &soft, RestoreMode::Symlink, None).unwrap();
+        let link = soft.join("2024/06/15/IMG_0001.jpg");
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), pack_file.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_restore_reports_missing_pack_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (_, pack_file) = add_pack_file(&pack, &manifest, b"one", packed("IMG_0001.jpg", None));
+        fs::remove_file(&pack_file).unwrap();
+
+        let report = restore_pack(&pack, &tmp.path().join("out"), RestoreMode::Copy, None).unwrap();
+        assert_eq!(report.restored, 0);
+        assert_eq!(report.missing, vec![pack_file]);
+    }
+
+    #[test]
+    fn test_restore_rejects_non_pack_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let err = restore_pack(tmp.path(), &tmp.path().join("out"), RestoreMode::Copy, None)
+            .unwrap_err();
+        assert!(matches!(err, Error::NotAPack(_)));
+        // Must not create a manifest in a directory that isn't a pack
+        assert!(!tmp.path().join(".photopack").exists());
+    }
+}
@@ -16,6 +16,8 @@ pub fn format_from_extension(ext: &str) -> Option<PhotoFormat> {
         "raf" => Some(PhotoFormat::Raf),
         "rw2" => Some(PhotoFormat::Rw2),
         "dng" => Some(PhotoFormat::Dng),
+        "mov" => Some(PhotoFormat::Mov),
+        "mp4" | "m4v" => Some(PhotoFormat::Mp4),
         _ => None,
     }
 }
@@ -24,6 +26,7 @@ pub fn format_from_extension(ext: &str) -> Option<PhotoFormat> {
 pub const SUPPORTED_EXTENSIONS: &[&str] = &[
     "jpg", "jpeg", "png", "tif", "tiff", "webp", "heic", "heif",
     "cr2", "cr3", "nef", "arw", "orf", "raf", "rw2", "dng",
+    "mov", "mp4", "m4v",
 ];
 
 #[cfg(test)]
@@ -37,11 +40,13 @@ mod tests {
         assert_eq!(format_from_extension("cr2"), Some(PhotoFormat::Cr2));
         assert_eq!(format_from_extension("dng"), Some(PhotoFormat::Dng));
         assert_eq!(format_from_extension("heic"), Some(PhotoFormat::Heic));
+        assert_eq!(format_from_extension("mov"), Some(PhotoFormat::Mov));
+        assert_eq!(format_from_extension("m4v"), Some(PhotoFormat::Mp4));
     }
 
     #[test]
     fn test_unknown_extension() {
         assert_eq!(format_from_extension("txt"), None);
-        assert_eq!(format_from_extension("mp4"), None);
+        assert_eq!(format_from_extension("avi"), None);
     }
 }
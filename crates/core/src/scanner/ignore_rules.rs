@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::error::{Error, Result};
+
+/// Name of the per-source ignore file, read from the source root.
+pub const IGNORE_FILE_NAME: &str = ".photopackignore";
+
+/// Gitignore-style exclusion rules for one source directory.
+///
+/// Combines the global patterns stored in the catalog with the source's own
+/// `.photopackignore` file. Patterns are anchored at the source root, exactly
+/// like a `.gitignore` at the top of a repository.
+pub struct IgnoreRules {
+    matcher: Gitignore,
+}
+
+impl IgnoreRules {
+    /// Rules that exclude nothing.
+    pub fn empty() -> Self {
+        Self {
+            matcher: Gitignore::empty(),
+        }
+    }
+
+    /// Build the rules for `root` from the global patterns plus `root/.photopackignore`.
+    /// Patterns from the ignore file come last, so they can re-include (`!pattern`)
+    /// paths excluded globally.
+    pub fn for_source(root: &Path, global_patterns: &[String]) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in global_patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| Error::InvalidIgnorePattern(e.to_string()))?;
+        }
+
+        let ignore_file = root.join(IGNORE_FILE_NAME);
+        if ignore_file.is_file() {
+            if let Some(e) = builder.add(&ignore_file) {
+                return Err(Error::InvalidIgnorePattern(e.to_string()));
+            }
+        }
+
+        let matcher = builder
+            .build()
+            .map_err(|e| Error::InvalidIgnorePattern(e.to_string()))?;
+        Ok(Self { matcher })
+    }
+
+    /// Whether `path` (located under the source root) is excluded.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.matcher.matched(path, is_dir).is_ignore()
+    }
+}
+
+/// Check that a single pattern parses as a gitignore line.
+pub fn validate_pattern(pattern: &str) -> Result<()> {
+    let mut builder = GitignoreBuilder::new("/");
+    builder
+        .add_line(None, pattern)
+        .map_err(|e| Error::InvalidIgnorePattern(e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_empty_rules_ignore_nothing() {
+        let rules = IgnoreRules::empty();
+        assert!(!rules.is_ignored(Path::new("/photos/a.jpg"), false));
+        assert!(!rules.is_ignored(Path::new("/photos/@eaDir"), true));
+    }
+
+    #[test]
+    fn test_global_patterns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let rules =
+            IgnoreRules::for_source(tmp.path(), &["@eaDir/".to_string(), "*.lrdata".to_string()])
+                .unwrap();
+
+        assert!(rules.is_ignored(&tmp.path().join("@eaDir"), true));
+        assert!(rules.is_ignored(&tmp.path().join("2024/@eaDir"), true));
+        assert!(rules.is_ignored(&tmp.path().join("Catalog Previews.lrdata"), true));
+        assert!(!rules.is_ignored(&tmp.path().join("photo.jpg"), false));
+        // Trailing slash only matches directories
+        assert!(!rules.is_ignored(&tmp.path().join("@eaDir"), false));
+    }
+
+    #[test]
+    fn test_ignore_file_in_source_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join(IGNORE_FILE_NAME),
+            "# caches\n.thumbnails/\n/exports\n",
+        )
+        .unwrap();
+        let rules = IgnoreRules::for_source(tmp.path(), &[]).unwrap();
+
+        assert!(rules.is_ignored(&tmp.path().join(".thumbnails"), true));
+        assert!(rules.is_ignored(&tmp.path().join("exports"), true));
+        // Leading slash anchors the pattern to the source root
+        assert!(!rules.is_ignored(&tmp.path().join("trip/exports"), true));
+    }
+
+    #[test]
+    fn test_ignore_file_can_reinclude_global_pattern() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join(IGNORE_FILE_NAME), "!keep.png\n").unwrap();
+        let rules = IgnoreRules::for_source(tmp.path(), &["*.png".to_string()]).unwrap();
+
+        assert!(rules.is_ignored(&tmp.path().join("other.png"), false));
+        assert!(!rules.is_ignored(&tmp.path().join("keep.png"), false));
+    }
+
+    #[test]
+    fn test_validate_pattern() {
+        assert!(validate_pattern("@eaDir/").is_ok());
+        assert!(validate_pattern("**/*.lrdata").is_ok());
+        assert!(matches!(
+            validate_pattern("photos/{a,b"),
+            Err(Error::InvalidIgnorePattern(_))
+        ));
+    }
+}
@@ -1,18 +1,69 @@
 pub mod formats;
+pub mod ignore_rules;
+pub mod moves;
+pub mod sidecars;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use walkdir::WalkDir;
 
-use crate::domain::ScannedFile;
+use crate::domain::{ScannedFile, ScannedSidecar, SidecarKind};
 use crate::error::Result;
+use crate::views;
 use formats::format_from_extension;
+use ignore_rules::IgnoreRules;
+
+/// Result of walking a source directory.
+#[derive(Debug, Default)]
+pub struct DirectoryScan {
+    /// Supported photo files that were not excluded.
+    pub files: Vec<ScannedFile>,
+    /// Paths skipped by ignore rules. Excluded directories are listed once and
+    /// never descended into; excluded files are only listed if they are photos.
+    pub excluded: Vec<PathBuf>,
+    /// Sidecar files (XMP, AAE, THM) bound to one of `files`. Orphans are dropped.
+    pub sidecars: Vec<ScannedSidecar>,
+}
 
 /// Recursively scan a directory for supported photo files.
 pub fn scan_directory(path: &Path) -> Result<Vec<ScannedFile>> {
+    Ok(scan_directory_with_rules(path, &IgnoreRules::empty())?.files)
+}
+
+/// Recursively scan a directory for supported photo files, honouring ignore rules.
+/// Ignored directories are pruned from the walk. When `path` is a pack, its
+/// link views and `.photopack/` (manifest, trash) are skipped too — only the
+/// content-addressed files are photos of the pack.
+pub fn scan_directory_with_rules(path: &Path, rules: &IgnoreRules) -> Result<DirectoryScan> {
     let mut files = Vec::new();
+    let mut excluded = Vec::new();
+    let mut sidecar_candidates = Vec::new();
+    let is_pack = path.join(".photopack").join("manifest.sqlite").is_file();
+
+    let walker = WalkDir::new(path).follow_links(true).into_iter();
+    let entries = walker.filter_entry(|entry| {
+        // Never exclude the source root itself
+        if entry.depth() == 0 {
+            return true;
+        }
+        if is_pack
+            && entry.depth() == 1
+            && (entry.file_name() == ".photopack"
+                || views::VIEW_DIRS.iter().any(|dir| entry.file_name() == *dir))
+        {
+            return false;
+        }
+        let is_dir = entry.file_type().is_dir();
+        if !rules.is_ignored(entry.path(), is_dir) {
+            return true;
+        }
+        if is_dir || is_supported_photo(entry.path()) {
+            excluded.push(entry.path().to_path_buf());
+        }
+        false
+    });
 
-    for entry in WalkDir::new(path).follow_links(true).into_iter().filter_map(|e| e.ok()) {
+    for entry in entries.filter_map(|e| e.ok()) {
         if !entry.file_type().is_file() {
             continue;
         }
@@ -25,10 +76,11 @@ pub fn scan_directory(path: &Path) -> Result<Vec<ScannedFile>> {
             None => continue,
         };
 
-        let format = match format_from_extension(&ext) {
-            Some(f) => f,
-            None => continue,
-        };
+        let format = format_from_extension(&ext);
+        let sidecar_kind = SidecarKind::from_extension(&ext);
+        if format.is_none() && sidecar_kind.is_none() {
+            continue;
+        }
 
         // Get metadata for size and mtime
         let metadata = match entry.metadata() {
@@ -43,6 +95,19 @@ pub fn scan_directory(path: &Path) -> Result<Vec<ScannedFile>> {
             .map(|d| d.as_secs() as i64)
             .unwrap_or(0);
 
+        let Some(format) = format else {
+            // A pack's sidecars belong to the manifest, not to its photos as a source
+            if let Some(kind) = sidecar_kind.filter(|_| !is_pack) {
+                sidecar_candidates.push(sidecars::SidecarCandidate {
+                    path: file_path.to_path_buf(),
+                    kind,
+                    size: metadata.len(),
+                    mtime,
+                });
+            }
+            continue;
+        };
+
         files.push(ScannedFile {
             path: file_path.to_path_buf(),
             size: metadata.len(),
@@ -51,7 +116,19 @@ pub fn scan_directory(path: &Path) -> Result<Vec<ScannedFile>> {
         });
     }
 
-    Ok(files)
+    let sidecars = sidecars::associate(&files, sidecar_candidates);
+    Ok(DirectoryScan {
+        files,
+        excluded,
+        sidecars,
+    })
+}
+
+pub(crate) fn is_supported_photo(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| format_from_extension(&e.to_lowercase()))
+        .is_some()
 }
 
 #[cfg(test)]
@@ -191,4 +268,94 @@ mod tests {
         #[cfg(unix)]
         assert_eq!(files.len(), 1);
     }
+
+    #[test]
+    fn test_scan_with_rules_prunes_ignored_directories() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = tmp.path().join("Catalog Previews.lrdata/A/0");
+        let synology = tmp.path().join("2024/@eaDir/photo.jpg");
+        fs::create_dir_all(&cache).unwrap();
+        fs::create_dir_all(&synology).unwrap();
+        fs::write(cache.join("preview.jpg"), b"cache").unwrap();
+        fs::write(synology.join("SYNOPHOTO_THUMB_XL.jpg"), b"thumb").unwrap();
+        fs::write(tmp.path().join("2024/photo.jpg"), b"photo").unwrap();
+
+        let rules = IgnoreRules::for_source(
+            tmp.path(),
+            &["*.lrdata/".to_string(), "@eaDir/".to_string()],
+        )
+        .unwrap();
+        let scan = scan_directory_with_rules(tmp.path(), &rules).unwrap();
+
+        assert_eq!(scan.files.len(), 1);
+        assert_eq!(scan.files[0].path, tmp.path().join("2024/photo.jpg"));
+        // Each pruned directory is reported once, its contents are never visited
+        let mut excluded = scan.excluded.clone();
+        excluded.sort();
+        assert_eq!(
+            excluded,
+            vec![
+                tmp.path().join("2024/@eaDir"),
+                tmp.path().join("Catalog Previews.lrdata"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_with_rules_reads_photopackignore() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join(".thumbnails")).unwrap();
+        fs::write(tmp.path().join(".thumbnails/t.png"), b"thumb").unwrap();
+        fs::write(tmp.path().join("keep.jpg"), b"keep").unwrap();
+        fs::write(tmp.path().join("skip.tmp.jpg"), b"skip").unwrap();
+        fs::write(tmp.path().join("notes.txt"), b"text").unwrap();
+        fs::write(
+            tmp.path().join(ignore_rules::IGNORE_FILE_NAME),
+            ".thumbnails/\n*.tmp.jpg\nnotes.txt\n",
+        )
+        .unwrap();
+
+        let rules = IgnoreRules::for_source(tmp.path(), &[]).unwrap();
+        let scan = scan_directory_with_rules(tmp.path(), &rules).unwrap();
+
+        assert_eq!(scan.files.len(), 1);
+        assert!(scan.files[0].path.ends_with("keep.jpg"));
+        // Ignored non-photo files are not reported
+        assert_eq!(scan.excluded.len(), 2);
+        assert!(scan.excluded.contains(&tmp.path().join(".thumbnails")));
+        assert!(scan.excluded.contains(&tmp.path().join("skip.tmp.jpg")));
+    }
+
+    #[test]
+    fn test_scan_with_rules_never_excludes_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("@eaDir");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("photo.jpg"), b"photo").unwrap();
+
+        let rules = IgnoreRules::for_source(&root, &["@eaDir/".to_string()]).unwrap();
+        let scan = scan_directory_with_rules(&root, &rules).unwrap();
+
+        assert_eq!(scan.files.len(), 1);
+        assert!(scan.excluded.is_empty());
+    }
+
+    #[test]
+    fn test_scan_skips_views_and_trash_only_inside_a_pack() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("ab")).unwrap();
+        fs::create_dir_all(tmp.path().join("by-date/2024")).unwrap();
+        fs::write(tmp.path().join("ab/abcd.jpg"), b"photo").unwrap();
+        fs::write(tmp.path().join("by-date/2024/IMG_0001.jpg"), b"photo").unwrap();
+
+        // Not a pack: by-date/ is an ordinary folder
+        assert_eq!(scan_directory(tmp.path()).unwrap().len(), 2);
+
+        fs::create_dir_all(tmp.path().join(".photopack/trash")).unwrap();
+        fs::write(tmp.path().join(".photopack/manifest.sqlite"), b"").unwrap();
+        fs::write(tmp.path().join(".photopack/trash/dead.jpg"), b"photo").unwrap();
+        let files = scan_directory(tmp.path()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].path.ends_with("ab/abcd.jpg"));
+    }
 }
@@ -0,0 +1,104 @@
+//! Detecting files that were moved or renamed inside a source between scans.
+//!
+//! A catalog path that vanished from disk and a newly found path are the same
+//! file when size, mtime and SHA-256 all match. Identical copies that moved
+//! together are paired in path order, so the result is deterministic.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A catalog row whose path vanished from disk: `(id, path, size, mtime, sha256)`.
+pub type VanishedPhoto = (i64, PathBuf, u64, i64, String);
+
+/// A catalog photo that moved: its row keeps `id` and takes the new path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedMove {
+    pub id: i64,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Pair vanished catalog rows with newly found files `(path, size, mtime, sha256)`.
+/// Unpaired entries on either side are left out. The result is sorted by new path.
+pub fn detect_moves<'a>(
+    vanished: Vec<VanishedPhoto>,
+    arrived: impl IntoIterator<Item = (&'a Path, u64, i64, &'a str)>,
+) -> Vec<DetectedMove> {
+    if vanished.is_empty() {
+        return Vec::new();
+    }
+
+    let mut by_key: HashMap<(u64, i64, String), Vec<(i64, PathBuf)>> = HashMap::new();
+    for (id, path, size, mtime, sha256) in vanished {
+        by_key.entry((size, mtime, sha256)).or_default().push((id, path));
+    }
+    for rows in by_key.values_mut() {
+        // Pop from the back: reverse order so the first path pairs first
+        rows.sort_by(|a, b| b.1.cmp(&a.1));
+    }
+
+    let mut arrived: Vec<(&Path, u64, i64, &str)> = arrived.into_iter().collect();
+    arrived.sort_by_key(|(path, ..)| *path);
+
+    arrived
+        .into_iter()
+        .filter_map(|(to, size, mtime, sha256)| {
+            let (id, from) = by_key.get_mut(&(size, mtime, sha256.to_string()))?.pop()?;
+            Some(DetectedMove {
+                id,
+                from,
+                to: to.to_path_buf(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i64, path: &str, sha: &str) -> VanishedPhoto {
+        (id, PathBuf::from(path), 100, 1000, sha.to_string())
+    }
+
+    #[test]
+    fn test_rename_detected() {
+        let moves = detect_moves(
+            vec![row(7, "/p/old/a.jpg", "aaa")],
+            [(Path::new("/p/new/a.jpg"), 100, 1000, "aaa")],
+        );
+        assert_eq!(
+            moves,
+            vec![DetectedMove {
+                id: 7,
+                from: PathBuf::from("/p/old/a.jpg"),
+                to: PathBuf::from("/p/new/a.jpg"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_any_key_mismatch_is_not_a_move() {
+        let vanished = || vec![row(7, "/p/a.jpg", "aaa")];
+        assert!(detect_moves(vanished(), [(Path::new("/p/b.jpg"), 101, 1000, "aaa")]).is_empty());
+        assert!(detect_moves(vanished(), [(Path::new("/p/b.jpg"), 100, 1001, "aaa")]).is_empty());
+        assert!(detect_moves(vanished(), [(Path::new("/p/b.jpg"), 100, 1000, "bbb")]).is_empty());
+    }
+
+    #[test]
+    fn test_identical_copies_paired_in_path_order() {
+        let moves = detect_moves(
+            vec![row(2, "/p/y.jpg", "aaa"), row(1, "/p/x.jpg", "aaa")],
+            [
+                (Path::new("/q/3.jpg"), 100, 1000, "aaa"),
+                (Path::new("/q/1.jpg"), 100, 1000, "aaa"),
+                (Path::new("/q/2.jpg"), 100, 1000, "aaa"),
+            ],
+        );
+        let pairs: Vec<(i64, &str)> = moves
+            .iter()
+            .map(|m| (m.id, m.to.to_str().unwrap()))
+            .collect();
+        assert_eq!(pairs, vec![(1, "/q/1.jpg"), (2, "/q/2.jpg")]);
+    }
+}
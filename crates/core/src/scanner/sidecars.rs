@@ -0,0 +1,176 @@
+//! Binding sidecar files (XMP, AAE, THM) to the photo they describe.
+//!
+//! A sidecar belongs to a photo in the same directory when its name is either
+//! the photo's full file name plus the sidecar extension (`IMG_1234.CR2.xmp`)
+//! or the photo's stem plus the extension (`IMG_1234.xmp`). Names are compared
+//! case-insensitively. The full-name form is the more specific one and wins;
+//! a stem-form sidecar binds to every photo sharing that stem (RAW+JPEG pairs).
+//! Each photo keeps at most one sidecar per kind.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::domain::{ScannedFile, ScannedSidecar, SidecarKind};
+
+/// A sidecar-looking file found by the walk, not yet bound to a photo.
+#[derive(Debug, Clone)]
+pub struct SidecarCandidate {
+    pub path: PathBuf,
+    pub kind: SidecarKind,
+    pub size: u64,
+    pub mtime: i64,
+}
+
+/// How a sidecar name matched its photo. Lower is more specific.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    FullName,
+    Stem,
+}
+
+/// Bind candidates to photos in `files`. Candidates matching no photo are dropped.
+/// The result is sorted by photo path, then kind.
+pub fn associate(files: &[ScannedFile], candidates: Vec<SidecarCandidate>) -> Vec<ScannedSidecar> {
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+
+    // (dir, lowercase file name) -> photo; (dir, lowercase stem) -> photos
+    let mut by_name: HashMap<(&Path, String), &Path> = HashMap::new();
+    let mut by_stem: HashMap<(&Path, String), Vec<&Path>> = HashMap::new();
+    for file in files {
+        let Some(dir) = file.path.parent() else {
+            continue;
+        };
+        if let Some(name) = file.path.file_name().and_then(|n| n.to_str()) {
+            by_name.insert((dir, name.to_lowercase()), &file.path);
+        }
+        if let Some(stem) = file.path.file_stem().and_then(|s| s.to_str()) {
+            by_stem
+                .entry((dir, stem.to_lowercase()))
+                .or_default()
+                .push(&file.path);
+        }
+    }
+
+    // (photo, kind) -> (match quality, candidate)
+    let mut best: HashMap<(&Path, SidecarKind), (Match, &SidecarCandidate)> = HashMap::new();
+    for candidate in &candidates {
+        let (Some(dir), Some(base)) = (
+            candidate.path.parent(),
+            candidate.path.file_stem().and_then(|s| s.to_str()),
+        ) else {
+            continue;
+        };
+        let base = base.to_lowercase();
+
+        let matches: Vec<(&Path, Match)> = match by_name.get(&(dir, base.clone())) {
+            Some(photo) => vec![(*photo, Match::FullName)],
+            None => by_stem
+                .get(&(dir, base))
+                .map(|photos| photos.iter().map(|p| (*p, Match::Stem)).collect())
+                .unwrap_or_default(),
+        };
+
+        for (photo, quality) in matches {
+            let slot = best.entry((photo, candidate.kind)).or_insert((quality, candidate));
+            if (quality, &candidate.path) < (slot.0, &slot.1.path) {
+                *slot = (quality, candidate);
+            }
+        }
+    }
+
+    let mut sidecars: Vec<ScannedSidecar> = best
+        .into_iter()
+        .map(|((photo, kind), (_, candidate))| ScannedSidecar {
+            path: candidate.path.clone(),
+            photo_path: photo.to_path_buf(),
+            kind,
+            size: candidate.size,
+            mtime: candidate.mtime,
+        })
+        .collect();
+    sidecars.sort_by(|a, b| (&a.photo_path, a.kind).cmp(&(&b.photo_path, b.kind)));
+    sidecars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::PhotoFormat;
+
+    fn photo(path: &str, format: PhotoFormat) -> ScannedFile {
+        ScannedFile {
+            path: PathBuf::from(path),
+            size: 100,
+            format,
+            mtime: 0,
+        }
+    }
+
+    fn candidate(path: &str) -> SidecarCandidate {
+        let path = PathBuf::from(path);
+        let ext = path.extension().unwrap().to_str().unwrap().to_lowercase();
+        SidecarCandidate {
+            kind: SidecarKind::from_extension(&ext).unwrap(),
+            path,
+            size: 10,
+            mtime: 0,
+        }
+    }
+
+    fn pairs(sidecars: &[ScannedSidecar]) -> Vec<(&str, &str)> {
+        sidecars
+            .iter()
+            .map(|s| (s.photo_path.to_str().unwrap(), s.path.to_str().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_stem_sidecar_binds_to_all_same_stem_photos() {
+        let files = vec![
+            photo("/p/IMG_1.CR2", PhotoFormat::Cr2),
+            photo("/p/IMG_1.JPG", PhotoFormat::Jpeg),
+            photo("/q/IMG_1.JPG", PhotoFormat::Jpeg),
+        ];
+        let sidecars = associate(&files, vec![candidate("/p/img_1.xmp")]);
+        assert_eq!(
+            pairs(&sidecars),
+            vec![("/p/IMG_1.CR2", "/p/img_1.xmp"), ("/p/IMG_1.JPG", "/p/img_1.xmp")]
+        );
+        assert!(sidecars.iter().all(|s| s.kind == SidecarKind::Xmp));
+    }
+
+    #[test]
+    fn test_full_name_sidecar_wins_over_stem() {
+        let files = vec![
+            photo("/p/IMG_1.CR2", PhotoFormat::Cr2),
+            photo("/p/IMG_1.JPG", PhotoFormat::Jpeg),
+        ];
+        let sidecars = associate(
+            &files,
+            vec![candidate("/p/IMG_1.xmp"), candidate("/p/IMG_1.CR2.xmp")],
+        );
+        assert_eq!(
+            pairs(&sidecars),
+            vec![("/p/IMG_1.CR2", "/p/IMG_1.CR2.xmp"), ("/p/IMG_1.JPG", "/p/IMG_1.xmp")]
+        );
+    }
+
+    #[test]
+    fn test_one_sidecar_per_kind_and_orphans_dropped() {
+        let files = vec![photo("/p/IMG_1.HEIC", PhotoFormat::Heic)];
+        let sidecars = associate(
+            &files,
+            vec![
+                candidate("/p/IMG_1.AAE"),
+                candidate("/p/IMG_1.xmp"),
+                candidate("/p/IMG_2.xmp"),
+                candidate("/other/IMG_1.thm"),
+            ],
+        );
+        assert_eq!(sidecars.len(), 2);
+        assert_eq!(sidecars[0].kind, SidecarKind::Xmp);
+        assert_eq!(sidecars[1].kind, SidecarKind::Aae);
+    }
+}
@@ -0,0 +1,56 @@
+//! Fixtures shared by the unit tests of the pack modules.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::hasher;
+use crate::manifest::Manifest;
+
+/// Manifest metadata for [`add_pack_file`]. Stored as a JPEG.
+#[derive(Default)]
+pub(crate) struct PackEntry<'a> {
+    pub name: &'a str,
+    pub exif_date: Option<&'a str>,
+    /// Camera make and model.
+    pub camera: Option<(&'a str, &'a str)>,
+    pub mtime: i64,
+}
+
+impl<'a> PackEntry<'a> {
+    /// An entry with only an original filename.
+    pub(crate) fn named(name: &'a str) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+/// Write `content` to its content-addressed path in `pack` and register it in
+/// the manifest. Returns the hash and the pack file path.
+pub(crate) fn add_pack_file(
+    pack: &Path,
+    manifest: &Manifest,
+    content: &[u8],
+    entry: PackEntry<'_>,
+) -> (String, PathBuf) {
+    let tmp = pack.join("incoming.tmp");
+    fs::write(&tmp, content).unwrap();
+    let sha = hasher::compute_sha256(&tmp).unwrap();
+    let path = pack.join(&sha[..2]).join(format!("{sha}.jpg"));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::rename(&tmp, &path).unwrap();
+    manifest
+        .insert_file(
+            &sha,
+            entry.name,
+            "JPEG",
+            content.len() as u64,
+            entry.exif_date,
+            entry.camera.map(|(make, _)| make),
+            entry.camera.map(|(_, model)| model),
+            entry.mtime,
+        )
+        .unwrap();
+    (sha, path)
+}
@@ -0,0 +1,407 @@
+//! Pack trash: stale pack files wait in `.photopack/trash/` for a retention
+//! window before they are deleted for good.
+//!
+//! Trashed files keep their manifest metadata (moved to the `trash` table), so
+//! they can be listed, restored to a directory, or put back into the pack when
+//! their hash becomes wanted again. Packed sidecars travel with their file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::domain::SidecarKind;
+use crate::error::{Error, Result};
+use crate::manifest::{Manifest, TrashEntry};
+use crate::vault_save::{build_sidecar_path, format_str_to_extension};
+
+/// Trash directory name inside `.photopack/`.
+pub const TRASH_DIR: &str = "trash";
+
+/// Days a trashed file is kept before `vault_save` purges it, unless configured.
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// Where a trashed file lives: `.photopack/trash/{sha256}.{ext}`.
+pub fn trash_path(pack_path: &Path, sha256: &str, format_str: &str) -> PathBuf {
+    pack_path
+        .join(".photopack")
+        .join(TRASH_DIR)
+        .join(format!("{}.{}", sha256, format_str_to_extension(format_str)))
+}
+
+/// Where a pack file lives: `{sha256[..2]}/{sha256}.{ext}`.
+pub(crate) fn content_path(pack_path: &Path, sha256: &str, format_str: &str) -> PathBuf {
+    pack_path
+        .join(&sha256[..2])
+        .join(format!("{}.{}", sha256, format_str_to_extension(format_str)))
+}
+
+fn sidecar_trash_path(pack_path: &Path, sha256: &str, kind: SidecarKind) -> PathBuf {
+    pack_path
+        .join(".photopack")
+        .join(TRASH_DIR)
+        .join(format!("{}.{}", sha256, kind.extension()))
+}
+
+/// Recorded sidecar kinds of a pack file.
+fn sidecar_kinds(manifest: &Manifest, sha256: &str) -> Result<Vec<SidecarKind>> {
+    Ok(manifest
+        .sidecars_for(sha256)?
+        .iter()
+        .filter_map(|s| SidecarKind::parse(&s.kind))
+        .collect())
+}
+
+/// Rename `source` to `target` if it exists, creating the target directory.
+fn move_if_present(source: &Path, target: &Path) -> Result<bool> {
+    if !source.is_file() {
+        return Ok(false);
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(source, target)?;
+    Ok(true)
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Move a pack file and its manifest entry to the trash.
+/// Returns the pack path the file was moved from, or `None` if the file was
+/// already gone from disk (the entry is trashed either way).
+pub fn move_to_trash(
+    pack_path: &Path,
+    manifest: &Manifest,
+    sha256: &str,
+    format_str: &str,
+) -> Result<Option<PathBuf>> {
+    let source = content_path(pack_path, sha256, format_str);
+    let target = trash_path(pack_path, sha256, format_str);
+
+    let moved = move_if_present(&source, &target)?.then_some(source);
+    for kind in sidecar_kinds(manifest, sha256)? {
+        move_if_present(
+            &build_sidecar_path(pack_path, sha256, kind),
+            &sidecar_trash_path(pack_path, sha256, kind),
+        )?;
+    }
+    manifest.trash_file(sha256)?;
+    Ok(moved)
+}
+
+/// Put a trashed file back into the pack's content store.
+/// Returns false if `sha256` is not in the trash.
+pub fn untrash(pack_path: &Path, manifest: &Manifest, sha256: &str) -> Result<bool> {
+    let Some(entry) = manifest
+        .list_trash()?
+        .into_iter()
+        .find(|e| e.sha256 == sha256)
+    else {
+        return Ok(false);
+    };
+
+    move_if_present(
+        &trash_path(pack_path, sha256, &entry.format),
+        &content_path(pack_path, sha256, &entry.format),
+    )?;
+    for kind in sidecar_kinds(manifest, sha256)? {
+        move_if_present(
+            &sidecar_trash_path(pack_path, sha256, kind),
+            &build_sidecar_path(pack_path, sha256, kind),
+        )?;
+    }
+    manifest.untrash_file(sha256)?;
+    Ok(true)
+}
+
+/// Find the single trash entry whose hash starts with `hash_prefix`.
+pub fn find_entry(manifest: &Manifest, hash_prefix: &str) -> Result<TrashEntry> {
+    let prefix = hash_prefix.to_lowercase();
+    let mut matches: Vec<TrashEntry> = manifest
+        .list_trash()?
+        .into_iter()
+        .filter(|e| !prefix.is_empty() && e.sha256.starts_with(&prefix))
+        .collect();
+    match matches.len() {
+        0 => Err(Error::NotInTrash(hash_prefix.to_string())),
+        1 => Ok(matches.remove(0)),
+        _ => Err(Error::AmbiguousHashPrefix(hash_prefix.to_string())),
+    }
+}
+
+/// `name`, then `name_{sha[..8]}`, then `name_{sha[..8]}_2`, `_3`, ...
+fn candidate_names(name: &str, sha256: &str) -> impl Iterator<Item = String> {
+    let suffixed = crate::restore::suffixed_name(name, sha256);
+    std::iter::once(name.to_string())
+        .chain(std::iter::once(suffixed.clone()))
+        .chain((2u32..).map(move |n| crate::restore::suffixed_name(&suffixed, &n.to_string())))
+}
+
+/// Copy `source` into `dest` under the first of `names` that is not taken.
+/// The target is created exclusively, so an existing file is never replaced.
+fn copy_to_free_name(
+    source: &Path,
+    dest: &Path,
+    names: impl Iterator<Item = String>,
+) -> Result<PathBuf> {
+    let mut input = fs::File::open(source)?;
+    for name in names {
+        let target = dest.join(name);
+        let mut output = match fs::OpenOptions::new().write(true).create_new(true).open(&target) {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if let Err(e) = std::io::copy(&mut input, &mut output) {
+            let _ = fs::remove_file(&target);
+            return Err(e.into());
+        }
+        return Ok(target);
+    }
+    unreachable!("candidate names are unbounded")
+}
+
+/// Move a trashed file out to `dest/original_filename` and drop it from the trash.
+/// A taken name gets the `_{sha[..8]}` suffix (then `_2`, `_3`, ...) instead of
+/// being overwritten. Its sidecars are restored next to it, named after the
+/// restored file and suffixed the same way if their name is taken.
+pub fn restore_to(
+    pack_path: &Path,
+    manifest: &Manifest,
+    entry: &TrashEntry,
+    dest: &Path,
+) -> Result<PathBuf> {
+    let source = trash_path(pack_path, &entry.sha256, &entry.format);
+    if !source.is_file() {
+        return Err(Error::NotInTrash(entry.sha256.clone()));
+    }
+
+    fs::create_dir_all(dest)?;
+    // Copy rather than rename: `dest` may be on another filesystem
+    let target = copy_to_free_name(
+        &source,
+        dest,
+        candidate_names(&entry.original_filename, &entry.sha256),
+    )?;
+    let restored_name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    for sidecar in manifest.sidecars_for(&entry.sha256)? {
+        let Some(kind) = SidecarKind::parse(&sidecar.kind) else {
+            continue;
+        };
+        let sidecar_source = sidecar_trash_path(pack_path, &entry.sha256, kind);
+        if !sidecar_source.is_file() {
+            continue;
+        }
+        let name =
+            crate::restore::sidecar_name(&restored_name, &entry.original_filename, &sidecar);
+        copy_to_free_name(&sidecar_source, dest, candidate_names(&name, &entry.sha256))?;
+        fs::remove_file(&sidecar_source)?;
+    }
+    fs::remove_file(&source)?;
+    manifest.remove_sidecars_for(&entry.sha256)?;
+    manifest.remove_trash(&entry.sha256)?;
+    Ok(target)
+}
+
+/// Delete trashed files for good: those trashed at least `older_than_days` ago,
+/// or everything with `None`. Returns the purged entries.
+pub fn purge(
+    pack_path: &Path,
+    manifest: &Manifest,
+    older_than_days: Option<u32>,
+) -> Result<Vec<TrashEntry>> {
+    let entries = match older_than_days {
+        Some(days) => manifest.list_trash_older_than(days)?,
+        None => manifest.list_trash()?,
+    };
+    for entry in &entries {
+        remove_if_present(&trash_path(pack_path, &entry.sha256, &entry.format))?;
+        for kind in sidecar_kinds(manifest, &entry.sha256)? {
+            remove_if_present(&sidecar_trash_path(pack_path, &entry.sha256, kind))?;
+        }
+        manifest.remove_sidecars_for(&entry.sha256)?;
+        manifest.remove_trash(&entry.sha256)?;
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{add_pack_file, PackEntry};
+
+    #[test]
+    fn test_move_to_trash_and_untrash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (sha, pack_file) =
+            add_pack_file(&pack, &manifest, b"one", PackEntry::named("a.jpg"));
+
+        let moved = move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+        assert_eq!(moved, Some(pack_file.clone()));
+        assert!(!pack_file.exists());
+        assert_eq!(fs::read(trash_path(&pack, &sha, "JPEG")).unwrap(), b"one");
+        assert!(!manifest.contains(&sha).unwrap());
+
+        assert!(untrash(&pack, &manifest, &sha).unwrap());
+        assert_eq!(fs::read(&pack_file).unwrap(), b"one");
+        assert!(manifest.contains(&sha).unwrap());
+        assert!(!untrash(&pack, &manifest, &sha).unwrap());
+    }
+
+    #[test]
+    fn test_sidecars_travel_with_trashed_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let dest = tmp.path().join("restored");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (sha, _) = add_pack_file(&pack, &manifest, b"one", PackEntry::named("IMG_1.CR2"));
+        let packed_xmp = build_sidecar_path(&pack, &sha, SidecarKind::Xmp);
+        fs::write(&packed_xmp, b"<xmp/>").unwrap();
+        manifest
+            .upsert_sidecar(&crate::manifest::ManifestSidecar {
+                sha256: sha.clone(),
+                kind: "XMP".to_string(),
+                sidecar_sha256: "x".repeat(64),
+                size: 6,
+                original_filename: "IMG_1.CR2.xmp".to_string(),
+            })
+            .unwrap();
+
+        move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+        assert!(!packed_xmp.exists());
+        assert!(sidecar_trash_path(&pack, &sha, SidecarKind::Xmp).is_file());
+
+        untrash(&pack, &manifest, &sha).unwrap();
+        assert_eq!(fs::read(&packed_xmp).unwrap(), b"<xmp/>");
+
+        move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+        let entry = find_entry(&manifest, &sha).unwrap();
+        restore_to(&pack, &manifest, &entry, &dest).unwrap();
+        assert_eq!(fs::read(dest.join("IMG_1.CR2.xmp")).unwrap(), b"<xmp/>");
+        assert!(!sidecar_trash_path(&pack, &sha, SidecarKind::Xmp).exists());
+        assert!(manifest.sidecars_for(&sha).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_entry_by_prefix() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        for sha in ["abc111", "abc222", "def333"] {
+            manifest
+                .insert_file(sha, "p.jpg", "JPEG", 10, None, None, None, 0)
+                .unwrap();
+            manifest.trash_file(sha).unwrap();
+        }
+
+        assert_eq!(find_entry(&manifest, "DEF").unwrap().sha256, "def333");
+        assert_eq!(find_entry(&manifest, "abc2").unwrap().sha256, "abc222");
+        assert!(matches!(
+            find_entry(&manifest, "abc"),
+            Err(Error::AmbiguousHashPrefix(_))
+        ));
+        assert!(matches!(find_entry(&manifest, "ff"), Err(Error::NotInTrash(_))));
+        assert!(matches!(find_entry(&manifest, ""), Err(Error::NotInTrash(_))));
+    }
+
+    #[test]
+    fn test_restore_to_directory_avoids_overwrite() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let dest = tmp.path().join("restored");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (sha, _) = add_pack_file(&pack, &manifest, b"one", PackEntry::named("a.jpg"));
+        move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("a.jpg"), b"existing").unwrap();
+
+        let entry = find_entry(&manifest, &sha).unwrap();
+        let restored = restore_to(&pack, &manifest, &entry, &dest).unwrap();
+        assert_eq!(restored, dest.join(format!("a_{}.jpg", &sha[..8])));
+        assert_eq!(fs::read(&restored).unwrap(), b"one");
+        assert_eq!(fs::read(dest.join("a.jpg")).unwrap(), b"existing");
+        assert!(manifest.list_trash().unwrap().is_empty());
+        assert!(!trash_path(&pack, &sha, "JPEG").exists());
+    }
+
+    #[test]
+    fn test_restore_twice_into_same_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let dest = tmp.path().join("restored");
+        let manifest = Manifest::open(&pack).unwrap();
+
+        // The same content trashed and restored three times over
+        let mut restored = Vec::new();
+        let mut sha = String::new();
+        for _ in 0..3 {
+            sha = add_pack_file(&pack, &manifest, b"one", PackEntry::named("a.jpg")).0;
+            move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+            let entry = find_entry(&manifest, &sha).unwrap();
+            restored.push(restore_to(&pack, &manifest, &entry, &dest).unwrap());
+        }
+        assert_eq!(
+            restored,
+            [
+                dest.join("a.jpg"),
+                dest.join(format!("a_{}.jpg", &sha[..8])),
+                dest.join(format!("a_{}_2.jpg", &sha[..8])),
+            ]
+        );
+        for path in &restored {
+            assert_eq!(fs::read(path).unwrap(), b"one");
+        }
+    }
+
+    #[test]
+    fn test_restore_keeps_sidecar_whose_name_is_taken() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let dest = tmp.path().join("restored");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (sha, _) = add_pack_file(&pack, &manifest, b"one", PackEntry::named("IMG_1.CR2"));
+        fs::write(build_sidecar_path(&pack, &sha, SidecarKind::Xmp), b"<xmp/>").unwrap();
+        manifest
+            .upsert_sidecar(&crate::manifest::ManifestSidecar {
+                sha256: sha.clone(),
+                kind: "XMP".to_string(),
+                sidecar_sha256: "x".repeat(64),
+                size: 6,
+                original_filename: "IMG_1.xmp".to_string(),
+            })
+            .unwrap();
+        move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+        // Someone else's sidecar already sits where ours would go
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("IMG_1.xmp"), b"theirs").unwrap();
+
+        let entry = find_entry(&manifest, &sha).unwrap();
+        let restored = restore_to(&pack, &manifest, &entry, &dest).unwrap();
+        assert_eq!(restored, dest.join("IMG_1.CR2"));
+        assert_eq!(fs::read(dest.join("IMG_1.xmp")).unwrap(), b"theirs");
+        let ours = dest.join(format!("IMG_1_{}.xmp", &sha[..8]));
+        assert_eq!(fs::read(ours).unwrap(), b"<xmp/>");
+        assert!(!sidecar_trash_path(&pack, &sha, SidecarKind::Xmp).exists());
+    }
+
+    #[test]
+    fn test_purge_respects_retention() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (sha, _) = add_pack_file(&pack, &manifest, b"one", PackEntry::named("a.jpg"));
+        move_to_trash(&pack, &manifest, &sha, "JPEG").unwrap();
+
+        assert!(purge(&pack, &manifest, Some(DEFAULT_RETENTION_DAYS)).unwrap().is_empty());
+        assert!(trash_path(&pack, &sha, "JPEG").exists());
+
+        let purged = purge(&pack, &manifest, None).unwrap();
+        assert_eq!(purged.len(), 1);
+        assert!(!trash_path(&pack, &sha, "JPEG").exists());
+        assert!(manifest.list_trash().unwrap().is_empty());
+    }
+}
@@ -1,12 +1,23 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::domain::{DuplicateGroup, PhotoFile, PhotoFormat};
-use crate::error::Result;
-use crate::manifest::Manifest;
+use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::domain::{
+    DuplicateGroup, FormatFamily, PackPolicy, PackRole, PhotoFile, PhotoFormat, Sidecar, SidecarKind,
+};
+use crate::error::{Error, Result};
+use crate::manifest::{Manifest, ManifestSidecar};
+use crate::{ranking, trash, views};
 
 /// Progress callback events for the vault save operation.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum VaultSaveProgress {
     /// Starting save with total count.
     Start { total: usize },
@@ -14,13 +25,22 @@ pub enum VaultSaveProgress {
     Copied { source: PathBuf, target: PathBuf },
     /// A file was skipped (already exists).
     Skipped { path: PathBuf },
-    /// A stale file was removed from the pack.
+    /// A file could not be copied (unreadable, changed since the scan, or a write error).
+    Failed { path: PathBuf, error: String },
+    /// A sidecar was copied next to its pack file (new or changed since the last save).
+    SidecarCopied { source: PathBuf, target: PathBuf },
+    /// A stale file was moved from the pack to the trash.
     Removed { path: PathBuf },
+    /// Trashed files past the retention window were deleted.
+    TrashPurged { count: usize },
+    /// The `by-date/` and `by-camera/` views were brought up to date.
+    ViewsSynced { linked: usize, removed: usize },
     /// Save completed.
     Complete {
         copied: usize,
         skipped: usize,
         removed: usize,
+        failed: usize,
     },
 }
 
@@ -69,12 +89,136 @@ pub fn build_content_path(pack_path: &Path, sha256: &str, format: PhotoFormat) -
         .join(format!("{}.{}", sha256, format.extension()))
 }
 
+/// Build the path of a packed sidecar: `pack_path/{sha256[..2]}/{sha256}.{ext}`,
+/// next to the pack file it belongs to.
+pub fn build_sidecar_path(pack_path: &Path, sha256: &str, kind: SidecarKind) -> PathBuf {
+    pack_path
+        .join(&sha256[..2])
+        .join(format!("{}.{}", sha256, kind.extension()))
+}
+
+/// Pick the sidecars to pack with each saved photo: its own, then — for kinds
+/// it has none of — those of other catalog photos with the same SHA-256, so an
+/// XMP next to an exact copy is not lost when that copy isn't the source of truth.
+pub fn select_sidecars<'a>(
+    to_save: &[&'a PhotoFile],
+    all_photos: &[PhotoFile],
+    sidecars: &'a [Sidecar],
+) -> Vec<(&'a PhotoFile, Vec<&'a Sidecar>)> {
+    let mut by_photo: HashMap<i64, Vec<&Sidecar>> = HashMap::new();
+    for sidecar in sidecars {
+        by_photo.entry(sidecar.photo_id).or_default().push(sidecar);
+    }
+    let mut ids_by_sha: HashMap<&str, Vec<i64>> = HashMap::new();
+    for photo in all_photos {
+        ids_by_sha.entry(photo.sha256.as_str()).or_default().push(photo.id);
+    }
+
+    to_save
+        .iter()
+        .filter_map(|photo| {
+            let copies = ids_by_sha.get(photo.sha256.as_str()).into_iter().flatten();
+            let mut chosen: BTreeMap<SidecarKind, &Sidecar> = BTreeMap::new();
+            for id in std::iter::once(&photo.id).chain(copies.filter(|id| **id != photo.id)) {
+                for sidecar in by_photo.get(id).into_iter().flatten() {
+                    chosen.entry(sidecar.kind).or_insert(*sidecar);
+                }
+            }
+            (!chosen.is_empty()).then(|| (*photo, chosen.into_values().collect()))
+        })
+        .collect()
+}
+
+/// Bring the packed sidecars of saved pack files up to date.
+///
+/// A sidecar is (re)written when the manifest records a different hash for it
+/// or its file is missing, so an edited XMP is re-packed even though the image
+/// bytes did not change. Packed sidecars of saved pack files whose source
+/// sidecar is gone are deleted; those of stale pack files are left for the
+/// trash. Returns the `(source, target)` pairs written.
+pub fn sync_sidecars(
+    pack_path: &Path,
+    manifest: &Manifest,
+    wanted: &[(&PhotoFile, Vec<&Sidecar>)],
+    saved_hashes: &HashSet<String>,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let recorded: HashMap<(String, String), ManifestSidecar> = manifest
+        .list_sidecars()?
+        .into_iter()
+        .map(|s| ((s.sha256.clone(), s.kind.clone()), s))
+        .collect();
+
+    let mut wanted_keys = HashSet::new();
+    let mut to_copy: Vec<(&PhotoFile, &Sidecar, PathBuf)> = Vec::new();
+    for (photo, sidecars) in wanted {
+        if !manifest.contains(&photo.sha256)? {
+            continue;
+        }
+        for sidecar in sidecars {
+            let key = (photo.sha256.clone(), sidecar.kind.as_str().to_string());
+            let target = build_sidecar_path(pack_path, &photo.sha256, sidecar.kind);
+            let up_to_date = recorded
+                .get(&key)
+                .is_some_and(|r| r.sidecar_sha256 == sidecar.sha256)
+                && fs::metadata(&target).is_ok_and(|m| m.len() == sidecar.size);
+            if !up_to_date {
+                to_copy.push((*photo, *sidecar, target));
+            }
+            wanted_keys.insert(key);
+        }
+    }
+
+    for (key, record) in &recorded {
+        if !saved_hashes.contains(&record.sha256) || wanted_keys.contains(key) {
+            continue;
+        }
+        if let Some(kind) = SidecarKind::parse(&record.kind) {
+            match fs::remove_file(build_sidecar_path(pack_path, &record.sha256, kind)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        manifest.remove_sidecar(&record.sha256, &record.kind)?;
+    }
+
+    // Parallel copy, then record in the manifest sequentially (Connection is not Sync)
+    let copied: Vec<(&PhotoFile, &Sidecar, &PathBuf)> = to_copy
+        .par_iter()
+        .filter_map(|(photo, sidecar, target)| {
+            replace_pack_file(&sidecar.path, target, &sidecar.sha256)
+                .ok()
+                .map(|()| (*photo, *sidecar, target))
+        })
+        .collect();
+
+    let mut written = Vec::with_capacity(copied.len());
+    for (photo, sidecar, target) in copied {
+        manifest.upsert_sidecar(&ManifestSidecar {
+            sha256: photo.sha256.clone(),
+            kind: sidecar.kind.as_str().to_string(),
+            sidecar_sha256: sidecar.sha256.clone(),
+            size: sidecar.size,
+            original_filename: sidecar
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        })?;
+        written.push((sidecar.path.clone(), target.clone()));
+    }
+    Ok(written)
+}
+
 /// Determine which photos to save to the vault:
 /// - For each duplicate group, take only the source-of-truth.
+///   A pinned member (hash in `pins`) wins over the recorded one.
 /// - For ungrouped photos, take the photo itself.
 pub fn select_photos_to_export<'a>(
     all_photos: &'a [PhotoFile],
     groups: &[DuplicateGroup],
+    pins: &HashSet<String>,
+    policy: &ranking::RankingPolicy,
 ) -> Vec<&'a PhotoFile> {
     let mut grouped_ids: HashSet<i64> = HashSet::new();
     let mut sot_ids: HashSet<i64> = HashSet::new();
@@ -83,7 +227,16 @@ pub fn select_photos_to_export<'a>(
         for member in &group.members {
             grouped_ids.insert(member.id);
         }
-        sot_ids.insert(group.source_of_truth_id);
+        let pinned: Vec<&PhotoFile> = group
+            .members
+            .iter()
+            .filter(|m| pins.contains(&m.sha256))
+            .collect();
+        if pinned.is_empty() || pinned.iter().any(|m| m.id == group.source_of_truth_id) {
+            sot_ids.insert(group.source_of_truth_id);
+        } else {
+            sot_ids.insert(ranking::elect_source_of_truth(&pinned, policy).id);
+        }
     }
 
     all_photos
@@ -98,25 +251,190 @@ pub fn select_photos_to_export<'a>(
         .collect()
 }
 
+/// A photo chosen for the pack, with the group and role recorded in the manifest.
+#[derive(Debug, Clone, Copy)]
+pub struct PackSelection<'a> {
+    pub photo: &'a PhotoFile,
+    /// Hash of the group's packed source of truth, which names the group in the
+    /// manifest (catalog group ids are reissued on every rescan).
+    pub group_sha256: Option<&'a str>,
+    pub role: PackRole,
+}
+
+/// Determine which photos to pack: the export selection (see
+/// [`select_photos_to_export`]) plus, under [`PackPolicy::Families`], the best
+/// copy of every other format family in each group (e.g. the camera JPEG of a
+/// RAW+JPEG pair). Copies byte-identical to a kept file are not packed twice.
+pub fn select_pack_files<'a>(
+    all_photos: &'a [PhotoFile],
+    groups: &[DuplicateGroup],
+    pins: &HashSet<String>,
+    ranking: &ranking::RankingPolicy,
+    pack_policy: PackPolicy,
+) -> Vec<PackSelection<'a>> {
+    let group_of: HashMap<i64, i64> = groups
+        .iter()
+        .flat_map(|g| g.members.iter().map(move |m| (m.id, g.id)))
+        .collect();
+    let mut selected: Vec<PackSelection<'a>> =
+        select_photos_to_export(all_photos, groups, pins, ranking)
+            .into_iter()
+            .map(|photo| match group_of.get(&photo.id) {
+                Some(_) => PackSelection {
+                    photo,
+                    group_sha256: Some(photo.sha256.as_str()),
+                    role: PackRole::Source,
+                },
+                None => PackSelection {
+                    photo,
+                    group_sha256: None,
+                    role: PackRole::Unique,
+                },
+            })
+            .collect();
+    if pack_policy == PackPolicy::Source {
+        return selected;
+    }
+
+    let by_id: HashMap<i64, &'a PhotoFile> = all_photos.iter().map(|p| (p.id, p)).collect();
+    let source_of: HashMap<i64, &'a PhotoFile> = selected
+        .iter()
+        .filter_map(|s| Some((*group_of.get(&s.photo.id)?, s.photo)))
+        .collect();
+    let mut kept: HashSet<&'a str> = selected.iter().map(|s| s.photo.sha256.as_str()).collect();
+
+    for group in groups {
+        let Some(&source) = source_of.get(&group.id) else {
+            continue;
+        };
+        let kept_family = source.format.family();
+        let families = [
+            FormatFamily::Raw,
+            FormatFamily::Lossless,
+            FormatFamily::Lossy,
+            FormatFamily::Video,
+        ];
+        for family in families.into_iter().filter(|f| *f != kept_family) {
+            let candidates: Vec<&'a PhotoFile> = group
+                .members
+                .iter()
+                .filter_map(|m| by_id.get(&m.id).copied())
+                .filter(|p| p.format.family() == family && !kept.contains(p.sha256.as_str()))
+                .collect();
+            if candidates.is_empty() {
+                continue;
+            }
+            let best = ranking::elect_with_pins(&candidates, pins, ranking);
+            kept.insert(best.sha256.as_str());
+            selected.push(PackSelection {
+                photo: best,
+                group_sha256: Some(source.sha256.as_str()),
+                role: PackRole::Alternate,
+            });
+        }
+    }
+    selected
+}
+
 /// Copy a single file to a content-addressed target path.
-/// Returns Ok(false) if skipped (file already exists — content-addressed: existence = correct).
+/// Returns Ok(false) if skipped (target already exists with the expected size —
+/// content-addressed: existence = correct). A target whose size differs is a
+/// leftover of an interrupted or damaged write and is replaced.
 /// Returns Ok(true) if copied.
-pub fn copy_photo_to_pack(source: &Path, target: &Path) -> Result<bool> {
-    if target.exists() {
+pub fn copy_photo_to_pack(
+    source: &Path,
+    target: &Path,
+    expected_sha256: &str,
+    expected_size: u64,
+) -> Result<bool> {
+    if !needs_copy(target, expected_size) {
         return Ok(false);
     }
 
-    if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent)?;
+    replace_pack_file(source, target, expected_sha256)?;
+    Ok(true)
+}
+
+/// Whether a content-addressed `target` is missing or has the wrong size.
+pub fn needs_copy(target: &Path, expected_size: u64) -> bool {
+    !fs::metadata(target).is_ok_and(|meta| meta.len() == expected_size)
+}
+
+/// Unique suffix for temp files, so parallel writers never share one.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Atomically write a copy of `source` to the pack file `target`.
+///
+/// The bytes go to a temp file next to `target` and are hashed while being
+/// written. Only if the SHA-256 matches `expected_sha256` is the temp file
+/// fsync'd and renamed over `target`, so a crash, full disk or changed source
+/// never leaves a truncated or wrong file under a hash name.
+pub fn replace_pack_file(source: &Path, target: &Path, expected_sha256: &str) -> Result<()> {
+    let parent = target.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp = parent.join(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = write_verified(source, &temp, expected_sha256).and_then(|()| {
+        fs::rename(&temp, target)?;
+        sync_dir(parent);
+        Ok(())
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Stream `source` into a new file at `temp`, hashing as it goes, then fsync.
+fn write_verified(source: &Path, temp: &Path, expected_sha256: &str) -> Result<()> {
+    let mut reader = fs::File::open(source)?;
+    let mut writer = fs::File::create(temp)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
     }
 
-    fs::copy(source, target)?;
-    Ok(true)
+    let actual = format!("{:x}", hasher.finalize());
+    if actual != expected_sha256 {
+        return Err(Error::HashMismatch {
+            path: source.to_path_buf(),
+            expected: expected_sha256.to_string(),
+            actual,
+        });
+    }
+
+    writer.sync_all()?;
+    Ok(())
+}
+
+/// Persist a rename by fsyncing the containing directory (no-op where unsupported).
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(handle) = fs::File::open(dir) {
+        let _ = handle.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
 }
 
-/// Remove pack files whose hashes are not in `desired_hashes`.
-/// Queries the manifest for all entries, removes stale files from disk and manifest.
-/// Returns the list of removed file paths.
+/// Move pack files whose hashes are not in `desired_hashes` to the trash.
+/// Queries the manifest for all entries; stale files lose their view links and
+/// are moved, with their manifest entries, to `.photopack/trash/` — nothing is
+/// deleted here (see [`trash::purge`]). Returns the pack paths moved to the trash.
 pub fn cleanup_pack_files(
     pack_path: &Path,
     desired_hashes: &HashSet<String>,
@@ -130,16 +448,12 @@ pub fn cleanup_pack_files(
     let mut removed = Vec::new();
     for (sha256, format_str) in &entries {
         if !desired_hashes.contains(sha256.as_str()) {
-            // Reconstruct format from string to get extension
-            let ext = format_str_to_extension(format_str);
-            let prefix = &sha256[..2];
-            let file_path = pack_path
-                .join(prefix)
-                .join(format!("{}.{}", sha256, ext));
-            if fs::remove_file(&file_path).is_ok() {
+            views::remove_links_for(pack_path, manifest, sha256);
+            if let Ok(Some(file_path)) =
+                trash::move_to_trash(pack_path, manifest, sha256, format_str)
+            {
                 removed.push(file_path);
             }
-            let _ = manifest.remove(sha256);
         }
     }
 
@@ -147,7 +461,7 @@ pub fn cleanup_pack_files(
 }
 
 /// Map a format string (as stored in manifest) back to file extension.
-fn format_str_to_extension(format_str: &str) -> &str {
+pub(crate) fn format_str_to_extension(format_str: &str) -> &str {
     match format_str {
         "CR2" => "cr2",
         "CR3" => "cr3",
@@ -162,6 +476,8 @@ fn format_str_to_extension(format_str: &str) -> &str {
         "JPEG" => "jpg",
         "HEIC" => "heic",
         "WebP" => "webp",
+        "MOV" => "mov",
+        "MP4" => "mp4",
         other => other,
     }
 }
@@ -170,6 +486,7 @@ fn format_str_to_extension(format_str: &str) -> &str {
 mod tests {
     use super::*;
     use crate::domain::*;
+    use crate::ranking::RankingPolicy;
 
     // ── parse_exif_date ─────────────────────────────────────────
 
@@ -235,6 +552,7 @@ mod tests {
             gps_lon: None,
             width: None,
             height: None,
+            content_id: None,
         });
         assert_eq!(date_for_photo(&photo), (2024, 6, 15));
     }
@@ -263,7 +581,7 @@ mod tests {
             make_photo_with_path(1, "/a.jpg"),
             make_photo_with_path(2, "/b.jpg"),
         ];
-        let selected = select_photos_to_export(&photos, &[]);
+        let selected = select_photos_to_export(&photos, &[], &HashSet::new(), &RankingPolicy::default());
         assert_eq!(selected.len(), 2);
     }
 
@@ -280,7 +598,7 @@ mod tests {
             source_of_truth_id: 1,
             confidence: Confidence::Certain,
         }];
-        let selected = select_photos_to_export(&photos, &groups);
+        let selected = select_photos_to_export(&photos, &groups, &HashSet::new(), &RankingPolicy::default());
         assert_eq!(selected.len(), 2);
         let ids: HashSet<i64> = selected.iter().map(|p| p.id).collect();
         assert!(ids.contains(&1), "SoT should be included");
@@ -301,6 +619,7 @@ mod tests {
             gps_lon: None,
             width: None,
             height: None,
+            content_id: None,
         });
         let (year, month, day) = date_for_photo(&photo);
         assert_eq!(year, 2024);
@@ -319,6 +638,7 @@ mod tests {
             gps_lon: None,
             width: None,
             height: None,
+            content_id: None,
         });
         let (year, month, day) = date_for_photo(&photo);
         assert_eq!(year, 2024);
@@ -351,7 +671,7 @@ mod tests {
                 confidence: Confidence::High,
             },
         ];
-        let selected = select_photos_to_export(&photos, &groups);
+        let selected = select_photos_to_export(&photos, &groups, &HashSet::new(), &RankingPolicy::default());
         // SoT 1 from group 1 + SoT 3 from group 2 + ungrouped 5 = 3
         assert_eq!(selected.len(), 3);
         let ids: HashSet<i64> = selected.iter().map(|p| p.id).collect();
@@ -372,14 +692,77 @@ mod tests {
             source_of_truth_id: 2,
             confidence: Confidence::Certain,
         }];
-        let selected = select_photos_to_export(&photos, &groups);
+        let selected = select_photos_to_export(&photos, &groups, &HashSet::new(), &RankingPolicy::default());
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, 2);
+    }
+
+    #[test]
+    fn test_select_photos_pinned_member_replaces_sot() {
+        let mut photos = vec![
+            make_photo_with_path(1, "/a.cr2"),
+            make_photo_with_path(2, "/a.jpg"),
+        ];
+        photos[1].sha256 = "edited".to_string();
+        let groups = vec![DuplicateGroup {
+            id: 1,
+            members: vec![photos[0].clone(), photos[1].clone()],
+            source_of_truth_id: 1,
+            confidence: Confidence::High,
+        }];
+        let pins = HashSet::from(["edited".to_string()]);
+        let selected = select_photos_to_export(&photos, &groups, &pins, &RankingPolicy::default());
         assert_eq!(selected.len(), 1);
         assert_eq!(selected[0].id, 2);
     }
 
+    #[test]
+    fn test_select_pack_files_keeps_one_copy_per_family() {
+        let mut photos = vec![
+            make_photo_with_path(1, "/a.cr2"),
+            make_photo_with_path(2, "/a.jpg"),
+            make_photo_with_path(3, "/b/a.jpg"),
+            make_photo_with_path(4, "/a.heic"),
+            make_photo_with_path(5, "/other.jpg"),
+        ];
+        photos[0].format = PhotoFormat::Cr2;
+        photos[2].size = 2000;
+        photos[3].format = PhotoFormat::Heic;
+        // A byte-identical copy of the kept RAW under another extension
+        photos[3].sha256 = photos[0].sha256.clone();
+        let groups = vec![DuplicateGroup {
+            id: 7,
+            members: photos[..4].to_vec(),
+            source_of_truth_id: 1,
+            confidence: Confidence::High,
+        }];
+        let ranking = RankingPolicy::default();
+
+        let source_only =
+            select_pack_files(&photos, &groups, &HashSet::new(), &ranking, PackPolicy::Source);
+        let roles: Vec<(i64, PackRole)> = source_only.iter().map(|s| (s.photo.id, s.role)).collect();
+        assert_eq!(roles, vec![(1, PackRole::Source), (5, PackRole::Unique)]);
+
+        let families =
+            select_pack_files(&photos, &groups, &HashSet::new(), &ranking, PackPolicy::Families);
+        let roles: Vec<(i64, Option<&str>, PackRole)> = families
+            .iter()
+            .map(|s| (s.photo.id, s.group_sha256, s.role))
+            .collect();
+        let group = Some(photos[0].sha256.as_str());
+        assert_eq!(
+            roles,
+            vec![
+                (1, group, PackRole::Source),
+                (5, None, PackRole::Unique),
+                (3, group, PackRole::Alternate),
+            ]
+        );
+    }
+
     #[test]
     fn test_select_photos_empty_input() {
-        let selected = select_photos_to_export(&[], &[]);
+        let selected = select_photos_to_export(&[], &[], &HashSet::new(), &RankingPolicy::default());
         assert!(selected.is_empty());
     }
 
@@ -415,6 +798,10 @@ mod tests {
 
     // ── copy_photo_to_pack ──────────────────────────────────────
 
+    fn sha_of(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
     #[test]
     fn test_copy_photo_to_pack_creates_prefix_dir() {
         let tmp = tempfile::tempdir().unwrap();
@@ -422,7 +809,7 @@ mod tests {
         fs::write(&source, b"photo data").unwrap();
 
         let target = tmp.path().join("a3/abcdef1234.jpg");
-        let result = copy_photo_to_pack(&source, &target).unwrap();
+        let result = copy_photo_to_pack(&source, &target, &sha_of(b"photo data"), 10).unwrap();
         assert!(result, "should copy when target doesn't exist");
         assert!(target.exists());
         assert_eq!(fs::read(&target).unwrap(), b"photo data");
@@ -435,12 +822,42 @@ mod tests {
         fs::write(&source, b"photo data").unwrap();
 
         let target = tmp.path().join("target.jpg");
-        fs::write(&target, b"existing content").unwrap();
+        fs::write(&target, b"existing!!").unwrap();
 
-        let result = copy_photo_to_pack(&source, &target).unwrap();
-        assert!(!result, "should skip when target exists (content-addressed)");
+        let result = copy_photo_to_pack(&source, &target, &sha_of(b"photo data"), 10).unwrap();
+        assert!(!result, "should skip when target exists with the expected size (content-addressed)");
         // Content should NOT be overwritten
-        assert_eq!(fs::read(&target).unwrap(), b"existing content");
+        assert_eq!(fs::read(&target).unwrap(), b"existing!!");
+    }
+
+    #[test]
+    fn test_copy_photo_to_pack_replaces_truncated_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.jpg");
+        fs::write(&source, b"photo data").unwrap();
+
+        // Leftover of an interrupted copy: right name, wrong size
+        let target = tmp.path().join("target.jpg");
+        fs::write(&target, b"photo").unwrap();
+
+        let result = copy_photo_to_pack(&source, &target, &sha_of(b"photo data"), 10).unwrap();
+        assert!(result, "size mismatch means damaged, not done");
+        assert_eq!(fs::read(&target).unwrap(), b"photo data");
+    }
+
+    #[test]
+    fn test_copy_photo_to_pack_rejects_changed_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.jpg");
+        // Source edited since it was scanned and hashed
+        fs::write(&source, b"edited photo data").unwrap();
+        let target = tmp.path().join("ab/target.jpg");
+
+        let err = copy_photo_to_pack(&source, &target, &sha_of(b"photo data"), 10).unwrap_err();
+        assert!(matches!(err, Error::HashMismatch { .. }));
+        assert!(!target.exists());
+        // No temp file left behind
+        assert_eq!(fs::read_dir(target.parent().unwrap()).unwrap().count(), 0);
     }
 
     #[test]
@@ -449,7 +866,43 @@ mod tests {
         let source = tmp.path().join("nonexistent.jpg");
         let target = tmp.path().join("target.jpg");
 
-        let result = copy_photo_to_pack(&source, &target);
+        let result = copy_photo_to_pack(&source, &target, &sha_of(b"photo data"), 10);
         assert!(result.is_err());
     }
+
+    // ── replace_pack_file ───────────────────────────────────────
+
+    #[test]
+    fn test_replace_pack_file_overwrites_damaged_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.jpg");
+        fs::write(&source, b"photo data").unwrap();
+        let sha = crate::hasher::compute_sha256(&source).unwrap();
+
+        let target = tmp.path().join(&sha[..2]).join(format!("{sha}.jpg"));
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::write(&target, b"photo dat\0").unwrap();
+
+        replace_pack_file(&source, &target, &sha).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"photo data");
+        // No temp file left behind
+        assert_eq!(fs::read_dir(target.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_replace_pack_file_rejects_wrong_hash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("source.jpg");
+        fs::write(&source, b"edited photo").unwrap();
+
+        let target = tmp.path().join("ab/target.jpg");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::write(&target, b"damaged").unwrap();
+
+        let err = replace_pack_file(&source, &target, &"ab".repeat(32)).unwrap_err();
+        assert!(matches!(err, Error::HashMismatch { .. }));
+        // Target untouched, temp file cleaned up
+        assert_eq!(fs::read(&target).unwrap(), b"damaged");
+        assert_eq!(fs::read_dir(target.parent().unwrap()).unwrap().count(), 1);
+    }
 }
@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::domain::SidecarKind;
+use crate::error::{Error, Result};
+use crate::hasher;
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::vault_save::format_str_to_extension;
+
+/// Progress callback events for the verify operation.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum VerifyProgress {
+    /// Starting verification with the number of pack files to re-hash.
+    Start { total: usize },
+    /// A pack file was re-hashed and matches its name.
+    Verified { path: PathBuf },
+    /// A pack file's content no longer matches its SHA-256 name.
+    Corrupted { path: PathBuf },
+    /// A manifest entry has no file on disk.
+    Missing { path: PathBuf },
+    /// A content-addressed file on disk has no manifest entry.
+    Orphaned { path: PathBuf },
+    /// A file in a shard directory doesn't follow the `{sha[..2]}/{sha}.{ext}` layout.
+    Misnamed { path: PathBuf },
+    /// Verification completed.
+    Complete { verified: usize, problems: usize },
+}
+
+/// A pack file whose content hash differs from the hash in its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CorruptedFile {
+    pub path: PathBuf,
+    pub expected_sha256: String,
+    /// `None` when the file could not be read.
+    pub actual_sha256: Option<String>,
+}
+
+/// Outcome of a pack verification.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    /// Number of files re-hashed successfully.
+    pub verified: usize,
+    /// Number of manifest entries in the pack (the sample is drawn from these).
+    pub total_entries: usize,
+    pub corrupted: Vec<CorruptedFile>,
+    pub missing: Vec<PathBuf>,
+    pub orphaned: Vec<PathBuf>,
+    pub misnamed: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    /// Total number of problems found.
+    pub fn problem_count(&self) -> usize {
+        self.corrupted.len() + self.missing.len() + self.orphaned.len() + self.misnamed.len()
+    }
+
+    /// Whether the pack passed verification.
+    pub fn is_clean(&self) -> bool {
+        self.problem_count() == 0
+    }
+
+    /// Manifest entries whose pack file is corrupted or missing, as `(sha256, path)`.
+    pub fn damaged_entries(&self) -> Vec<(String, PathBuf)> {
+        let corrupted = self
+            .corrupted
+            .iter()
+            .map(|c| (c.expected_sha256.clone(), c.path.clone()));
+        // Missing paths are built from the hash: `{sha[..2]}/{sha}.{ext}`
+        let missing = self.missing.iter().filter_map(|path| {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            Some((stem, path.clone()))
+        });
+        corrupted.chain(missing).collect()
+    }
+}
+
+/// Number of entries to re-hash for a sample of `percent`% of `total` (at least one).
+fn sample_size(total: usize, percent: Option<f64>) -> usize {
+    match percent {
+        None => total,
+        Some(p) => ((total as f64 * p / 100.0).ceil() as usize).min(total),
+    }
+}
+
+/// Whether `name` is a shard directory name (two lowercase hex characters).
+fn is_shard_dir(name: &str) -> bool {
+    name.len() == 2 && name.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Parse a content-addressed file name into `(sha256, extension)`.
+fn parse_content_name(name: &str) -> Option<(&str, &str)> {
+    let (stem, ext) = name.split_once('.')?;
+    let is_sha = stem.len() == 64 && stem.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    (is_sha && !ext.is_empty()).then_some((stem, ext))
+}
+
+/// Walk the shard directories and classify every file that isn't the expected
+/// path of a manifest entry (or of a recorded sidecar) as orphaned or misnamed.
+fn find_unexpected_files(
+    pack_path: &Path,
+    entries: &HashMap<&str, &ManifestEntry>,
+    sidecar_names: &HashSet<String>,
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut orphaned = Vec::new();
+    let mut misnamed = Vec::new();
+
+    let mut shards: Vec<_> = std::fs::read_dir(pack_path)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|e| is_shard_dir(&e.file_name().to_string_lossy()))
+        .map(|e| e.path())
+        .collect();
+    shards.sort();
+
+    for shard in shards {
+        let shard_name = shard.file_name().unwrap_or_default().to_string_lossy().to_string();
+        for entry in walkdir::WalkDir::new(&shard).min_depth(1).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let path = entry.into_path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let nested = path.parent() != Some(shard.as_path());
+            if !nested && sidecar_names.contains(&name) {
+                continue;
+            }
+
+            match parse_content_name(&name) {
+                Some((sha, ext)) if !nested && sha.starts_with(&shard_name) => {
+                    match entries.get(sha) {
+                        // Expected path — re-hashed separately
+                        Some(e) if format_str_to_extension(&e.format) == ext => {}
+                        Some(_) => misnamed.push(path),
+                        None => orphaned.push(path),
+                    }
+                }
+                _ => misnamed.push(path),
+            }
+        }
+    }
+
+    Ok((orphaned, misnamed))
+}
+
+/// Verify a pack's integrity against its manifest.
+///
+/// Re-hashes `sample_percent`% of the manifest entries (all when `None`),
+/// least recently verified first, and records the verification time of every
+/// entry that matches. Orphaned and misnamed files are detected by walking
+/// the shard directories, which needs no hashing and always covers the whole pack.
+pub fn verify_pack(
+    pack_path: &Path,
+    sample_percent: Option<f64>,
+    mut progress_cb: Option<&mut dyn FnMut(VerifyProgress)>,
+) -> Result<VerifyReport> {
+    if let Some(p) = sample_percent {
+        if !(p > 0.0 && p <= 100.0) {
+            return Err(Error::InvalidSamplePercent(p));
+        }
+    }
+
+    let manifest = Manifest::open(pack_path)?;
+    let entries = manifest.list_entries_by_verification()?;
+    let by_sha: HashMap<&str, &ManifestEntry> =
+        entries.iter().map(|e| (e.sha256.as_str(), e)).collect();
+
+    let sample = &entries[..sample_size(entries.len(), sample_percent)];
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(VerifyProgress::Start {
+            total: sample.len(),
+        });
+    }
+
+    // Parallel re-hash of the sample, collect results
+    let results: Vec<(&ManifestEntry, PathBuf, Option<std::io::Result<String>>)> = sample
+        .par_iter()
+        .map(|entry| {
+            let ext = format_str_to_extension(&entry.format);
+            let path = pack_path
+                .join(&entry.sha256[..2])
+                .join(format!("{}.{}", entry.sha256, ext));
+            if !path.exists() {
+                return (entry, path, None);
+            }
+            let actual = hasher::compute_sha256(&path);
+            (entry, path, Some(actual))
+        })
+        .collect();
+
+    // Report progress sequentially (callback is not Send)
+    let mut report = VerifyReport {
+        total_entries: entries.len(),
+        ..Default::default()
+    };
+    let mut verified_hashes = Vec::new();
+    for (entry, path, actual) in results {
+        match actual {
+            None => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(VerifyProgress::Missing { path: path.clone() });
+                }
+                report.missing.push(path);
+            }
+            Some(Ok(actual)) if actual == entry.sha256 => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(VerifyProgress::Verified { path });
+                }
+                report.verified += 1;
+                verified_hashes.push(entry.sha256.clone());
+            }
+            Some(actual) => {
+                if let Some(ref mut cb) = progress_cb {
+                    cb(VerifyProgress::Corrupted { path: path.clone() });
+                }
+                report.corrupted.push(CorruptedFile {
+                    path,
+                    expected_sha256: entry.sha256.clone(),
+                    actual_sha256: actual.ok(),
+                });
+            }
+        }
+    }
+    manifest.mark_verified(&verified_hashes)?;
+
+    let sidecar_names: HashSet<String> = manifest
+        .list_sidecars()?
+        .iter()
+        .filter_map(|s| {
+            let kind = SidecarKind::parse(&s.kind)?;
+            Some(format!("{}.{}", s.sha256, kind.extension()))
+        })
+        .collect();
+    let (orphaned, misnamed) = find_unexpected_files(pack_path, &by_sha, &sidecar_names)?;
+    if let Some(ref mut cb) = progress_cb {
+        for path in &orphaned {
+            cb(VerifyProgress::Orphaned { path: path.clone() });
+        }
+        for path in &misnamed {
+            cb(VerifyProgress::Misnamed { path: path.clone() });
+        }
+    }
+    report.orphaned = orphaned;
+    report.misnamed = misnamed;
+
+    if let Some(ref mut cb) = progress_cb {
+        cb(VerifyProgress::Complete {
+            verified: report.verified,
+            problems: report.problem_count(),
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_support::{add_pack_file, PackEntry};
+
+    fn photo() -> PackEntry<'static> {
+        PackEntry::named("photo.jpg")
+    }
+
+    #[test]
+    fn test_sample_size() {
+        assert_eq!(sample_size(200, None), 200);
+        assert_eq!(sample_size(200, Some(10.0)), 20);
+        assert_eq!(sample_size(200, Some(0.1)), 1);
+        assert_eq!(sample_size(3, Some(50.0)), 2);
+        assert_eq!(sample_size(0, Some(10.0)), 0);
+        assert_eq!(sample_size(7, Some(100.0)), 7);
+    }
+
+    #[test]
+    fn test_parse_content_name() {
+        let sha = "a".repeat(64);
+        assert_eq!(
+            parse_content_name(&format!("{sha}.jpg")),
+            Some((sha.as_str(), "jpg"))
+        );
+        assert_eq!(parse_content_name("IMG_0001.jpg"), None);
+        assert_eq!(parse_content_name(&format!("{}.jpg", "A".repeat(64))), None);
+        assert_eq!(parse_content_name(&sha), None);
+    }
+
+    #[test]
+    fn test_verify_clean_pack() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        add_pack_file(tmp.path(), &manifest, b"photo one", photo());
+        add_pack_file(tmp.path(), &manifest, b"photo two", photo());
+
+        let report = verify_pack(tmp.path(), None, None).unwrap();
+        assert!(report.is_clean(), "{report:?}");
+        assert_eq!(report.verified, 2);
+        assert_eq!(report.total_entries, 2);
+        assert!(manifest
+            .list_entries_by_verification()
+            .unwrap()
+            .iter()
+            .all(|e| e.verified_at.is_some()));
+    }
+
+    #[test]
+    fn test_verify_detects_corrupted_and_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        let (sha, corrupted) = add_pack_file(tmp.path(), &manifest, b"photo one", photo());
+        let (_, missing) = add_pack_file(tmp.path(), &manifest, b"photo two", photo());
+        fs::write(&corrupted, b"bit rot").unwrap();
+        fs::remove_file(&missing).unwrap();
+
+        let report = verify_pack(tmp.path(), None, None).unwrap();
+        assert_eq!(report.verified, 0);
+        assert_eq!(report.missing, vec![missing]);
+        assert_eq!(report.corrupted.len(), 1);
+        assert_eq!(report.corrupted[0].path, corrupted);
+        assert_eq!(report.corrupted[0].expected_sha256, sha);
+        assert_ne!(report.corrupted[0].actual_sha256.as_deref(), Some(sha.as_str()));
+        // Failed entries are not marked verified
+        assert!(manifest
+            .list_entries_by_verification()
+            .unwrap()
+            .iter()
+            .all(|e| e.verified_at.is_none()));
+    }
+
+    #[test]
+    fn test_verify_detects_orphaned_and_misnamed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        let (sha, path) = add_pack_file(tmp.path(), &manifest, b"photo one", photo());
+
+        // Content-addressed file the manifest doesn't know about
+        let orphan_sha = "f".repeat(64);
+        let orphan = tmp.path().join("ff").join(format!("{orphan_sha}.png"));
+        fs::create_dir_all(orphan.parent().unwrap()).unwrap();
+        fs::write(&orphan, b"orphan").unwrap();
+
+        // Wrong shard, non-hash name, wrong extension for the manifest format
+        let wrong_shard = tmp.path().join("00").join(format!("{orphan_sha}.png"));
+        fs::create_dir_all(wrong_shard.parent().unwrap()).unwrap();
+        fs::write(&wrong_shard, b"x").unwrap();
+        let not_a_hash = path.parent().unwrap().join("IMG_0001.jpg");
+        fs::write(&not_a_hash, b"x").unwrap();
+        let wrong_ext = path.parent().unwrap().join(format!("{sha}.png"));
+        fs::write(&wrong_ext, b"x").unwrap();
+
+        // Non-shard directories (metadata, views) are not inspected
+        fs::create_dir_all(tmp.path().join("by-date")).unwrap();
+        fs::write(tmp.path().join("by-date/IMG_0001.jpg"), b"x").unwrap();
+
+        let report = verify_pack(tmp.path(), None, None).unwrap();
+        assert_eq!(report.verified, 1);
+        assert_eq!(report.orphaned, vec![orphan]);
+        let mut expected_misnamed = vec![wrong_shard, not_a_hash, wrong_ext];
+        expected_misnamed.sort();
+        let mut misnamed = report.misnamed.clone();
+        misnamed.sort();
+        assert_eq!(misnamed, expected_misnamed);
+    }
+
+    #[test]
+    fn test_verify_sample_rotates_through_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manifest = Manifest::open(tmp.path()).unwrap();
+        for i in 0..4 {
+            let content = format!("photo {i}");
+            add_pack_file(tmp.path(), &manifest, content.as_bytes(), photo());
+        }
+
+        let first = verify_pack(tmp.path(), Some(50.0), None).unwrap();
+        assert_eq!(first.verified, 2);
+        let second = verify_pack(tmp.path(), Some(50.0), None).unwrap();
+        assert_eq!(second.verified, 2);
+
+        // Two 50% samples cover every entry once
+        assert!(manifest
+            .list_entries_by_verification()
+            .unwrap()
+            .iter()
+            .all(|e| e.verified_at.is_some()));
+    }
+
+    #[test]
+    fn test_verify_rejects_invalid_sample() {
+        let tmp = tempfile::tempdir().unwrap();
+        for p in [0.0, -5.0, 150.0, f64::NAN] {
+            assert!(matches!(
+                verify_pack(tmp.path(), Some(p), None),
+                Err(Error::InvalidSamplePercent(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,377 @@
+//! Browsable views of the pack: `by-date/` and `by-camera/` link farms.
+//!
+//! Each view entry is a hard link to the hash-named pack file (zero extra
+//! storage), or a relative symlink where the filesystem has no hard links.
+//! Every link is recorded in the manifest's `view_links` table, which is how
+//! stale links are found — the view trees themselves are never trusted.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::restore;
+use crate::vault_save::format_str_to_extension;
+
+/// View sorted by `YYYY/MM/DD/original_filename`.
+pub const BY_DATE_DIR: &str = "by-date";
+/// View sorted by `<camera>/YYYY/MM/DD/original_filename`.
+pub const BY_CAMERA_DIR: &str = "by-camera";
+/// Top-level view directories inside the pack. Scans of a pack skip them.
+pub const VIEW_DIRS: [&str; 2] = [BY_DATE_DIR, BY_CAMERA_DIR];
+/// Camera directory for entries without EXIF make/model.
+pub const UNKNOWN_CAMERA_DIR: &str = "unknown-camera";
+
+/// Outcome of a view sync.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ViewSync {
+    /// Links created or re-created.
+    pub linked: usize,
+    /// Stale links removed.
+    pub removed: usize,
+}
+
+/// Camera directory name: make + model, without the make repeated
+/// (`Canon` + `Canon EOS R5` → `Canon EOS R5`) and with path-unsafe characters replaced.
+pub(crate) fn camera_dir(entry: &ManifestEntry) -> PathBuf {
+    let make = entry.camera_make.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    let model = entry.camera_model.as_deref().map(str::trim).filter(|s| !s.is_empty());
+
+    let name = match (make, model) {
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            model.to_string()
+        }
+        (Some(make), Some(model)) => format!("{make} {model}"),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => String::new(),
+    };
+
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim_end_matches(['.', ' ']);
+
+    if sanitized.is_empty() {
+        PathBuf::from(UNKNOWN_CAMERA_DIR)
+    } else {
+        PathBuf::from(sanitized)
+    }
+}
+
+/// Plan every view link as `(path relative to the pack, entry)`.
+pub fn plan_view_links(entries: &[ManifestEntry]) -> Vec<(PathBuf, &ManifestEntry)> {
+    let by_date = restore::plan_paths(entries, restore::date_dir);
+    let by_camera = restore::plan_paths(entries, |e| camera_dir(e).join(restore::date_dir(e)));
+
+    let by_date = by_date
+        .into_iter()
+        .zip(entries)
+        .map(|(path, entry)| (Path::new(BY_DATE_DIR).join(path), entry));
+    let by_camera = by_camera
+        .into_iter()
+        .zip(entries)
+        .map(|(path, entry)| (Path::new(BY_CAMERA_DIR).join(path), entry));
+    by_date.chain(by_camera).collect()
+}
+
+/// Content-addressed path of an entry, relative to the pack.
+fn content_path(entry: &ManifestEntry) -> PathBuf {
+    Path::new(&entry.sha256[..2]).join(format!(
+        "{}.{}",
+        entry.sha256,
+        format_str_to_extension(&entry.format)
+    ))
+}
+
+/// Bring the views in line with the manifest: create missing links, re-link
+/// links whose pack file was replaced (repair breaks hard links), and remove
+/// recorded links that are no longer wanted. With `enabled = false` every
+/// recorded link is removed.
+///
+/// Files in the view directories that the manifest doesn't know about are
+/// never touched.
+pub fn sync_views(pack_path: &Path, manifest: &Manifest, enabled: bool) -> Result<ViewSync> {
+    let entries = if enabled {
+        manifest.list_files()?
+    } else {
+        Vec::new()
+    };
+    let desired = plan_view_links(&entries);
+    let desired_paths: HashSet<&PathBuf> = desired.iter().map(|(path, _)| path).collect();
+    let recorded: HashMap<PathBuf, String> = manifest.list_view_links()?.into_iter().collect();
+
+    let mut sync = ViewSync::default();
+    for path in recorded.keys() {
+        if !desired_paths.contains(path) {
+            remove_link(&pack_path.join(path))?;
+            manifest.remove_view_link(path)?;
+            sync.removed += 1;
+        }
+    }
+
+    for (path, entry) in &desired {
+        let link = pack_path.join(path);
+        let target = pack_path.join(content_path(entry));
+        if !target.is_file() {
+            continue;
+        }
+        match recorded.get(path) {
+            Some(sha256) if *sha256 == entry.sha256 && link_is_current(&link, &target) => continue,
+            Some(_) => remove_link(&link)?,
+            // Unrecorded file in the way: not ours, leave it
+            None if link.symlink_metadata().is_ok() => continue,
+            None => {}
+        }
+        create_link(&target, &link, &relative_target(path, &content_path(entry)))?;
+        manifest.insert_view_link(path, &entry.sha256)?;
+        sync.linked += 1;
+    }
+
+    for dir in VIEW_DIRS {
+        prune_empty_dirs(&pack_path.join(dir));
+    }
+
+    Ok(sync)
+}
+
+/// Remove the view links of a pack file being deleted from the pack.
+pub(crate) fn remove_links_for(pack_path: &Path, manifest: &Manifest, sha256: &str) {
+    for path in manifest.view_links_for(sha256).unwrap_or_default() {
+        let _ = fs::remove_file(pack_path.join(&path));
+        let _ = manifest.remove_view_link(&path);
+    }
+}
+
+fn remove_link(link: &Path) -> Result<()> {
+    match fs::remove_file(link) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `link` still resolves to the current `target` file.
+fn link_is_current(link: &Path, target: &Path) -> bool {
+    let Ok(meta) = link.symlink_metadata() else {
+        return false;
+    };
+    if meta.file_type().is_symlink() {
+        return match (link.canonicalize(), target.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        };
+    }
+    same_file(&meta, target)
+}
+
+#[cfg(unix)]
+fn same_file(link_meta: &fs::Metadata, target: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(target)
+        .map(|t| t.dev() == link_meta.dev() && t.ino() == link_meta.ino())
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn same_file(link_meta: &fs::Metadata, target: &Path) -> bool {
+    fs::metadata(target)
+        .map(|t| t.len() == link_meta.len())
+        .unwrap_or(false)
+}
+
+/// Symlink target for a link at `link` (relative to the pack) pointing at
+/// `content` (relative to the pack), so the pack can be moved or mounted elsewhere.
+fn relative_target(link: &Path, content: &Path) -> PathBuf {
+    let depth = link.components().count().saturating_sub(1);
+    let mut target: PathBuf = std::iter::repeat_n("..", depth).collect();
+    target.push(content);
+    target
+}
+
+/// Hard-link `link` to `target`, falling back to a symlink to `symlink_target`.
+fn create_link(target: &Path, link: &Path, symlink_target: &Path) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::hard_link(target, link).is_ok() {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(symlink_target, link)?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(symlink_target, link)?;
+    Ok(())
+}
+
+/// Remove empty directories under `root` (including `root`), deepest first.
+fn prune_empty_dirs(root: &Path) {
+    if !root.is_dir() {
+        return;
+    }
+    for entry in walkdir::WalkDir::new(root)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+    {
+        // Fails (harmlessly) on non-empty directories
+        let _ = fs::remove_dir(entry.path());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{add_pack_file, PackEntry};
+
+    /// A pack entry dated 2024-06-15, shot on a Canon EOS R5.
+    fn canon(name: &str) -> PackEntry<'_> {
+        PackEntry {
+            exif_date: Some("2024:06:15 10:00:00"),
+            camera: Some(("Canon", "Canon EOS R5")),
+            ..PackEntry::named(name)
+        }
+    }
+
+    fn entry(sha256: &str, name: &str, make: Option<&str>, model: Option<&str>) -> ManifestEntry {
+        ManifestEntry {
+            sha256: sha256.to_string(),
+            original_filename: name.to_string(),
+            format: "JPEG".to_string(),
+            size: 3,
+            exif_date: Some("2024:06:15 10:00:00".to_string()),
+            camera_make: make.map(str::to_string),
+            camera_model: model.map(str::to_string),
+            mtime: None,
+            verified_at: None,
+            content_id: None,
+            group_sha256: None,
+            role: None,
+        }
+    }
+
+    #[test]
+    fn test_camera_dir() {
+        let dir = |make, model| camera_dir(&entry("aa", "a.jpg", make, model));
+        assert_eq!(dir(Some("Canon"), Some("Canon EOS R5")), PathBuf::from("Canon EOS R5"));
+        assert_eq!(dir(Some("FUJIFILM"), Some("X-T4")), PathBuf::from("FUJIFILM X-T4"));
+        assert_eq!(dir(None, Some("iPhone 15 Pro")), PathBuf::from("iPhone 15 Pro"));
+        assert_eq!(dir(Some("Acme"), Some("A/B: 1")), PathBuf::from("Acme A_B_ 1"));
+        assert_eq!(dir(Some("  "), None), PathBuf::from(UNKNOWN_CAMERA_DIR));
+        assert_eq!(dir(None, None), PathBuf::from(UNKNOWN_CAMERA_DIR));
+    }
+
+    #[test]
+    fn test_plan_view_links() {
+        let entries = vec![entry("11111111aa", "IMG_0001.jpg", Some("Apple"), Some("iPhone 15"))];
+        let planned: Vec<PathBuf> = plan_view_links(&entries)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                PathBuf::from("by-date/2024/06/15/IMG_0001.jpg"),
+                PathBuf::from("by-camera/Apple iPhone 15/2024/06/15/IMG_0001.jpg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_views_links_and_removes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (sha, _) = add_pack_file(&pack, &manifest, b"one", canon("IMG_0001.jpg"));
+
+        let sync = sync_views(&pack, &manifest, true).unwrap();
+        assert_eq!(sync, ViewSync { linked: 2, removed: 0 });
+        let by_date = pack.join("by-date/2024/06/15/IMG_0001.jpg");
+        let by_camera = pack.join("by-camera/Canon EOS R5/2024/06/15/IMG_0001.jpg");
+        assert_eq!(fs::read(&by_date).unwrap(), b"one");
+        assert_eq!(fs::read(&by_camera).unwrap(), b"one");
+
+        // Idempotent
+        assert_eq!(sync_views(&pack, &manifest, true).unwrap(), ViewSync::default());
+
+        // Removing the pack file's links leaves empty view trees pruned
+        remove_links_for(&pack, &manifest, &sha);
+        assert!(!by_date.exists());
+        sync_views(&pack, &manifest, false).unwrap();
+        assert!(!pack.join(BY_DATE_DIR).exists());
+        assert!(!pack.join(BY_CAMERA_DIR).exists());
+    }
+
+    #[test]
+    fn test_sync_views_disabled_removes_recorded_links_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        add_pack_file(&pack, &manifest, b"one", canon("IMG_0001.jpg"));
+        sync_views(&pack, &manifest, true).unwrap();
+        let user_file = pack.join("by-date/notes.txt");
+        fs::write(&user_file, b"mine").unwrap();
+
+        let sync = sync_views(&pack, &manifest, false).unwrap();
+        assert_eq!(sync, ViewSync { linked: 0, removed: 2 });
+        assert!(manifest.list_view_links().unwrap().is_empty());
+        assert_eq!(fs::read(&user_file).unwrap(), b"mine");
+        assert!(!pack.join(BY_CAMERA_DIR).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sync_views_relinks_replaced_pack_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let manifest = Manifest::open(&pack).unwrap();
+        let (_, pack_file) = add_pack_file(&pack, &manifest, b"one", canon("IMG_0001.jpg"));
+        sync_views(&pack, &manifest, true).unwrap();
+
+        // Atomic replace (as repair does) gives the pack file a new inode
+        let replacement = pack.join("replacement.tmp");
+        fs::write(&replacement, b"one").unwrap();
+        fs::rename(&replacement, &pack_file).unwrap();
+
+        let sync = sync_views(&pack, &manifest, true).unwrap();
+        assert_eq!(sync.linked, 2);
+        assert_eq!(fs::read(pack.join("by-date/2024/06/15/IMG_0001.jpg")).unwrap(), b"one");
+    }
+
+    #[test]
+    fn test_relative_target() {
+        assert_eq!(
+            relative_target(Path::new("by-date/2024/06/15/a.jpg"), Path::new("ab/abcd.jpg")),
+            PathBuf::from("../../../../ab/abcd.jpg")
+        );
+        assert_eq!(
+            relative_target(Path::new("by-camera/X/2024/06/15/a.jpg"), Path::new("ab/abcd.jpg")),
+            PathBuf::from("../../../../../ab/abcd.jpg")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_view_is_current() {
+        let tmp = tempfile::tempdir().unwrap();
+        let pack = tmp.path().join("pack");
+        let target = pack.join("ab/abcd.jpg");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::write(&target, b"one").unwrap();
+        let relative = Path::new("by-date/2024/06/15/a.jpg");
+        let link = pack.join(relative);
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(relative_target(relative, Path::new("ab/abcd.jpg")), &link)
+            .unwrap();
+
+        assert_eq!(fs::read(&link).unwrap(), b"one");
+        assert!(link_is_current(&link, &target));
+        fs::remove_file(&target).unwrap();
+        assert!(!link_is_current(&link, &target));
+    }
+}
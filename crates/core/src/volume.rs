@@ -0,0 +1,159 @@
+//! Volume identity for sources on removable or network storage.
+//!
+//! When a source is added, a marker file holding a random identity is written
+//! at its root. A scan compares the marker with the identity stored in the
+//! catalog: an unmounted volume (missing root, or an empty mount point without
+//! the marker) or a different volume mounted at the same path is reported as
+//! offline, and the source's photos are kept instead of purged as deleted.
+//! Sources without a recorded identity only count as present once their root
+//! holds at least one photo, so an empty mount point never adopts a marker.
+
+use std::fs;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::domain::Source;
+use crate::scanner;
+
+/// Marker file written at the root of every source.
+pub const MARKER_FILE: &str = ".photopack-source";
+
+/// Whether a source's volume is present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeState {
+    /// The source root exists and carries the expected marker.
+    Online,
+    /// The source root is missing, or its marker is missing or belongs to
+    /// another volume.
+    Offline,
+    /// The root holds photos but the source has no recorded identity yet
+    /// (added before markers, or on read-only media): trusted as present.
+    Unmarked,
+}
+
+/// Read the identity stored in the marker at `root`, if any.
+pub fn read_marker(root: &Path) -> Option<String> {
+    let content = fs::read_to_string(root.join(MARKER_FILE)).ok()?;
+    let id = content.trim();
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// Return the identity of the volume at `root`, writing a new marker if there
+/// is none. Returns `None` when the marker cannot be written (read-only media).
+pub fn ensure_marker(root: &Path) -> Option<String> {
+    if let Some(id) = read_marker(root) {
+        return Some(id);
+    }
+    let id = new_identity(root);
+    fs::write(root.join(MARKER_FILE), format!("{id}\n")).ok()?;
+    Some(id)
+}
+
+/// Remove the marker at `root` if it holds `volume_id`. Best effort.
+pub fn remove_marker(root: &Path, volume_id: &str) {
+    if read_marker(root).as_deref() == Some(volume_id) {
+        let _ = fs::remove_file(root.join(MARKER_FILE));
+    }
+}
+
+/// Check whether the volume of `source` is present.
+pub fn check(source: &Source) -> VolumeState {
+    if !source.path.is_dir() {
+        return VolumeState::Offline;
+    }
+    match &source.volume_id {
+        None if contains_photo(&source.path) => VolumeState::Unmarked,
+        None => VolumeState::Offline,
+        Some(expected) if read_marker(&source.path).as_deref() == Some(expected) => {
+            VolumeState::Online
+        }
+        Some(_) => VolumeState::Offline,
+    }
+}
+
+/// Whether any supported photo lies under `root`. Stops at the first one.
+fn contains_photo(root: &Path) -> bool {
+    WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .any(|e| e.file_type().is_file() && scanner::is_supported_photo(e.path()))
+}
+
+/// A random-enough identity: hash of the path, the time and the process id.
+fn new_identity(root: &Path) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(root.to_string_lossy().as_bytes());
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    format!("{:x}", hasher.finalize())[..32].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn source(path: PathBuf, volume_id: Option<String>) -> Source {
+        Source {
+            id: 1,
+            path,
+            last_scanned: None,
+            volume_id,
+            online: true,
+            priority: 0,
+        }
+    }
+
+    #[test]
+    fn test_ensure_marker_is_stable() {
+        let tmp = tempfile::tempdir().unwrap();
+        let id = ensure_marker(tmp.path()).unwrap();
+        assert_eq!(id.len(), 32);
+        assert_eq!(ensure_marker(tmp.path()), Some(id.clone()));
+        assert_eq!(read_marker(tmp.path()), Some(id));
+    }
+
+    #[test]
+    fn test_check_volume_state() {
+        let tmp = tempfile::tempdir().unwrap();
+        let id = ensure_marker(tmp.path()).unwrap();
+
+        assert_eq!(check(&source(tmp.path().into(), Some(id.clone()))), VolumeState::Online);
+        // Another volume mounted at the same path
+        assert_eq!(
+            check(&source(tmp.path().into(), Some("other".into()))),
+            VolumeState::Offline
+        );
+        // Unmounted: the root is gone
+        assert_eq!(
+            check(&source(tmp.path().join("missing"), Some(id.clone()))),
+            VolumeState::Offline
+        );
+
+        // Empty mount point: the marker is gone
+        remove_marker(tmp.path(), &id);
+        assert!(read_marker(tmp.path()).is_none());
+        assert_eq!(check(&source(tmp.path().into(), Some(id))), VolumeState::Offline);
+    }
+
+    #[test]
+    fn test_unmarked_source_needs_a_photo() {
+        let tmp = tempfile::tempdir().unwrap();
+        // Empty mount point of a source added before markers
+        assert_eq!(check(&source(tmp.path().into(), None)), VolumeState::Offline);
+
+        fs::create_dir_all(tmp.path().join("2024")).unwrap();
+        fs::write(tmp.path().join("2024/notes.txt"), b"x").unwrap();
+        assert_eq!(check(&source(tmp.path().into(), None)), VolumeState::Offline);
+
+        fs::write(tmp.path().join("2024/IMG_0001.JPG"), b"x").unwrap();
+        assert_eq!(check(&source(tmp.path().into(), None)), VolumeState::Unmarked);
+    }
+}
@@ -0,0 +1,397 @@
+#!/usr/bin/env python3
+"""Regenerate the HEIF fixtures with the system libheif (x265) and libde265.
+
+    python3 generate.py SOURCE.heic
+
+SOURCE is any HEIC photo; the committed fixtures come from `data/test.heic`
+of the libheif-rs 1.1.0 crate (see README.md). Writes the fixtures next to
+this script and prints the reference luma digests for the conformance test.
+"""
+
+import ctypes
+import hashlib
+import os
+import struct
+import sys
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+heif = ctypes.CDLL("libheif.so.1")
+de265 = ctypes.CDLL("libde265.so.0")
+libjpeg = ctypes.CDLL("libjpeg.so.62")
+libc = ctypes.CDLL(None)
+
+COLORSPACE_RGB, CHROMA_INTERLEAVED_RGB, CHANNEL_INTERLEAVED = 1, 10, 10
+COMPRESSION_HEVC = 1
+ORIENTATION_ROTATE_CW_90 = 6
+DE265_DISABLE_DEBLOCKING, DE265_DISABLE_SAO = 7, 8
+JPEG_COMPRESS_STRUCT_SIZE = 520  # libjpeg 6.2 ABI, LP64
+
+
+class HeifError(ctypes.Structure):
+    _fields_ = [("code", ctypes.c_int), ("subcode", ctypes.c_int), ("message", ctypes.c_char_p)]
+
+
+class EncodingOptions(ctypes.Structure):
+    # struct heif_encoding_options, version 5 (libheif 1.14+)
+    _fields_ = [
+        ("version", ctypes.c_uint8),
+        ("save_alpha_channel", ctypes.c_uint8),
+        ("macOS_compatibility_workaround", ctypes.c_uint8),
+        ("save_two_colr_boxes_when_ICC_and_nclx_available", ctypes.c_uint8),
+        ("output_nclx_profile", ctypes.c_void_p),
+        ("macOS_compatibility_workaround_no_nclx_profile", ctypes.c_uint8),
+        ("image_orientation", ctypes.c_int),
+    ]
+
+
+for name in [
+    "heif_context_read_from_file",
+    "heif_context_get_primary_image_handle",
+    "heif_decode_image",
+    "heif_image_create",
+    "heif_image_add_plane",
+    "heif_image_scale_image",
+    "heif_context_get_encoder_for_format",
+    "heif_encoder_set_lossy_quality",
+    "heif_encoder_set_lossless",
+    "heif_encoder_set_parameter",
+    "heif_context_encode_image",
+    "heif_context_encode_thumbnail",
+    "heif_context_write_to_file",
+]:
+    getattr(heif, name).restype = HeifError
+for name in ["heif_context_alloc", "heif_image_get_plane", "heif_image_get_plane_readonly"]:
+    getattr(heif, name).restype = ctypes.c_void_p
+heif.heif_encoding_options_alloc.restype = ctypes.POINTER(EncodingOptions)
+de265.de265_new_decoder.restype = ctypes.c_void_p
+de265.de265_get_next_picture.restype = ctypes.c_void_p
+de265.de265_get_image_plane.restype = ctypes.c_void_p
+libjpeg.jpeg_std_error.restype = ctypes.c_void_p
+libc.fopen.restype = ctypes.c_void_p
+
+
+def check(err):
+    if err.code != 0:
+        raise RuntimeError(err.message.decode())
+
+
+# ── Images ───────────────────────────────────────────────────────
+
+
+class Image:
+    """Interleaved 8-bit RGB."""
+
+    def __init__(self, width, height, rgb):
+        self.width, self.height, self.rgb = width, height, rgb
+
+    def crop(self, x0, y0, width, height):
+        rows = [self.rgb[((y0 + y) * self.width + x0) * 3:][: width * 3] for y in range(height)]
+        return Image(width, height, b"".join(rows))
+
+    def rotate_anticlockwise(self):
+        row_bytes, rows = self.width * 3, []
+        for x in reversed(range(self.width)):
+            row = bytearray(self.height * 3)
+            for c in range(3):
+                row[c::3] = self.rgb[x * 3 + c::row_bytes]
+            rows.append(bytes(row))
+        return Image(self.height, self.width, b"".join(rows))
+
+
+def heif_rgb(handle_image):
+    stride = ctypes.c_int()
+    plane = heif.heif_image_get_plane_readonly(handle_image, CHANNEL_INTERLEAVED, ctypes.byref(stride))
+    width = heif.heif_image_get_width(handle_image, CHANNEL_INTERLEAVED)
+    height = heif.heif_image_get_height(handle_image, CHANNEL_INTERLEAVED)
+    rows = [ctypes.string_at(plane + y * stride.value, width * 3) for y in range(height)]
+    return Image(width, height, b"".join(rows))
+
+
+def decode_rgb(path):
+    """libheif's displayed image: loop filters, clap, irot and grid applied."""
+    ctx = heif.heif_context_alloc()
+    check(heif.heif_context_read_from_file(ctypes.c_void_p(ctx), path.encode(), None))
+    handle = ctypes.c_void_p()
+    check(heif.heif_context_get_primary_image_handle(ctypes.c_void_p(ctx), ctypes.byref(handle)))
+    img = ctypes.c_void_p()
+    check(heif.heif_decode_image(handle, ctypes.byref(img), COLORSPACE_RGB, CHROMA_INTERLEAVED_RGB, None))
+    return heif_rgb(img)
+
+
+def to_heif(image):
+    img = ctypes.c_void_p()
+    check(heif.heif_image_create(image.width, image.height, COLORSPACE_RGB, CHROMA_INTERLEAVED_RGB,
+                                 ctypes.byref(img)))
+    check(heif.heif_image_add_plane(img, CHANNEL_INTERLEAVED, image.width, image.height, 8))
+    stride = ctypes.c_int()
+    plane = heif.heif_image_get_plane(img, CHANNEL_INTERLEAVED, ctypes.byref(stride))
+    for y in range(image.height):
+        row = image.rgb[y * image.width * 3:][: image.width * 3]
+        ctypes.memmove(plane + y * stride.value, row, len(row))
+    return img
+
+
+def scale(image, width, height):
+    out = ctypes.c_void_p()
+    check(heif.heif_image_scale_image(to_heif(image), ctypes.byref(out), width, height, None))
+    return heif_rgb(out)
+
+
+# ── Encoding ─────────────────────────────────────────────────────
+
+
+def encode(path, image, quality=50, params=(), lossless=False, orientation=None, thumbnail=None):
+    ctx = ctypes.c_void_p(heif.heif_context_alloc())
+    encoder = ctypes.c_void_p()
+    check(heif.heif_context_get_encoder_for_format(ctx, COMPRESSION_HEVC, ctypes.byref(encoder)))
+    check(heif.heif_encoder_set_lossy_quality(encoder, quality))
+    if lossless:
+        check(heif.heif_encoder_set_lossless(encoder, 1))
+    for name, value in params:
+        check(heif.heif_encoder_set_parameter(encoder, name.encode(), value.encode()))
+    options = heif.heif_encoding_options_alloc()
+    if orientation is not None:
+        assert options.contents.version >= 5
+        options.contents.image_orientation = orientation
+    source = to_heif(image)
+    handle = ctypes.c_void_p()
+    check(heif.heif_context_encode_image(ctx, source, encoder, options, ctypes.byref(handle)))
+    if thumbnail:
+        thumb = ctypes.c_void_p()
+        check(heif.heif_context_encode_thumbnail(ctx, source, handle, encoder, options, thumbnail,
+                                                 ctypes.byref(thumb)))
+    check(heif.heif_context_write_to_file(ctx, path.encode()))
+
+
+def jpeg(path, image):
+    """Quality-90 JPEG through the system libjpeg, as a photo library exports."""
+    cinfo = ctypes.create_string_buffer(JPEG_COMPRESS_STRUCT_SIZE)
+    err = ctypes.create_string_buffer(512)
+    ctypes.memmove(cinfo, ctypes.byref(ctypes.c_void_p(libjpeg.jpeg_std_error(err))), 8)
+    libjpeg.jpeg_CreateCompress(cinfo, 62, JPEG_COMPRESS_STRUCT_SIZE)
+    file = libc.fopen(path.encode(), b"wb")
+    libjpeg.jpeg_stdio_dest(cinfo, ctypes.c_void_p(file))
+    # image_width, image_height, input_components, in_color_space (JCS_RGB)
+    struct.pack_into("=IIii", cinfo, 48, image.width, image.height, 3, 2)
+    libjpeg.jpeg_set_defaults(cinfo)
+    libjpeg.jpeg_set_quality(cinfo, 90, 1)
+    libjpeg.jpeg_start_compress(cinfo, 1)
+    rows = (ctypes.c_char_p * image.height)(
+        *[image.rgb[y * image.width * 3:][: image.width * 3] for y in range(image.height)]
+    )
+    assert libjpeg.jpeg_write_scanlines(cinfo, rows, image.height) == image.height
+    libjpeg.jpeg_finish_compress(cinfo)
+    libjpeg.jpeg_destroy_compress(cinfo)
+    libc.fclose(ctypes.c_void_p(file))
+
+
+# ── Container ────────────────────────────────────────────────────
+
+
+def boxes(data, start=0, end=None):
+    end = len(data) if end is None else end
+    while start + 8 <= end:
+        size, kind = struct.unpack(">I4s", data[start:start + 8])
+        yield kind, data[start + 8:start + size]
+        start += size
+
+
+def parse(data):
+    """Items of a libheif-written file: kind, hvcC payload and data."""
+    meta = next(body for kind, body in boxes(data) if kind == b"meta")
+    children = dict(boxes(meta, 4))
+    items = {"primary": struct.unpack(">H", children[b"pitm"][4:6])[0]}
+    for kind, infe in boxes(children[b"iinf"], 6):
+        item_id, = struct.unpack(">H", infe[4:6])
+        items[item_id] = {"kind": infe[8:12], "hvcc": None}
+    iloc = children[b"iloc"]
+    assert iloc[0] == 0 and iloc[4] == 0x44 and iloc[5] in (0x00, 0x40)
+    base_size = iloc[5] >> 4
+    count, = struct.unpack(">H", iloc[6:8])
+    pos = 8
+    for _ in range(count):
+        item_id, = struct.unpack(">H", iloc[pos:pos + 2])
+        base = int.from_bytes(iloc[pos + 4:pos + 4 + base_size], "big")
+        pos += 4 + base_size
+        extents, offset, length = struct.unpack(">HII", iloc[pos:pos + 10])
+        assert extents == 1
+        items[item_id]["data"] = data[base + offset:base + offset + length]
+        pos += 10
+    iprp = dict(boxes(children[b"iprp"]))
+    properties = list(boxes(iprp[b"ipco"]))
+    ipma = iprp[b"ipma"]
+    count, = struct.unpack(">I", ipma[4:8])
+    pos = 8
+    for _ in range(count):
+        item_id, n = struct.unpack(">HB", ipma[pos:pos + 3])
+        for p in ipma[pos + 3:pos + 3 + n]:
+            kind, body = properties[(p & 0x7F) - 1]
+            if kind == b"hvcC":
+                items[item_id]["hvcc"] = body
+        pos += 3 + n
+    return items
+
+
+def box(kind, payload):
+    return struct.pack(">I4s", len(payload) + 8, kind) + payload
+
+
+def full_box(kind, version, payload):
+    return box(kind, bytes([version, 0, 0, 0]) + payload)
+
+
+def write_grid(path, size, columns, rows, tiles):
+    """A `grid` primary over separately encoded `(hvcC, data, w, h)` tiles."""
+    ids = list(range(2, 2 + len(tiles)))
+    properties, ipma = [], b""
+    for (hvcc, _, w, h), item_id in zip(tiles, ids):
+        properties += [box(b"hvcC", hvcc), full_box(b"ispe", 0, struct.pack(">II", w, h))]
+        ipma += struct.pack(">HBBB", item_id, 2, 0x80 | len(properties) - 1, 0x80 | len(properties))
+    properties.append(full_box(b"ispe", 0, struct.pack(">II", *size)))
+    ipma = struct.pack(">I", len(ids) + 1) + struct.pack(">HBB", 1, 1, 0x80 | len(properties)) + ipma
+    descriptor = bytes([0, 0, rows - 1, columns - 1]) + struct.pack(">HH", *size)
+    iinf = struct.pack(">H", len(ids) + 1) + b"".join(
+        full_box(b"infe", 2, struct.pack(">HH4s", i, 0, kind) + b"\0")
+        for i, kind in [(1, b"grid")] + [(i, b"hvc1") for i in ids]
+    )
+    iref = box(b"dimg", struct.pack(">HH", 1, len(ids)) + b"".join(struct.pack(">H", i) for i in ids))
+    ftyp = box(b"ftyp", b"heic\0\0\0\0mif1heic")
+
+    def meta(mdat_start):
+        iloc = struct.pack(">HH", 0x4400, len(ids) + 1)
+        iloc += struct.pack(">HHHHII", 1, 1, 0, 1, 0, len(descriptor))
+        offset = mdat_start
+        for (_, data, _, _), item_id in zip(tiles, ids):
+            iloc += struct.pack(">HHHHII", item_id, 0, 0, 1, offset, len(data))
+            offset += len(data)
+        return full_box(b"meta", 0, b"".join([
+            full_box(b"hdlr", 0, b"\0\0\0\0pict" + b"\0" * 13),
+            full_box(b"pitm", 0, struct.pack(">H", 1)),
+            full_box(b"iloc", 1, iloc),
+            full_box(b"iinf", 0, iinf),
+            box(b"iprp", box(b"ipco", b"".join(properties)) + full_box(b"ipma", 0, ipma)),
+            full_box(b"iref", 0, iref),
+            box(b"idat", descriptor),
+        ]))
+
+    start = len(ftyp) + len(meta(0)) + 8
+    mdat = box(b"mdat", b"".join(data for _, data, _, _ in tiles))
+    open(path, "wb").write(ftyp + meta(start) + mdat)
+
+
+# ── Reference luma ───────────────────────────────────────────────
+
+
+def nal_units(hvcc, data):
+    """Parameter sets from `hvcC`, then the length-prefixed slice NAL units."""
+    units, pos = [], 23
+    for _ in range(hvcc[22]):
+        count, = struct.unpack(">H", hvcc[pos + 1:pos + 3])
+        pos += 3
+        for _ in range(count):
+            length, = struct.unpack(">H", hvcc[pos:pos + 2])
+            units.append(hvcc[pos + 2:pos + 2 + length])
+            pos += 2 + length
+    pos = 0
+    while pos < len(data):
+        length, = struct.unpack(">I", data[pos:pos + 4])
+        units.append(data[pos + 4:pos + 4 + length])
+        pos += 4 + length
+    return units
+
+
+def unfiltered_luma(hvcc, data):
+    """libde265's luma plane with deblocking and SAO disabled."""
+    ctx = de265.de265_new_decoder()
+    de265.de265_set_parameter_bool(ctypes.c_void_p(ctx), DE265_DISABLE_DEBLOCKING, 1)
+    de265.de265_set_parameter_bool(ctypes.c_void_p(ctx), DE265_DISABLE_SAO, 1)
+    for unit in nal_units(hvcc, data):
+        de265.de265_push_NAL(ctypes.c_void_p(ctx), unit, len(unit), ctypes.c_int64(0), None)
+    de265.de265_flush_data(ctypes.c_void_p(ctx))
+    more, img = ctypes.c_int(1), ctypes.c_void_p()
+    while more.value and not img.value:
+        de265.de265_decode(ctypes.c_void_p(ctx), ctypes.byref(more))
+        img = ctypes.c_void_p(de265.de265_get_next_picture(ctypes.c_void_p(ctx)))
+    assert img.value, "no picture"
+    width = de265.de265_get_image_width(img, 0)
+    height = de265.de265_get_image_height(img, 0)
+    stride = ctypes.c_int()
+    plane = de265.de265_get_image_plane(img, 0, ctypes.byref(stride))
+    luma = b"".join(ctypes.string_at(plane + y * stride.value, width) for y in range(height))
+    de265.de265_free_decoder(ctypes.c_void_p(ctx))
+    return width, height, luma
+
+
+# ── Fixtures ─────────────────────────────────────────────────────
+
+CONFORMANCE = [
+    # (name, x265 parameters, lossless)
+    ("default", [], False),
+    ("ctb16", [("x265:ctu", "16"), ("x265:min-cu-size", "8")], False),
+    ("ctb32-tu-depth", [("x265:ctu", "32"), ("tu-intra-depth", "4")], False),
+    ("transform-skip", [("x265:tskip", "1"), ("x265:rdoq-level", "0"), ("x265:signhide", "0")], False),
+    ("scaling-list", [("x265:scaling-list", "default")], False),
+    ("no-wpp", [("x265:wpp", "0")], False),
+    ("no-strong-smoothing", [("x265:strong-intra-smoothing", "0"), ("x265:aq-mode", "0")], False),
+    ("lossless", [], True),
+]
+
+
+def print_digest(name, item_id, hvcc, data):
+    width, height, luma = unfiltered_luma(hvcc, data)
+    digest = hashlib.sha256(luma).hexdigest()
+    print(f'("{name}", {item_id}, ({width}, {height}), "{digest}"),')
+
+
+def main(source_path):
+    out = lambda name: os.path.join(HERE, name)
+    source = decode_rgb(source_path)
+    # libheif stores odd-sized images as a padded 1x1 grid: keep these even
+    photo = scale(source, 826, 896)
+
+    # Single hvc1 primary with a thmb item, as phones write them
+    encode(out("single.heic"), photo, thumbnail=320)
+    jpeg(out("single.jpg"), decode_rgb(out("single.heic")))
+
+    # Stored sideways with an irot turning it upright, as cameras write it
+    encode(out("irot.heic"), photo.rotate_anticlockwise(), orientation=ORIENTATION_ROTATE_CW_90)
+    jpeg(out("irot.jpg"), decode_rgb(out("irot.heic")))
+
+    # 2x2 grid of 512x512 tiles cropped to 1000x980, as phones tile primaries
+    large = scale(source, 1000, 1084).crop(0, 52, 1000, 980)
+    tiles = []
+    for i in range(4):
+        x0, y0 = (i % 2) * 512, (i // 2) * 512
+        tile = large.crop(x0, y0, min(512, 1000 - x0), min(512, 980 - y0))
+        rows = [tile.rgb[y * tile.width * 3:][: tile.width * 3] + bytes((512 - tile.width) * 3)
+                for y in range(tile.height)]
+        tile = Image(512, 512, b"".join(rows) + bytes((512 - tile.height) * 512 * 3))
+        encode(out("tile.heic"), tile)
+        items = parse(open(out("tile.heic"), "rb").read())
+        primary = items[items["primary"]]
+        tiles.append((primary["hvcc"], primary["data"], 512, 512))
+    os.remove(out("tile.heic"))
+    write_grid(out("grid.heic"), (1000, 980), 2, 2, tiles)
+    jpeg(out("grid.jpg"), decode_rgb(out("grid.heic")))
+
+    # Conformance streams: unfiltered luma pinned by digest
+    for name in ["single", "irot"]:
+        items = parse(open(out(f"{name}.heic"), "rb").read())
+        for item_id, item in items.items():
+            if item_id != "primary" and item["hvcc"]:
+                print_digest(f"{name}.heic", item_id, item["hvcc"], item["data"])
+    for i, (hvcc, data, _, _) in enumerate(tiles):
+        print_digest("grid.heic", 2 + i, hvcc, data)
+    os.makedirs(out("conformance"), exist_ok=True)
+    small = scale(source, 200, 216)
+    for name, params, lossless in CONFORMANCE:
+        path = out(f"conformance/{name}.heic")
+        encode(path, small, params=params, lossless=lossless)
+        items = parse(open(path, "rb").read())
+        primary = items[items["primary"]]
+        print_digest(f"conformance/{name}.heic", items["primary"], primary["hvcc"], primary["data"])
+
+
+if __name__ == "__main__":
+    main(sys.argv[1])
@@ -1,6 +1,9 @@
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
+use photopack_core::export::{ExportEncoder, ImageEncoder, ImageFormat};
+#[cfg(target_os = "macos")]
+use photopack_core::export::SipsEncoder;
 use photopack_core::Vault;
 
 /// Create a JPEG with a gradient pattern seeded by (r, g, b) to ensure distinct perceptual hashes.
@@ -456,6 +459,15 @@ fn test_scan_with_progress_callback() {
                 photopack_core::ScanProgress::FilesRemoved { count } => {
                     events.push(format!("removed:{count}"));
                 }
+                photopack_core::ScanProgress::FileMoved { .. } => {
+                    events.push("moved".to_string());
+                }
+                photopack_core::ScanProgress::SourceOffline { .. } => {
+                    events.push("offline".to_string());
+                }
+                photopack_core::ScanProgress::Excluded { .. } => {
+                    events.push("excluded".to_string());
+                }
                 photopack_core::ScanProgress::PhaseComplete { phase } => {
                     events.push(format!("phase:{phase}"));
                 }
@@ -497,7 +509,7 @@ fn test_scan_ignores_non_photo_files() {
     fs::create_dir_all(&photos_dir).unwrap();
 
     fs::write(photos_dir.join("readme.txt"), b"hello").unwrap();
-    fs::write(photos_dir.join("video.mp4"), b"fake video").unwrap();
+    fs::write(photos_dir.join("video.avi"), b"fake video").unwrap();
     fs::write(photos_dir.join("doc.pdf"), b"fake pdf").unwrap();
     create_jpeg(&photos_dir.join("real.jpg"), 100, 100, 100);
 
@@ -508,6 +520,428 @@ fn test_scan_ignores_non_photo_files() {
     assert_eq!(vault.status().unwrap().total_photos, 1);
 }
 
+// ── Ignore rules ─────────────────────────────────────────────────
+
+#[test]
+fn test_scan_honours_photopackignore() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let previews = photos_dir.join("Catalog Previews.lrdata/0/A");
+    fs::create_dir_all(&previews).unwrap();
+
+    create_jpeg(&photos_dir.join("real.jpg"), 10, 20, 30);
+    // A cached preview that would otherwise be grouped with the original
+    copy_file(&photos_dir.join("real.jpg"), &previews.join("preview.jpg"));
+    fs::write(photos_dir.join(".photopackignore"), "*.lrdata/\n").unwrap();
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+
+    let mut excluded = Vec::new();
+    vault
+        .scan(Some(&mut |progress| {
+            if let photopack_core::ScanProgress::Excluded { path } = progress {
+                excluded.push(path);
+            }
+        }))
+        .unwrap();
+
+    let photos = vault.photos().unwrap();
+    assert_eq!(photos.len(), 1);
+    assert!(photos[0].path.ends_with("real.jpg"));
+    assert_eq!(vault.status().unwrap().total_groups, 0);
+    assert_eq!(excluded.len(), 1);
+    assert!(excluded[0].ends_with("Catalog Previews.lrdata"));
+}
+
+#[test]
+fn test_scan_honours_global_ignore_patterns() {
+    let tmp = tempfile::tempdir().unwrap();
+    let source_a = tmp.path().join("nas");
+    let source_b = tmp.path().join("laptop");
+    fs::create_dir_all(source_a.join("@eaDir")).unwrap();
+    fs::create_dir_all(source_b.join("sub/@eaDir")).unwrap();
+
+    create_jpeg(&source_a.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&source_a.join("@eaDir/thumb.jpg"), 40, 50, 60);
+    create_jpeg(&source_b.join("b.jpg"), 70, 80, 90);
+    create_jpeg(&source_b.join("sub/@eaDir/thumb.jpg"), 100, 110, 120);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&source_a).unwrap();
+    vault.add_source(&source_b).unwrap();
+    assert!(vault.add_ignore_pattern("@eaDir/").unwrap());
+    assert!(!vault.add_ignore_pattern("@eaDir/").unwrap());
+    assert_eq!(vault.ignore_patterns().unwrap(), vec!["@eaDir/".to_string()]);
+
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_photos, 2);
+}
+
+#[test]
+fn test_new_ignore_pattern_removes_previously_scanned_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    fs::create_dir_all(photos_dir.join(".thumbnails")).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join(".thumbnails/a.jpg"), 40, 50, 60);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_photos, 2);
+
+    vault.add_ignore_pattern(".thumbnails/").unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_photos, 1);
+
+    assert!(vault.remove_ignore_pattern(".thumbnails/").unwrap());
+    assert!(vault.ignore_patterns().unwrap().is_empty());
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_photos, 2);
+}
+
+#[test]
+fn test_invalid_ignore_pattern_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+
+    assert!(vault.add_ignore_pattern("broken{a,b").is_err());
+    assert!(vault.ignore_patterns().unwrap().is_empty());
+}
+
+// ── Matching profiles ────────────────────────────────────────────
+
+#[test]
+fn test_match_profile_persisted_and_applied_on_scan() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    fs::create_dir_all(&photos_dir).unwrap();
+    create_jpeg(&photos_dir.join("a.jpg"), 100, 100, 100);
+    copy_file(&photos_dir.join("a.jpg"), &photos_dir.join("b.jpg"));
+    create_jpeg_checkerboard(&photos_dir.join("c.jpg"), 8, [0, 0, 0], [255, 255, 255]);
+
+    let db_path = tmp.path().join("catalog.db");
+    {
+        let vault = Vault::open(&db_path).unwrap();
+        assert_eq!(vault.match_config().unwrap(), photopack_core::matching::MatchConfig::default());
+        vault.add_source(&photos_dir).unwrap();
+        let scans = vault.set_match_profile("scans").unwrap();
+        assert_eq!(scans.profile_name(), Some("scans"));
+    }
+
+    let mut vault = Vault::open(&db_path).unwrap();
+    assert_eq!(
+        vault.match_config().unwrap(),
+        photopack_core::matching::MatchConfig::profile("scans").unwrap()
+    );
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 1);
+
+    // Exact copies group under every profile
+    vault.set_match_profile("strict").unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 1);
+
+    let err = vault.set_match_profile("loose").unwrap_err();
+    assert!(matches!(err, photopack_core::error::Error::UnknownMatchProfile(ref name) if name == "loose"));
+    assert_eq!(
+        vault.match_config().unwrap(),
+        photopack_core::matching::MatchConfig::profile("strict").unwrap()
+    );
+}
+
+#[test]
+fn test_group_evidence_explains_members() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    fs::create_dir_all(&photos_dir).unwrap();
+    create_jpeg(&photos_dir.join("a.jpg"), 100, 100, 100);
+    copy_file(&photos_dir.join("a.jpg"), &photos_dir.join("b.jpg"));
+    create_jpeg(&photos_dir.join("other.jpg"), 10, 200, 30);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+
+    let groups = vault.groups().unwrap();
+    assert_eq!(groups.len(), 1);
+    let evidence = vault.group_evidence(groups[0].id).unwrap();
+    assert_eq!(evidence.len(), 1);
+    assert_eq!(evidence[0].phase, photopack_core::domain::MatchPhase::Sha256);
+    let member_ids: Vec<i64> = groups[0].members.iter().map(|m| m.id).collect();
+    assert!(member_ids.contains(&evidence[0].photo_a));
+    assert!(member_ids.contains(&evidence[0].photo_b));
+
+    // Evidence is rebuilt with the groups on rescan
+    vault.scan(None).unwrap();
+    let group_id = vault.groups().unwrap()[0].id;
+    assert_eq!(vault.group_evidence(group_id).unwrap().len(), 1);
+
+    assert!(matches!(
+        vault.group_evidence(9999),
+        Err(photopack_core::error::Error::GroupNotFound(9999))
+    ));
+}
+
+// ── Manual split / merge ─────────────────────────────────────────
+
+#[test]
+fn test_split_survives_rescan_and_move() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    create_jpeg(&photos.join("sunset.jpg"), 200, 100, 50);
+    create_png(&photos.join("sunset.png"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 1);
+
+    vault
+        .split_photos(&photos.join("sunset.jpg"), &photos.join("sunset.png"))
+        .unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 0, "split applies at once");
+    assert_eq!(vault.match_overrides().unwrap().len(), 1);
+
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 0, "split survives rescan");
+
+    // Keyed by content: a renamed file keeps its decision
+    fs::create_dir_all(photos.join("sorted")).unwrap();
+    fs::rename(photos.join("sunset.png"), photos.join("sorted/renamed.png")).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 0, "split survives move");
+
+    assert!(vault
+        .clear_match_override(&photos.join("sunset.jpg"), &photos.join("sorted/renamed.png"))
+        .unwrap());
+    assert_eq!(vault.status().unwrap().total_groups, 1);
+    assert!(vault.match_overrides().unwrap().is_empty());
+}
+
+#[test]
+fn test_merge_groups_unrelated_photos_until_undone() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    create_jpeg(&photos.join("print.jpg"), 10, 20, 30);
+    create_jpeg_checkerboard(&photos.join("negative.jpg"), 8, [0, 0, 0], [255, 255, 255]);
+    create_jpeg(&photos.join("other.jpg"), 200, 50, 120);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_groups, 0);
+
+    vault
+        .merge_photos(&photos.join("print.jpg"), &photos.join("negative.jpg"))
+        .unwrap();
+    vault.scan(None).unwrap();
+    let groups = vault.groups().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].members.len(), 2);
+    let evidence = vault.group_evidence(groups[0].id).unwrap();
+    assert_eq!(evidence[0].phase, photopack_core::domain::MatchPhase::Manual);
+
+    // Byte-identical files and unknown files are rejected
+    copy_file(&photos.join("other.jpg"), &photos.join("other copy.jpg"));
+    vault.scan(None).unwrap();
+    assert!(matches!(
+        vault.split_photos(&photos.join("other.jpg"), &photos.join("other copy.jpg")),
+        Err(photopack_core::error::Error::IdenticalContent { .. })
+    ));
+    assert!(matches!(
+        vault.merge_photos(&photos.join("print.jpg"), &photos.join("missing.jpg")),
+        Err(photopack_core::error::Error::PhotoNotInCatalog(_))
+    ));
+
+    assert!(vault
+        .clear_match_override(&photos.join("negative.jpg"), &photos.join("print.jpg"))
+        .unwrap());
+    assert_eq!(vault.status().unwrap().total_groups, 1, "only the exact copies remain");
+}
+
+// ── Source-of-truth pins ─────────────────────────────────────────
+
+#[test]
+fn test_pin_overrides_election_on_scan_and_pack() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+    create_jpeg(&photos.join("edit.jpg"), 200, 100, 50);
+    create_png(&photos.join("master.png"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+    let sot_name = |vault: &Vault| {
+        let group = &vault.groups().unwrap()[0];
+        let sot = group
+            .members
+            .iter()
+            .find(|m| m.id == group.source_of_truth_id)
+            .unwrap();
+        sot.path.file_name().unwrap().to_string_lossy().to_string()
+    };
+    assert_eq!(sot_name(&vault), "master.png");
+
+    vault.pin_photo(&photos.join("edit.jpg")).unwrap();
+    assert_eq!(sot_name(&vault), "edit.jpg", "pin applies at once");
+    assert_eq!(vault.pinned_hashes().unwrap().len(), 1);
+
+    vault.scan(None).unwrap();
+    assert_eq!(sot_name(&vault), "edit.jpg", "pin survives rescan");
+
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    let pack_files = list_pack_files(&vault_dir);
+    assert_eq!(pack_files.len(), 1);
+    assert_eq!(pack_files[0].extension().unwrap(), "jpg");
+
+    assert!(vault.unpin_photo(&photos.join("edit.jpg")).unwrap());
+    assert!(!vault.unpin_photo(&photos.join("edit.jpg")).unwrap());
+    assert_eq!(sot_name(&vault), "master.png");
+    assert!(matches!(
+        vault.pin_photo(&photos.join("missing.jpg")),
+        Err(photopack_core::error::Error::PhotoNotInCatalog(_))
+    ));
+}
+
+// ── Ranking policy ───────────────────────────────────────────────
+
+#[test]
+fn test_ranking_policy_and_source_priority() {
+    let tmp = tempfile::tempdir().unwrap();
+    let camera = tmp.path().join("camera");
+    let edits = tmp.path().join("edits");
+    fs::create_dir_all(&camera).unwrap();
+    fs::create_dir_all(&edits).unwrap();
+    create_png(&camera.join("beach.png"), 30, 120, 200);
+    create_jpeg(&edits.join("beach.jpg"), 30, 120, 200);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&camera).unwrap();
+    vault.add_source(&edits).unwrap();
+    vault.scan(None).unwrap();
+    let sot_dir = |vault: &Vault| {
+        let group = &vault.groups().unwrap()[0];
+        let sot = group
+            .members
+            .iter()
+            .find(|m| m.id == group.source_of_truth_id)
+            .unwrap();
+        sot.path.parent().unwrap().file_name().unwrap().to_string_lossy().to_string()
+    };
+    assert_eq!(sot_dir(&vault), "camera", "format tier wins by default");
+
+    // Source priority only decides once it ranks above the format tier
+    let source = vault.set_source_priority(&edits, 10).unwrap();
+    assert_eq!(source.priority, 10);
+    assert_eq!(sot_dir(&vault), "camera");
+
+    let policy = photopack_core::ranking::RankingPolicy::parse("source,format").unwrap();
+    vault.set_ranking_policy(&policy).unwrap();
+    assert_eq!(sot_dir(&vault), "edits");
+    vault.scan(None).unwrap();
+    assert_eq!(sot_dir(&vault), "edits", "policy survives rescan");
+
+    let stored = vault.ranking_policy().unwrap();
+    assert_eq!(stored.to_config_value(), "source,format");
+    assert_eq!(stored.source_priority.values().copied().max(), Some(10));
+
+    assert!(matches!(
+        vault.set_source_priority(&tmp.path().join("elsewhere"), 1),
+        Err(photopack_core::error::Error::SourceNotRegistered(_))
+    ));
+}
+
+// ── Pack policy ──────────────────────────────────────────────────
+
+#[test]
+fn test_pack_policy_families_keeps_lossy_copy() {
+    use photopack_core::domain::{PackPolicy, PackRole};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+    create_png(&photos.join("shot.png"), 200, 100, 50);
+    create_jpeg(&photos.join("shot.jpg"), 200, 100, 50);
+    copy_file(&photos.join("shot.jpg"), &photos.join("shot copy.jpg"));
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+    assert!(matches!(
+        vault.pack_policy(),
+        Err(photopack_core::error::Error::VaultPathNotSet)
+    ));
+    vault.set_vault_path(&vault_dir).unwrap();
+    assert_eq!(vault.pack_policy().unwrap(), PackPolicy::Source);
+    vault.vault_save(None).unwrap();
+    assert_eq!(list_pack_files(&vault_dir).len(), 1, "only the PNG by default");
+
+    vault.set_pack_policy(PackPolicy::Families).unwrap();
+    vault.vault_save(None).unwrap();
+    let mut exts: Vec<String> = list_pack_files(&vault_dir)
+        .iter()
+        .map(|p| p.extension().unwrap().to_string_lossy().to_string())
+        .collect();
+    exts.sort();
+    assert_eq!(exts, vec!["jpg", "png"], "one copy per family, no byte-identical twin");
+
+    // Entries name their group by the hash of its source of truth (the PNG)
+    let manifest_roles = || {
+        let manifest = photopack_core::manifest::Manifest::open(&vault_dir).unwrap();
+        let mut roles: Vec<(String, Option<String>, Option<PackRole>)> = manifest
+            .list_files()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.format, e.group_sha256, e.role))
+            .collect();
+        roles.sort_by(|a, b| a.0.cmp(&b.0));
+        roles
+    };
+    let png_sha = vault
+        .groups()
+        .unwrap()[0]
+        .members
+        .iter()
+        .find(|m| m.path.ends_with("shot.png"))
+        .unwrap()
+        .sha256
+        .clone();
+    let roles = manifest_roles();
+    assert_eq!(
+        roles,
+        vec![
+            ("JPEG".to_string(), Some(png_sha.clone()), Some(PackRole::Alternate)),
+            ("PNG".to_string(), Some(png_sha), Some(PackRole::Source)),
+        ]
+    );
+
+    // Back to source only: the alternate goes to the trash
+    vault.set_pack_policy(PackPolicy::Source).unwrap();
+    vault.vault_save(None).unwrap();
+    assert_eq!(list_pack_files(&vault_dir).len(), 1);
+
+    // A rescan regroups the catalog; the recorded group does not move
+    let group_id = vault.groups().unwrap()[0].id;
+    create_jpeg(&photos.join("other.jpg"), 10, 20, 30);
+    copy_file(&photos.join("other.jpg"), &photos.join("other copy.jpg"));
+    vault.scan(None).unwrap();
+    assert!(vault.groups().unwrap().iter().all(|g| g.id != group_id));
+    vault.vault_save(None).unwrap();
+    let png = manifest_roles().into_iter().find(|(format, ..)| format == "PNG");
+    assert_eq!(png.as_ref(), roles.last());
+}
+
 // ── Rescan clears stale groups ───────────────────────────────────
 
 #[test]
@@ -716,30 +1150,251 @@ fn test_scan_removes_deleted_and_picks_up_new() {
     assert!(!names.contains(&"delete_me.jpg".to_string()));
 }
 
-// ── Nested directories ───────────────────────────────────────────
+// ── Moved and renamed files ──────────────────────────────────────
 
+/// Reorganizing a source keeps photo ids (and so group membership) instead of
+/// re-cataloging every file as new.
 #[test]
-fn test_scan_nested_directories() {
+fn test_scan_detects_moved_files_and_keeps_identity() {
     let tmp = tempfile::tempdir().unwrap();
-    let root = tmp.path().join("photos");
-    let sub = root.join("2024/vacation");
-    fs::create_dir_all(&sub).unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
 
-    create_jpeg(&root.join("top.jpg"), 10, 20, 30);
-    create_jpeg(&sub.join("nested.jpg"), 40, 50, 60);
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    copy_file(&photos.join("a.jpg"), &photos.join("a_copy.jpg"));
+    create_jpeg(&photos.join("b.jpg"), 40, 50, 60);
 
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
-    vault.add_source(&root).unwrap();
+    vault.add_source(&photos).unwrap();
     vault.scan(None).unwrap();
+    let id_of = |vault: &Vault, name: &str| {
+        vault
+            .photos()
+            .unwrap()
+            .into_iter()
+            .find(|p| p.path.file_name().unwrap() == name)
+            .map(|p| p.id)
+    };
+    let (a, copy, b) = (
+        id_of(&vault, "a.jpg").unwrap(),
+        id_of(&vault, "a_copy.jpg").unwrap(),
+        id_of(&vault, "b.jpg").unwrap(),
+    );
+    let mut members_before: Vec<i64> = vault.groups().unwrap()[0].members.iter().map(|m| m.id).collect();
+    members_before.sort();
 
-    assert_eq!(vault.status().unwrap().total_photos, 2);
+    fs::create_dir_all(photos.join("2024")).unwrap();
+    fs::rename(photos.join("a.jpg"), photos.join("2024/a.jpg")).unwrap();
+    fs::rename(photos.join("b.jpg"), photos.join("2024/renamed.jpg")).unwrap();
+
+    let mut events = Vec::new();
+    vault
+        .scan(Some(&mut |progress| match progress {
+            photopack_core::ScanProgress::FileMoved { from, to } => events.push((from, to)),
+            photopack_core::ScanProgress::FilesRemoved { .. } => panic!("nothing was deleted"),
+            _ => {}
+        }))
+        .unwrap();
+
+    events.sort();
+    assert_eq!(
+        events,
+        vec![
+            (photos.join("a.jpg"), photos.join("2024/a.jpg")),
+            (photos.join("b.jpg"), photos.join("2024/renamed.jpg")),
+        ]
+    );
+    assert_eq!(vault.status().unwrap().total_photos, 3);
+    assert_eq!(id_of(&vault, "a.jpg"), Some(a));
+    assert_eq!(id_of(&vault, "a_copy.jpg"), Some(copy));
+    assert_eq!(id_of(&vault, "renamed.jpg"), Some(b));
+
+    let groups = vault.groups().unwrap();
+    assert_eq!(groups.len(), 1);
+    let mut members_after: Vec<i64> = groups[0].members.iter().map(|m| m.id).collect();
+    members_after.sort();
+    assert_eq!(members_after, members_before);
 }
 
-// ── Cross-directory & cross-format regression tests ─────────────
+/// A file replaced by different content at a new path is a delete plus an add.
+#[test]
+fn test_scan_changed_content_is_not_a_move() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
 
-/// Helper: create a PNG with the same gradient pattern as create_jpeg so the
-/// perceptual hashes will match, but the SHA-256 will differ (different format).
-fn create_png(path: &Path, r: u8, g: u8, b: u8) {
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+    let old_id = vault.photos().unwrap()[0].id;
+
+    fs::remove_file(photos.join("a.jpg")).unwrap();
+    create_jpeg(&photos.join("b.jpg"), 70, 80, 90);
+
+    let mut moved = 0;
+    vault
+        .scan(Some(&mut |progress| {
+            if let photopack_core::ScanProgress::FileMoved { .. } = progress {
+                moved += 1;
+            }
+        }))
+        .unwrap();
+
+    assert_eq!(moved, 0);
+    let photos_list = vault.photos().unwrap();
+    assert_eq!(photos_list.len(), 1);
+    assert_ne!(photos_list[0].id, old_id);
+}
+
+// ── Offline sources ──────────────────────────────────────────────
+
+/// An unmounted volume is marked offline; its photos stay cataloged and grouped.
+#[test]
+fn test_unmounted_source_is_offline_and_keeps_photos() {
+    let tmp = tempfile::tempdir().unwrap();
+    let laptop = tmp.path().join("laptop");
+    let backup = tmp.path().join("Backup");
+    let unmounted = tmp.path().join("Backup.unmounted");
+    fs::create_dir_all(&laptop).unwrap();
+    fs::create_dir_all(&backup).unwrap();
+
+    create_jpeg(&laptop.join("a.jpg"), 10, 20, 30);
+    copy_file(&laptop.join("a.jpg"), &backup.join("a.jpg"));
+    create_jpeg(&backup.join("b.jpg"), 40, 50, 60);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&laptop).unwrap();
+    let source = vault.add_source(&backup).unwrap();
+    assert!(source.volume_id.is_some());
+    assert!(backup.join(photopack_core::volume::MARKER_FILE).is_file());
+    vault.scan(None).unwrap();
+
+    // Unmount
+    fs::rename(&backup, &unmounted).unwrap();
+    let mut offline = Vec::new();
+    vault
+        .scan(Some(&mut |progress| {
+            if let photopack_core::ScanProgress::SourceOffline { source } = progress {
+                offline.push(source);
+            }
+        }))
+        .unwrap();
+
+    assert_eq!(offline.len(), 1);
+    assert_eq!(vault.status().unwrap().total_photos, 3);
+    assert_eq!(vault.groups().unwrap().len(), 1, "offline copy still matched");
+    let sources = vault.sources().unwrap();
+    let backup_source = sources.iter().find(|s| s.id == source.id).unwrap();
+    assert!(!backup_source.online);
+
+    // Remount
+    fs::rename(&unmounted, &backup).unwrap();
+    vault.scan(None).unwrap();
+    let sources = vault.sources().unwrap();
+    assert!(sources.iter().all(|s| s.online));
+    assert_eq!(vault.status().unwrap().total_photos, 3);
+}
+
+/// An empty mount point (the folder exists, the volume isn't there) or another
+/// volume mounted at the path is offline too, not a source whose files were deleted.
+#[test]
+fn test_empty_mount_point_or_other_volume_is_offline() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mount = tmp.path().join("Backup");
+    let stash = tmp.path().join("stash");
+    fs::create_dir_all(&mount).unwrap();
+
+    create_jpeg(&mount.join("a.jpg"), 10, 20, 30);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&mount).unwrap();
+    vault.scan(None).unwrap();
+
+    // Unmounted: the mount point stays behind, empty
+    fs::rename(&mount, &stash).unwrap();
+    fs::create_dir_all(&mount).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_photos, 1);
+    assert!(!vault.sources().unwrap()[0].online);
+
+    // A different volume mounted at the same path
+    fs::write(mount.join(photopack_core::volume::MARKER_FILE), "another-volume\n").unwrap();
+    create_jpeg(&mount.join("other.jpg"), 40, 50, 60);
+    vault.scan(None).unwrap();
+    let photos = vault.photos().unwrap();
+    assert_eq!(photos.len(), 1);
+    assert_eq!(photos[0].path.file_name().unwrap(), "a.jpg");
+    assert!(!vault.sources().unwrap()[0].online);
+}
+
+/// A source added before volume markers has no identity to check: an empty
+/// mount point must stay offline rather than adopt a fresh marker.
+#[test]
+fn test_unmarked_source_empty_mount_point_is_offline() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mount = tmp.path().join("Backup");
+    let stash = tmp.path().join("stash");
+    let db = tmp.path().join("catalog.db");
+    fs::create_dir_all(&mount).unwrap();
+    create_jpeg(&mount.join("a.jpg"), 10, 20, 30);
+
+    let mut vault = Vault::open(&db).unwrap();
+    vault.add_source(&mount).unwrap();
+    vault.scan(None).unwrap();
+
+    // Forget the identity, as in a catalog from before markers
+    fs::remove_file(mount.join(photopack_core::volume::MARKER_FILE)).unwrap();
+    rusqlite::Connection::open(&db)
+        .unwrap()
+        .execute("UPDATE sources SET volume_id = NULL", [])
+        .unwrap();
+
+    fs::rename(&mount, &stash).unwrap();
+    fs::create_dir_all(&mount).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.status().unwrap().total_photos, 1);
+    let source = &vault.sources().unwrap()[0];
+    assert!(!source.online);
+    assert!(source.volume_id.is_none());
+    assert!(!mount.join(photopack_core::volume::MARKER_FILE).exists());
+
+    // Remounted: the photos are back, so the source adopts a marker
+    fs::remove_dir(&mount).unwrap();
+    fs::rename(&stash, &mount).unwrap();
+    vault.scan(None).unwrap();
+    let source = &vault.sources().unwrap()[0];
+    assert!(source.online);
+    assert!(source.volume_id.is_some());
+    assert!(mount.join(photopack_core::volume::MARKER_FILE).is_file());
+    assert_eq!(vault.status().unwrap().total_photos, 1);
+}
+
+// ── Nested directories ───────────────────────────────────────────
+
+#[test]
+fn test_scan_nested_directories() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path().join("photos");
+    let sub = root.join("2024/vacation");
+    fs::create_dir_all(&sub).unwrap();
+
+    create_jpeg(&root.join("top.jpg"), 10, 20, 30);
+    create_jpeg(&sub.join("nested.jpg"), 40, 50, 60);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&root).unwrap();
+    vault.scan(None).unwrap();
+
+    assert_eq!(vault.status().unwrap().total_photos, 2);
+}
+
+// ── Cross-directory & cross-format regression tests ─────────────
+
+/// Helper: create a PNG with the same gradient pattern as create_jpeg so the
+/// perceptual hashes will match, but the SHA-256 will differ (different format).
+fn create_png(path: &Path, r: u8, g: u8, b: u8) {
     let img = image::RgbImage::from_fn(64, 64, |x, y| {
         image::Rgb([
             r.wrapping_add((x * 3) as u8),
@@ -832,7 +1487,7 @@ fn test_cross_format_cross_directory_merge_into_one_group() {
 /// Helper: create JPEG with a checkerboard pattern (structurally different from gradients).
 fn create_jpeg_checkerboard(path: &Path, block_size: u32, c1: [u8; 3], c2: [u8; 3]) {
     let img = image::RgbImage::from_fn(64, 64, |x, y| {
-        if (x / block_size + y / block_size) % 2 == 0 {
+        if (x / block_size + y / block_size).is_multiple_of(2) {
             image::Rgb(c1)
         } else {
             image::Rgb(c2)
@@ -844,7 +1499,7 @@ fn create_jpeg_checkerboard(path: &Path, block_size: u32, c1: [u8; 3], c2: [u8;
 /// Helper: create PNG with a checkerboard pattern.
 fn create_png_checkerboard(path: &Path, block_size: u32, c1: [u8; 3], c2: [u8; 3]) {
     let img = image::RgbImage::from_fn(64, 64, |x, y| {
-        if (x / block_size + y / block_size) % 2 == 0 {
+        if (x / block_size + y / block_size).is_multiple_of(2) {
             image::Rgb(c1)
         } else {
             image::Rgb(c2)
@@ -1045,7 +1700,7 @@ fn test_photos_api_correct_source_ids() {
 
     // Verify each photo's source_id matches the directory it came from
     for photo in &photos {
-        if photo.path.starts_with(&dir_a.canonicalize().unwrap()) {
+        if photo.path.starts_with(dir_a.canonicalize().unwrap()) {
             assert_eq!(photo.source_id, source_a.id);
         } else {
             assert_eq!(photo.source_id, source_b.id);
@@ -1889,6 +2544,93 @@ fn test_all_raw_formats_beat_jpeg() {
     }
 }
 
+// ── RAW embedded previews ─────────────────────────────────────────
+
+/// Wrap JPEG bytes in a minimal little-endian TIFF container (like CR2/NEF/ARW):
+/// IFD0 carries the orientation plus JPEGInterchangeFormat/Length pointing at the preview.
+fn create_raw_with_preview(path: &Path, jpeg: &[u8], orientation: u16) {
+    let mut data = b"II*\0".to_vec();
+    data.extend_from_slice(&8u32.to_le_bytes());
+    let preview_offset = 8 + 2 + 3 * 12 + 4;
+    data.extend_from_slice(&3u16.to_le_bytes());
+    for (tag, typ, value) in [
+        (0x0112u16, 3u16, orientation as u32),
+        (0x0201, 4, preview_offset),
+        (0x0202, 4, jpeg.len() as u32),
+    ] {
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&typ.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(jpeg);
+    fs::write(path, data).unwrap();
+}
+
+#[test]
+fn test_raw_preview_gets_perceptual_hash_and_groups_with_jpeg() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("photos");
+    fs::create_dir_all(&dir).unwrap();
+
+    create_jpeg(&dir.join("IMG_0001.jpg"), 30, 90, 150);
+    let jpeg = fs::read(dir.join("IMG_0001.jpg")).unwrap();
+    // No EXIF anywhere: only the embedded preview can link the two files
+    create_raw_with_preview(&dir.join("IMG_0001.cr2"), &jpeg, 1);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&dir).unwrap();
+    vault.scan(None).unwrap();
+
+    let photos = vault.photos().unwrap();
+    let raw = photos
+        .iter()
+        .find(|p| p.format == photopack_core::domain::PhotoFormat::Cr2)
+        .unwrap();
+    assert!(raw.phash.is_some(), "RAW should be hashed via its preview");
+    assert!(raw.dhash.is_some());
+
+    let groups = vault.groups().unwrap();
+    assert_eq!(groups.len(), 1, "RAW and JPEG sibling should be grouped");
+    assert_eq!(groups[0].members.len(), 2);
+    assert_eq!(groups[0].source_of_truth_id, raw.id, "RAW must be SOT");
+}
+
+#[test]
+fn test_raw_preview_orientation_matches_rotated_export() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("photos");
+    fs::create_dir_all(&dir).unwrap();
+
+    // Sensor-oriented (landscape) preview with orientation=6 in the RAW container
+    let landscape = image::RgbImage::from_fn(96, 64, |x, y| {
+        image::Rgb([(x * 2) as u8, (y * 3) as u8, ((x + y) % 256) as u8])
+    });
+    let mut preview = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut preview, 95)
+        .encode_image(&landscape)
+        .unwrap();
+    create_raw_with_preview(&dir.join("IMG_0002.nef"), &preview, 6);
+
+    // The camera/iOS export has the pixels physically rotated 90° CW
+    let rotated = image::imageops::rotate90(&landscape);
+    rotated.save(dir.join("IMG_0002.jpg")).unwrap();
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&dir).unwrap();
+    vault.scan(None).unwrap();
+
+    let photos = vault.photos().unwrap();
+    let raw = photos.iter().find(|p| p.path.ends_with("IMG_0002.nef")).unwrap();
+    let jpg = photos.iter().find(|p| p.path.ends_with("IMG_0002.jpg")).unwrap();
+    let distance = photopack_core::hasher::perceptual::hamming_distance(
+        raw.phash.unwrap(),
+        jpg.phash.unwrap(),
+    );
+    assert!(distance <= 2, "orientation must be applied, distance={distance}");
+}
+
 // ── Vault save ────────────────────────────────────────────────────
 
 fn count_files_recursive(dir: &std::path::Path) -> usize {
@@ -2348,15 +3090,28 @@ fn test_vault_save_progress_events_order() {
                 photopack_core::vault_save::VaultSaveProgress::Skipped { .. } => {
                     events.push("skipped".to_string());
                 }
+                photopack_core::vault_save::VaultSaveProgress::SidecarCopied { .. } => {
+                    events.push("sidecar".to_string());
+                }
                 photopack_core::vault_save::VaultSaveProgress::Removed { .. } => {
                     events.push("removed".to_string());
                 }
+                photopack_core::vault_save::VaultSaveProgress::TrashPurged { .. } => {
+                    events.push("purged".to_string());
+                }
+                photopack_core::vault_save::VaultSaveProgress::ViewsSynced { .. } => {
+                    events.push("views".to_string());
+                }
+                photopack_core::vault_save::VaultSaveProgress::Failed { .. } => {
+                    events.push("failed".to_string());
+                }
                 photopack_core::vault_save::VaultSaveProgress::Complete {
                     copied,
                     skipped,
                     removed,
+                    failed,
                 } => {
-                    events.push(format!("complete:{copied}:{skipped}:{removed}"));
+                    events.push(format!("complete:{copied}:{skipped}:{removed}:{failed}"));
                 }
             }
         }))
@@ -2365,7 +3120,48 @@ fn test_vault_save_progress_events_order() {
     // Should be: start → copied × 2 → complete
     assert_eq!(events[0], "start:2");
     assert_eq!(events.iter().filter(|e| *e == "copied").count(), 2);
-    assert!(events.last().unwrap().starts_with("complete:2:0:0"));
+    assert!(events.last().unwrap().starts_with("complete:2:0:0:0"));
+}
+
+#[test]
+fn test_vault_save_reports_file_changed_since_scan() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("kept.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join("edited.jpg"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.set_vault_path(&vault_dir).unwrap();
+
+    // Edited after the scan: the copy no longer matches the catalogued hash
+    let edited = photos_dir.join("edited.jpg").canonicalize().unwrap();
+    create_jpeg(&edited, 90, 90, 90);
+
+    let mut events = Vec::new();
+    let mut counts = None;
+    let failed = vault
+        .vault_save(Some(&mut |progress| match progress {
+            photopack_core::vault_save::VaultSaveProgress::Failed { path, .. } => {
+                events.push(path);
+            }
+            photopack_core::vault_save::VaultSaveProgress::Complete {
+                copied, failed, ..
+            } => counts = Some((copied, failed)),
+            _ => {}
+        }))
+        .unwrap();
+
+    assert_eq!(events, std::slice::from_ref(&edited));
+    assert_eq!(counts, Some((1, 1)));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].path, edited);
+    assert!(failed[0].error.contains("hash mismatch"), "{}", failed[0].error);
 }
 
 #[test]
@@ -2661,7 +3457,45 @@ fn test_pack_manifest_records_metadata() {
 
     let photos = vault.photos().unwrap();
     assert!(manifest.contains(&photos[0].sha256).unwrap());
-    assert_eq!(manifest.version().unwrap(), "1");
+    assert_eq!(manifest.version().unwrap(), "8");
+}
+
+/// A truncated pack file (interrupted copy) is re-copied, not skipped forever.
+#[test]
+fn test_pack_recopies_truncated_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("photo.jpg"), 100, 100, 100);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.vault_save(None).unwrap();
+
+    let pack_files = list_pack_files(&vault_dir);
+    assert_eq!(pack_files.len(), 1);
+    let original = fs::read(&pack_files[0]).unwrap();
+    fs::write(&pack_files[0], &original[..original.len() / 2]).unwrap();
+
+    use photopack_core::vault_save::VaultSaveProgress;
+    let mut copied = 0;
+    vault
+        .vault_save(Some(&mut |progress| {
+            if let VaultSaveProgress::Complete { copied: c, .. } = progress {
+                copied = c;
+            }
+        }))
+        .unwrap();
+
+    assert_eq!(copied, 1);
+    assert_eq!(fs::read(&pack_files[0]).unwrap(), original);
+    // No temp files left in the shard directory
+    assert_eq!(list_pack_files(&vault_dir).len(), 1);
 }
 
 /// Two identical files → one pack file (structural dedup).
@@ -2909,87 +3743,1312 @@ fn test_vault_sync_cleanup_reports_removed_count() {
     assert_eq!(count_files_recursive(&vault_dir), 0, "Pack should be empty after cleanup");
 }
 
-// ── Export (HEIC conversion) tests ──────────────────────────────
+// ── Pack verification ───────────────────────────────────────────
+
+/// Scan `photos_dir` and pack it into `vault_dir`, returning the vault.
+fn pack_photos(tmp: &Path, photos_dir: &Path, vault_dir: &Path) -> Vault {
+    let mut vault = Vault::open(&tmp.join("catalog.db")).unwrap();
+    vault.add_source(photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.set_vault_path(vault_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    vault
+}
 
-#[cfg(target_os = "macos")]
 #[test]
-fn test_export_converts_jpeg_to_heic() {
+fn test_verify_pack_clean_after_pack() {
     let tmp = tempfile::tempdir().unwrap();
     let photos_dir = tmp.path().join("photos");
-    let export_dir = tmp.path().join("export");
+    let vault_dir = tmp.path().join("vault");
     fs::create_dir_all(&photos_dir).unwrap();
-    fs::create_dir_all(&export_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
 
-    create_jpeg(&photos_dir.join("photo.jpg"), 100, 150, 200);
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_png(&photos_dir.join("b.png"), 200, 50, 175);
 
-    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
-    vault.add_source(&photos_dir).unwrap();
-    vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
 
-    let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .collect();
+    use photopack_core::verify::VerifyProgress;
+    let mut events = Vec::new();
+    let report = vault
+        .verify_pack(
+            None,
+            Some(&mut |progress| match progress {
+                VerifyProgress::Start { total } => events.push(format!("start:{total}")),
+                VerifyProgress::Verified { .. } => events.push("verified".to_string()),
+                VerifyProgress::Complete { verified, problems } => {
+                    events.push(format!("complete:{verified}:{problems}"))
+                }
+                _ => events.push("problem".to_string()),
+            }),
+        )
+        .unwrap();
 
-    assert_eq!(exported.len(), 1);
-    assert_eq!(exported[0].path().extension().unwrap(), "heic");
-    assert!(
-        exported[0]
-            .path()
-            .file_stem()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .starts_with("photo")
-    );
+    assert!(report.is_clean(), "{report:?}");
+    assert_eq!(report.verified, 2);
+    assert_eq!(events, ["start:2", "verified", "verified", "complete:2:0"]);
 }
 
-#[cfg(target_os = "macos")]
 #[test]
-fn test_export_deduplicates_groups() {
+fn test_verify_pack_detects_tampering() {
     let tmp = tempfile::tempdir().unwrap();
     let photos_dir = tmp.path().join("photos");
-    let export_dir = tmp.path().join("export");
+    let vault_dir = tmp.path().join("vault");
     fs::create_dir_all(&photos_dir).unwrap();
-    fs::create_dir_all(&export_dir).unwrap();
-
-    create_jpeg(&photos_dir.join("original.jpg"), 100, 150, 200);
-    copy_file(
-        &photos_dir.join("original.jpg"),
-        &photos_dir.join("copy.jpg"),
-    );
-    create_jpeg(&photos_dir.join("unique.jpg"), 10, 200, 30);
+    fs::create_dir_all(&vault_dir).unwrap();
 
-    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
-    vault.add_source(&photos_dir).unwrap();
-    vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join("b.jpg"), 200, 50, 175);
 
-    // 1 SOT from group + 1 unique = 2 HEIC files
-    assert_eq!(count_files_recursive(&export_dir), 2);
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let mut pack_files = list_pack_files(&vault_dir);
+    pack_files.sort();
+    assert_eq!(pack_files.len(), 2);
+
+    // Flip a byte in one file, delete the other, drop a stray file into a shard
+    let mut bytes = fs::read(&pack_files[0]).unwrap();
+    let mid = bytes.len() / 2;
+    bytes[mid] ^= 0xFF;
+    fs::write(&pack_files[0], bytes).unwrap();
+    fs::remove_file(&pack_files[1]).unwrap();
+    let stray = pack_files[0].parent().unwrap().join("IMG_0001.jpg");
+    fs::write(&stray, b"stray").unwrap();
+
+    let report = vault.verify_pack(None, None).unwrap();
+    assert!(!report.is_clean());
+    assert_eq!(report.verified, 0);
+    assert_eq!(report.corrupted.len(), 1);
+    assert_eq!(report.corrupted[0].path, pack_files[0]);
+    assert_eq!(report.missing, vec![pack_files[1].clone()]);
+    assert_eq!(report.misnamed, vec![stray]);
+    assert!(report.orphaned.is_empty());
 }
 
-#[cfg(target_os = "macos")]
 #[test]
-fn test_export_skips_existing() {
+fn test_verify_pack_sample_covers_pack_over_runs() {
     let tmp = tempfile::tempdir().unwrap();
     let photos_dir = tmp.path().join("photos");
-    let export_dir = tmp.path().join("export");
+    let vault_dir = tmp.path().join("vault");
     fs::create_dir_all(&photos_dir).unwrap();
-    fs::create_dir_all(&export_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
 
-    create_jpeg(&photos_dir.join("photo.jpg"), 100, 100, 100);
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join("b.jpg"), 200, 50, 175);
+    create_png(&photos_dir.join("c.png"), 80, 160, 240);
 
-    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
-    vault.add_source(&photos_dir).unwrap();
-    vault.scan(None).unwrap();
-    // First export
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+
+    let first = vault.verify_pack(Some(34.0), None).unwrap();
+    assert_eq!((first.verified, first.total_entries), (2, 3));
+    let second = vault.verify_pack(Some(34.0), None).unwrap();
+    assert_eq!(second.verified, 2);
+
+    let manifest = photopack_core::manifest::Manifest::open(&vault_dir).unwrap();
+    assert!(manifest
+        .list_entries_by_verification()
+        .unwrap()
+        .iter()
+        .all(|e| e.verified_at.is_some()));
+}
+
+#[test]
+fn test_verify_pack_requires_vault_path() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    let err = vault.verify_pack(None, None).unwrap_err();
+    assert!(matches!(err, photopack_core::error::Error::VaultPathNotSet));
+}
+
+// ── Pack repair ─────────────────────────────────────────────────
+
+#[test]
+fn test_repair_pack_restores_corrupted_and_missing_from_sources() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join("b.jpg"), 200, 50, 175);
+
+    let mut vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    // Rescan so the pack's own files are in the catalog too
+    vault.scan(None).unwrap();
+
+    let mut pack_files = list_pack_files(&vault_dir);
+    pack_files.sort();
+    let originals: Vec<Vec<u8>> = pack_files.iter().map(|p| fs::read(p).unwrap()).collect();
+    let mut bytes = originals[0].clone();
+    let mid = bytes.len() / 2;
+    bytes[mid] ^= 0xFF;
+    fs::write(&pack_files[0], bytes).unwrap();
+    fs::remove_file(&pack_files[1]).unwrap();
+
+    let report = vault.repair_pack(None).unwrap();
+    assert_eq!(report.repaired.len(), 2);
+    assert!(report.unrecoverable.is_empty());
+    for repaired in &report.repaired {
+        assert!(
+            repaired.source.starts_with(photos_dir.canonicalize().unwrap()),
+            "must restore from a source, not the pack: {}",
+            repaired.source.display()
+        );
+    }
+    assert_eq!(fs::read(&pack_files[0]).unwrap(), originals[0]);
+    assert_eq!(fs::read(&pack_files[1]).unwrap(), originals[1]);
+    assert!(vault.verify_pack(None, None).unwrap().is_clean());
+}
+
+#[test]
+fn test_repair_pack_reports_unrecoverable_when_sources_changed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let pack_files = list_pack_files(&vault_dir);
+    fs::write(&pack_files[0], b"bit rot").unwrap();
+    // The only source copy was edited after packing
+    create_jpeg(&photos_dir.join("a.jpg"), 90, 90, 90);
+
+    let report = vault.repair_pack(None).unwrap();
+    assert!(report.repaired.is_empty());
+    assert_eq!(report.unrecoverable, vec![pack_files[0].clone()]);
+    assert_eq!(fs::read(&pack_files[0]).unwrap(), b"bit rot");
+}
+
+#[test]
+fn test_repair_pack_clean_pack_is_noop() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let report = vault.repair_pack(None).unwrap();
+    assert_eq!(report, photopack_core::repair::RepairReport::default());
+}
+
+// ── Pack views (by-date/, by-camera/) ───────────────────────────
+
+/// List view files (relative to the pack) under `by-date/` and `by-camera/`.
+fn list_view_files(vault_dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = ["by-date", "by-camera"]
+        .iter()
+        .flat_map(|dir| walkdir::WalkDir::new(vault_dir.join(dir)))
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_type().is_dir())
+        .map(|e| {
+            e.path()
+                .strip_prefix(vault_dir)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_pack_views_link_to_pack_files_and_are_not_scanned() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_png(&photos_dir.join("b.png"), 200, 50, 175);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.set_pack_views(true).unwrap();
+    vault.vault_save(None).unwrap();
+
+    let views = list_view_files(&vault_dir);
+    assert_eq!(views.len(), 4, "{views:?}");
+    assert!(views.iter().any(|v| v.starts_with("by-date/") && v.ends_with("/a.jpg")));
+    assert!(views
+        .iter()
+        .any(|v| v.starts_with("by-camera/unknown-camera/") && v.ends_with("/b.png")));
+    let mut pack_bytes: Vec<Vec<u8>> = list_pack_files(&vault_dir)
+        .iter()
+        .filter(|p| !p.starts_with(vault_dir.join("by-date")) && !p.starts_with(vault_dir.join("by-camera")))
+        .map(|p| fs::read(p).unwrap())
+        .collect();
+    let mut date_bytes: Vec<Vec<u8>> = views
+        .iter()
+        .filter(|v| v.starts_with("by-date/"))
+        .map(|v| fs::read(vault_dir.join(v)).unwrap())
+        .collect();
+    pack_bytes.sort();
+    date_bytes.sort();
+    assert_eq!(date_bytes, pack_bytes);
+
+    // The pack is a source: rescanning must not catalog the view links
+    let before = vault.photos().unwrap().len();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.photos().unwrap().len(), before + 2);
+    assert!(vault.verify_pack(None, None).unwrap().is_clean());
+}
+
+#[test]
+fn test_pack_views_stale_links_removed_with_pack_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_png(&photos_dir.join("b.png"), 200, 50, 175);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    // Keep the pack out of the sources so removed photos really leave the pack
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.remove_source(&vault_dir).unwrap();
+    vault.set_pack_views(true).unwrap();
+    vault.vault_save(None).unwrap();
+    assert_eq!(list_view_files(&vault_dir).len(), 4);
+
+    fs::remove_file(photos_dir.join("b.png")).unwrap();
+    vault.scan(None).unwrap();
+    vault.vault_save(None).unwrap();
+
+    let views = list_view_files(&vault_dir);
+    assert_eq!(views.len(), 2, "{views:?}");
+    assert!(views.iter().all(|v| v.ends_with("/a.jpg")));
+    // Emptied camera directory is pruned
+    assert!(!vault_dir.join("by-camera/unknown-camera").join("b.png").exists());
+}
+
+#[test]
+fn test_pack_views_disabled_removes_links() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    assert!(!vault.pack_views_enabled().unwrap());
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    assert!(list_view_files(&vault_dir).is_empty());
+
+    vault.set_pack_views(true).unwrap();
+    vault.vault_save(None).unwrap();
+    assert_eq!(list_view_files(&vault_dir).len(), 2);
+
+    vault.set_pack_views(false).unwrap();
+    vault.vault_save(None).unwrap();
+    assert!(list_view_files(&vault_dir).is_empty());
+    assert!(!vault_dir.join("by-date").exists());
+    assert_eq!(list_pack_files(&vault_dir).len(), 1);
+}
+
+// ── Pack trash ──────────────────────────────────────────────────
+
+#[test]
+fn test_pack_cleanup_moves_stale_files_to_trash() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("photo.jpg"), 100, 100, 100);
+
+    let mut vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let pack_file = list_pack_files(&vault_dir).remove(0);
+    let original = fs::read(&pack_file).unwrap();
+
+    vault.remove_source(&photos_dir).unwrap();
+    vault.vault_save(None).unwrap();
+
+    assert!(!pack_file.exists());
+    let trash = vault.trash_entries().unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].original_filename, "photo.jpg");
+    let trashed = vault_dir
+        .join(".photopack/trash")
+        .join(pack_file.file_name().unwrap());
+    assert_eq!(fs::read(&trashed).unwrap(), original);
+    // Trash is not a pack problem
+    assert!(vault.verify_pack(None, None).unwrap().is_clean());
+
+    // Still within retention: a second sync keeps it
+    vault.vault_save(None).unwrap();
+    assert_eq!(vault.trash_entries().unwrap().len(), 1);
+
+    let restore_dir = tmp.path().join("rescued");
+    let restored = vault
+        .restore_from_trash(&trash[0].sha256[..10], &restore_dir)
+        .unwrap();
+    assert_eq!(restored, restore_dir.join("photo.jpg"));
+    assert_eq!(fs::read(&restored).unwrap(), original);
+    assert!(vault.trash_entries().unwrap().is_empty());
+    assert!(!trashed.exists());
+}
+
+#[test]
+fn test_pack_brings_back_trashed_file_when_wanted_again() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("photo.jpg"), 100, 100, 100);
+
+    let mut vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    vault.remove_source(&photos_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    assert_eq!(count_files_recursive(&vault_dir), 0);
+
+    // Undo: the source comes back
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    let mut copied = usize::MAX;
+    vault
+        .vault_save(Some(&mut |progress| {
+            if let photopack_core::vault_save::VaultSaveProgress::Complete { copied: c, .. } =
+                progress
+            {
+                copied = c;
+            }
+        }))
+        .unwrap();
+
+    assert_eq!(copied, 0, "restored from the trash, not re-copied");
+    assert_eq!(count_files_recursive(&vault_dir), 1);
+    assert!(vault.trash_entries().unwrap().is_empty());
+    assert!(vault.verify_pack(None, None).unwrap().is_clean());
+}
+
+#[test]
+fn test_trash_purged_after_retention() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_png(&photos_dir.join("b.png"), 200, 50, 175);
+
+    let mut vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    assert_eq!(vault.trash_retention_days().unwrap(), 30);
+    vault.remove_source(&photos_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    assert_eq!(vault.trash_entries().unwrap().len(), 2);
+
+    // Nothing is old enough for a regular purge
+    assert!(vault.purge_trash(false).unwrap().is_empty());
+
+    vault.set_trash_retention_days(0).unwrap();
+    let mut purged = 0;
+    vault
+        .vault_save(Some(&mut |progress| {
+            if let photopack_core::vault_save::VaultSaveProgress::TrashPurged { count } = progress {
+                purged = count;
+            }
+        }))
+        .unwrap();
+    assert_eq!(purged, 2);
+    assert!(vault.trash_entries().unwrap().is_empty());
+    assert_eq!(
+        fs::read_dir(vault_dir.join(".photopack/trash")).unwrap().count(),
+        0
+    );
+}
+
+#[test]
+fn test_restore_from_trash_unknown_hash() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let result = vault.restore_from_trash("deadbeef", &tmp.path().join("out"));
+    assert!(matches!(
+        result,
+        Err(photopack_core::error::Error::NotInTrash(_))
+    ));
+}
+
+// ── Unpack (restore from manifest) ──────────────────────────────
+
+#[test]
+fn test_restore_pack_rebuilds_tree_with_collision_suffixes() {
+    use photopack_core::restore::RestoreMode;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    let dest = tmp.path().join("restored");
+    fs::create_dir_all(photos_dir.join("2023")).unwrap();
+    fs::create_dir_all(photos_dir.join("2024")).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    // Same filename, different content, same mtime day
+    create_jpeg(&photos_dir.join("2023/IMG_0001.jpg"), 10, 20, 30);
+    create_jpeg_checkerboard(&photos_dir.join("2024/IMG_0001.jpg"), 8, [255, 0, 0], [0, 0, 255]);
+    create_png(&photos_dir.join("unique.png"), 200, 50, 175);
+
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let report = vault
+        .restore_pack(&dest, RestoreMode::Copy, None)
+        .unwrap();
+    assert_eq!(report.restored, 3);
+    assert!(report.missing.is_empty());
+
+    let mut names: Vec<String> = walkdir::WalkDir::new(&dest)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 3);
+    assert!(names.contains(&"unique.png".to_string()));
+    assert!(names
+        .iter()
+        .filter(|n| *n != "unique.png")
+        .all(|n| n.starts_with("IMG_0001_") && n.ends_with(".jpg")));
+
+    // Every restored file is byte-identical to a pack file
+    let mut pack_bytes: Vec<Vec<u8>> = list_pack_files(&vault_dir)
+        .iter()
+        .map(|p| fs::read(p).unwrap())
+        .collect();
+    let mut restored_bytes: Vec<Vec<u8>> = walkdir::WalkDir::new(&dest)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| fs::read(e.path()).unwrap())
+        .collect();
+    pack_bytes.sort();
+    restored_bytes.sort();
+    assert_eq!(restored_bytes, pack_bytes);
+}
+
+#[test]
+fn test_restore_pack_from_copied_pack_without_catalog() {
+    use photopack_core::restore::{self, RestoreMode};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_png(&photos_dir.join("b.png"), 200, 50, 175);
+    drop(pack_photos(tmp.path(), &photos_dir, &vault_dir));
+
+    // "Another machine": copy the pack, then remove the catalog and sources
+    let moved = tmp.path().join("elsewhere/pack");
+    for entry in walkdir::WalkDir::new(&vault_dir).into_iter().filter_map(|e| e.ok()) {
+        let target = moved.join(entry.path().strip_prefix(&vault_dir).unwrap());
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).unwrap();
+        } else {
+            fs::copy(entry.path(), &target).unwrap();
+        }
+    }
+    fs::remove_dir_all(&vault_dir).unwrap();
+    fs::remove_dir_all(&photos_dir).unwrap();
+    fs::remove_file(tmp.path().join("catalog.db")).unwrap();
+
+    let dest = tmp.path().join("restored");
+    let report = restore::restore_pack(&moved, &dest, RestoreMode::Copy, None).unwrap();
+    assert_eq!(report.restored, 2);
+    assert_eq!(count_files_recursive(&dest), 2);
+    assert!(!tmp.path().join("catalog.db").exists());
+}
+
+#[test]
+fn test_restore_pack_requires_pack_path() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    let result = vault.restore_pack(
+        &tmp.path().join("out"),
+        photopack_core::restore::RestoreMode::Copy,
+        None,
+    );
+    assert!(matches!(
+        result,
+        Err(photopack_core::error::Error::VaultPathNotSet)
+    ));
+}
+
+// ── Sidecars (XMP, AAE, THM) ────────────────────────────────────
+
+#[test]
+fn test_sidecars_packed_next_to_pack_file_and_recorded() {
+    use photopack_core::domain::SidecarKind;
+    use photopack_core::vault_save::build_sidecar_path;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("IMG_1.jpg"), 10, 20, 30);
+    fs::write(photos_dir.join("IMG_1.jpg.xmp"), b"<x:xmpmeta rating=5/>").unwrap();
+    fs::write(photos_dir.join("IMG_1.AAE"), b"<plist/>").unwrap();
+    fs::write(photos_dir.join("orphan.xmp"), b"<x:xmpmeta/>").unwrap();
+
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let sha = vault.photos().unwrap()[0].sha256.clone();
+
+    assert_eq!(
+        fs::read(build_sidecar_path(&vault_dir, &sha, SidecarKind::Xmp)).unwrap(),
+        b"<x:xmpmeta rating=5/>"
+    );
+    assert_eq!(
+        fs::read(build_sidecar_path(&vault_dir, &sha, SidecarKind::Aae)).unwrap(),
+        b"<plist/>"
+    );
+
+    let manifest = photopack_core::manifest::Manifest::open(&vault_dir).unwrap();
+    let recorded = manifest.sidecars_for(&sha).unwrap();
+    let names: Vec<&str> = recorded.iter().map(|s| s.original_filename.as_str()).collect();
+    assert_eq!(names, vec!["IMG_1.AAE", "IMG_1.jpg.xmp"]);
+    assert_eq!(manifest.list_sidecars().unwrap().len(), 2, "orphans are not packed");
+
+    // Packed sidecars are neither orphaned nor misnamed, and not scanned as photos
+    let report = vault.verify_pack(None, None).unwrap();
+    assert!(report.is_clean(), "{report:?}");
+    assert_eq!(vault.photos().unwrap().len(), 1);
+}
+
+#[test]
+fn test_sidecar_edit_repacks_without_image_change() {
+    use photopack_core::domain::SidecarKind;
+    use photopack_core::vault_save::{build_sidecar_path, VaultSaveProgress};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("IMG_1.jpg"), 10, 20, 30);
+    fs::write(photos_dir.join("IMG_1.xmp"), b"rating=1").unwrap();
+
+    let mut vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let sha = vault.photos().unwrap()[0].sha256.clone();
+    let packed = build_sidecar_path(&vault_dir, &sha, SidecarKind::Xmp);
+    assert_eq!(fs::read(&packed).unwrap(), b"rating=1");
+
+    // Edit the sidecar only
+    fs::write(photos_dir.join("IMG_1.xmp"), b"rating=5, keywords=beach").unwrap();
+    vault.scan(None).unwrap();
+
+    let mut copied = usize::MAX;
+    let mut sidecars_copied = 0;
+    vault
+        .vault_save(Some(&mut |progress| match progress {
+            VaultSaveProgress::SidecarCopied { .. } => sidecars_copied += 1,
+            VaultSaveProgress::Complete { copied: c, .. } => copied = c,
+            _ => {}
+        }))
+        .unwrap();
+    assert_eq!(copied, 0, "image bytes are unchanged");
+    assert_eq!(sidecars_copied, 1);
+    assert_eq!(fs::read(&packed).unwrap(), b"rating=5, keywords=beach");
+
+    // Unchanged on the next run
+    sidecars_copied = 0;
+    vault
+        .vault_save(Some(&mut |progress| {
+            if let VaultSaveProgress::SidecarCopied { .. } = progress {
+                sidecars_copied += 1;
+            }
+        }))
+        .unwrap();
+    assert_eq!(sidecars_copied, 0);
+
+    // Deleting the sidecar drops it from the pack
+    fs::remove_file(photos_dir.join("IMG_1.xmp")).unwrap();
+    vault.scan(None).unwrap();
+    vault.vault_save(None).unwrap();
+    assert!(!packed.exists());
+    let manifest = photopack_core::manifest::Manifest::open(&vault_dir).unwrap();
+    assert!(manifest.list_sidecars().unwrap().is_empty());
+}
+
+#[test]
+fn test_restore_pack_places_sidecars_next_to_photo() {
+    use photopack_core::restore::RestoreMode;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    let dest = tmp.path().join("restored");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("IMG_1.jpg"), 10, 20, 30);
+    fs::write(photos_dir.join("IMG_1.jpg.xmp"), b"<xmp/>").unwrap();
+    create_png(&photos_dir.join("IMG_2.png"), 200, 50, 175);
+    fs::write(photos_dir.join("IMG_2.thm"), b"thumb").unwrap();
+
+    let vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    let report = vault.restore_pack(&dest, RestoreMode::Copy, None).unwrap();
+    assert_eq!(report.restored, 2);
+
+    let find = |name: &str| {
+        walkdir::WalkDir::new(&dest)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .find(|e| e.file_name().to_string_lossy() == name)
+            .map(|e| e.into_path())
+            .unwrap_or_else(|| panic!("{name} not restored"))
+    };
+    let photo = find("IMG_1.jpg");
+    let xmp = find("IMG_1.jpg.xmp");
+    assert_eq!(xmp.parent(), photo.parent());
+    assert_eq!(fs::read(&xmp).unwrap(), b"<xmp/>");
+    assert_eq!(find("IMG_2.thm").parent(), find("IMG_2.png").parent());
+}
+
+// ── Live Photos and videos ──────────────────────────────────────
+
+fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Write a minimal QuickTime movie whose `moov/meta` carries the Apple creation
+/// date and (optionally) content identifier. `media` makes the bytes unique.
+fn create_mov(path: &Path, creation_date: &str, content_id: Option<&str>, media: &[u8]) {
+    let mut items = vec![("com.apple.quicktime.creationdate", creation_date)];
+    if let Some(cid) = content_id {
+        items.push(("com.apple.quicktime.content.identifier", cid));
+    }
+    let mut keys = vec![0u8; 4];
+    keys.extend_from_slice(&(items.len() as u32).to_be_bytes());
+    let mut ilst = Vec::new();
+    for (i, (key, value)) in items.iter().enumerate() {
+        keys.extend(mp4_box(b"mdta", key.as_bytes()));
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0u8; 4]);
+        data.extend_from_slice(value.as_bytes());
+        ilst.extend(mp4_box(&((i + 1) as u32).to_be_bytes(), &mp4_box(b"data", &data)));
+    }
+    let mut meta = mp4_box(b"hdlr", &[0u8; 24]);
+    meta.extend(mp4_box(b"keys", &keys));
+    meta.extend(mp4_box(b"ilst", &ilst));
+
+    let mut file = mp4_box(b"ftyp", b"qt  \0\0\0\0qt  ");
+    file.extend(mp4_box(b"mdat", media));
+    file.extend(mp4_box(b"moov", &mp4_box(b"meta", &meta)));
+    fs::write(path, file).unwrap();
+}
+
+/// Create a JPEG with an EXIF `DateTimeOriginal` and an Apple MakerNote holding
+/// the Live Photo content identifier, as an iPhone writes it.
+fn create_live_jpeg(path: &Path, r: u8, g: u8, b: u8, date: &str, content_id: &str) {
+    create_jpeg(path, r, g, b);
+
+    // MakerNote: "Apple iOS\0", version, "MM", then an IFD with tag 0x0011
+    let mut note = b"Apple iOS\0\0\x01MM".to_vec();
+    let value = format!("{content_id}\0");
+    note.extend_from_slice(&1u16.to_be_bytes());
+    note.extend_from_slice(&0x0011u16.to_be_bytes());
+    note.extend_from_slice(&2u16.to_be_bytes());
+    note.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    note.extend_from_slice(&((note.len() + 8) as u32).to_be_bytes());
+    note.extend_from_slice(&0u32.to_be_bytes());
+    note.extend_from_slice(value.as_bytes());
+
+    // Big-endian TIFF: IFD0 -> Exif IFD (DateTimeOriginal, MakerNote) -> data
+    let date = format!("{date}\0");
+    let entry = |tag: u16, kind: u16, count: u32, value: u32| {
+        let mut e = tag.to_be_bytes().to_vec();
+        e.extend_from_slice(&kind.to_be_bytes());
+        e.extend_from_slice(&count.to_be_bytes());
+        e.extend_from_slice(&value.to_be_bytes());
+        e
+    };
+    let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend(entry(0x8769, 4, 1, 26));
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+    tiff.extend_from_slice(&2u16.to_be_bytes());
+    tiff.extend(entry(0x9003, 2, date.len() as u32, 56));
+    tiff.extend(entry(0x927c, 7, note.len() as u32, 56 + date.len() as u32));
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+    tiff.extend_from_slice(date.as_bytes());
+    tiff.extend_from_slice(&note);
+
+    let mut app1 = b"Exif\0\0".to_vec();
+    app1.extend(tiff);
+    let jpeg = fs::read(path).unwrap();
+    let mut out = jpeg[..2].to_vec();
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+    out.extend(app1);
+    out.extend_from_slice(&jpeg[2..]);
+    fs::write(path, out).unwrap();
+}
+
+fn set_mtime(path: &Path, secs: u64) {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+#[test]
+fn test_scan_groups_identical_videos_only() {
+    let tmp = tempfile::tempdir().unwrap();
+    let phone = tmp.path().join("phone");
+    let backup = tmp.path().join("backup");
+    fs::create_dir_all(&phone).unwrap();
+    fs::create_dir_all(&backup).unwrap();
+
+    create_live_jpeg(&phone.join("IMG_1.JPG"), 10, 20, 30, "2024:06:15 10:30:00", "CID-1");
+    create_mov(&phone.join("IMG_1.MOV"), "2024-06-15T10:30:00+0200", Some("CID-1"), b"motion-1");
+    copy_file(&phone.join("IMG_1.MOV"), &backup.join("IMG_1.MOV"));
+    // Same timestamp, different clip
+    create_mov(&phone.join("IMG_2.MOV"), "2024-06-15T10:30:00+0200", None, b"motion-2");
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&phone).unwrap();
+    vault.add_source(&backup).unwrap();
+    vault.scan(None).unwrap();
+
+    let photos = vault.photos().unwrap();
+    assert_eq!(photos.len(), 4);
+    let movie = photos.iter().find(|p| p.path == phone.join("IMG_1.MOV")).unwrap();
+    assert_eq!(movie.format, photopack_core::domain::PhotoFormat::Mov);
+    let exif = movie.exif.as_ref().unwrap();
+    assert_eq!(exif.date.as_deref(), Some("2024-06-15 10:30:00"));
+    assert_eq!(exif.content_id.as_deref(), Some("CID-1"));
+    let still = photos.iter().find(|p| p.path == phone.join("IMG_1.JPG")).unwrap();
+    assert_eq!(still.exif.as_ref().unwrap().content_id.as_deref(), Some("CID-1"));
+
+    // Only the byte-identical clips group: not the still, not the other clip
+    let groups = vault.groups().unwrap();
+    assert_eq!(groups.len(), 1);
+    let mut members: Vec<&Path> = groups[0].members.iter().map(|m| m.path.as_path()).collect();
+    members.sort();
+    assert_eq!(members, vec![backup.join("IMG_1.MOV"), phone.join("IMG_1.MOV")]);
+}
+
+#[test]
+fn test_live_photo_motion_ranked_and_unpacked_with_still() {
+    use photopack_core::restore::RestoreMode;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let phone = tmp.path().join("phone");
+    let backup = tmp.path().join("backup");
+    let vault_dir = tmp.path().join("vault");
+    let dest = tmp.path().join("restored");
+    fs::create_dir_all(&phone).unwrap();
+    fs::create_dir_all(&backup).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+
+    // The motion clip is dated the next day (UTC) and its older copy sits alone
+    create_live_jpeg(&phone.join("IMG_1.JPG"), 10, 20, 30, "2024:06:15 23:30:00", "CID-1");
+    create_mov(&phone.join("IMG_1.MOV"), "2024-06-16T06:30:00+0000", Some("CID-1"), b"motion");
+    copy_file(&phone.join("IMG_1.MOV"), &backup.join("clip.MOV"));
+    set_mtime(&backup.join("clip.MOV"), 1_600_000_000);
+    set_mtime(&phone.join("IMG_1.MOV"), 1_700_000_000);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&backup).unwrap();
+    vault.add_source(&phone).unwrap();
+    vault.scan(None).unwrap();
+
+    let groups = vault.groups().unwrap();
+    assert_eq!(groups.len(), 1);
+    let sot = groups[0]
+        .members
+        .iter()
+        .find(|m| m.id == groups[0].source_of_truth_id)
+        .unwrap();
+    assert_eq!(sot.path, phone.join("IMG_1.MOV"), "the clip beside its still is kept");
+
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    let manifest = photopack_core::manifest::Manifest::open(&vault_dir).unwrap();
+    let entries = manifest.list_files().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.content_id.as_deref() == Some("CID-1")));
+
+    vault.restore_pack(&dest, RestoreMode::Copy, None).unwrap();
+    let still = dest.join("2024/06/15/IMG_1.JPG");
+    let motion = dest.join("2024/06/15/IMG_1.MOV");
+    assert!(still.is_file());
+    assert_eq!(fs::read(&motion).unwrap(), fs::read(phone.join("IMG_1.MOV")).unwrap());
+}
+
+// ── Dry-run plans ────────────────────────────────────────────────
+
+#[test]
+fn test_plan_vault_save_previews_copies_and_removals() {
+    use photopack_core::plan::PlannedAction;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos.join("b.jpg"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.scan(None).unwrap();
+
+    let plan = vault.plan_vault_save().unwrap();
+    assert_eq!(plan.summary().copies, 2);
+    assert!(plan.summary().bytes_to_write > 0);
+    assert!(list_pack_files(&vault_dir).is_empty(), "planning copies nothing");
+
+    vault.vault_save(None).unwrap();
+    let plan = vault.plan_vault_save().unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.summary().skips, 2);
+
+    // A lossless copy supersedes the packed JPEG
+    create_png(&photos.join("a.png"), 10, 20, 30);
+    vault.scan(None).unwrap();
+    let plan = vault.plan_vault_save().unwrap();
+    assert_eq!(plan.summary().copies, 1);
+    let removed: Vec<_> = plan
+        .actions
+        .iter()
+        .filter_map(|a| match a {
+            PlannedAction::Remove { path, .. } => Some(path.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(removed.len(), 1);
+    assert!(removed[0].is_file());
+
+    vault.vault_save(None).unwrap();
+    assert!(!removed[0].exists(), "plan named the file the save trashed");
+}
+
+#[test]
+fn test_plan_vault_save_previews_trash_purge() {
+    use photopack_core::plan::PlannedAction;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+    create_jpeg(&photos_dir.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join("b.jpg"), 200, 100, 50);
+
+    let mut vault = pack_photos(tmp.path(), &photos_dir, &vault_dir);
+    vault.remove_source(&photos_dir).unwrap();
+    vault.vault_save(None).unwrap();
+    assert_eq!(vault.trash_entries().unwrap().len(), 2);
+
+    // Within the retention window: nothing to purge
+    assert_eq!(vault.plan_vault_save().unwrap().summary().purges, 0);
+
+    vault.set_trash_retention_days(0).unwrap();
+    let plan = vault.plan_vault_save().unwrap();
+    let purged: Vec<_> = plan
+        .actions
+        .iter()
+        .filter_map(|a| match a {
+            PlannedAction::Purge { path, .. } => Some(path.clone()),
+            _ => None,
+        })
+        .collect();
+    let trashed_bytes: u64 = vault.trash_entries().unwrap().iter().map(|e| e.size).sum();
+    assert_eq!(plan.summary().purges, 2);
+    assert_eq!(plan.summary().bytes_freed, trashed_bytes);
+    assert!(purged.iter().all(|p| p.is_file()), "planning deletes nothing");
+
+    vault.vault_save(None).unwrap();
+    assert!(purged.iter().all(|p| !p.exists()), "plan named the files the save purged");
+    assert!(vault.plan_vault_save().unwrap().is_empty());
+
+    // With no retention, a file the save trashes is purged in the same run
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.vault_save(None).unwrap();
+    create_png(&photos_dir.join("a.png"), 10, 20, 30);
+    vault.scan(None).unwrap();
+    let plan = vault.plan_vault_save().unwrap();
+    let removed: Vec<_> = plan
+        .actions
+        .iter()
+        .filter_map(|a| match a {
+            PlannedAction::Remove { path, .. } => Some(path.clone()),
+            _ => None,
+        })
+        .collect();
+    let summary = plan.summary();
+    assert_eq!((summary.removals, summary.purges), (1, 1));
+    assert_eq!(summary.bytes_freed, fs::metadata(&removed[0]).unwrap().len());
+}
+
+#[test]
+fn test_plan_export_previews_conversions() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    copy_file(&photos.join("a.jpg"), &photos.join("a_copy.jpg"));
+    create_jpeg(&photos.join("b.jpg"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+
+    let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+    let plan = vault.plan_export(&export_dir, &encoder).unwrap();
+    assert_eq!(plan.summary().conversions, 2);
+    assert_eq!(fs::read_dir(&export_dir).unwrap().count(), 0);
+
+    vault.export(&export_dir, &encoder, 85, None).unwrap();
+    let plan = vault.plan_export(&export_dir, &encoder).unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(plan.summary().skips, 2);
+}
+
+// ── Queries (ls --where, selective pack/export) ─────────────────
+
+#[test]
+fn test_query_photos_filters_sorts_and_limits() {
+    use photopack_core::query::{Filter, Query, SortKey};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    copy_file(&photos.join("a.jpg"), &photos.join("a_copy.jpg"));
+    create_png(&photos.join("b.png"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+
+    let names = |expr: &str| -> Vec<String> {
+        let query = Query {
+            filter: Some(Filter::parse(expr).unwrap()),
+            ..Query::default()
+        };
+        vault
+            .query_photos(&query)
+            .unwrap()
+            .iter()
+            .map(|p| p.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    };
+
+    assert_eq!(names("format in (lossless)"), vec!["b.png"]);
+    assert_eq!(names("format = jpg and not path ~ '*copy*'"), vec!["a.jpg"]);
+    assert_eq!(names("role = duplicate").len(), 1);
+    assert_eq!(names("role in (best_copy, unique)").len(), 2);
+    assert_eq!(names("confidence = certain").len(), 2);
+    assert_eq!(names("date >= 2000-01 and size > 0"), vec!["a.jpg", "a_copy.jpg", "b.png"]);
+    assert!(names("date < 2000 or camera_model = 'ILCE-7M3'").is_empty());
+
+    let largest = vault.photos().unwrap().iter().map(|p| p.size).max().unwrap();
+    let query = Query {
+        filter: None,
+        sort: vec![SortKey::parse("size:desc").unwrap()],
+        limit: Some(1),
+    };
+    let top = vault.query_photos(&query).unwrap();
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].size, largest);
+}
+
+#[test]
+fn test_vault_save_matching_leaves_other_pack_files_alone() {
+    use photopack_core::plan::PlannedAction;
+    use photopack_core::query::Filter;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let vault_dir = tmp.path().join("vault");
+    fs::create_dir_all(&photos).unwrap();
+    fs::create_dir_all(&vault_dir).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    create_png(&photos.join("b.png"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.set_vault_path(&vault_dir).unwrap();
+    vault.scan(None).unwrap();
+
+    let pngs = Filter::parse("format = png").unwrap();
+    let plan = vault.plan_vault_save_matching(&pngs).unwrap();
+    assert_eq!(plan.summary().copies, 1);
+    vault.vault_save_matching(&pngs, None).unwrap();
+    let packed = list_pack_files(&vault_dir);
+    assert_eq!(packed.len(), 1);
+    assert_eq!(packed[0].extension().unwrap(), "png");
+
+    let jpegs = Filter::parse("format = jpeg").unwrap();
+    vault.vault_save_matching(&jpegs, None).unwrap();
+    assert_eq!(list_pack_files(&vault_dir).len(), 2);
+
+    // The PNG is outside the filter, yet neither planned for removal nor trashed
+    let plan = vault.plan_vault_save_matching(&jpegs).unwrap();
+    assert!(!plan
+        .actions
+        .iter()
+        .any(|a| matches!(a, PlannedAction::Remove { .. })));
+    vault.vault_save_matching(&jpegs, None).unwrap();
+    assert_eq!(list_pack_files(&vault_dir).len(), 2);
+}
+
+#[test]
+fn test_export_matching_converts_only_matching_photos() {
+    use photopack_core::query::Filter;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    create_jpeg(&photos.join("b.jpg"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+
+    let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+    let filter = Filter::parse("path ~ '*/a.jpg'").unwrap();
+    let plan = vault.plan_export_matching(&export_dir, &encoder, &filter).unwrap();
+    assert_eq!(plan.summary().conversions, 1);
+
+    vault
+        .export_matching(&export_dir, &encoder, 85, &filter, None)
+        .unwrap();
+    let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .collect();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].file_name(), "a.jpg");
+}
+
+// ── Quarantine ───────────────────────────────────────────────────
+
+#[test]
+fn test_quarantine_duplicates_and_undo() {
+    use photopack_core::domain::Confidence;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    let backup = photos.join("backup");
+    fs::create_dir_all(&backup).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    copy_file(&photos.join("a.jpg"), &backup.join("a.jpg"));
+    // Same picture in another format: not byte-identical, so kept at `Certain`
+    create_png(&photos.join("b.png"), 200, 100, 50);
+    create_jpeg(&backup.join("b.jpg"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+    assert_eq!(vault.groups().unwrap().len(), 2);
+    let exact = vault
+        .groups()
+        .unwrap()
+        .into_iter()
+        .find(|g| g.confidence == Confidence::Certain)
+        .unwrap();
+    let redundant = exact
+        .members
+        .iter()
+        .find(|m| m.id != exact.source_of_truth_id)
+        .unwrap()
+        .path
+        .clone();
+
+    let quarantine = tmp.path().join("quarantine");
+    let report = vault
+        .quarantine_duplicates(&quarantine, Confidence::Certain, None)
+        .unwrap();
+    assert_eq!(report.moved.len(), 1);
+    assert_eq!(report.moved[0].original, redundant);
+    assert!(!redundant.exists());
+    assert!(report.moved[0].quarantined.is_file());
+    assert!(report.moved[0]
+        .quarantined
+        .starts_with(quarantine.canonicalize().unwrap()));
+    assert_eq!(vault.photos().unwrap().len(), 3, "moved photo leaves the catalog");
+    assert_eq!(vault.groups().unwrap().len(), 1);
+
+    let undo = vault.undo_quarantine(&report.journal, None).unwrap();
+    assert_eq!(undo.restored, vec![redundant.clone()]);
+    assert!(redundant.is_file());
+    vault.scan(None).unwrap();
+    assert_eq!(vault.photos().unwrap().len(), 4);
+    assert_eq!(vault.groups().unwrap().len(), 2);
+}
+
+#[test]
+fn test_quarantine_refuses_folder_inside_source() {
+    use photopack_core::domain::Confidence;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    assert!(matches!(
+        vault.quarantine_duplicates(&photos.join("quarantine"), Confidence::Certain, None),
+        Err(photopack_core::error::Error::QuarantineInsideSource(_))
+    ));
+}
+
+#[test]
+fn test_link_duplicates_keeps_paths_and_reports_savings() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(photos.join("backup")).unwrap();
+    create_jpeg(&photos.join("a.jpg"), 10, 20, 30);
+    copy_file(&photos.join("a.jpg"), &photos.join("backup/a.jpg"));
+    let bytes = fs::read(photos.join("a.jpg")).unwrap();
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos).unwrap();
+    vault.scan(None).unwrap();
+
+    let report = vault.link_duplicates(None).unwrap();
+    assert_eq!(report.linked.len(), 1);
+    assert_eq!(report.bytes_saved, bytes.len() as u64);
+    assert_eq!(fs::read(photos.join("a.jpg")).unwrap(), bytes);
+    assert_eq!(fs::read(photos.join("backup/a.jpg")).unwrap(), bytes);
+
+    vault.scan(None).unwrap();
+    assert_eq!(vault.photos().unwrap().len(), 2, "linked copies stay cataloged");
+    assert_eq!(vault.groups().unwrap().len(), 1);
+}
+
+// ── Export (HEIC conversion) tests ──────────────────────────────
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_export_converts_jpeg_to_heic() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("photo.jpg"), 100, 150, 200);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
+
+    let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .collect();
+
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].path().extension().unwrap(), "heic");
+    assert!(
+        exported[0]
+            .path()
+            .file_stem()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("photo")
+    );
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_export_deduplicates_groups() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("original.jpg"), 100, 150, 200);
+    copy_file(
+        &photos_dir.join("original.jpg"),
+        &photos_dir.join("copy.jpg"),
+    );
+    create_jpeg(&photos_dir.join("unique.jpg"), 10, 200, 30);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
+
+    // 1 SOT from group + 1 unique = 2 HEIC files
+    assert_eq!(count_files_recursive(&export_dir), 2);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_export_skips_existing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("photo.jpg"), 100, 100, 100);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    // First export
     use photopack_core::export::ExportProgress;
     let mut first_converted = 0;
     vault
         .export(&export_dir,
+            &SipsEncoder,
             85,
             Some(&mut |progress| {
                 if let ExportProgress::Complete { converted, .. } = progress {
@@ -3005,6 +5064,7 @@ fn test_export_skips_existing() {
     vault
         .export(
             &export_dir,
+            &SipsEncoder,
             85,
             Some(&mut |progress| {
                 if let ExportProgress::Complete { skipped, .. } = progress {
@@ -3030,7 +5090,7 @@ fn test_export_date_organization() {
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
     vault.add_source(&photos_dir).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
         .into_iter()
@@ -3070,22 +5130,25 @@ fn test_export_progress_events_order() {
     vault
         .export(
             &export_dir,
+            &SipsEncoder,
             85,
             Some(&mut |progress| match progress {
                 ExportProgress::Start { total } => events.push(format!("start:{total}")),
                 ExportProgress::Converted { .. } => events.push("converted".to_string()),
                 ExportProgress::Skipped { .. } => events.push("skipped".to_string()),
+                ExportProgress::Failed { .. } => events.push("failed".to_string()),
                 ExportProgress::Complete {
                     converted,
                     skipped,
-                } => events.push(format!("complete:{converted}:{skipped}")),
+                    failed,
+                } => events.push(format!("complete:{converted}:{skipped}:{failed}")),
             }),
         )
         .unwrap();
 
     assert_eq!(events[0], "start:2");
     assert!(events.contains(&"converted".to_string()));
-    assert_eq!(events.last().unwrap(), "complete:2:0");
+    assert_eq!(events.last().unwrap(), "complete:2:0:0");
 }
 
 #[cfg(target_os = "macos")]
@@ -3095,7 +5158,7 @@ fn test_export_nonexistent_path_errors() {
     let export_dir = tmp.path().join("nonexistent_export");
 
     let vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
-    let err = vault.export(&export_dir, 85, None).unwrap_err();
+    let err = vault.export(&export_dir, &SipsEncoder, 85, None).unwrap_err();
     assert!(err.to_string().contains("does not exist"));
 }
 
@@ -3113,7 +5176,7 @@ fn test_export_converts_png_to_heic() {
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
     vault.add_source(&photos_dir).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
         .into_iter()
@@ -3145,7 +5208,7 @@ fn test_export_multiple_photos_all_heic_extension() {
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
     vault.add_source(&photos_dir).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
         .into_iter()
@@ -3182,7 +5245,7 @@ fn test_export_multiple_sources() {
     vault.add_source(&source_a).unwrap();
     vault.add_source(&source_b).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     assert_eq!(count_files_recursive(&export_dir), 2);
 }
@@ -3203,7 +5266,7 @@ fn test_export_nested_source_photos() {
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
     vault.add_source(&source).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     assert_eq!(count_files_recursive(&export_dir), 2);
 }
@@ -3223,6 +5286,7 @@ fn test_export_empty_catalog_succeeds() {
     vault
         .export(
             &export_dir,
+            &SipsEncoder,
             85,
             Some(&mut |progress| match progress {
                 ExportProgress::Start { total: t } => total = t,
@@ -3265,7 +5329,7 @@ fn test_export_all_grouped_only_sots_exported() {
     assert_eq!(vault.status().unwrap().total_photos, 4);
     assert_eq!(vault.status().unwrap().total_groups, 2);
 
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     // Only 2 SOTs exported, not 4
     assert_eq!(count_files_recursive(&export_dir), 2);
@@ -3285,7 +5349,7 @@ fn test_export_after_rescan_includes_new_photos() {
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
     vault.add_source(&photos_dir).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     assert_eq!(count_files_recursive(&export_dir), 1);
 
@@ -3299,11 +5363,13 @@ fn test_export_after_rescan_includes_new_photos() {
     vault
         .export(
             &export_dir,
+            &SipsEncoder,
             85,
             Some(&mut |progress| {
                 if let ExportProgress::Complete {
                     converted: c,
                     skipped: s,
+                    ..
                 } = progress
                 {
                     converted = c;
@@ -3341,7 +5407,7 @@ fn test_export_independent_from_vault_save() {
 
     // Both operations work independently
     vault.vault_save(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     // Pack has content-addressed .jpg, export has .heic
     let pack_files = list_pack_files(&vault_dir);
@@ -3371,7 +5437,7 @@ fn test_export_heic_file_is_nonempty() {
     let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
     vault.add_source(&photos_dir).unwrap();
     vault.scan(None).unwrap();
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
         .into_iter()
@@ -3405,7 +5471,7 @@ fn test_export_cross_source_dedup() {
 
     assert_eq!(vault.status().unwrap().total_groups, 1);
 
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     // Only 1 SOT exported, not 2
     assert_eq!(count_files_recursive(&export_dir), 1);
@@ -3431,6 +5497,7 @@ fn test_export_converted_event_has_correct_paths() {
     vault
         .export(
             &export_dir,
+            &SipsEncoder,
             85,
             Some(&mut |progress| {
                 if let ExportProgress::Converted { source, target } = progress {
@@ -3485,12 +5552,217 @@ fn test_export_multiple_groups_correct_count() {
     assert_eq!(vault.status().unwrap().total_photos, 7);
     assert_eq!(vault.status().unwrap().total_groups, 2);
 
-    vault.export(&export_dir, 85, None).unwrap();
+    vault.export(&export_dir, &SipsEncoder, 85, None).unwrap();
 
     // 1 SOT from group1 + 1 SOT from group2 + 2 unique = 4
     assert_eq!(count_files_recursive(&export_dir), 4);
 }
 
+// ── Export (pure-Rust encoders) tests ───────────────────────────
+
+#[test]
+fn test_export_jpeg_encoder_converts_and_dedupes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    create_png(&photos_dir.join("original.png"), 100, 150, 200);
+    copy_file(
+        &photos_dir.join("original.png"),
+        &photos_dir.join("copy.png"),
+    );
+    create_jpeg(&photos_dir.join("unique.jpg"), 10, 200, 30);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+    vault.export(&export_dir, &encoder, 85, None).unwrap();
+
+    let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .collect();
+
+    // 1 SOT from group + 1 unique
+    assert_eq!(exported.len(), 2);
+    for entry in &exported {
+        assert_eq!(entry.path().extension().unwrap(), "jpg");
+        assert_eq!(
+            image::ImageFormat::from_path(entry.path()).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        assert!(image::open(entry.path()).is_ok());
+    }
+}
+
+#[test]
+fn test_export_reports_unreadable_source() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("kept.jpg"), 10, 20, 30);
+    create_jpeg(&photos_dir.join("gone.jpg"), 200, 100, 50);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    let gone = photos_dir.join("gone.jpg").canonicalize().unwrap();
+    fs::remove_file(&gone).unwrap();
+
+    use photopack_core::export::ExportProgress;
+    let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+    let mut events = Vec::new();
+    let mut counts = None;
+    let failed = vault
+        .export(
+            &export_dir,
+            &encoder,
+            85,
+            Some(&mut |progress| match progress {
+                ExportProgress::Failed { path, .. } => events.push(path),
+                ExportProgress::Complete {
+                    converted, failed, ..
+                } => counts = Some((converted, failed)),
+                _ => {}
+            }),
+        )
+        .unwrap();
+
+    assert_eq!(events, std::slice::from_ref(&gone));
+    assert_eq!(counts, Some((1, 1)));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].path, gone);
+}
+
+#[test]
+fn test_export_webp_encoder_skips_existing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    create_jpeg(&photos_dir.join("photo.jpg"), 100, 100, 100);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+
+    use photopack_core::export::ExportProgress;
+    let encoder = ImageEncoder::new(ImageFormat::WebP);
+    let mut counts = Vec::new();
+    for _ in 0..2 {
+        vault
+            .export(
+                &export_dir,
+                &encoder,
+                85,
+                Some(&mut |progress| {
+                    if let ExportProgress::Complete {
+                        converted, skipped, ..
+                    } = progress
+                    {
+                        counts.push((converted, skipped));
+                    }
+                }),
+            )
+            .unwrap();
+    }
+
+    assert_eq!(counts, [(1, 0), (0, 1)]);
+    let relative: Vec<_> = walkdir::WalkDir::new(&export_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().strip_prefix(&export_dir).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(relative.len(), 1);
+    // YYYY / MM / DD / photo.webp
+    assert_eq!(relative[0].components().count(), 4);
+    assert_eq!(relative[0].file_name().unwrap(), "photo.webp");
+}
+
+#[test]
+fn test_export_raw_through_embedded_preview() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos_dir = tmp.path().join("photos");
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&photos_dir).unwrap();
+    fs::create_dir_all(&export_dir).unwrap();
+
+    let preview = tmp.path().join("preview.jpg");
+    create_jpeg(&preview, 40, 80, 120);
+    create_raw_with_preview(&photos_dir.join("IMG_0001.dng"), &fs::read(&preview).unwrap(), 1);
+
+    let mut vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    vault.add_source(&photos_dir).unwrap();
+    vault.scan(None).unwrap();
+    let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+    vault.export(&export_dir, &encoder, 85, None).unwrap();
+
+    let exported: Vec<_> = walkdir::WalkDir::new(&export_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .collect();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].file_name(), "IMG_0001.jpg");
+    let img = image::open(exported[0].path()).unwrap();
+    assert_eq!((img.width(), img.height()), (64, 64));
+}
+
+/// Encoder that is never available, standing in for a missing external tool.
+struct MissingToolEncoder;
+
+impl ExportEncoder for MissingToolEncoder {
+    fn name(&self) -> &'static str {
+        "missing-tool"
+    }
+
+    fn extension(&self) -> &'static str {
+        "heic"
+    }
+
+    fn is_available(&self) -> bool {
+        false
+    }
+
+    fn encode(&self, _source: &Path, _target: &Path, _quality: u8) -> photopack_core::error::Result<()> {
+        unreachable!("unavailable encoders must not be called")
+    }
+}
+
+#[test]
+fn test_export_unavailable_encoder_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let export_dir = tmp.path().join("export");
+    fs::create_dir_all(&export_dir).unwrap();
+
+    let vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    let err = vault
+        .export(&export_dir, &MissingToolEncoder, 85, None)
+        .unwrap_err();
+    assert!(matches!(err, photopack_core::error::Error::EncoderNotAvailable(ref name) if name == "missing-tool"));
+}
+
+#[test]
+fn test_export_nonexistent_path_errors_with_jpeg_encoder() {
+    let tmp = tempfile::tempdir().unwrap();
+    let export_dir = tmp.path().join("nonexistent_export");
+
+    let vault = Vault::open(&tmp.path().join("catalog.db")).unwrap();
+    let encoder = ImageEncoder::new(ImageFormat::Jpeg);
+    let err = vault.export(&export_dir, &encoder, 85, None).unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+}
+
 // ── Phash version tracking / cache invalidation ─────────────────
 
 #[test]